@@ -0,0 +1,206 @@
+use std::{hint::black_box, sync::Arc};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use raytracer::{
+    display::HeadlessDisplay,
+    object::BvhBuild,
+    pipeline::Pipeline,
+    prelude::*,
+    scene_file,
+    text,
+    utils::{pixel_rng, Range}
+};
+
+/*
+
+Benchmarks
+
+Intersection throughput of each primitive, single rays traced and paths followed through the example scenes and a
+scene of many instances of one mesh, and whole frames, traced a sample at a time and a tile at a time sorted by material.
+Run with cargo bench --bench render, adding --features f32 to compare tracing in single precision
+
+*/
+
+const SCENES: [&str; 4] = ["spheres", "mirror", "studio", "random_spheres"];
+
+fn load(name: &str) -> Scene {
+    scene_file::load(format!("{}/scenes/{}.ron", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+}
+
+// A fan of rays spread over the view, so hits and misses are both measured
+fn ray_fan(origin: Vec3d, count: usize) -> Vec<Ray> {
+    (0..count).map(|i| {
+        let x = (i % 32) as Float / 16.0 - 1.0;
+        let y = (i / 32) as Float / 16.0 - 1.0;
+        Ray::new(origin.clone(), Vec3d::new(x, y, -1.0))
+    }).collect()
+}
+
+fn intersection(c: &mut Criterion) {
+    let material = Material::Shiny { spclr_exp: 500.0, refl_rat: 0.1, double_sided: true };
+    let font = text::load_font(format!("{}/scenes/fonts/Hack-Regular.ttf", env!("CARGO_MANIFEST_DIR"))).unwrap();
+    let text_mesh = || text::text_mesh(&font, "Ray\nTracer", 2.0, 0.5, Color::Red as usize, material.clone()).unwrap();
+    let objs: Vec<(&str, Box<dyn Object>)> = vec![
+        ("sphere", Box::new(Sphere::new(Vec3d::new(0.0, 0.0, -5.0), 2.0, Color::Red as usize, material.clone()))),
+        ("triangle", Box::new(Triangle::new(
+            [Vec3d::new(-2.0, -2.0, -5.0), Vec3d::new(2.0, -2.0, -5.0), Vec3d::new(0.0, 2.0, -5.0)],
+            Color::Red as usize,
+            material.clone()
+        ))),
+        // Axis aligned box made of 12 triangles
+        ("prism", Box::new(RectangularPrism::new(Vec3d::new(-1.0, -1.0, -6.0), 2.0, 2.0, 2.0, Color::Red as usize, material.clone()))),
+        // Curved sheet bulging towards the rays
+        ("bezier_patch", Box::new(BezierPatch::new(
+            std::array::from_fn(|i| {
+                let (col, row) = ((i % 4) as Float, (i / 4) as Float);
+                let bulge = if (1.0..=2.0).contains(&col) && (1.0..=2.0).contains(&row) { 2.0 } else { 0.0 };
+                Vec3d::new(col * 4.0 / 3.0 - 2.0, row * 4.0 / 3.0 - 2.0, -6.0 + bulge)
+            }),
+            Color::Red as usize,
+            material.clone()
+        ))),
+        // The same box as the prism, bounded by its six planes
+        ("convex_polyhedron", Box::new(ConvexPolyhedron::new(
+            [(0.0, 1.0), (1.0, 0.0), (0.0, 0.0)].into_iter().flat_map(|(x, y)| {
+                let axis = Vec3d::new(x, y, 1.0 - x - y);
+                let center = Vec3d::new(0.0, 0.0, -5.0);
+                [Plane::new(&center + &axis, axis.clone()), Plane::new(&center - &axis, &axis * -1.0)]
+            }).collect(),
+            Color::Red as usize,
+            material.clone()
+        ))),
+        // A thin strand bent across the rays
+        ("curve", Box::new(Curve::new(
+            [Vec3d::new(-2.0, -1.0, -5.0), Vec3d::new(0.0, 3.0, -5.0), Vec3d::new(2.0, -1.0, -5.0)],
+            0.05,
+            0.01,
+            Color::Red as usize,
+            material.clone()
+        ))),
+        // Solid text, a few thousand triangles, with its hierarchy split by the surface area heuristic and at the median
+        ("mesh", Box::new(text_mesh())),
+        ("mesh_median", Box::new(text_mesh().with_bvh(BvhBuild::Median))),
+        // A 64x64 sheet of disks facing the rays
+        ("point_cloud", Box::new(PointCloud::new(
+            (0..64 * 64).map(|i| {
+                let center = Vec3d::new((i % 64) as Float / 16.0 - 2.0, (i / 64) as Float / 16.0 - 2.0, -5.0);
+                Splat::new(center, Some(Vec3d::new(0.0, 0.0, 1.0)), None)
+            }).collect(),
+            0.04,
+            Color::Red as usize,
+            material
+        )))
+    ];
+
+    let rays = ray_fan(Vec3d::new(0.0, 0.0, 0.0), 1024);
+    let t_range = Range::new(1.0, Float::INFINITY);
+
+    let mut group = c.benchmark_group("intersection");
+    for (name, obj) in &objs {
+        group.bench_function(*name, |b| b.iter(|| {
+            for ray in &rays {
+                black_box(obj.get_closest_intersection(black_box(ray), &t_range));
+            }
+        }));
+    }
+    group.finish();
+}
+
+fn trace_ray(c: &mut Criterion) {
+    let mut group = c.benchmark_group("trace_ray");
+    for name in SCENES {
+        let scene = load(name);
+        let rays = ray_fan(scene.camera_origin().clone(), 1024);
+        let t_range = Range::new(1.0, Float::INFINITY);
+
+        group.bench_function(name, |b| b.iter(|| {
+            for ray in &rays {
+                black_box(scene.trace_ray(black_box(ray), &t_range, 2));
+            }
+        }));
+    }
+    group.finish();
+}
+
+fn trace_path(c: &mut Criterion) {
+    let mut group = c.benchmark_group("trace_path");
+    for name in SCENES {
+        let scene = load(name);
+        let rays = ray_fan(scene.camera_origin().clone(), 1024);
+        let t_range = Range::new(1.0, Float::INFINITY);
+        let mut rng = pixel_rng(0, 0, 0);
+
+        group.bench_function(name, |b| b.iter(|| {
+            for ray in &rays {
+                black_box(scene.trace_path(black_box(ray), &t_range, 2, &mut rng));
+            }
+        }));
+    }
+    group.finish();
+}
+
+const INSTANCES: usize = 16 * 16;
+
+// The ith of a grid of turned copies of a mesh, sharing the mesh's hierarchy of triangles, turned further as time goes on
+fn instance(mesh: &Arc<dyn Object>, i: usize, time: Float) -> Box<dyn Object> {
+    let offset = Vec3d::new((i % 16) as Float * 2.0 - 16.0, (i / 16) as Float * 1.5 - 12.0, -20.0);
+    Box::new(Instance::new(Arc::clone(mesh), Mat3::rotation_y((i * 15) as Float + time * 90.0), offset))
+}
+
+fn instances(c: &mut Criterion) {
+    let font = text::load_font(format!("{}/scenes/fonts/Hack-Regular.ttf", env!("CARGO_MANIFEST_DIR"))).unwrap();
+    let mesh: Arc<dyn Object> = Arc::new(text::text_mesh(&font, "Ray", 1.0, 0.2, Color::Red as usize, Material::default()).unwrap());
+    let lights = vec![LightSource::Ambient { intensity: 0.2 }, LightSource::Directional { intensity: 0.8, dir: Vec3d::new(-1.0, -1.0, -1.0), color: Color::White as usize }];
+    let objs: Vec<_> = (0..INSTANCES).map(|i| instance(&mesh, i, 0.0)).collect();
+    let mut scene = Scene::new(Vec3d::new(0.0, 0.0, 0.0), Color::Black as usize, lights, objs).unwrap();
+
+    let rays = ray_fan(scene.camera_origin().clone(), 1024);
+    let t_range = Range::new(1.0, Float::INFINITY);
+
+    c.bench_function("trace_ray/instances", |b| b.iter(|| {
+        for ray in &rays {
+            black_box(scene.trace_ray(black_box(ray), &t_range, 2));
+        }
+    }));
+
+    // A frame of animation turning every instance
+    let mut time = 0.0;
+    c.bench_function("animate/instances", |b| b.iter(|| {
+        time += 1.0 / 60.0;
+        scene.set_objects((0..INSTANCES).map(|i| (i, instance(&mesh, i, time))));
+    }));
+
+    // Whole frames, where neighbouring rays pass through the same instances and triangles
+    let mut renderer = Renderer::builder()
+        .screen_width(320)
+        .display(Box::new(HeadlessDisplay::new()))
+        .build(Arc::new(scene))
+        .unwrap();
+    let mut group = c.benchmark_group("frame");
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::new("320x180", "instances"), |b| b.iter(|| renderer.trace_rays().unwrap()));
+    group.finish();
+}
+
+fn frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+    group.sample_size(10);
+    for name in SCENES {
+        let scene = Arc::new(load(name));
+        for (pipeline, size) in [(Pipeline::Immediate, "320x180"), (Pipeline::Sorted, "320x180 sorted")] {
+            let mut renderer = Renderer::builder()
+                .screen_width(320)
+                .pipeline(pipeline)
+                .display(Box::new(HeadlessDisplay::new()))
+                .build(scene.clone())
+                .unwrap();
+
+            group.bench_function(BenchmarkId::new(size, name), |b| b.iter(|| renderer.trace_rays().unwrap()));
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, intersection, trace_ray, trace_path, instances, frame);
+criterion_main!(benches);
@@ -0,0 +1,25 @@
+// Generates the C header for the ffi feature's functions (see src/ffi.rs) in the build's output directory. A copy is kept
+// in include/ for C programs that link a library built elsewhere, which the ffi tests check is up to date
+fn main() {
+    #[cfg(feature = "ffi")]
+    {
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let out_dir = std::env::var("OUT_DIR").unwrap();
+        cbindgen::Builder::new()
+            .with_src(format!("{}/src/ffi.rs", dir))
+            .with_config(cbindgen::Config {
+                language: cbindgen::Language::C,
+                style: cbindgen::Style::Both,
+                include_guard: Some("RAYTRACER_H".to_string()),
+                header: Some("// Generated from src/ffi.rs by build.rs when building with the ffi feature, don't edit".to_string()),
+                usize_is_size_t: true,
+                enumeration: cbindgen::EnumConfig { prefix_with_name: true, rename_variants: cbindgen::RenameRule::ScreamingSnakeCase, ..Default::default() },
+                ..Default::default()
+            })
+            .generate()
+            .expect("couldn't generate the C header from src/ffi.rs")
+            .write_to_file(format!("{}/raytracer.h", out_dir));
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,88 @@
+// Generated from src/ffi.rs by build.rs when building with the ffi feature, don't edit
+
+#ifndef RAYTRACER_H
+#define RAYTRACER_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum RtStatus {
+  RT_STATUS_OK,
+  RT_STATUS_NULL_POINTER,
+  RT_STATUS_INVALID_ARGUMENT,
+  RT_STATUS_BUFFER_TOO_SMALL,
+  RT_STATUS_RENDER_FAILED,
+} RtStatus;
+
+typedef enum RtMaterialKind {
+  RT_MATERIAL_KIND_MATTE,
+  RT_MATERIAL_KIND_SHINY,
+  RT_MATERIAL_KIND_EMISSIVE,
+} RtMaterialKind;
+
+typedef enum RtLightKind {
+  RT_LIGHT_KIND_AMBIENT,
+  RT_LIGHT_KIND_POINT,
+  RT_LIGHT_KIND_DIRECTIONAL,
+} RtLightKind;
+
+typedef struct RtScene RtScene;
+
+typedef struct RtMaterial {
+  enum RtMaterialKind kind;
+  double spclr_exp;
+  double refl_rat;
+  double intensity;
+} RtMaterial;
+
+typedef struct RtRenderOptions {
+  uint32_t width;
+  uint32_t height;
+  uint32_t samples;
+  uint32_t max_depth;
+  uint32_t threads;
+  double fov;
+  uint64_t seed;
+} RtRenderOptions;
+
+struct RtScene *rt_scene_new(const double *camera, uint32_t background);
+
+void rt_scene_free(struct RtScene *scene);
+
+enum RtStatus rt_scene_add_sphere(struct RtScene *scene,
+                                  const double *center,
+                                  double radius,
+                                  uint32_t color,
+                                  const struct RtMaterial *material);
+
+enum RtStatus rt_scene_add_prism(struct RtScene *scene,
+                                 const double *origin,
+                                 double width,
+                                 double height,
+                                 double depth,
+                                 uint32_t color,
+                                 const struct RtMaterial *material);
+
+enum RtStatus rt_scene_add_triangle(struct RtScene *scene,
+                                    const double *points,
+                                    uint32_t color,
+                                    const struct RtMaterial *material);
+
+enum RtStatus rt_scene_add_light(struct RtScene *scene,
+                                 enum RtLightKind kind,
+                                 double intensity,
+                                 const double *vec);
+
+struct RtRenderOptions rt_render_options_default(void);
+
+enum RtStatus rt_render(struct RtScene *scene,
+                        const struct RtRenderOptions *options,
+                        uint8_t *rgba,
+                        size_t len);
+
+const char *rt_last_error(void);
+
+#endif  /* RAYTRACER_H */
@@ -0,0 +1,240 @@
+use std::{fmt, str::FromStr};
+
+use crate::{color::Color, linalg::{Float, Vec3d}, object::{Object, Primitive}, VIEW_DISTANCE};
+
+/*
+
+AOVs
+
+Arbitrary output variables: channels recorded for every canvas unit alongside its color (the beauty), so a render can
+be taken apart and adjusted in compositing, or handed to a denoiser. Every channel holds three values per canvas unit,
+and like the beauty, each is the filter weighted average of the unit's samples
+
+Depth is linear, measured along the camera's view direction rather than along each ray, so a wall facing the camera is
+the same depth all over, as depth of field and rasterized overlays expect. Besides the preview every AOV has, it can be
+exported exactly as floats (channel_floats, e.g. to an EXR) or as 16-bit levels over the distance the camera sees
+(depth_levels, e.g. to a 16-bit PNG)
+
+Object and material IDs number what each canvas unit shows, for masking objects in compositing. They aren't averaged,
+as an average of two IDs is a third, unrelated one: each unit takes the ID its samples saw with the most filter weight.
+Objects are numbered in the order the scene was given them, and materials in the order they first appear on those
+objects, so objects with the same material share its ID. Units that show the background are -1
+
+Hard IDs leave jagged edges when used as masks, so the coverage AOVs record how much of each unit every object covers
+instead, in ranks like Cryptomatte: the first rank holds the object covering the most of the unit and the fraction of
+the unit's filter weight it covers, the second rank the object covering the next most, and so on. A soft, anti-aliased
+matte of any object can be put together from the ranks afterwards (matte), and a few ranks are enough for all but the
+busiest units
+
+Normal, depth and albedo describe the first surface each camera ray hits. Direct is the light that surface sends to
+the camera straight from the lights, and indirect is the rest of the beauty, which arrived by reflecting or bouncing off
+other surfaces first, so the two add up to the beauty. Direct light is traced again for every sample with the same
+random numbers as its color, so recording it about doubles the time a frame takes
+
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Aov {
+    Normal,          // Unit normal of the surface, facing the camera, in world space
+    Depth,           // Distance to the surface along the camera's view direction, infinite where nothing is hit
+    Albedo,          // Linear color of the surface before it's lit, black where nothing is hit
+    Direct,          // Linear light arriving straight from the lights off the surface
+    Indirect,        // Linear light arriving off the surface after reflecting or bouncing off others first
+    Motion,          // Motion vector in the first two values, the third left at 0 (see motion.rs)
+    ObjectId,        // Index of the object, in every value
+    MaterialId,      // Index of the object's material among the scene's materials, in every value
+    Coverage(usize)  // The object covering the most of the unit after this many others, then the fraction it covers
+}
+
+impl Aov {
+    // Whether finding it needs the surface each sample's camera ray hits
+    pub(crate) fn needs_surface(&self) -> bool {
+        matches!(self, Aov::Normal | Aov::Depth | Aov::Albedo | Aov::ObjectId | Aov::MaterialId | Aov::Coverage(_))
+    }
+
+    // Whether finding it needs the direct light of every sample
+    pub(crate) fn needs_direct(&self) -> bool {
+        matches!(self, Aov::Direct | Aov::Indirect)
+    }
+
+    // A channel's values as packed 0RGB, to look at or save as a PNG. Normals map each axis from -1..1 to 0..255,
+    // depth is white up close fading to black at the furthest surface, and motion vectors are gray where nothing moved,
+    // with red and green showing movement across and down, scaled to the largest. Every ID gets a color of its own, and
+    // the background is black. Coverage shows its object's ID color, darkened where the object only covers part of a unit
+    pub fn encode(&self, values: &[[Float; 3]]) -> Vec<u32> {
+        match self {
+            Aov::Normal => values.iter().map(|n| {
+                let [x, y, z] = n.map(|axis| (axis * 0.5 + 0.5) * 255.0);
+                Color::from_rgb(x, y, z) as u32
+            }).collect(),
+            Aov::Depth => {
+                let furthest = values.iter().map(|v| v[0]).filter(|d| d.is_finite()).fold(0.0, Float::max);
+                values.iter().map(|v| {
+                    let shade = if v[0].is_finite() && furthest > 0.0 { (1.0 - v[0] / furthest) * 255.0 } else { 0.0 };
+                    Color::from_rgb(shade, shade, shade) as u32
+                }).collect()
+            },
+            Aov::Albedo | Aov::Direct | Aov::Indirect => values.iter().map(|&rgb| Color::from_linear(rgb) as u32).collect(),
+            Aov::Motion => {
+                let largest = values.iter().map(|v| v[0].abs().max(v[1].abs())).fold(0.0, Float::max);
+                let scale = if largest > 0.0 { 127.5 / largest } else { 0.0 };
+                values.iter().map(|v| Color::from_rgb(127.5 + v[0] * scale, 127.5 + v[1] * scale, 127.5) as u32).collect()
+            },
+            Aov::ObjectId | Aov::MaterialId => values.iter().map(|v| if v[0] < 0.0 { 0 } else { id_color(v[0] as u32) }).collect(),
+            Aov::Coverage(_) => values.iter().map(|v| if v[0] < 0.0 { 0 } else { Color::scale(id_color(v[0] as u32) as usize, v[1]) as u32 }).collect()
+        }
+    }
+}
+
+// One of the three values of an AOV as 32-bit floats, e.g. for a channel of an EXR
+#[allow(clippy::unnecessary_cast)] // Only unnecessary when tracing in f32
+pub fn channel_floats(values: &[[Float; 3]], channel: usize) -> Vec<f32> {
+    values.iter().map(|v| v[channel] as f32).collect()
+}
+
+// Depths from the depth AOV as 16-bit levels, from 0 at the camera to 65535 at the furthest the camera sees (and where
+// nothing was hit), so a level is about 1.5 thousandths of a unit
+pub fn depth_levels(values: &[[Float; 3]]) -> Vec<u16> {
+    values.iter().map(|v| (v[0] / VIEW_DISTANCE).clamp(0.0, 1.0) * u16::MAX as Float).map(|level| level.round() as u16).collect()
+}
+
+// A bright color picked at random by an ID, so neighbouring IDs look nothing alike
+fn id_color(id: u32) -> u32 {
+    let hash = (id.wrapping_add(1)).wrapping_mul(0x9E3779B1);
+    (hash ^ (hash >> 15)) & 0xFFFFFF | 0x404040
+}
+
+// The material ID of every object: the index of its material among the distinct materials of the objects before it
+pub(crate) fn material_ids(objs: &[Primitive]) -> Vec<usize> {
+    let mut materials = Vec::new();
+    objs.iter().map(|obj| {
+        let material = obj.get_material();
+        materials.iter().position(|&seen| seen == material).unwrap_or_else(|| {
+            materials.push(material);
+            materials.len() - 1
+        })
+    }).collect()
+}
+
+// The first surface a sample's camera ray hits
+#[derive(Clone)]
+pub(crate) struct Surface {
+    pub(crate) object: usize,
+    pub(crate) material: usize,
+    pub(crate) normal: Vec3d,
+    pub(crate) depth: Float,
+    pub(crate) albedo: [Float; 3]
+}
+
+// What the AOVs need from a sample
+#[derive(Clone)]
+pub(crate) struct Sample {
+    pub(crate) surface: Option<Surface>,
+    pub(crate) direct: [Float; 3] // Linear, before exposure
+}
+
+// The value of every AOV but motion from a canvas unit's samples, each with its filter weight, given the unit's exposed
+// color. Motion is found once for the whole unit, so its value is left as it is
+pub(crate) fn resolve(aovs: &[Aov], samples: &[(Sample, Float)], beauty: [Float; 3], exposure: Float, values: &mut [[Float; 3]]) {
+    let direct = average(samples.iter().map(|(sample, weight)| (sample.direct, *weight))).map(|channel| channel * exposure);
+
+    for (aov, value) in aovs.iter().zip(values.iter_mut()) {
+        *value = match aov {
+            Aov::Normal => average(samples.iter().map(|(sample, weight)| {
+                let normal = sample.surface.as_ref().map_or([0.0; 3], |surface| [surface.normal.x(), surface.normal.y(), surface.normal.z()]);
+                (normal, *weight)
+            })),
+            // Depths are only averaged over the samples that hit something, so edges don't fade off into the distance
+            Aov::Depth if samples.iter().any(|(sample, weight)| sample.surface.is_some() && *weight > 0.0) => {
+                average(samples.iter().filter_map(|(sample, weight)| sample.surface.as_ref().map(|surface| ([surface.depth; 3], *weight))))
+            },
+            Aov::Depth => [Float::INFINITY; 3],
+            Aov::Albedo => average(samples.iter().map(|(sample, weight)| (sample.surface.as_ref().map_or([0.0; 3], |surface| surface.albedo), *weight))),
+            Aov::Direct => direct,
+            Aov::Indirect => [0, 1, 2].map(|c| (beauty[c] - direct[c]).max(0.0)),
+            Aov::Motion => *value,
+            Aov::ObjectId => [most_seen(samples.iter().map(|(sample, weight)| (sample.surface.as_ref().map(|surface| surface.object), *weight))); 3],
+            Aov::MaterialId => [most_seen(samples.iter().map(|(sample, weight)| (sample.surface.as_ref().map(|surface| surface.material), *weight))); 3],
+            Aov::Coverage(rank) => {
+                let seen = coverage(samples.iter().map(|(sample, weight)| (sample.surface.as_ref().map(|surface| surface.object), *weight)));
+                let total: Float = seen.iter().map(|(_, weight)| weight).sum();
+                match seen.iter().filter_map(|(id, weight)| id.map(|id| (id, weight))).nth(*rank) {
+                    Some((id, weight)) if total > 0.0 => [id as Float, weight / total, 0.0],
+                    _ => [-1.0, 0.0, 0.0]
+                }
+            }
+        };
+    }
+}
+
+// The ID seen with the most filter weight, -1 for nothing
+fn most_seen(ids: impl Iterator<Item = (Option<usize>, Float)>) -> Float {
+    coverage(ids).first().and_then(|(id, _)| *id).map_or(-1.0, |id| id as Float)
+}
+
+// The total filter weight every ID (or nothing, None) was seen with, the most seen first. Ties go to the ID seen first
+fn coverage(ids: impl Iterator<Item = (Option<usize>, Float)>) -> Vec<(Option<usize>, Float)> {
+    let mut seen: Vec<(Option<usize>, Float)> = Vec::new();
+    for (id, weight) in ids {
+        match seen.iter_mut().find(|(seen_id, _)| *seen_id == id) {
+            Some((_, total)) => *total += weight,
+            None => seen.push((id, weight))
+        }
+    }
+    seen.sort_by(|a, b| b.1.total_cmp(&a.1));
+    seen
+}
+
+// A soft matte of an object from the values of coverage AOVs (of any ranks): the fraction of every canvas unit the
+// object covers, as far as the ranks recorded tell
+pub fn matte(ranks: &[&[[Float; 3]]], object: usize) -> Vec<Float> {
+    let units = ranks.first().map_or(0, |rank| rank.len());
+    (0..units).map(|i| {
+        ranks.iter().map(|rank| rank[i]).filter(|v| v[0] == object as Float).map(|v| v[1]).sum()
+    }).collect()
+}
+
+// The filter weighted average of values, 0 if every weight is
+fn average(values: impl Iterator<Item = ([Float; 3], Float)>) -> [Float; 3] {
+    let (total, total_weight) = values.fold(([0.0; 3], 0.0), |(total, total_weight), (value, weight)| {
+        ([0, 1, 2].map(|c| total[c] + value[c] * weight), total_weight + weight)
+    });
+    if total_weight > 0.0 { total.map(|channel| channel / total_weight) } else { [0.0; 3] }
+}
+
+impl fmt::Display for Aov {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Aov::Normal => write!(f, "normal"),
+            Aov::Depth => write!(f, "depth"),
+            Aov::Albedo => write!(f, "albedo"),
+            Aov::Direct => write!(f, "direct"),
+            Aov::Indirect => write!(f, "indirect"),
+            Aov::Motion => write!(f, "motion"),
+            Aov::ObjectId => write!(f, "object-id"),
+            Aov::MaterialId => write!(f, "material-id"),
+            Aov::Coverage(rank) => write!(f, "coverage{}", rank)
+        }
+    }
+}
+
+impl FromStr for Aov {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(Aov::Normal),
+            "depth" => Ok(Aov::Depth),
+            "albedo" => Ok(Aov::Albedo),
+            "direct" => Ok(Aov::Direct),
+            "indirect" => Ok(Aov::Indirect),
+            "motion" => Ok(Aov::Motion),
+            "object-id" => Ok(Aov::ObjectId),
+            "material-id" => Ok(Aov::MaterialId),
+            _ => match s.strip_prefix("coverage").and_then(|rank| rank.parse().ok()) {
+                Some(rank) => Ok(Aov::Coverage(rank)),
+                None => Err(format!("unknown AOV \"{}\", expected normal, depth, albedo, direct, indirect, motion, object-id, material-id or coverage followed by a rank (e.g. coverage0)", s))
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use crate::linalg::{consts::PI, Float, Vec3d};
+
+/*
+
+Atmosphere
+
+A sky for outdoor scenes, from single scattering of sunlight in an earth-like atmosphere. Air molecules scatter blue
+light more than red (Rayleigh scattering), which gives a blue sky and reddish sunsets, while larger particles scatter
+all colors mostly forwards (Mie scattering), which gives the bright haze around the sun. The same model fades distant
+objects into the sky
+
+Lengths are in metres. The viewer stands on the ground, whatever the camera's height in the scene
+
+*/
+
+const EARTH_RADIUS: Float = 6360e3;
+const ATMOSPHERE_RADIUS: Float = 6420e3;
+const VIEWER_HEIGHT: Float = 1.0;
+
+// Heights at which the density of air molecules and larger particles falls to 1/e of that at the ground
+const RAYLEIGH_SCALE_HEIGHT: Float = 7994.0;
+const MIE_SCALE_HEIGHT: Float = 1200.0;
+
+// Fraction of red, green and blue light scattered per metre at the ground
+const RAYLEIGH_SCATTERING: [Float; 3] = [5.8e-6, 13.5e-6, 33.1e-6];
+const MIE_SCATTERING: Float = 21e-6;
+const MIE_EXTINCTION: Float = MIE_SCATTERING * 1.1;
+const MIE_ANISOTROPY: Float = 0.76; // How much particles scatter forwards rather than backwards
+
+const VIEW_STEPS: usize = 16;
+const LIGHT_STEPS: usize = 8;
+
+const SUN_ANGULAR_RADIUS: Float = 0.5; // Degrees, about twice the real sun so it shows up at low resolutions
+
+pub struct Atmosphere {
+    sun_dir: Vec3d,       // Direction towards the sun
+    sun_intensity: Float, // Brightness of sunlight, larger values give a brighter sky
+    haze: Float           // Metres of atmosphere per unit of scene distance, larger values fade distant objects sooner
+}
+
+impl Atmosphere {
+    pub fn new(sun_dir: Vec3d, sun_intensity: Float, haze: Float) -> Self {
+        Self {
+            sun_dir: sun_dir.normalize(),
+            sun_intensity,
+            haze
+        }
+    }
+
+    // Move the sun, e.g. as the day passes
+    pub fn set_sun_dir(&mut self, sun_dir: &Vec3d) {
+        self.sun_dir = sun_dir.normalize();
+    }
+
+    // Color of the sky seen looking in a direction, including the sun itself, as sRGB encoded channels
+    pub fn sky_color(&self, dir: &Vec3d) -> [Float; 3] {
+        let dir = dir.normalize();
+        let (mut light, transmittance) = self.scatter(&dir, Float::INFINITY);
+
+        if &dir * &self.sun_dir > SUN_ANGULAR_RADIUS.to_radians().cos() {
+            for (l, t) in light.iter_mut().zip(transmittance) {
+                *l += self.sun_intensity * t;
+            }
+        }
+
+        to_color(light)
+    }
+
+    // Color of an object seen dist away in a direction through the atmosphere, as sRGB encoded channels
+    pub fn apply_haze(&self, color: [Float; 3], dir: &Vec3d, dist: Float) -> [Float; 3] {
+        if self.haze <= 0.0 {
+            return color;
+        }
+
+        let (light, transmittance) = self.scatter(&dir.normalize(), dist * self.haze);
+        let scattered = to_color(light);
+        [0, 1, 2].map(|c| color[c] * transmittance[c] + scattered[c])
+    }
+
+    // Sunlight scattered towards the viewer along a view ray of some length (stopping at the ground or the top of the
+    // atmosphere), and the fraction of red, green and blue light from its end that reaches the viewer
+    fn scatter(&self, dir: &Vec3d, length: Float) -> ([Float; 3], [Float; 3]) {
+        let origin = Vec3d::new(0.0, EARTH_RADIUS + VIEWER_HEIGHT, 0.0);
+        let length = length.min(ray_exit(&origin, dir, EARTH_RADIUS).unwrap_or(Float::INFINITY));
+        let Some(length) = ray_exit(&origin, dir, ATMOSPHERE_RADIUS).map(|exit| exit.min(length)) else {
+            return ([0.0; 3], [1.0; 3]);
+        };
+
+        let ds = length / VIEW_STEPS as Float;
+        let mut view_depth = (0.0, 0.0); // Amount of air and particles passed through so far, (Rayleigh, Mie)
+        let mut rayleigh = [0.0; 3];
+        let mut mie = [0.0; 3];
+
+        for i in 0..VIEW_STEPS {
+            let p = &origin + &(dir * ((i as Float + 0.5) * ds));
+            let (density_r, density_m) = densities(&p);
+            view_depth.0 += density_r * ds;
+            view_depth.1 += density_m * ds;
+
+            // Sunlight reaching this point, unless the earth is in the way
+            if ray_exit(&p, &self.sun_dir, EARTH_RADIUS).is_some() {
+                continue;
+            }
+            let light_depth = self.optical_depth_to_sun(&p);
+
+            for c in 0..3 {
+                let tau = RAYLEIGH_SCATTERING[c] * (view_depth.0 + light_depth.0) + MIE_EXTINCTION * (view_depth.1 + light_depth.1);
+                let attenuation = (-tau).exp();
+                rayleigh[c] += attenuation * density_r * ds;
+                mie[c] += attenuation * density_m * ds;
+            }
+        }
+
+        // How much light is scattered from the sun's direction towards the viewer
+        let cos_theta = dir * &self.sun_dir;
+        let phase_r = 3.0 / (16.0 * PI) * (1.0 + cos_theta * cos_theta);
+        let g = MIE_ANISOTROPY;
+        let phase_m = 3.0 / (8.0 * PI) * ((1.0 - g * g) * (1.0 + cos_theta * cos_theta))
+            / ((2.0 + g * g) * (1.0 + g * g - 2.0 * g * cos_theta).powf(1.5));
+
+        let light = [0, 1, 2].map(|c| {
+            self.sun_intensity * (rayleigh[c] * RAYLEIGH_SCATTERING[c] * phase_r + mie[c] * MIE_SCATTERING * phase_m)
+        });
+        let transmittance = [0, 1, 2].map(|c| (-(RAYLEIGH_SCATTERING[c] * view_depth.0 + MIE_EXTINCTION * view_depth.1)).exp());
+
+        (light, transmittance)
+    }
+
+    // Amount of air and particles between a point and the top of the atmosphere towards the sun, (Rayleigh, Mie)
+    fn optical_depth_to_sun(&self, p: &Vec3d) -> (Float, Float) {
+        let length = ray_exit(p, &self.sun_dir, ATMOSPHERE_RADIUS).unwrap_or(0.0);
+        let ds = length / LIGHT_STEPS as Float;
+
+        (0..LIGHT_STEPS).fold((0.0, 0.0), |(depth_r, depth_m), i| {
+            let (density_r, density_m) = densities(&(p + &(&self.sun_dir * ((i as Float + 0.5) * ds))));
+            (depth_r + density_r * ds, depth_m + density_m * ds)
+        })
+    }
+}
+
+// Density of air molecules and larger particles at a point, relative to that at the ground
+fn densities(p: &Vec3d) -> (Float, Float) {
+    let height = (p.magnitude() - EARTH_RADIUS).max(0.0);
+    ((-height / RAYLEIGH_SCALE_HEIGHT).exp(), (-height / MIE_SCALE_HEIGHT).exp())
+}
+
+// Distance along a normalized direction from a point to where it first crosses a sphere around the earth's center, if
+// it does, in front of the point
+fn ray_exit(origin: &Vec3d, dir: &Vec3d, radius: Float) -> Option<Float> {
+    let b = origin * dir;
+    let c = origin * origin - radius * radius;
+    let discnm = b * b - c;
+    if discnm < 0.0 {
+        return None;
+    }
+
+    let discnm_sqrt = discnm.sqrt();
+    [-b - discnm_sqrt, -b + discnm_sqrt].into_iter().find(|&t| t > 0.0)
+}
+
+// Map scattered light, which has no upper limit, to a displayable color
+fn to_color(light: [Float; 3]) -> [Float; 3] {
+    light.map(|l| 1.0 - (-l).exp())
+}
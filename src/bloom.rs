@@ -0,0 +1,125 @@
+use std::{fmt, str::FromStr};
+
+use crate::{linalg::Float, post::{Frame, PostEffect}};
+
+/*
+
+Bloom
+
+Light spilling over from the brightest parts of a frame onto their surroundings, as it does through a camera lens, so
+emissive objects and specular highlights glow. Once a frame is traced, the light of every canvas unit brighter than the
+threshold is blurred and added back over the frame, in linear light before it's encoded for display. It's the first of
+the frame's post-processing effects (see post.rs)
+
+The blur's radius is a fraction of the frame's height, so a glow covers as much of the frame at every resolution (and
+while the camera moves)
+
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bloom {
+    pub threshold: Float, // Linear luminance above which light blooms
+    pub intensity: Float, // How strongly the blurred light is added back
+    pub radius: Float     // How far the light spreads, as a fraction of the frame's height
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            threshold: 0.7,
+            intensity: 1.0,
+            radius: 0.03
+        }
+    }
+}
+
+// How much each channel counts towards luminance (Rec. 709)
+const LUMINANCE: [Float; 3] = [0.2126, 0.7152, 0.0722];
+
+impl PostEffect for Bloom {
+    fn apply(&self, frame: &mut Frame) {
+        let (width, height) = (frame.width, frame.height);
+        let mut glow: Vec<[Float; 3]> = frame.pixels.iter().map(|&rgb| self.bright_part(rgb)).collect();
+
+        let kernel = self.kernel(height);
+        if kernel.len() > 1 {
+            let mut blurred = vec![[0.0; 3]; glow.len()];
+            // Across each row, then down each column
+            for row in 0..height {
+                let line: Vec<usize> = (0..width).map(|col| row * width + col).collect();
+                blur(&glow, &mut blurred, &line, &kernel);
+            }
+            for col in 0..width {
+                let line: Vec<usize> = (0..height).map(|row| row * width + col).collect();
+                blur(&blurred, &mut glow, &line, &kernel);
+            }
+        }
+
+        for (rgb, glow) in frame.pixels.iter_mut().zip(glow) {
+            *rgb = [0, 1, 2].map(|c| rgb[c] + glow[c] * self.intensity);
+        }
+    }
+}
+
+impl Bloom {
+    // The light of a canvas unit past the threshold, keeping its hue
+    fn bright_part(&self, rgb: [Float; 3]) -> [Float; 3] {
+        let luminance: Float = rgb.iter().zip(LUMINANCE).map(|(c, w)| c * w).sum();
+        if luminance <= self.threshold {
+            return [0.0; 3];
+        }
+        let kept = (luminance - self.threshold) / luminance;
+        rgb.map(|c| c * kept)
+    }
+
+    // Weights of a gaussian from its center out to the radius, on one side, summing to 1 over both sides
+    fn kernel(&self, height: usize) -> Vec<Float> {
+        let reach = (self.radius * height as Float).ceil().max(0.0) as usize;
+        let sigma = (reach as Float / 3.0).max(Float::EPSILON);
+        let weights: Vec<Float> = (0..=reach).map(|x| (-((x * x) as Float) / (2.0 * sigma * sigma)).exp()).collect();
+        let total = weights[0] + 2.0 * weights[1..].iter().sum::<Float>();
+        weights.into_iter().map(|w| w / total).collect()
+    }
+}
+
+// Blur the units of src at the indices of a line into dst, repeating the line's ends past its edges
+fn blur(src: &[[Float; 3]], dst: &mut [[Float; 3]], line: &[usize], kernel: &[Float]) {
+    let last = line.len() as isize - 1;
+    for (i, &index) in line.iter().enumerate() {
+        let mut sum = [0.0; 3];
+        for (offset, weight) in kernel.iter().enumerate() {
+            let taps: &[isize] = if offset == 0 { &[0] } else { &[-(offset as isize), offset as isize] };
+            for tap in taps {
+                let rgb = src[line[(i as isize + tap).clamp(0, last) as usize]];
+                for c in 0..3 {
+                    sum[c] += rgb[c] * weight;
+                }
+            }
+        }
+        dst[index] = sum;
+    }
+}
+
+impl fmt::Display for Bloom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{}", self.threshold, self.intensity, self.radius)
+    }
+}
+
+// Threshold, intensity and radius separated by commas, e.g. "0.7,1.0,0.03". Any left off keep their defaults
+impl FromStr for Bloom {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s.split(',')
+            .map(|value| value.trim().parse::<Float>().map_err(|_| format!("invalid bloom value \"{}\"", value)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let defaults = Bloom::default();
+        match values.as_slice() {
+            [threshold] => Ok(Bloom { threshold: *threshold, ..defaults }),
+            [threshold, intensity] => Ok(Bloom { threshold: *threshold, intensity: *intensity, ..defaults }),
+            [threshold, intensity, radius] => Ok(Bloom { threshold: *threshold, intensity: *intensity, radius: *radius }),
+            _ => Err(format!("expected threshold, intensity and radius separated by commas, got \"{}\"", s))
+        }
+    }
+}
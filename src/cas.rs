@@ -0,0 +1,54 @@
+use crate::{linalg::Float, post::{Frame, PostEffect}};
+
+/*
+
+Contrast-Adaptive Sharpening
+
+Crisps up frames softened after tracing, by FXAA or wide reconstruction filters, in the manner of AMD's FidelityFX CAS.
+Each canvas unit is pushed away from its four neighbours, by less where they already differ a lot, so flat areas and
+soft detail sharpen while edges that are already crisp don't ring or halo. Sharpness goes from 0 (subtle) to 1 (strong)
+
+Works on each channel of the linear light separately, treating light past full brightness as full
+
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cas {
+    pub sharpness: Float
+}
+
+impl Default for Cas {
+    fn default() -> Self {
+        Self { sharpness: 0.5 }
+    }
+}
+
+impl PostEffect for Cas {
+    fn apply(&self, frame: &mut Frame) {
+        // The (negative) weight given to the neighbours at full strength
+        let peak = -1.0 / (8.0 - 3.0 * self.sharpness.clamp(0.0, 1.0));
+
+        let pixels: Vec<[Float; 3]> = (0..frame.pixels.len()).map(|i| {
+            let (x, y) = ((i % frame.width) as isize, (i / frame.width) as isize);
+            let center = frame.pixels[i];
+            let cross = [frame.at(x, y - 1), frame.at(x - 1, y), frame.at(x + 1, y), frame.at(x, y + 1)];
+            let corners = [frame.at(x - 1, y - 1), frame.at(x + 1, y - 1), frame.at(x - 1, y + 1), frame.at(x + 1, y + 1)];
+
+            [0, 1, 2].map(|c| {
+                let value = |rgb: &[Float; 3]| rgb[c].clamp(0.0, 1.0);
+                // The darkest and brightest of the cross, plus those of the whole 3x3 square, softening the extremes
+                let cross_values = cross.iter().map(value).chain([value(&center)]);
+                let all_values = cross_values.clone().chain(corners.iter().map(value));
+                let darkest = cross_values.clone().fold(1.0, Float::min) + all_values.clone().fold(1.0, Float::min);
+                let brightest = cross_values.fold(0.0, Float::max) + all_values.fold(0.0, Float::max);
+
+                // Headroom before the neighbourhood would clip either way, relative to its brightness
+                let amount = if brightest > 0.0 { (darkest.min(2.0 - brightest) / brightest).clamp(0.0, 1.0).sqrt() } else { 0.0 };
+                let weight = amount * peak;
+                let neighbours: Float = cross.iter().map(|rgb| rgb[c]).sum();
+                ((neighbours * weight + center[c]) / (1.0 + 4.0 * weight)).max(0.0)
+            })
+        }).collect();
+        frame.pixels = pixels;
+    }
+}
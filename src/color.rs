@@ -1,3 +1,5 @@
+use crate::linalg::Float;
+
 #[derive(Copy, Clone)]
 pub enum Color {
     // Reds
@@ -74,7 +76,82 @@ pub enum Color {
     Black = 0x000000
 }
 
+impl From<Color> for usize {
+    fn from(c: Color) -> Self {
+        c as usize
+    }
+}
+
 impl Color {
+    // Look up a named color, e.g. "cornflowerblue" or "Cornflower Blue"
+    // Matching ignores case, spaces, underscores and hyphens
+    pub fn from_name(name: &str) -> Option<Color> {
+        let name: String = name
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '_' | '-'))
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        match name.as_str() {
+            "salmon" => Some(Color::Salmon),
+            "crimson" => Some(Color::Crimson),
+            "red" => Some(Color::Red),
+            "darkred" => Some(Color::DarkRed),
+            "pink" => Some(Color::Pink),
+            "deeppink" => Some(Color::DeepPink),
+            "coral" => Some(Color::Coral),
+            "darkorange" => Some(Color::DarkOrange),
+            "orange" => Some(Color::Orange),
+            "gold" => Some(Color::Gold),
+            "yellow" => Some(Color::Yellow),
+            "lightyellow" => Some(Color::LightYellow),
+            "lavender" => Some(Color::Lavender),
+            "plum" => Some(Color::Plum),
+            "violet" => Some(Color::Violet),
+            "magenta" => Some(Color::Magenta),
+            "darkviolet" => Some(Color::DarkViolet),
+            "purple" => Some(Color::Purple),
+            "indigo" => Some(Color::Indigo),
+            "lime" => Some(Color::Lime),
+            "limegreen" => Some(Color::LimeGreen),
+            "seagreen" => Some(Color::SeaGreen),
+            "green" => Some(Color::Green),
+            "darkgreen" => Some(Color::DarkGreen),
+            "olive" => Some(Color::Olive),
+            "teal" => Some(Color::Teal),
+            "cyan" => Some(Color::Cyan),
+            "lightcyan" => Some(Color::LightCyan),
+            "turquoise" => Some(Color::Turquoise),
+            "steelblue" => Some(Color::SteelBlue),
+            "lightblue" => Some(Color::LightBlue),
+            "skyblue" => Some(Color::SkyBlue),
+            "deepskyblue" => Some(Color::DeepSkyBlue),
+            "dodgerblue" => Some(Color::DodgerBlue),
+            "cornflowerblue" => Some(Color::CornflowerBlue),
+            "royalblue" => Some(Color::RoyalBlue),
+            "blue" => Some(Color::Blue),
+            "darkblue" => Some(Color::DarkBlue),
+            "navy" => Some(Color::Navy),
+            "cornsilk" => Some(Color::Cornsilk),
+            "wheat" => Some(Color::Wheat),
+            "tan" => Some(Color::Tan),
+            "goldenrod" => Some(Color::Goldenrod),
+            "saddlebrown" => Some(Color::SaddleBrown),
+            "sienna" => Some(Color::Sienna),
+            "brown" => Some(Color::Brown),
+            "maroon" => Some(Color::Maroon),
+            "white" => Some(Color::White),
+            "ghostwhite" => Some(Color::GhostWhite),
+            "whitesmoke" => Some(Color::WhiteSmoke),
+            "ivory" => Some(Color::Ivory),
+            "lightgray" => Some(Color::LightGray),
+            "gray" => Some(Color::Gray),
+            "slategray" => Some(Color::SlateGray),
+            "black" => Some(Color::Black),
+            _ => None
+        }
+    }
+
     pub fn r(c: usize) -> usize {
         (c >> 16) & 0xFF
     }
@@ -87,22 +164,62 @@ impl Color {
         c & 0xFF
     }
 
-    pub fn scale(c: usize, factor: f64) -> usize {
-        let r = (Color::r(c) as f64 * factor).clamp(0.0, 255.0) as usize;
-        let g = (Color::g(c) as f64 * factor).clamp(0.0, 255.0) as usize;
-        let b = (Color::b(c) as f64 * factor).clamp(0.0, 255.0) as usize;
+    // Pack channels given in the range 0 to 255, clamping any outside it
+    pub fn from_rgb(r: Float, g: Float, b: Float) -> usize {
+        let r = r.clamp(0.0, 255.0) as usize;
+        let g = g.clamp(0.0, 255.0) as usize;
+        let b = b.clamp(0.0, 255.0) as usize;
+
+        (r << 16) | (g << 8) | b
+    }
+
+    // Linear RGB, 1 at full brightness, of a packed color whose channels are sRGB encoded for display
+    pub fn to_linear(c: usize) -> [Float; 3] {
+        Color::decode(Color::to_rgb(c))
+    }
+
+    // The channels of a packed color as shares of full brightness, still sRGB encoded
+    pub fn to_rgb(c: usize) -> [Float; 3] {
+        [Color::r(c), Color::g(c), Color::b(c)].map(|channel| channel as Float / 255.0)
+    }
+
+    // Linear RGB of sRGB encoded channels, which may go past full brightness
+    pub fn decode(rgb: [Float; 3]) -> [Float; 3] {
+        rgb.map(|channel| srgb_to_linear(channel.max(0.0)))
+    }
+
+    // sRGB encoded channels of linear RGB, without limiting them to full brightness
+    pub fn encode(rgb: [Float; 3]) -> [Float; 3] {
+        rgb.map(|channel| linear_to_srgb(channel.max(0.0)))
+    }
+
+    // Encode linear RGB for display and pack it, clamping channels outside 0 to 1
+    pub fn from_linear(rgb: [Float; 3]) -> usize {
+        let [r, g, b] = rgb.map(|channel| (linear_to_srgb(channel.clamp(0.0, 1.0)) * 255.0).round());
+        Color::from_rgb(r, g, b)
+    }
+
+    // Perceived brightness, in the range 0 to 255
+    pub fn luminance(c: usize) -> Float {
+        0.2126 * Color::r(c) as Float + 0.7152 * Color::g(c) as Float + 0.0722 * Color::b(c) as Float
+    }
+
+    pub fn scale(c: usize, factor: Float) -> usize {
+        let r = (Color::r(c) as Float * factor).clamp(0.0, 255.0) as usize;
+        let g = (Color::g(c) as Float * factor).clamp(0.0, 255.0) as usize;
+        let b = (Color::b(c) as Float * factor).clamp(0.0, 255.0) as usize;
     
         (r << 16) | (g << 8) | b
     }
 
     pub fn add(a: usize, b: usize) -> usize {
-        let ra = Color::r(a) as f64;
-        let ga = Color::g(a) as f64;
-        let ba = Color::b(a) as f64;
+        let ra = Color::r(a) as Float;
+        let ga = Color::g(a) as Float;
+        let ba = Color::b(a) as Float;
     
-        let rb = Color::r(b) as f64;
-        let gb = Color::g(b) as f64;
-        let bb = Color::b(b) as f64;
+        let rb = Color::r(b) as Float;
+        let gb = Color::g(b) as Float;
+        let bb = Color::b(b) as Float;
         
         let r = (ra + rb).clamp(0.0, 255.0) as usize;
         let g = (ga + gb).clamp(0.0, 255.0) as usize;
@@ -110,4 +227,33 @@ impl Color {
     
         (r << 16) | (g << 8) | b
     }
-}
\ No newline at end of file
+}
+
+// The sRGB transfer functions, between a channel's share of full brightness in linear light and its encoded value
+pub(crate) fn srgb_to_linear(v: Float) -> Float {
+    if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+}
+
+pub(crate) fn linear_to_srgb(v: Float) -> Float {
+    if v <= 0.0031308 { v * 12.92 } else { 1.055 * v.powf(1.0 / 2.4) - 0.055 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<usize> {
+        Color::from_name(name).map(|c| c as usize)
+    }
+
+    #[test]
+    fn names_ignore_case_spaces_and_separators() {
+        for name in ["light yellow", "LightYellow", "light_yellow", "LIGHT-YELLOW"] {
+            assert_eq!(lookup(name), Some(Color::LightYellow as usize), "{}", name);
+        }
+        assert_eq!(lookup("red"), Some(Color::Red as usize));
+        assert_eq!(lookup("dark red"), Some(Color::DarkRed as usize));
+        assert_eq!(lookup("chartreuse-ish"), None);
+        assert_eq!(lookup(""), None);
+    }
+}
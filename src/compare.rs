@@ -0,0 +1,66 @@
+use std::{fmt, str::FromStr};
+
+use crate::{integrator::Integrator, linalg::Float};
+
+/*
+
+Comparison
+
+Renders every frame twice, with the renderer's own settings on the left of the window and some of them changed on the
+right, split by a divider that can be dragged with the mouse, to see what a change in quality is worth (or costs) on
+the same view, e.g. path tracing against Whitted, more samples or bounces, or a frame without post-processing
+
+Only the settings given are changed for the right side, written as name=value pairs separated by commas
+    integrator=path   how light is found: whitted, path or ao
+    samples=16        anti-aliasing samples per canvas unit
+    bounces=4         times a ray may be reflected
+    exposure=1.5      brightness multiplier
+    raw               without FXAA, sharpening, bloom or post effects
+e.g. "integrator=path,samples=16" or "raw"
+
+Both sides are traced one after the other for every frame, so the window updates at half the rate
+
+*/
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Comparison {
+    pub integrator: Option<Integrator>,
+    pub num_samples: Option<usize>,
+    pub max_depth: Option<u32>,
+    pub exposure: Option<Float>,
+    pub raw: bool // Whether the right side is left as traced, without post-processing
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let settings: Vec<String> = [
+            self.integrator.map(|integrator| format!("integrator={}", integrator)),
+            self.num_samples.map(|num_samples| format!("samples={}", num_samples)),
+            self.max_depth.map(|max_depth| format!("bounces={}", max_depth)),
+            self.exposure.map(|exposure| format!("exposure={}", exposure)),
+            self.raw.then(|| "raw".to_string())
+        ].into_iter().flatten().collect();
+        write!(f, "{}", settings.join(","))
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut comparison = Comparison::default();
+        for setting in s.split(',').map(str::trim) {
+            let (name, value) = setting.split_once('=').unwrap_or((setting, ""));
+            let invalid = || format!("invalid value \"{}\" for {}", value, name);
+            match name {
+                "integrator" => comparison.integrator = Some(value.parse()?),
+                "samples" => comparison.num_samples = Some(value.parse().map_err(|_| invalid())?),
+                "bounces" => comparison.max_depth = Some(value.parse().map_err(|_| invalid())?),
+                "exposure" => comparison.exposure = Some(value.parse().map_err(|_| invalid())?),
+                "raw" if value.is_empty() => comparison.raw = true,
+                _ => return Err(format!("unknown setting \"{}\", expected integrator, samples, bounces, exposure or raw", setting))
+            }
+        }
+        Ok(comparison)
+    }
+}
@@ -0,0 +1,645 @@
+use std::{error::Error, fmt, sync::Arc, thread};
+
+use crate::{aov::Aov, bloom::Bloom, compare::Comparison, controls::Controls, display::{Display, HeadlessDisplay}, error::RendererError, filter::Filter, image::Image, integrator::Integrator, linalg::Float, pipeline::Pipeline, post::PostEffect, sample_pattern::SamplePattern, stereo::Stereo, upscale::Upscale, Renderer, Scene};
+
+/*
+
+Renderer Config
+
+Every setting the renderer is created with. Start from the defaults and override what's needed, either directly or
+through a RendererBuilder
+
+*/
+
+pub const MAX_DEPTH: u32 = 64; // Most times a ray may be reflected, deep enough for any scene while bounding the recursion
+
+#[derive(Clone)]
+pub struct RendererConfig {
+    pub num_threads: usize,
+    pub screen_width: usize,
+    pub aspect_ratio: Float,
+    pub screen_height: Option<usize>, // Pixels tall, instead of the height the width and aspect ratio give
+    pub canvas_unit_size: usize, // The square length of pixels that a canvas unit will take up
+    pub render_scale: Float,     // Canvas units per pixel along each side on top of that, e.g. 0.75 to trace fewer or 2 to supersample
+    pub upscale: Upscale,        // How a canvas smaller than the window is stretched over it
+    pub fullscreen: bool,        // Open the window fullscreen, rather than screen_width wide
+    pub num_samples: usize,      // Number of samples used when performing anti-aliasing
+    pub filter: Filter,          // How anti-aliasing samples are weighted when combined
+    pub sample_pattern: SamplePattern, // Where anti-aliasing samples are placed within a canvas unit
+    pub firefly_clamp: Option<Float>, // Limit samples to this many times the median brightness of their canvas unit
+    pub max_depth: u32,          // Number of times a ray may be reflected
+    pub integrator: Integrator,  // How the light along each ray is found
+    pub pipeline: Pipeline,      // The order the samples of a tile are traced in
+    pub fov: Float,              // Vertical field of view (deg)
+    pub background: Option<usize>, // Color seen where rays hit nothing, instead of the scene's
+    pub exposure: Float,         // Brightness multiplier applied to the traced colors
+    pub fxaa: bool,              // Smooth jagged edges after tracing, a cheap alternative to more samples
+    pub cas: Option<Float>,      // Sharpness (0 to 1) of contrast-adaptive sharpening after tracing, countering softness
+    pub bloom: Option<Bloom>,    // Glow spread from the brightest parts of every frame
+    pub post_effects: Vec<Arc<dyn PostEffect>>, // Applied to every frame in order after bloom, before it's presented
+    pub comparison: Option<Comparison>, // Settings changed for the right side of the window, to compare against the rest
+    pub stereo: Stereo,          // Render a view for each eye, and how to combine them
+    pub interocular: Float,      // Distance between the eyes when rendering in stereo
+    pub preview_scale: usize,    // How many times larger canvas units are while the camera moves, 1 to always trace at full size
+    pub progressive: bool,       // Trace every change at 1/8, 1/4 and 1/2 resolution with fewer samples before full size, presenting each as it finishes
+    pub controls: Controls,      // Keys that move the camera and how fast they move it
+    pub gamepad_sensitivity: Float, // Camera speed with a gamepad's sticks fully pushed, relative to the keys (needs the gamepad feature)
+    pub day_length: Float,       // Seconds a whole day takes to pass while the time of day is animated
+    pub turntable: bool,         // Start with the camera moving round the point orbit_radius in front of it
+    pub orbit_radius: Float,     // Distance from the camera to the point the turntable moves it round
+    pub orbit_speed: Float,      // Degrees the turntable moves the camera round every frame, the other way when negative
+    pub aovs: Vec<Aov>,          // Channels recorded for every canvas unit alongside its color, e.g. for compositing
+    pub seed: u64                // Seeds the random numbers used while tracing
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            num_threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            screen_width: 800,
+            aspect_ratio: 16.0 / 9.0,
+            screen_height: None,
+            canvas_unit_size: 1,
+            render_scale: 1.0,
+            upscale: Upscale::Nearest,
+            fullscreen: false,
+            num_samples: 1,
+            filter: Filter::Box,
+            sample_pattern: SamplePattern::Random,
+            firefly_clamp: None,
+            max_depth: 2,
+            integrator: Integrator::Whitted,
+            pipeline: Pipeline::Immediate,
+            fov: 2.0 * Float::atan(0.5).to_degrees(), // A viewport 1 unit tall, 1 unit in front of the camera
+            background: None,
+            exposure: 1.0,
+            fxaa: false,
+            cas: None,
+            bloom: None,
+            post_effects: Vec::new(),
+            comparison: None,
+            stereo: Stereo::Mono,
+            interocular: 0.2,
+            preview_scale: 4,
+            progressive: false,
+            controls: Controls::default(),
+            gamepad_sensitivity: 1.0,
+            day_length: 60.0,
+            turntable: false,
+            orbit_radius: 5.0,
+            orbit_speed: 1.0,
+            aovs: Vec::new(),
+            seed: 0
+        }
+    }
+}
+
+impl RendererConfig {
+    pub fn screen_height(&self) -> usize {
+        self.screen_height.unwrap_or((self.screen_width as Float / self.aspect_ratio) as usize)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.num_threads == 0 {
+            return Err(ConfigError::ZeroThreads);
+        }
+        if self.num_samples == 0 || self.comparison.and_then(|c| c.num_samples) == Some(0) {
+            return Err(ConfigError::ZeroSamples);
+        }
+        if self.canvas_unit_size == 0 {
+            return Err(ConfigError::ZeroCanvasUnitSize);
+        }
+        if self.preview_scale == 0 {
+            return Err(ConfigError::ZeroPreviewScale);
+        }
+        if !(self.render_scale.is_finite() && self.render_scale > 0.0) {
+            return Err(ConfigError::InvalidRenderScale(self.render_scale));
+        }
+        if !(self.aspect_ratio.is_finite() && self.aspect_ratio > 0.0) {
+            return Err(ConfigError::InvalidAspectRatio(self.aspect_ratio));
+        }
+        if !(self.fov > 0.0 && self.fov < 180.0) {
+            return Err(ConfigError::InvalidFov(self.fov));
+        }
+        if !(self.exposure.is_finite() && self.exposure >= 0.0) {
+            return Err(ConfigError::InvalidExposure(self.exposure));
+        }
+        if let Some(exposure) = self.comparison.and_then(|c| c.exposure).filter(|e| !(e.is_finite() && *e >= 0.0)) {
+            return Err(ConfigError::InvalidExposure(exposure));
+        }
+        if self.comparison.is_some() && self.stereo != Stereo::Mono {
+            return Err(ConfigError::StereoComparison);
+        }
+        if let Some(sharpness) = self.cas.filter(|s| !(0.0..=1.0).contains(s)) {
+            return Err(ConfigError::InvalidSharpness(sharpness));
+        }
+        if let Some(bloom) = self.bloom.filter(|b| ![b.threshold, b.intensity, b.radius].iter().all(|v| v.is_finite() && *v >= 0.0)) {
+            return Err(ConfigError::InvalidBloom(bloom));
+        }
+        if !(self.interocular.is_finite() && self.interocular >= 0.0) {
+            return Err(ConfigError::InvalidInterocular(self.interocular));
+        }
+        if !(self.gamepad_sensitivity.is_finite() && self.gamepad_sensitivity >= 0.0) {
+            return Err(ConfigError::InvalidGamepadSensitivity(self.gamepad_sensitivity));
+        }
+        if !(self.day_length.is_finite() && self.day_length > 0.0) {
+            return Err(ConfigError::InvalidDayLength(self.day_length));
+        }
+        if !(self.orbit_radius.is_finite() && self.orbit_radius > 0.0) {
+            return Err(ConfigError::InvalidOrbitRadius(self.orbit_radius));
+        }
+        if !self.orbit_speed.is_finite() {
+            return Err(ConfigError::InvalidOrbitSpeed(self.orbit_speed));
+        }
+        if let Some(max_depth) = [Some(self.max_depth), self.comparison.and_then(|c| c.max_depth)].into_iter().flatten().find(|d| *d > MAX_DEPTH) {
+            return Err(ConfigError::InvalidMaxDepth(max_depth));
+        }
+        if let Some(factor) = self.firefly_clamp.filter(|factor| factor.is_nan() || *factor < 1.0) {
+            return Err(ConfigError::InvalidFireflyClamp(factor));
+        }
+
+        let (width, height) = (self.screen_width, self.screen_height());
+        if width == 0 || height == 0 {
+            return Err(ConfigError::EmptyWindow { width, height });
+        }
+        if !width.is_multiple_of(self.canvas_unit_size) || !height.is_multiple_of(self.canvas_unit_size) {
+            return Err(ConfigError::CanvasUnitMismatch { width, height, canvas_unit_size: self.canvas_unit_size });
+        }
+
+        Ok(())
+    }
+}
+
+/*
+
+Renderer Builder
+
+Fluent construction of a renderer, e.g. Renderer::builder().screen_width(1280).samples(4).build(scene)
+
+*/
+
+#[derive(Default)]
+pub struct RendererBuilder {
+    config: RendererConfig,
+    display: Option<Box<dyn Display>> // Where frames are presented. Defaults to a window
+}
+
+impl RendererBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn threads(mut self, num_threads: usize) -> Self {
+        self.config.num_threads = num_threads;
+        self
+    }
+
+    pub fn screen_width(mut self, screen_width: usize) -> Self {
+        self.config.screen_width = screen_width;
+        self
+    }
+
+    pub fn aspect_ratio(mut self, aspect_ratio: Float) -> Self {
+        self.config.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    // Set the height outright rather than by the aspect ratio, e.g. to render an image of an exact size
+    pub fn screen_height(mut self, screen_height: usize) -> Self {
+        self.config.screen_height = Some(screen_height);
+        self
+    }
+
+    pub fn canvas_unit_size(mut self, canvas_unit_size: usize) -> Self {
+        self.config.canvas_unit_size = canvas_unit_size;
+        self
+    }
+
+    pub fn render_scale(mut self, render_scale: Float) -> Self {
+        self.config.render_scale = render_scale;
+        self
+    }
+
+    pub fn upscale(mut self, upscale: Upscale) -> Self {
+        self.config.upscale = upscale;
+        self
+    }
+
+    pub fn fullscreen(mut self, fullscreen: bool) -> Self {
+        self.config.fullscreen = fullscreen;
+        self
+    }
+
+    pub fn samples(mut self, num_samples: usize) -> Self {
+        self.config.num_samples = num_samples;
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.config.filter = filter;
+        self
+    }
+
+    pub fn sample_pattern(mut self, sample_pattern: SamplePattern) -> Self {
+        self.config.sample_pattern = sample_pattern;
+        self
+    }
+
+    pub fn firefly_clamp(mut self, factor: Float) -> Self {
+        self.config.firefly_clamp = Some(factor);
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: u32) -> Self {
+        self.config.max_depth = max_depth;
+        self
+    }
+
+    pub fn integrator(mut self, integrator: Integrator) -> Self {
+        self.config.integrator = integrator;
+        self
+    }
+
+    pub fn pipeline(mut self, pipeline: Pipeline) -> Self {
+        self.config.pipeline = pipeline;
+        self
+    }
+
+    pub fn fov(mut self, fov: Float) -> Self {
+        self.config.fov = fov;
+        self
+    }
+
+    pub fn background(mut self, color: impl Into<usize>) -> Self {
+        self.config.background = Some(color.into());
+        self
+    }
+
+    pub fn exposure(mut self, exposure: Float) -> Self {
+        self.config.exposure = exposure;
+        self
+    }
+
+    pub fn fxaa(mut self, fxaa: bool) -> Self {
+        self.config.fxaa = fxaa;
+        self
+    }
+
+    pub fn cas(mut self, sharpness: Float) -> Self {
+        self.config.cas = Some(sharpness);
+        self
+    }
+
+    pub fn bloom(mut self, bloom: Bloom) -> Self {
+        self.config.bloom = Some(bloom);
+        self
+    }
+
+    // Add an effect to apply to every frame, after those added before it
+    pub fn post_effect(mut self, effect: impl PostEffect + 'static) -> Self {
+        self.config.post_effects.push(Arc::new(effect));
+        self
+    }
+
+    pub fn compare(mut self, comparison: Comparison) -> Self {
+        self.config.comparison = Some(comparison);
+        self
+    }
+
+    pub fn stereo(mut self, stereo: Stereo) -> Self {
+        self.config.stereo = stereo;
+        self
+    }
+
+    pub fn interocular(mut self, interocular: Float) -> Self {
+        self.config.interocular = interocular;
+        self
+    }
+
+    pub fn preview_scale(mut self, preview_scale: usize) -> Self {
+        self.config.preview_scale = preview_scale;
+        self
+    }
+
+    pub fn progressive(mut self, progressive: bool) -> Self {
+        self.config.progressive = progressive;
+        self
+    }
+
+    pub fn controls(mut self, controls: Controls) -> Self {
+        self.config.controls = controls;
+        self
+    }
+
+    pub fn gamepad_sensitivity(mut self, sensitivity: Float) -> Self {
+        self.config.gamepad_sensitivity = sensitivity;
+        self
+    }
+
+    pub fn day_length(mut self, day_length: Float) -> Self {
+        self.config.day_length = day_length;
+        self
+    }
+
+    pub fn turntable(mut self, turntable: bool) -> Self {
+        self.config.turntable = turntable;
+        self
+    }
+
+    pub fn orbit(mut self, radius: Float, speed: Float) -> Self {
+        self.config.orbit_radius = radius;
+        self.config.orbit_speed = speed;
+        self
+    }
+
+    // Record an AOV alongside every canvas unit's color
+    pub fn aov(mut self, aov: Aov) -> Self {
+        if !self.config.aovs.contains(&aov) {
+            self.config.aovs.push(aov);
+        }
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = seed;
+        self
+    }
+
+    pub fn config(&self) -> &RendererConfig {
+        &self.config
+    }
+
+    pub fn display(mut self, display: Box<dyn Display>) -> Self {
+        self.display = Some(display);
+        self
+    }
+
+    pub fn build(self, scene: Arc<Scene>) -> Result<Renderer, ConfigError> {
+        let renderer = match self.display {
+            Some(display) => Renderer::with_display(self.config, scene, display),
+            None => Renderer::new(self.config, scene)
+        };
+        renderer.map_err(ConfigError::from)
+    }
+
+    // Trace a single frame without a window and return it. Any display that was set is ignored
+    pub fn render_to_image(self, scene: Arc<Scene>) -> Result<Image, RendererError> {
+        let mut renderer = self.display(Box::new(HeadlessDisplay::new().with_frame_limit(1))).build(scene)?;
+        renderer.run()?;
+        let (pixels, width, height) = renderer.frame_buffer();
+        Ok(Image { pixels: pixels.to_vec(), width, height })
+    }
+}
+
+/*
+
+Config Error
+
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    ZeroThreads,
+    ZeroSamples,
+    ZeroCanvasUnitSize,
+    ZeroPreviewScale,
+    InvalidRenderScale(Float),
+    InvalidAspectRatio(Float),
+    InvalidFov(Float),
+    InvalidExposure(Float),
+    InvalidSharpness(Float),
+    InvalidBloom(Bloom),
+    InvalidFireflyClamp(Float),
+    InvalidMaxDepth(u32),
+    InvalidInterocular(Float),
+    InvalidGamepadSensitivity(Float),
+    InvalidDayLength(Float),
+    InvalidOrbitRadius(Float),
+    InvalidOrbitSpeed(Float),
+    StereoComparison,
+    SharedScene,           // A background was given for a scene something else holds too, so it can't be changed
+    Display(DisplayError), // The display frames are presented on couldn't be set up, e.g. no window could be opened
+    EmptyWindow { width: usize, height: usize },
+    CanvasUnitMismatch { width: usize, height: usize, canvas_unit_size: usize }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ZeroThreads => write!(f, "thread count must be at least 1"),
+            ConfigError::ZeroSamples => write!(f, "sample count must be at least 1"),
+            ConfigError::ZeroCanvasUnitSize => write!(f, "canvas unit size must be at least 1"),
+            ConfigError::ZeroPreviewScale => write!(f, "preview scale must be at least 1"),
+            ConfigError::InvalidRenderScale(s) => write!(f, "render scale must be positive, got {}", s),
+            ConfigError::InvalidAspectRatio(r) => write!(f, "aspect ratio must be positive, got {}", r),
+            ConfigError::InvalidFov(fov) => write!(f, "field of view must be between 0 and 180 degrees, got {}", fov),
+            ConfigError::InvalidExposure(e) => write!(f, "exposure must be non-negative, got {}", e),
+            ConfigError::InvalidSharpness(s) => write!(f, "sharpness must be between 0 and 1, got {}", s),
+            ConfigError::InvalidBloom(b) => write!(f, "bloom threshold, intensity and radius must be non-negative, got {}", b),
+            ConfigError::InvalidFireflyClamp(c) => write!(f, "firefly clamp must be at least 1, got {}", c),
+            ConfigError::InvalidMaxDepth(d) => write!(f, "max depth must be at most {}, got {}", MAX_DEPTH, d),
+            ConfigError::InvalidInterocular(d) => write!(f, "interocular distance must be non-negative, got {}", d),
+            ConfigError::InvalidGamepadSensitivity(s) => write!(f, "gamepad sensitivity must be non-negative, got {}", s),
+            ConfigError::InvalidDayLength(d) => write!(f, "day length must be positive, got {}", d),
+            ConfigError::InvalidOrbitRadius(r) => write!(f, "orbit radius must be positive, got {}", r),
+            ConfigError::InvalidOrbitSpeed(s) => write!(f, "orbit speed must be finite, got {}", s),
+            ConfigError::StereoComparison => write!(f, "comparisons can't be rendered in stereo"),
+            ConfigError::SharedScene => write!(f, "the background can't be changed while the scene is shared"),
+            ConfigError::Display(e) => write!(f, "unable to set up display: {}", e.0),
+            ConfigError::EmptyWindow { width, height } => write!(f, "window dimensions must be non-zero, got {}x{}", width, height),
+            ConfigError::CanvasUnitMismatch { width, height, canvas_unit_size } => {
+                write!(f, "window dimensions {}x{} must be a multiple of canvas unit size {}", width, height, canvas_unit_size)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Display(e) => Some(e.0.as_ref()),
+            _ => None
+        }
+    }
+}
+
+// Why Renderer::new or with_display failed, as a config error: the config itself, a background for a scene that's shared,
+// or otherwise the display, keeping the error behind it
+impl From<RendererError> for ConfigError {
+    fn from(e: RendererError) -> Self {
+        match e {
+            RendererError::Config(e) => e,
+            RendererError::SceneShared => ConfigError::SharedScene,
+            e => ConfigError::Display(DisplayError(Arc::new(e)))
+        }
+    }
+}
+
+// Why the display couldn't be set up, shared so config errors can still be cloned. Errors are only equal to themselves
+#[derive(Debug, Clone)]
+pub struct DisplayError(pub Arc<RendererError>);
+
+impl PartialEq for DisplayError {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{linalg::Vec3d, object::Primitive};
+
+    // Why validate rejects the default config changed by edit
+    fn rejected(edit: impl FnOnce(&mut RendererConfig)) -> ConfigError {
+        let mut config = RendererConfig::default();
+        edit(&mut config);
+        config.validate().expect_err("the config should be rejected")
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert_eq!(RendererConfig::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_zero_threads() {
+        assert_eq!(rejected(|c| c.num_threads = 0), ConfigError::ZeroThreads);
+    }
+
+    #[test]
+    fn rejects_zero_samples() {
+        assert_eq!(rejected(|c| c.num_samples = 0), ConfigError::ZeroSamples);
+        assert_eq!(rejected(|c| c.comparison = Some(Comparison { num_samples: Some(0), ..Default::default() })), ConfigError::ZeroSamples);
+    }
+
+    #[test]
+    fn rejects_zero_canvas_unit_size() {
+        assert_eq!(rejected(|c| c.canvas_unit_size = 0), ConfigError::ZeroCanvasUnitSize);
+    }
+
+    #[test]
+    fn rejects_zero_preview_scale() {
+        assert_eq!(rejected(|c| c.preview_scale = 0), ConfigError::ZeroPreviewScale);
+    }
+
+    #[test]
+    fn rejects_invalid_render_scale() {
+        assert_eq!(rejected(|c| c.render_scale = 0.0), ConfigError::InvalidRenderScale(0.0));
+        assert!(matches!(rejected(|c| c.render_scale = Float::NAN), ConfigError::InvalidRenderScale(_)));
+    }
+
+    #[test]
+    fn rejects_invalid_aspect_ratio() {
+        assert_eq!(rejected(|c| c.aspect_ratio = -1.0), ConfigError::InvalidAspectRatio(-1.0));
+        assert_eq!(rejected(|c| c.aspect_ratio = Float::INFINITY), ConfigError::InvalidAspectRatio(Float::INFINITY));
+    }
+
+    #[test]
+    fn rejects_invalid_fov() {
+        assert_eq!(rejected(|c| c.fov = 0.0), ConfigError::InvalidFov(0.0));
+        assert_eq!(rejected(|c| c.fov = 180.0), ConfigError::InvalidFov(180.0));
+    }
+
+    #[test]
+    fn rejects_invalid_exposure() {
+        assert_eq!(rejected(|c| c.exposure = -0.5), ConfigError::InvalidExposure(-0.5));
+        assert_eq!(rejected(|c| c.comparison = Some(Comparison { exposure: Some(-2.0), ..Default::default() })), ConfigError::InvalidExposure(-2.0));
+    }
+
+    #[test]
+    fn rejects_stereo_comparison() {
+        assert_eq!(rejected(|c| {
+            c.comparison = Some(Comparison::default());
+            c.stereo = Stereo::Anaglyph;
+        }), ConfigError::StereoComparison);
+    }
+
+    #[test]
+    fn rejects_invalid_sharpness() {
+        assert_eq!(rejected(|c| c.cas = Some(1.5)), ConfigError::InvalidSharpness(1.5));
+    }
+
+    #[test]
+    fn rejects_invalid_bloom() {
+        let bloom = Bloom { radius: -1.0, ..Default::default() };
+        assert_eq!(rejected(|c| c.bloom = Some(bloom)), ConfigError::InvalidBloom(bloom));
+    }
+
+    #[test]
+    fn rejects_invalid_interocular() {
+        assert_eq!(rejected(|c| c.interocular = -0.1), ConfigError::InvalidInterocular(-0.1));
+    }
+
+    #[test]
+    fn rejects_invalid_gamepad_sensitivity() {
+        assert_eq!(rejected(|c| c.gamepad_sensitivity = -1.0), ConfigError::InvalidGamepadSensitivity(-1.0));
+    }
+
+    #[test]
+    fn rejects_invalid_day_length() {
+        assert_eq!(rejected(|c| c.day_length = 0.0), ConfigError::InvalidDayLength(0.0));
+    }
+
+    #[test]
+    fn rejects_invalid_orbit() {
+        assert_eq!(rejected(|c| c.orbit_radius = 0.0), ConfigError::InvalidOrbitRadius(0.0));
+        assert_eq!(rejected(|c| c.orbit_speed = Float::INFINITY), ConfigError::InvalidOrbitSpeed(Float::INFINITY));
+    }
+
+    #[test]
+    fn rejects_too_deep_max_depth() {
+        assert_eq!(rejected(|c| c.max_depth = MAX_DEPTH + 1), ConfigError::InvalidMaxDepth(MAX_DEPTH + 1));
+        assert_eq!(rejected(|c| c.comparison = Some(Comparison { max_depth: Some(u32::MAX), ..Default::default() })), ConfigError::InvalidMaxDepth(u32::MAX));
+        assert_eq!(RendererConfig { max_depth: MAX_DEPTH, ..Default::default() }.validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_invalid_firefly_clamp() {
+        assert_eq!(rejected(|c| c.firefly_clamp = Some(0.5)), ConfigError::InvalidFireflyClamp(0.5));
+    }
+
+    #[test]
+    fn rejects_empty_window() {
+        assert_eq!(rejected(|c| c.screen_width = 0), ConfigError::EmptyWindow { width: 0, height: 0 });
+        assert_eq!(rejected(|c| c.screen_height = Some(0)), ConfigError::EmptyWindow { width: 800, height: 0 });
+    }
+
+    #[test]
+    fn rejects_canvas_unit_mismatch() {
+        assert_eq!(rejected(|c| {
+            c.screen_height = Some(450);
+            c.canvas_unit_size = 4;
+        }), ConfigError::CanvasUnitMismatch { width: 800, height: 450, canvas_unit_size: 4 });
+    }
+
+    fn empty_scene() -> Arc<Scene> {
+        Arc::new(Scene::new(Vec3d::new(0.0, 0.0, 0.0), 0x000000, Vec::new(), Vec::<Primitive>::new()).unwrap())
+    }
+
+    #[test]
+    fn builder_returns_config_errors() {
+        let display = Box::new(HeadlessDisplay::new());
+        assert!(matches!(RendererBuilder::new().samples(0).display(display).build(empty_scene()), Err(ConfigError::ZeroSamples)));
+    }
+
+    #[test]
+    fn display_errors_keep_their_source() {
+        let e = ConfigError::from(RendererError::WindowCreation("no display available".into()));
+        let source = e.source().and_then(|source| source.downcast_ref::<RendererError>());
+        assert!(matches!(source, Some(RendererError::WindowCreation(_))));
+        assert_eq!(source.and_then(Error::source).map(ToString::to_string).as_deref(), Some("no display available"));
+        assert_eq!(e.clone(), e);
+    }
+
+    #[test]
+    fn background_replaces_the_scenes() {
+        let image = RendererBuilder::new().threads(1).screen_width(8).screen_height(4).background(0xFF8000usize).render_to_image(empty_scene()).unwrap();
+        assert!(image.pixels.iter().all(|&p| p == 0xFF8000));
+
+        let scene = empty_scene();
+        let display = Box::new(HeadlessDisplay::new());
+        let shared = RendererBuilder::new().background(0xFF8000usize).display(display).build(Arc::clone(&scene));
+        assert!(matches!(shared, Err(ConfigError::SharedScene)));
+    }
+}
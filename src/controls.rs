@@ -0,0 +1,274 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use serde::Deserialize;
+
+use crate::{display::Key, linalg::Float};
+
+/*
+
+Controls
+
+Which keys move the camera and how fast it moves, read from a TOML file so they can be changed without recompiling.
+Everything is optional and falls back to the defaults (WASD to walk, arrow keys to look), e.g.
+
+    move_speed = 0.5         # Distance walked each frame a key is held
+    turn_speed = 5.0         # Degrees turned left or right each frame
+    tilt_speed = 3.0         # Degrees looked up or down each frame
+    mouse_sensitivity = 0.2  # Degrees turned per pixel the mouse is dragged
+
+    [keys]
+    forward = "I"
+    back = "K"
+    left = "J"
+    right = "L"
+    look_left = "left"
+    look_right = "right"
+    look_up = "up"
+    look_down = "down"
+    pause = "P"              # Stops tracing, leaving the last frame on screen, until pressed again
+    samples = ["1", "2", "3", "4"] # Trace 1, 2, 4 or 8 samples per canvas unit
+    fewer_bounces = "minus"
+    more_bounces = "equal"
+    crosshair = "C"          # Shows a crosshair and what's under it in the title bar
+    next_light = "tab"       # Selects the next light that can be moved (or none after the last), moved by the keys below
+    light_forward = "I"
+    light_back = "K"
+    light_left = "J"
+    light_right = "L"
+    light_up = "U"
+    light_down = "O"
+    spawn = "N"              # Drops a random sphere in front of the camera
+    time_of_day = "T"        # Moves the first directional light along a sun arc as time passes, until pressed again
+    fxaa = "F"               # Smooths jagged edges after tracing (FXAA), until pressed again
+    fullscreen = "F11"       # Switches between a window and fullscreen
+    turntable = "R"          # Moves the camera round the point in front of it, until pressed again
+
+Keys are named as they're labelled, e.g. "W", "1", "F5", "space" or "left shift"
+
+*/
+
+#[derive(Clone)]
+pub struct Controls {
+    pub keys: KeyBindings,
+    pub move_speed: Float,
+    pub turn_speed: Float,
+    pub tilt_speed: Float,
+    pub mouse_sensitivity: Float
+}
+
+#[derive(Clone)]
+pub struct KeyBindings {
+    pub forward: Key,
+    pub back: Key,
+    pub left: Key,
+    pub right: Key,
+    pub look_left: Key,
+    pub look_right: Key,
+    pub look_up: Key,
+    pub look_down: Key,
+    pub pause: Key,
+    pub samples: [Key; 4], // Keys that trace 1, 2, 4 and 8 samples per canvas unit
+    pub fewer_bounces: Key,
+    pub more_bounces: Key,
+    pub crosshair: Key,
+    pub next_light: Key,
+    pub light_forward: Key,
+    pub light_back: Key,
+    pub light_left: Key,
+    pub light_right: Key,
+    pub light_up: Key,
+    pub light_down: Key,
+    pub spawn: Key,
+    pub time_of_day: Key,
+    pub fxaa: Key,
+    pub fullscreen: Key,
+    pub turntable: Key
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Self {
+            keys: KeyBindings::default(),
+            move_speed: 0.3,
+            turn_speed: 5.0,
+            tilt_speed: 3.0,
+            mouse_sensitivity: 0.2
+        }
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            forward: Key::W,
+            back: Key::S,
+            left: Key::A,
+            right: Key::D,
+            look_left: Key::Left,
+            look_right: Key::Right,
+            look_up: Key::Up,
+            look_down: Key::Down,
+            pause: Key::P,
+            samples: [Key::Key1, Key::Key2, Key::Key3, Key::Key4],
+            fewer_bounces: Key::Minus,
+            more_bounces: Key::Equal,
+            crosshair: Key::C,
+            next_light: Key::Tab,
+            light_forward: Key::I,
+            light_back: Key::K,
+            light_left: Key::J,
+            light_right: Key::L,
+            light_up: Key::U,
+            light_down: Key::O,
+            spawn: Key::N,
+            time_of_day: Key::T,
+            fxaa: Key::F,
+            fullscreen: Key::F11,
+            turntable: Key::R
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ControlsDesc {
+    #[serde(default)]
+    keys: KeysDesc,
+    move_speed: Option<Float>,
+    turn_speed: Option<Float>,
+    tilt_speed: Option<Float>,
+    mouse_sensitivity: Option<Float>
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct KeysDesc {
+    forward: Option<String>,
+    back: Option<String>,
+    left: Option<String>,
+    right: Option<String>,
+    look_left: Option<String>,
+    look_right: Option<String>,
+    look_up: Option<String>,
+    look_down: Option<String>,
+    pause: Option<String>,
+    samples: Option<[String; 4]>,
+    fewer_bounces: Option<String>,
+    more_bounces: Option<String>,
+    crosshair: Option<String>,
+    next_light: Option<String>,
+    light_forward: Option<String>,
+    light_back: Option<String>,
+    light_left: Option<String>,
+    light_right: Option<String>,
+    light_up: Option<String>,
+    light_down: Option<String>,
+    spawn: Option<String>,
+    time_of_day: Option<String>,
+    fxaa: Option<String>,
+    fullscreen: Option<String>,
+    turntable: Option<String>
+}
+
+impl Controls {
+    // Read the controls stored at path
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ControlsError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    // Controls from the contents of a controls file, with anything left out kept at its default
+    pub fn parse(src: &str) -> Result<Self, ControlsError> {
+        let desc: ControlsDesc = toml::from_str(src).map_err(|e| ControlsError::Parse(e.to_string()))?;
+        let defaults = Self::default();
+
+        let speed = |name: &'static str, value: Option<Float>, default: Float| match value {
+            Some(value) if !(value.is_finite() && value >= 0.0) => Err(ControlsError::InvalidSpeed(name, value)),
+            value => Ok(value.unwrap_or(default))
+        };
+        let key = |name: Option<String>, default: Key| match name {
+            Some(name) => Key::from_name(&name).ok_or(ControlsError::UnknownKey(name)),
+            None => Ok(default)
+        };
+
+        let (keys, default_keys) = (desc.keys, defaults.keys);
+        Ok(Self {
+            keys: KeyBindings {
+                forward: key(keys.forward, default_keys.forward)?,
+                back: key(keys.back, default_keys.back)?,
+                left: key(keys.left, default_keys.left)?,
+                right: key(keys.right, default_keys.right)?,
+                look_left: key(keys.look_left, default_keys.look_left)?,
+                look_right: key(keys.look_right, default_keys.look_right)?,
+                look_up: key(keys.look_up, default_keys.look_up)?,
+                look_down: key(keys.look_down, default_keys.look_down)?,
+                pause: key(keys.pause, default_keys.pause)?,
+                samples: match keys.samples {
+                    Some(names) => {
+                        let [a, b, c, d] = names.map(|name| Key::from_name(&name).ok_or(ControlsError::UnknownKey(name)));
+                        [a?, b?, c?, d?]
+                    }
+                    None => default_keys.samples
+                },
+                fewer_bounces: key(keys.fewer_bounces, default_keys.fewer_bounces)?,
+                more_bounces: key(keys.more_bounces, default_keys.more_bounces)?,
+                crosshair: key(keys.crosshair, default_keys.crosshair)?,
+                next_light: key(keys.next_light, default_keys.next_light)?,
+                light_forward: key(keys.light_forward, default_keys.light_forward)?,
+                light_back: key(keys.light_back, default_keys.light_back)?,
+                light_left: key(keys.light_left, default_keys.light_left)?,
+                light_right: key(keys.light_right, default_keys.light_right)?,
+                light_up: key(keys.light_up, default_keys.light_up)?,
+                light_down: key(keys.light_down, default_keys.light_down)?,
+                spawn: key(keys.spawn, default_keys.spawn)?,
+                time_of_day: key(keys.time_of_day, default_keys.time_of_day)?,
+                fxaa: key(keys.fxaa, default_keys.fxaa)?,
+                fullscreen: key(keys.fullscreen, default_keys.fullscreen)?,
+                turntable: key(keys.turntable, default_keys.turntable)?
+            },
+            move_speed: speed("move_speed", desc.move_speed, defaults.move_speed)?,
+            turn_speed: speed("turn_speed", desc.turn_speed, defaults.turn_speed)?,
+            tilt_speed: speed("tilt_speed", desc.tilt_speed, defaults.tilt_speed)?,
+            mouse_sensitivity: speed("mouse_sensitivity", desc.mouse_sensitivity, defaults.mouse_sensitivity)?
+        })
+    }
+}
+
+/*
+
+Controls Error
+
+*/
+
+#[derive(Debug)]
+pub enum ControlsError {
+    Io(io::Error),
+    Parse(String),
+    UnknownKey(String),
+    InvalidSpeed(&'static str, Float) // The setting and the value it was given
+}
+
+impl fmt::Display for ControlsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlsError::Io(e) => write!(f, "couldn't read controls file: {}", e),
+            ControlsError::Parse(e) => write!(f, "invalid controls file: {}", e),
+            ControlsError::UnknownKey(name) => write!(f, "unknown key \"{}\"", name),
+            ControlsError::InvalidSpeed(name, value) => write!(f, "{} must be non-negative, got {}", name, value)
+        }
+    }
+}
+
+impl Error for ControlsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ControlsError::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for ControlsError {
+    fn from(e: io::Error) -> Self {
+        ControlsError::Io(e)
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::linalg::{Float, Vec3d};
+use crate::object::HitRecord;
+use crate::texture::Texture;
+
+/*
+
+Decals
+
+Images projected onto whatever surfaces are in front of them and blended over their color, e.g. posters, labels and dirt
+marks. A decal projects straight along its direction, like a slide projector with parallel beams, through a box centred
+on its position: width by height across and depth along the direction. Surfaces inside the box that face back towards
+the decal are painted, with the image upright when the direction is horizontal and its top towards -z when it points
+straight down. Transparent parts of the image leave the surface as it was
+
+*/
+
+pub struct Decal {
+    pos: Vec3d,
+    dir: Vec3d,   // Unit direction the image is projected in
+    right: Vec3d, // Unit direction of the image's rows
+    up: Vec3d,    // Unit direction of the image's columns, bottom to top
+    width: Float,
+    height: Float,
+    depth: Float,
+    texture: Arc<Texture>,
+    opacity: Float // Fraction of the image blended over the surface where the image is opaque
+}
+
+impl Decal {
+    pub fn new(pos: Vec3d, dir: Vec3d, (width, height): (Float, Float), texture: Arc<Texture>) -> Self {
+        let dir = dir.normalize();
+        let world_up = if dir.y().abs() > 0.999 { Vec3d::new(0.0, 0.0, dir.y().signum()) } else { Vec3d::new(0.0, 1.0, 0.0) };
+        let right = dir.cross(&world_up).normalize();
+        let up = right.cross(&dir);
+        Self {
+            pos,
+            dir,
+            right,
+            up,
+            width,
+            height,
+            depth: width.max(height),
+            texture,
+            opacity: 1.0
+        }
+    }
+
+    // How far in front of and behind its position the decal reaches, all of it by default
+    pub fn with_depth(mut self, depth: Float) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn with_opacity(mut self, opacity: Float) -> Self {
+        self.opacity = opacity.clamp(0.0, 1.0);
+        self
+    }
+
+    // The color of a surface with the decal painted over it where it's hit, if the decal covers that point
+    pub fn apply(&self, hit: &HitRecord, color: usize) -> usize {
+        // Only surfaces facing the projector, so it doesn't show through on the back of thin objects
+        if &hit.facing_normal() * &self.dir >= 0.0 {
+            return color;
+        }
+
+        let offset = &hit.point - &self.pos;
+        let (u, v) = (&offset * &self.right / self.width + 0.5, &offset * &self.up / self.height + 0.5);
+        if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) || (&offset * &self.dir).abs() > self.depth / 2.0 {
+            return color;
+        }
+
+        let (texel, alpha) = self.texture.sample_with_alpha((u, v));
+        let coverage = alpha * self.opacity;
+        if coverage <= 0.0 {
+            return color;
+        }
+
+        // Blend in linear light, so half-transparent edges don't darken
+        let (base, texel) = (Color::to_linear(color), Color::to_linear(texel));
+        Color::from_linear([0, 1, 2].map(|c| base[c] * (1.0 - coverage) + texel[c] * coverage))
+    }
+}
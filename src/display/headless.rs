@@ -0,0 +1,72 @@
+use crate::error::RendererError;
+
+use super::{Display, Key};
+
+/*
+
+Headless Display
+
+Keeps the most recently presented frame in memory instead of showing it, for batch rendering and tests.
+It never reports input, and closes itself once an optional frame limit is reached
+
+*/
+
+pub struct HeadlessDisplay {
+    frame: Vec<u32>,
+    width: usize,
+    height: usize,
+    frames_presented: usize,
+    frame_limit: Option<usize>
+}
+
+impl HeadlessDisplay {
+    pub fn new() -> Self {
+        Self {
+            frame: Vec::new(),
+            width: 0,
+            height: 0,
+            frames_presented: 0,
+            frame_limit: None
+        }
+    }
+
+    // Close the display after this many frames have been presented
+    pub fn with_frame_limit(mut self, frame_limit: usize) -> Self {
+        self.frame_limit = Some(frame_limit);
+        self
+    }
+
+    // The last presented frame and its dimensions
+    pub fn frame(&self) -> (&[u32], usize, usize) {
+        (&self.frame, self.width, self.height)
+    }
+
+    pub fn frames_presented(&self) -> usize {
+        self.frames_presented
+    }
+}
+
+impl Default for HeadlessDisplay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for HeadlessDisplay {
+    fn is_open(&self) -> bool {
+        self.frame_limit.is_none_or(|limit| self.frames_presented < limit)
+    }
+
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) -> Result<(), RendererError> {
+        self.frame.clear();
+        self.frame.extend_from_slice(buffer);
+        self.width = width;
+        self.height = height;
+        self.frames_presented += 1;
+        Ok(())
+    }
+
+    fn keys_down(&self) -> Vec<Key> {
+        Vec::new()
+    }
+}
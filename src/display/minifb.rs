@@ -0,0 +1,117 @@
+use crate::error::RendererError;
+
+use super::{Display, Key};
+
+/*
+
+Minifb Display
+
+A native window backed by minifb, which can be resized
+
+minifb can't change a window's border once it's open, or tell how big the screen is, so going fullscreen opens a new
+window without a border or title bar in the top left corner of the screen, at the size the window had. Going back opens
+a window where the old one was
+
+*/
+
+pub struct MinifbDisplay {
+    window: minifb::Window,
+    title: String,
+    windowed: Option<(isize, isize)> // Where the window was before it went fullscreen, while it is
+}
+
+impl MinifbDisplay {
+    pub fn new(title: &str, width: usize, height: usize) -> Result<Self, RendererError> {
+        Ok(Self {
+            window: open_window(title, width, height, false)?,
+            title: title.to_string(),
+            windowed: None
+        })
+    }
+}
+
+fn open_window(title: &str, width: usize, height: usize, borderless: bool) -> Result<minifb::Window, RendererError> {
+    let mut window = minifb::Window::new(
+        title,
+        width,
+        height,
+        minifb::WindowOptions { resize: !borderless, borderless, title: !borderless, topmost: borderless, ..Default::default() },
+    )
+    .map_err(|e| RendererError::WindowCreation(Box::new(e)))?;
+
+    window.set_target_fps(60);
+
+    Ok(window)
+}
+
+impl Display for MinifbDisplay {
+    fn is_open(&self) -> bool {
+        self.window.is_open()
+    }
+
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) -> Result<(), RendererError> {
+        self.window.update_with_buffer(buffer, width, height).map_err(|e| RendererError::Present(Box::new(e)))
+    }
+
+    fn keys_down(&self) -> Vec<Key> {
+        self.window.get_keys().into_iter().filter_map(convert_key).collect()
+    }
+
+    fn mouse_pos(&self) -> Option<(f32, f32)> {
+        self.window.get_mouse_pos(minifb::MouseMode::Discard)
+    }
+
+    fn mouse_down(&self) -> bool {
+        self.window.get_mouse_down(minifb::MouseButton::Left)
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.window.set_title(title);
+        self.title = title.to_string();
+    }
+
+    fn scroll(&self) -> f32 {
+        self.window.get_scroll_wheel().map_or(0.0, |(_, y)| y)
+    }
+
+    fn size(&self) -> Option<(usize, usize)> {
+        Some(self.window.get_size())
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool) -> Result<(), RendererError> {
+        if fullscreen == self.windowed.is_some() {
+            return Ok(());
+        }
+
+        let (width, height) = self.window.get_size();
+        let mut window = open_window(&self.title, width, height, fullscreen)?;
+        if fullscreen {
+            self.windowed = Some(self.window.get_position());
+            window.set_position(0, 0);
+        } else if let Some((x, y)) = self.windowed.take() {
+            window.set_position(x, y);
+        }
+        self.window = window;
+        Ok(())
+    }
+}
+
+fn convert_key(key: minifb::Key) -> Option<Key> {
+    use minifb::Key as M;
+
+    Some(match key {
+        M::A => Key::A, M::B => Key::B, M::C => Key::C, M::D => Key::D, M::E => Key::E, M::F => Key::F, M::G => Key::G,
+        M::H => Key::H, M::I => Key::I, M::J => Key::J, M::K => Key::K, M::L => Key::L, M::M => Key::M, M::N => Key::N,
+        M::O => Key::O, M::P => Key::P, M::Q => Key::Q, M::R => Key::R, M::S => Key::S, M::T => Key::T, M::U => Key::U,
+        M::V => Key::V, M::W => Key::W, M::X => Key::X, M::Y => Key::Y, M::Z => Key::Z,
+        M::Key0 => Key::Key0, M::Key1 => Key::Key1, M::Key2 => Key::Key2, M::Key3 => Key::Key3, M::Key4 => Key::Key4,
+        M::Key5 => Key::Key5, M::Key6 => Key::Key6, M::Key7 => Key::Key7, M::Key8 => Key::Key8, M::Key9 => Key::Key9,
+        M::F1 => Key::F1, M::F2 => Key::F2, M::F3 => Key::F3, M::F4 => Key::F4, M::F5 => Key::F5, M::F6 => Key::F6,
+        M::F7 => Key::F7, M::F8 => Key::F8, M::F9 => Key::F9, M::F10 => Key::F10, M::F11 => Key::F11, M::F12 => Key::F12,
+        M::Up => Key::Up, M::Down => Key::Down, M::Left => Key::Left, M::Right => Key::Right,
+        M::Escape => Key::Escape, M::Tab => Key::Tab, M::Space => Key::Space, M::Enter => Key::Enter, M::Backspace => Key::Backspace,
+        M::LeftShift => Key::LeftShift, M::RightShift => Key::RightShift, M::LeftCtrl => Key::LeftCtrl, M::RightCtrl => Key::RightCtrl,
+        M::Minus => Key::Minus, M::Equal => Key::Equal,
+        _ => return None
+    })
+}
@@ -0,0 +1,184 @@
+#[cfg(feature = "minifb")]
+mod minifb;
+#[cfg(feature = "winit")]
+mod winit;
+mod headless;
+#[cfg(feature = "stream")]
+mod stream;
+mod video;
+
+#[cfg(feature = "minifb")]
+pub use self::minifb::MinifbDisplay;
+#[cfg(feature = "winit")]
+pub use self::winit::WinitDisplay;
+pub use headless::HeadlessDisplay;
+#[cfg(feature = "stream")]
+pub use self::stream::StreamDisplay;
+pub use video::VideoDisplay;
+
+use crate::error::RendererError;
+
+/*
+
+Display
+
+A backend that presents rendered frames and reports user input. The renderer only talks to this trait, so the tracer
+doesn't depend on any particular windowing library
+
+*/
+
+pub trait Display {
+    // Whether the display can still show frames, e.g. the window hasn't been closed
+    fn is_open(&self) -> bool;
+
+    // Show a frame of packed 0RGB pixels, row by row from the top left
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) -> Result<(), RendererError>;
+
+    // The keys currently held down
+    fn keys_down(&self) -> Vec<Key>;
+
+    // Pointer position in pixels, if the pointer is over the display
+    fn mouse_pos(&self) -> Option<(f32, f32)> {
+        None
+    }
+
+    // Whether the primary (left) mouse button is held down
+    fn mouse_down(&self) -> bool {
+        false
+    }
+
+    // Show text in the window's title bar, for displays that have one
+    fn set_title(&mut self, _title: &str) {}
+
+    // How far the mouse wheel turned since the last frame was presented, in notches, positive when scrolled up
+    fn scroll(&self) -> f32 {
+        0.0
+    }
+
+    // The size frames are shown at, in pixels, for displays that can be resized (e.g. windows). The renderer traces
+    // frames to fit it from then on
+    fn size(&self) -> Option<(usize, usize)> {
+        None
+    }
+
+    // Switch between a window and covering the screen without a border, for displays that can. The renderer follows
+    // the new size through size()
+    fn set_fullscreen(&mut self, _fullscreen: bool) -> Result<(), RendererError> {
+        Ok(())
+    }
+}
+
+// Title of the window the renderer opens
+pub const WINDOW_TITLE: &str = "Press ESC to exit";
+
+// The window the renderer opens when no display is given. minifb is preferred when both windowing backends are enabled.
+// Without a windowing backend compiled in, frames are kept in memory
+pub fn default_display(width: usize, height: usize) -> Result<Box<dyn Display>, RendererError> {
+    #[cfg(feature = "minifb")]
+    {
+        Ok(Box::new(MinifbDisplay::new(WINDOW_TITLE, width, height)?))
+    }
+    #[cfg(all(feature = "winit", not(feature = "minifb")))]
+    {
+        Ok(Box::new(WinitDisplay::new(WINDOW_TITLE, width, height)?))
+    }
+    #[cfg(not(any(feature = "minifb", feature = "winit")))]
+    {
+        let _ = (width, height);
+        Ok(Box::new(HeadlessDisplay::new()))
+    }
+}
+
+// Keys understood by the renderer, independent of the backend
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Up, Down, Left, Right,
+    Escape, Tab, Space, Enter, Backspace,
+    LeftShift, RightShift, LeftCtrl, RightCtrl,
+    Minus, Equal
+}
+
+impl Key {
+    // Look up a key by the name it's written with in config files, e.g. "W", "1", "F5" or "left shift", ignoring case,
+    // spaces, underscores and dashes
+    pub fn from_name(name: &str) -> Option<Key> {
+        let name: String = name
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '_' | '-'))
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+
+        if let [c @ (b'A'..=b'Z' | b'0'..=b'9')] = name.as_bytes() {
+            let code = if c.is_ascii_digit() { format!("Digit{}", *c as char) } else { format!("Key{}", *c as char) };
+            return Key::from_dom_code(&code);
+        }
+        if name.starts_with('F') && name[1..].parse::<usize>().is_ok() {
+            return Key::from_dom_code(&name);
+        }
+
+        Some(match name.as_str() {
+            "UP" => Key::Up,
+            "DOWN" => Key::Down,
+            "LEFT" => Key::Left,
+            "RIGHT" => Key::Right,
+            "ESCAPE" | "ESC" => Key::Escape,
+            "TAB" => Key::Tab,
+            "SPACE" => Key::Space,
+            "ENTER" | "RETURN" => Key::Enter,
+            "BACKSPACE" => Key::Backspace,
+            "LEFTSHIFT" | "SHIFT" => Key::LeftShift,
+            "RIGHTSHIFT" => Key::RightShift,
+            "LEFTCTRL" | "CTRL" => Key::LeftCtrl,
+            "RIGHTCTRL" => Key::RightCtrl,
+            "MINUS" => Key::Minus,
+            "EQUAL" => Key::Equal,
+            _ => return None
+        })
+    }
+
+    // Look up a key by its DOM KeyboardEvent.code, e.g. "KeyW" or "ArrowUp", for displays driven from a browser
+    pub fn from_dom_code(code: &str) -> Option<Key> {
+        const LETTERS: [Key; 26] = [
+            Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+            Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z
+        ];
+        const DIGITS: [Key; 10] = [Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9];
+        const FUNCTION: [Key; 12] = [Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12];
+
+        if let Some(letter) = code.strip_prefix("Key") {
+            if let [c @ b'A'..=b'Z'] = letter.as_bytes() {
+                return Some(LETTERS[(c - b'A') as usize]);
+            }
+        }
+        if let Some(digit) = code.strip_prefix("Digit") {
+            if let [c @ b'0'..=b'9'] = digit.as_bytes() {
+                return Some(DIGITS[(c - b'0') as usize]);
+            }
+        }
+        if let Some(n) = code.strip_prefix('F').and_then(|n| n.parse::<usize>().ok()) {
+            return FUNCTION.get(n.wrapping_sub(1)).copied();
+        }
+
+        Some(match code {
+            "ArrowUp" => Key::Up,
+            "ArrowDown" => Key::Down,
+            "ArrowLeft" => Key::Left,
+            "ArrowRight" => Key::Right,
+            "Escape" => Key::Escape,
+            "Tab" => Key::Tab,
+            "Space" => Key::Space,
+            "Enter" => Key::Enter,
+            "Backspace" => Key::Backspace,
+            "ShiftLeft" => Key::LeftShift,
+            "ShiftRight" => Key::RightShift,
+            "ControlLeft" => Key::LeftCtrl,
+            "ControlRight" => Key::RightCtrl,
+            "Minus" => Key::Minus,
+            "Equal" => Key::Equal,
+            _ => return None
+        })
+    }
+}
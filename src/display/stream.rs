@@ -0,0 +1,187 @@
+use std::{
+    collections::HashSet,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex},
+    thread,
+    time::Duration
+};
+
+use tungstenite::{Bytes, Message, WebSocket};
+
+use crate::{error::RendererError, image};
+
+use super::{Display, Key};
+
+/*
+
+Stream Display
+
+Serves frames to browsers, so a headless machine can render while someone watches from elsewhere. Visiting the address
+serves a page that connects back over a WebSocket at /ws, receives every presented frame as a PNG and sends its key
+presses back to control the camera
+
+*/
+
+pub struct StreamDisplay {
+    addr: SocketAddr,
+    shared: Arc<Shared>
+}
+
+// State shared between the renderer and the connection threads
+struct Shared {
+    frame: Mutex<(u64, Bytes)>, // The latest frame as a PNG, and a count that increments with every frame
+    keys: Mutex<HashSet<Key>>,
+    clients: AtomicUsize
+}
+
+impl StreamDisplay {
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Self, RendererError> {
+        let listener = TcpListener::bind(addr).map_err(|e| RendererError::WindowCreation(Box::new(e)))?;
+        let addr = listener.local_addr().map_err(|e| RendererError::WindowCreation(Box::new(e)))?;
+
+        let shared = Arc::new(Shared {
+            frame: Mutex::new((0, Bytes::new())),
+            keys: Mutex::new(HashSet::new()),
+            clients: AtomicUsize::new(0)
+        });
+
+        let accept_shared = Arc::clone(&shared);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let shared = Arc::clone(&accept_shared);
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, &shared) {
+                        log::debug!("Stream connection closed: {}", e);
+                    }
+                });
+            }
+        });
+
+        log::info!("Streaming frames at http://{}", addr);
+        Ok(Self { addr, shared })
+    }
+
+    // The address being served, useful when bound to port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Display for StreamDisplay {
+    fn is_open(&self) -> bool {
+        true
+    }
+
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) -> Result<(), RendererError> {
+        // Don't spend time encoding frames nobody is watching
+        if self.shared.clients.load(Ordering::Relaxed) == 0 {
+            return Ok(());
+        }
+
+        let mut png = Vec::new();
+        image::write_png(&mut png, buffer, width, height).map_err(|e| RendererError::Present(Box::new(e)))?;
+
+        let mut frame = self.shared.frame.lock().unwrap();
+        *frame = (frame.0 + 1, Bytes::from(png));
+        Ok(())
+    }
+
+    fn keys_down(&self) -> Vec<Key> {
+        self.shared.keys.lock().unwrap().iter().copied().collect()
+    }
+}
+
+fn handle_connection(stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    let mut request_start = [0; 8];
+    let n = stream.peek(&mut request_start)?;
+
+    if request_start[..n].starts_with(b"GET /ws ") {
+        let socket = tungstenite::accept(stream).map_err(|e| io::Error::other(e.to_string()))?;
+
+        shared.clients.fetch_add(1, Ordering::Relaxed);
+        let result = stream_frames(socket, shared);
+        shared.clients.fetch_sub(1, Ordering::Relaxed);
+
+        // Release anything the viewer was holding down
+        shared.keys.lock().unwrap().clear();
+        result
+    } else {
+        serve_page(stream)
+    }
+}
+
+// Send every new frame and apply key messages ("down KeyW", "up KeyW" or "clear") until the viewer disconnects
+fn stream_frames(mut socket: WebSocket<TcpStream>, shared: &Shared) -> io::Result<()> {
+    // Reads time out so new frames are sent without waiting for input
+    socket.get_ref().set_read_timeout(Some(Duration::from_millis(10)))?;
+    let mut frames_sent = 0;
+
+    loop {
+        let (frame_count, png) = shared.frame.lock().unwrap().clone();
+        if frame_count != frames_sent {
+            frames_sent = frame_count;
+            socket.send(Message::Binary(png)).map_err(to_io)?;
+        }
+
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                let mut keys = shared.keys.lock().unwrap();
+                match text.as_str().split_once(' ') {
+                    Some(("down", code)) => keys.extend(Key::from_dom_code(code)),
+                    Some(("up", code)) => {
+                        if let Some(key) = Key::from_dom_code(code) {
+                            keys.remove(&key);
+                        }
+                    }
+                    _ if text.as_str() == "clear" => keys.clear(),
+                    _ => {}
+                }
+            }
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e)) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {}
+            Err(e) => return Err(to_io(e))
+        }
+    }
+}
+
+fn serve_page(stream: TcpStream) -> io::Result<()> {
+    // Skip the request headers
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut stream = &stream;
+    write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", PAGE.len(), PAGE)?;
+    stream.flush()
+}
+
+fn to_io(e: tungstenite::Error) -> io::Error {
+    io::Error::other(e.to_string())
+}
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head><title>Ray Tracer</title></head>
+<body style="margin: 0; background: #000; display: flex; justify-content: center; align-items: center; height: 100vh">
+<img id="frame" style="max-width: 100%; max-height: 100%; image-rendering: pixelated">
+<script>
+const frame = document.getElementById("frame");
+const socket = new WebSocket(`ws://${location.host}/ws`);
+socket.binaryType = "blob";
+socket.onmessage = (e) => {
+    const url = URL.createObjectURL(e.data);
+    frame.onload = () => URL.revokeObjectURL(url);
+    frame.src = url;
+};
+const send = (msg) => socket.readyState === WebSocket.OPEN && socket.send(msg);
+addEventListener("keydown", (e) => { if (!e.repeat) send(`down ${e.code}`); e.preventDefault(); });
+addEventListener("keyup", (e) => send(`up ${e.code}`));
+addEventListener("blur", () => send("clear"));
+</script>
+</body>
+</html>
+"#;
@@ -0,0 +1,95 @@
+use std::{
+    io::{BufWriter, Write},
+    path::Path,
+    process::{Child, ChildStdin, Command, Stdio}
+};
+
+use crate::error::RendererError;
+
+use super::{Display, Key};
+
+/*
+
+Video Display
+
+Records every frame presented to another display into a video file by piping raw frames to an ffmpeg process, e.g. to
+capture a session in the window or a sequence of headless frames. The container and codec follow the file extension
+(.mp4, .webm, ...). ffmpeg must be installed and on the PATH
+
+*/
+
+pub struct VideoDisplay {
+    inner: Box<dyn Display>,
+    ffmpeg: Child,
+    stdin: Option<BufWriter<ChildStdin>>,
+    width: usize,
+    height: usize
+}
+
+impl VideoDisplay {
+    pub fn new(inner: Box<dyn Display>, path: impl AsRef<Path>, width: usize, height: usize, fps: u32) -> Result<Self, RendererError> {
+        let mut ffmpeg = Command::new("ffmpeg")
+            .args(["-loglevel", "error", "-y"])
+            // Packed 0RGB pixels are stored as B, G, R, 0 bytes
+            .args(["-f", "rawvideo", "-pix_fmt", "bgr0", "-s", &format!("{}x{}", width, height), "-r", &fps.to_string(), "-i", "-"])
+            // Most players only support 4:2:0 chroma subsampling, which needs even dimensions
+            .args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2", "-pix_fmt", "yuv420p"])
+            .arg(path.as_ref())
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| RendererError::VideoEncoder(format!("couldn't run ffmpeg: {}", e).into()))?;
+
+        let stdin = ffmpeg.stdin.take().map(BufWriter::new);
+
+        Ok(Self { inner, ffmpeg, stdin, width, height })
+    }
+}
+
+impl Display for VideoDisplay {
+    fn is_open(&self) -> bool {
+        self.inner.is_open()
+    }
+
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) -> Result<(), RendererError> {
+        if (width, height) != (self.width, self.height) {
+            return Err(RendererError::Present(
+                format!("video is {}x{}, can't record a {}x{} frame", self.width, self.height, width, height).into()
+            ));
+        }
+
+        if let Some(stdin) = self.stdin.as_mut() {
+            for p in buffer {
+                stdin.write_all(&p.to_le_bytes()).map_err(|e| RendererError::Present(Box::new(e)))?;
+            }
+        }
+
+        self.inner.present(buffer, width, height)
+    }
+
+    fn keys_down(&self) -> Vec<Key> {
+        self.inner.keys_down()
+    }
+
+    fn mouse_pos(&self) -> Option<(f32, f32)> {
+        self.inner.mouse_pos()
+    }
+
+    fn mouse_down(&self) -> bool {
+        self.inner.mouse_down()
+    }
+}
+
+impl Drop for VideoDisplay {
+    // Closing ffmpeg's input ends the video, then wait for the file to be written
+    fn drop(&mut self) {
+        if let Some(mut stdin) = self.stdin.take() {
+            let _ = stdin.flush();
+        }
+
+        match self.ffmpeg.wait() {
+            Ok(status) if status.success() => {}
+            Ok(status) => log::error!("ffmpeg exited with {}", status),
+            Err(e) => log::error!("Couldn't wait for ffmpeg: {}", e)
+        }
+    }
+}
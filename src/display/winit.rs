@@ -0,0 +1,251 @@
+use std::{collections::HashSet, num::NonZeroU32, rc::Rc, time::Duration};
+
+use softbuffer::{Context, Surface};
+use winit::{
+    application::ApplicationHandler,
+    dpi::LogicalSize,
+    event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+    event_loop::{ActiveEventLoop, EventLoop},
+    keyboard::{KeyCode, PhysicalKey},
+    platform::pump_events::{EventLoopExtPumpEvents, PumpStatus},
+    window::{Fullscreen, Window, WindowId}
+};
+
+use crate::error::RendererError;
+
+use super::{Display, Key};
+
+/*
+
+Winit Display
+
+A native window created with winit and drawn to with softbuffer. Frames are scaled to the window's physical size when
+presented, so HiDPI screens and resized windows are handled. Window events are processed every time a frame is presented
+
+Fullscreen covers the monitor the window is on without changing its video mode (borderless)
+
+*/
+
+pub struct WinitDisplay {
+    event_loop: EventLoop<()>,
+    state: WindowState
+}
+
+struct WindowState {
+    title: String,
+    width: usize,
+    height: usize,
+    window: Option<Rc<Window>>,
+    surface: Option<Surface<Rc<Window>, Rc<Window>>>,
+    error: Option<RendererError>, // Set when the window couldn't be created while handling events
+    open: bool,
+    keys: HashSet<Key>,
+    mouse_pos: Option<(f32, f32)>, // In physical pixels of the window
+    mouse_down: bool,
+    scroll: f32 // Notches scrolled since the last frame was presented
+}
+
+impl WinitDisplay {
+    pub fn new(title: &str, width: usize, height: usize) -> Result<Self, RendererError> {
+        let event_loop = EventLoop::new().map_err(|e| RendererError::WindowCreation(Box::new(e)))?;
+
+        let mut display = Self {
+            event_loop,
+            state: WindowState {
+                title: title.to_string(),
+                width,
+                height,
+                window: None,
+                surface: None,
+                error: None,
+                open: true,
+                keys: HashSet::new(),
+                mouse_pos: None,
+                mouse_down: false,
+                scroll: 0.0
+            }
+        };
+
+        // The window is created once the event loop reports that the application has resumed
+        display.pump_events();
+        if let Some(e) = display.state.error.take() {
+            return Err(e);
+        }
+
+        Ok(display)
+    }
+
+    fn pump_events(&mut self) {
+        if let PumpStatus::Exit(_) = self.event_loop.pump_app_events(Some(Duration::ZERO), &mut self.state) {
+            self.state.open = false;
+        }
+    }
+}
+
+impl Display for WinitDisplay {
+    fn is_open(&self) -> bool {
+        self.state.open
+    }
+
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) -> Result<(), RendererError> {
+        if let (Some(window), Some(surface)) = (&self.state.window, &mut self.state.surface) {
+            let size = window.inner_size();
+            if let (Some(surface_width), Some(surface_height)) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) {
+                surface.resize(surface_width, surface_height).map_err(present_error)?;
+
+                let mut surface_buffer = surface.buffer_mut().map_err(present_error)?;
+                let (surface_width, surface_height) = (size.width as usize, size.height as usize);
+
+                // Nearest neighbour scale the frame to the window
+                for y in 0..surface_height {
+                    let src_row = y * height / surface_height;
+                    for x in 0..surface_width {
+                        surface_buffer[y * surface_width + x] = buffer[src_row * width + x * width / surface_width];
+                    }
+                }
+
+                surface_buffer.present().map_err(present_error)?;
+            }
+        }
+
+        self.state.width = width;
+        self.state.height = height;
+        self.state.scroll = 0.0;
+        self.pump_events();
+
+        match self.state.error.take() {
+            Some(e) => Err(e),
+            None => Ok(())
+        }
+    }
+
+    fn keys_down(&self) -> Vec<Key> {
+        self.state.keys.iter().copied().collect()
+    }
+
+    // Mapped from window pixels to frame pixels
+    fn mouse_pos(&self) -> Option<(f32, f32)> {
+        let size = self.state.window.as_ref()?.inner_size();
+        let (x, y) = self.state.mouse_pos?;
+        Some((
+            x * self.state.width as f32 / size.width.max(1) as f32,
+            y * self.state.height as f32 / size.height.max(1) as f32
+        ))
+    }
+
+    fn mouse_down(&self) -> bool {
+        self.state.mouse_down
+    }
+
+    fn set_title(&mut self, title: &str) {
+        if let Some(window) = &self.state.window {
+            window.set_title(title);
+        }
+        self.state.title = title.to_string();
+    }
+
+    fn scroll(&self) -> f32 {
+        self.state.scroll
+    }
+
+    // In logical pixels, as the window was opened with, so HiDPI screens aren't traced at several times the resolution
+    fn size(&self) -> Option<(usize, usize)> {
+        let window = self.state.window.as_ref()?;
+        let size = window.inner_size().to_logical::<f64>(window.scale_factor());
+        Some((size.width.round() as usize, size.height.round() as usize))
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool) -> Result<(), RendererError> {
+        if let Some(window) = &self.state.window {
+            window.set_fullscreen(fullscreen.then_some(Fullscreen::Borderless(None)));
+        }
+        Ok(())
+    }
+}
+
+impl ApplicationHandler for WindowState {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+
+        let attributes = Window::default_attributes()
+            .with_title(self.title.clone())
+            .with_inner_size(LogicalSize::new(self.width as f64, self.height as f64));
+
+        let result = event_loop.create_window(attributes)
+            .map_err(|e| RendererError::WindowCreation(Box::new(e)))
+            .and_then(|window| {
+                let window = Rc::new(window);
+                let context = Context::new(window.clone()).map_err(|e| RendererError::WindowCreation(e.to_string().into()))?;
+                let surface = Surface::new(&context, window.clone()).map_err(|e| RendererError::WindowCreation(e.to_string().into()))?;
+                Ok((window, surface))
+            });
+
+        match result {
+            Ok((window, surface)) => {
+                self.window = Some(window);
+                self.surface = Some(surface);
+            }
+            Err(e) => {
+                self.error = Some(e);
+                self.open = false;
+            }
+        }
+    }
+
+    fn window_event(&mut self, _event_loop: &ActiveEventLoop, _window_id: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => self.open = false,
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let PhysicalKey::Code(code) = event.physical_key {
+                    if let Some(key) = convert_key(code) {
+                        match event.state {
+                            ElementState::Pressed => self.keys.insert(key),
+                            ElementState::Released => self.keys.remove(&key)
+                        };
+                    }
+                }
+            }
+            // Don't keep keys held down after the window loses focus, since their release won't be reported
+            WindowEvent::Focused(false) => self.keys.clear(),
+            WindowEvent::CursorMoved { position, .. } => self.mouse_pos = Some((position.x as f32, position.y as f32)),
+            WindowEvent::CursorLeft { .. } => self.mouse_pos = None,
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => self.mouse_down = state == ElementState::Pressed,
+            // Touchpads scroll by pixels, counted here as a notch every line's worth of them
+            WindowEvent::MouseWheel { delta, .. } => self.scroll += match delta {
+                MouseScrollDelta::LineDelta(_, lines) => lines,
+                MouseScrollDelta::PixelDelta(pixels) => pixels.y as f32 / PIXELS_PER_LINE
+            },
+            _ => {}
+        }
+    }
+}
+
+const PIXELS_PER_LINE: f32 = 20.0;
+
+// softbuffer errors can hold raw window handles, which can't be sent across threads, so only their message is kept
+fn present_error(e: softbuffer::SoftBufferError) -> RendererError {
+    RendererError::Present(e.to_string().into())
+}
+
+fn convert_key(code: KeyCode) -> Option<Key> {
+    use KeyCode as C;
+
+    Some(match code {
+        C::KeyA => Key::A, C::KeyB => Key::B, C::KeyC => Key::C, C::KeyD => Key::D, C::KeyE => Key::E, C::KeyF => Key::F,
+        C::KeyG => Key::G, C::KeyH => Key::H, C::KeyI => Key::I, C::KeyJ => Key::J, C::KeyK => Key::K, C::KeyL => Key::L,
+        C::KeyM => Key::M, C::KeyN => Key::N, C::KeyO => Key::O, C::KeyP => Key::P, C::KeyQ => Key::Q, C::KeyR => Key::R,
+        C::KeyS => Key::S, C::KeyT => Key::T, C::KeyU => Key::U, C::KeyV => Key::V, C::KeyW => Key::W, C::KeyX => Key::X,
+        C::KeyY => Key::Y, C::KeyZ => Key::Z,
+        C::Digit0 => Key::Key0, C::Digit1 => Key::Key1, C::Digit2 => Key::Key2, C::Digit3 => Key::Key3, C::Digit4 => Key::Key4,
+        C::Digit5 => Key::Key5, C::Digit6 => Key::Key6, C::Digit7 => Key::Key7, C::Digit8 => Key::Key8, C::Digit9 => Key::Key9,
+        C::F1 => Key::F1, C::F2 => Key::F2, C::F3 => Key::F3, C::F4 => Key::F4, C::F5 => Key::F5, C::F6 => Key::F6,
+        C::F7 => Key::F7, C::F8 => Key::F8, C::F9 => Key::F9, C::F10 => Key::F10, C::F11 => Key::F11, C::F12 => Key::F12,
+        C::ArrowUp => Key::Up, C::ArrowDown => Key::Down, C::ArrowLeft => Key::Left, C::ArrowRight => Key::Right,
+        C::Escape => Key::Escape, C::Tab => Key::Tab, C::Space => Key::Space, C::Enter => Key::Enter, C::Backspace => Key::Backspace,
+        C::ShiftLeft => Key::LeftShift, C::ShiftRight => Key::RightShift, C::ControlLeft => Key::LeftCtrl, C::ControlRight => Key::RightCtrl,
+        C::Minus => Key::Minus, C::Equal => Key::Equal,
+        _ => return None
+    })
+}
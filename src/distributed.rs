@@ -0,0 +1,618 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt,
+    io::{self, BufReader, BufWriter, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration
+};
+
+use log::{info, warn};
+
+use crate::{config::RendererConfig, display::HeadlessDisplay, filter::Filter, integrator::Integrator, linalg::Float, sample_pattern::SamplePattern, progress::Progress, scene_file::{self, LoadOptions}, utils::Instant, Renderer};
+
+/*
+
+Distributed Rendering
+
+Splits a frame into bands of rows and farms them out to workers over TCP. The coordinator sends each worker the scene
+file and render settings once, then hands out bands one at a time as workers finish them, so faster machines take on more
+of the frame. A band is given to another worker if the connection to its worker is lost.
+
+Workers trace bands exactly as a local render would, so the assembled frame matches a render on a single machine.
+Scenes that read files (meshes, fonts, point clouds or images) are refused, as the files would be looked for on each
+worker, which would also hand any image a worker can open back to whoever connects to it.
+
+Each side first sends the protocol's magic bytes and version, so anything else connecting is turned away before a
+message is read. Messages are then a tag byte followed by little endian fields:
+    Job   (0): scene source, screen width, aspect ratio, canvas unit size, samples, max depth, fov, exposure, seed
+    Band  (1): first row, end row
+    Rows  (2): first row, end row, pixels
+    Error (3): message
+
+*/
+
+const ROWS_PER_BAND: usize = 16;
+
+const MAGIC: &[u8; 4] = b"RTDR";
+const VERSION: u64 = 1;
+
+const MAX_STRING_LEN: usize = 64 << 20; // Longest scene source or error message accepted, in bytes
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10); // How long a worker waits for a coordinator to send its job
+const BAND_TIMEOUT: Duration = Duration::from_secs(300); // How long a coordinator waits for a worker to trace a band
+
+const TAG_JOB: u8 = 0;
+const TAG_BAND: u8 = 1;
+const TAG_ROWS: u8 = 2;
+const TAG_ERROR: u8 = 3;
+
+// Workers build scenes without reading files, as the files would be looked for on their own machine
+fn scene_options() -> LoadOptions {
+    LoadOptions { no_files: true, ..Default::default() }
+}
+
+// Render a frame of the scene described by scene_src on the given workers, returning packed 0RGB pixels the size of the screen
+pub fn render(
+    scene_src: &str,
+    config: &RendererConfig,
+    workers: &[String],
+    mut progress_callback: impl FnMut(&Progress)
+) -> Result<Vec<u32>, DistributedError> {
+    // Fail before connecting if the job can't be rendered, instead of once per worker
+    config.validate().map_err(|e| DistributedError::InvalidJob(e.to_string()))?;
+    scene_file::parse_with(scene_src, &scene_options()).map_err(|e| DistributedError::InvalidJob(e.to_string()))?;
+
+    let start = Instant::now();
+    let (screen_width, screen_height) = (config.screen_width, config.screen_height());
+    let (canvas_width, canvas_height) = (screen_width / config.canvas_unit_size, screen_height / config.canvas_unit_size);
+
+    let queue: VecDeque<(usize, usize)> = (0..canvas_height)
+        .step_by(ROWS_PER_BAND)
+        .map(|row| (row, (row + ROWS_PER_BAND).min(canvas_height)))
+        .collect();
+    let bands_total = queue.len();
+
+    let bands = Arc::new(Mutex::new(Bands { queue, remaining: bands_total }));
+    let canvas = Arc::new(Mutex::new(vec![0; canvas_width * canvas_height]));
+    let (done_tx, done_rx) = mpsc::channel();
+
+    let mut handles = vec![];
+    for addr in workers {
+        let addr = addr.clone();
+        let job = encode_job(scene_src, config);
+        let bands = Arc::clone(&bands);
+        let canvas = Arc::clone(&canvas);
+        let done_tx = done_tx.clone();
+
+        handles.push(thread::spawn(move || {
+            if let Err(e) = drive_worker(&addr, &job, &bands, &canvas, canvas_width, &done_tx) {
+                warn!("Worker {} failed: {}", addr, e);
+                return Err(e);
+            }
+            Ok(())
+        }));
+    }
+    drop(done_tx);
+
+    // Receiving stops once every worker has stopped
+    for (bands_finished, _) in done_rx.into_iter().enumerate() {
+        progress_callback(&Progress { tiles_done: bands_finished + 1, tiles_total: bands_total, elapsed: start.elapsed() });
+    }
+
+    let mut last_error = None;
+    for handle in handles {
+        if let Err(e) = handle.join().map_err(|_| DistributedError::Worker("worker thread panicked".into()))? {
+            last_error = Some(e);
+        }
+    }
+
+    // Bands left over means every worker failed
+    if bands.lock().unwrap().remaining > 0 {
+        return Err(last_error.unwrap_or(DistributedError::NoWorkers));
+    }
+
+    info!("Rendered frame on {} workers in {:.2?}", workers.len(), start.elapsed());
+
+    // Scale canvas units up to screen pixels
+    let canvas = canvas.lock().unwrap();
+    let unit = config.canvas_unit_size;
+    Ok((0..screen_height).flat_map(|y| (0..screen_width).map(move |x| (y, x)))
+        .map(|(y, x)| canvas[(y / unit) * canvas_width + x / unit])
+        .collect())
+}
+
+// The bands of a frame still waiting for a worker, and the number not yet traced (including those being traced)
+struct Bands {
+    queue: VecDeque<(usize, usize)>,
+    remaining: usize
+}
+
+// Hand bands to one worker until every band is traced
+fn drive_worker(
+    addr: &str,
+    job: &[u8],
+    bands: &Mutex<Bands>,
+    canvas: &Mutex<Vec<u32>>,
+    canvas_width: usize,
+    done_tx: &mpsc::Sender<()>
+) -> Result<(), DistributedError> {
+    let stream = TcpStream::connect(addr)?;
+    // A worker that stops answering gives its band back rather than holding up the frame
+    stream.set_read_timeout(Some(BAND_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+
+    write_hello(&mut writer)?;
+    writer.flush()?;
+    read_hello(&mut reader)?;
+    writer.write_all(job)?;
+
+    loop {
+        let band = {
+            let mut bands = bands.lock().unwrap();
+            if bands.remaining == 0 {
+                return Ok(());
+            }
+            bands.queue.pop_front()
+        };
+
+        // Other workers are still tracing the last bands, wait in case one of them fails and its band comes back
+        let Some(band) = band else {
+            thread::sleep(Duration::from_millis(10));
+            continue;
+        };
+
+        match trace_band(&mut reader, &mut writer, band, canvas_width) {
+            Ok(pixels) => {
+                canvas.lock().unwrap()[band.0 * canvas_width..band.1 * canvas_width].copy_from_slice(&pixels);
+                bands.lock().unwrap().remaining -= 1;
+                let _ = done_tx.send(());
+            }
+            Err(e) => {
+                // Let another worker trace it
+                bands.lock().unwrap().queue.push_back(band);
+                return Err(e);
+            }
+        }
+    }
+}
+
+fn trace_band(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    band: (usize, usize),
+    canvas_width: usize
+) -> Result<Vec<u32>, DistributedError> {
+    write_band(writer, band)?;
+    writer.flush()?;
+
+    match read_u8(reader)? {
+        TAG_ROWS => read_rows(reader, band, canvas_width),
+        TAG_ERROR => Err(DistributedError::Worker(read_string(reader)?)),
+        tag => Err(DistributedError::Protocol(format!("unexpected message {}", tag)))
+    }
+}
+
+// Accept coordinators on addr and trace the bands they send, using num_threads threads. Runs until the listener fails
+pub fn serve_worker(addr: impl ToSocketAddrs, num_threads: usize) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    info!("Worker listening on {}", listener.local_addr()?);
+    serve(listener, num_threads)
+}
+
+fn serve(listener: TcpListener, num_threads: usize) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let peer = stream.peer_addr()?;
+        info!("Coordinator {} connected", peer);
+
+        // Coordinators are served one at a time, each job already uses every thread
+        match serve_coordinator(stream, num_threads) {
+            Ok(()) => info!("Coordinator {} finished", peer),
+            Err(e) => warn!("Coordinator {} disconnected: {}", peer, e)
+        }
+    }
+
+    Ok(())
+}
+
+fn serve_coordinator(stream: TcpStream, num_threads: usize) -> Result<(), DistributedError> {
+    // Anything that connects without sending a job soon is let go, so the next coordinator isn't kept waiting
+    stream.set_read_timeout(Some(HELLO_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream.try_clone()?);
+
+    read_hello(&mut reader)?;
+    write_hello(&mut writer)?;
+    writer.flush()?;
+
+    if read_u8(&mut reader)? != TAG_JOB {
+        return Err(DistributedError::Protocol("expected a job".into()));
+    }
+    let (scene_src, config) = decode_job(&mut reader, num_threads)?;
+    // The coordinator may wait a while between bands when there are none left to hand out
+    stream.set_read_timeout(None)?;
+
+    let renderer = scene_file::parse_with(&scene_src, &scene_options())
+        .map_err(|e| DistributedError::Worker(e.to_string()))
+        .and_then(|scene| {
+            Renderer::with_display(config, Arc::new(scene), Box::new(HeadlessDisplay::new()))
+                .map_err(|e| DistributedError::Worker(e.to_string()))
+        });
+
+    let mut renderer = match renderer {
+        Ok(renderer) => renderer,
+        Err(e) => {
+            // The coordinator reports the error when it asks for its first band
+            read_u8(&mut reader)?;
+            writer.write_all(&[TAG_ERROR])?;
+            write_string(&mut writer, &e.to_string())?;
+            writer.flush()?;
+            return Err(e);
+        }
+    };
+
+    loop {
+        // The coordinator closes the connection once there are no bands left
+        let tag = match read_u8(&mut reader) {
+            Ok(tag) => tag,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e.into())
+        };
+        if tag != TAG_BAND {
+            return Err(DistributedError::Protocol(format!("unexpected message {}", tag)));
+        }
+
+        let band = read_band(&mut reader)?;
+        let pixels = renderer.render_rows(band.0, band.1).map_err(|e| DistributedError::Worker(e.to_string()))?;
+
+        write_rows(&mut writer, band, &pixels)?;
+        writer.flush()?;
+    }
+}
+
+/*
+
+Encoding
+
+*/
+
+fn encode_job(scene_src: &str, config: &RendererConfig) -> Vec<u8> {
+    let mut job = vec![TAG_JOB];
+    // Writing to a Vec can't fail
+    let _ = write_string(&mut job, scene_src);
+    for value in [config.screen_width as u64, config.screen_height() as u64, config.canvas_unit_size as u64, config.num_samples as u64] {
+        let _ = write_u64(&mut job, value);
+    }
+    for value in [config.max_depth as u64, float_bits(config.fov), float_bits(config.exposure), config.seed] {
+        let _ = write_u64(&mut job, value);
+    }
+    // No firefly clamp is sent as 0, which isn't a valid clamp
+    let firefly_clamp = float_bits(config.firefly_clamp.unwrap_or(0.0));
+    for value in [encode_filter(config.filter), encode_sample_pattern(config.sample_pattern), firefly_clamp, encode_integrator(config.integrator)] {
+        let _ = write_u64(&mut job, value);
+    }
+    // No background is sent as u64::MAX, which isn't a color
+    let _ = write_u64(&mut job, config.background.map_or(u64::MAX, |color| color as u64));
+    job
+}
+
+// Numbers are sent as f64 whatever precision rays are traced with
+#[allow(clippy::useless_conversion)] // Only useless when tracing in f64
+fn float_bits(x: Float) -> u64 {
+    f64::from(x).to_bits()
+}
+
+fn bits_float(bits: u64) -> Float {
+    f64::from_bits(bits) as Float
+}
+
+fn decode_job(reader: &mut impl Read, num_threads: usize) -> Result<(String, RendererConfig), DistributedError> {
+    let scene_src = read_string(reader)?;
+    let config = RendererConfig {
+        num_threads,
+        screen_width: read_usize(reader)?,
+        screen_height: Some(read_usize(reader)?),
+        canvas_unit_size: read_usize(reader)?,
+        num_samples: read_usize(reader)?,
+        max_depth: u32::try_from(read_u64(reader)?).map_err(|e| DistributedError::Protocol(format!("max depth: {}", e)))?,
+        fov: bits_float(read_u64(reader)?),
+        exposure: bits_float(read_u64(reader)?),
+        seed: read_u64(reader)?,
+        filter: decode_filter(read_u64(reader)?)?,
+        sample_pattern: decode_sample_pattern(read_u64(reader)?)?,
+        firefly_clamp: Some(bits_float(read_u64(reader)?)).filter(|factor| *factor != 0.0),
+        integrator: decode_integrator(read_u64(reader)?)?,
+        background: match read_u64(reader)? {
+            u64::MAX => None,
+            color => Some(usize::try_from(color).map_err(|_| DistributedError::Protocol(format!("{} is too large", color)))?)
+        },
+        // Workers trace bands of a single view
+        ..RendererConfig::default()
+    };
+    Ok((scene_src, config))
+}
+
+fn encode_filter(filter: Filter) -> u64 {
+    match filter {
+        Filter::Box => 0,
+        Filter::Tent => 1,
+        Filter::Gaussian => 2
+    }
+}
+
+fn encode_integrator(integrator: Integrator) -> u64 {
+    match integrator {
+        Integrator::Whitted => 0,
+        Integrator::PathTraced => 1,
+        Integrator::AmbientOcclusion => 2
+    }
+}
+
+fn decode_integrator(value: u64) -> Result<Integrator, DistributedError> {
+    match value {
+        0 => Ok(Integrator::Whitted),
+        1 => Ok(Integrator::PathTraced),
+        2 => Ok(Integrator::AmbientOcclusion),
+        _ => Err(DistributedError::Protocol(format!("unknown integrator {}", value)))
+    }
+}
+
+fn encode_sample_pattern(sample_pattern: SamplePattern) -> u64 {
+    match sample_pattern {
+        SamplePattern::Random => 0,
+        SamplePattern::RotatedGrid => 1
+    }
+}
+
+fn decode_sample_pattern(value: u64) -> Result<SamplePattern, DistributedError> {
+    match value {
+        0 => Ok(SamplePattern::Random),
+        1 => Ok(SamplePattern::RotatedGrid),
+        _ => Err(DistributedError::Protocol(format!("unknown sample pattern {}", value)))
+    }
+}
+
+fn decode_filter(value: u64) -> Result<Filter, DistributedError> {
+    match value {
+        0 => Ok(Filter::Box),
+        1 => Ok(Filter::Tent),
+        2 => Ok(Filter::Gaussian),
+        _ => Err(DistributedError::Protocol(format!("unknown filter {}", value)))
+    }
+}
+
+fn write_hello(writer: &mut impl Write) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    write_u64(writer, VERSION)
+}
+
+fn read_hello(reader: &mut impl Read) -> Result<(), DistributedError> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(DistributedError::Protocol("not a raytracer coordinator or worker".into()));
+    }
+    match read_u64(reader)? {
+        VERSION => Ok(()),
+        version => Err(DistributedError::Protocol(format!("expected version {}, got {}", VERSION, version)))
+    }
+}
+
+fn write_band(writer: &mut impl Write, (row_start, row_end): (usize, usize)) -> io::Result<()> {
+    writer.write_all(&[TAG_BAND])?;
+    write_u64(writer, row_start as u64)?;
+    write_u64(writer, row_end as u64)
+}
+
+// The band asked for, after its tag
+fn read_band(reader: &mut impl Read) -> Result<(usize, usize), DistributedError> {
+    Ok((read_usize(reader)?, read_usize(reader)?))
+}
+
+fn write_rows(writer: &mut impl Write, (row_start, row_end): (usize, usize), pixels: &[u32]) -> io::Result<()> {
+    writer.write_all(&[TAG_ROWS])?;
+    write_u64(writer, row_start as u64)?;
+    write_u64(writer, row_end as u64)?;
+    write_u64(writer, pixels.len() as u64)?;
+    for p in pixels {
+        writer.write_all(&p.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+// The pixels of the band that was asked for, after their tag
+fn read_rows(reader: &mut impl Read, (row_start, row_end): (usize, usize), canvas_width: usize) -> Result<Vec<u32>, DistributedError> {
+    let (start, end) = read_band(reader)?;
+    if (start, end) != (row_start, row_end) {
+        return Err(DistributedError::Protocol(format!("asked for rows {}..{}, got {}..{}", row_start, row_end, start, end)));
+    }
+
+    let len = read_usize(reader)?;
+    if len != (row_end - row_start) * canvas_width {
+        return Err(DistributedError::Protocol(format!("expected {} pixels, got {}", (row_end - row_start) * canvas_width, len)));
+    }
+
+    let mut bytes = vec![0; len * 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes.chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect())
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut b = [0; 1];
+    reader.read_exact(&mut b)?;
+    Ok(b[0])
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut b = [0; 8];
+    reader.read_exact(&mut b)?;
+    Ok(u64::from_le_bytes(b))
+}
+
+fn read_usize(reader: &mut impl Read) -> Result<usize, DistributedError> {
+    let value = read_u64(reader)?;
+    usize::try_from(value).map_err(|_| DistributedError::Protocol(format!("{} is too large", value)))
+}
+
+fn write_u64(writer: &mut impl Write, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String, DistributedError> {
+    let len = read_usize(reader)?;
+    if len > MAX_STRING_LEN {
+        return Err(DistributedError::Protocol(format!("string of {} bytes is longer than {}", len, MAX_STRING_LEN)));
+    }
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|e| DistributedError::Protocol(e.to_string()))
+}
+
+fn write_string(writer: &mut impl Write, s: &str) -> io::Result<()> {
+    write_u64(writer, s.len() as u64)?;
+    writer.write_all(s.as_bytes())
+}
+
+/*
+
+Distributed Error
+
+*/
+
+#[derive(Debug)]
+pub enum DistributedError {
+    // A connection to a worker or coordinator failed
+    Io(io::Error),
+
+    // A message didn't follow the protocol
+    Protocol(String),
+
+    // The scene or settings can't be rendered
+    InvalidJob(String),
+
+    // A worker failed to render its part of the frame
+    Worker(String),
+
+    // No worker was able to render any part of the frame
+    NoWorkers
+}
+
+impl fmt::Display for DistributedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DistributedError::Io(e) => write!(f, "connection failed: {}", e),
+            DistributedError::Protocol(e) => write!(f, "protocol error: {}", e),
+            DistributedError::InvalidJob(e) => write!(f, "invalid job: {}", e),
+            DistributedError::Worker(e) => write!(f, "worker failed: {}", e),
+            DistributedError::NoWorkers => write!(f, "no workers available")
+        }
+    }
+}
+
+impl Error for DistributedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DistributedError::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for DistributedError {
+    fn from(e: io::Error) -> Self {
+        DistributedError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RendererBuilder;
+
+    const SCENE: &str = r#"(
+        camera: (0.0, 1.0, 0.0),
+        lights: [Ambient(intensity: 0.2), Point(intensity: 0.8, pos: (2.0, 4.0, -2.0))],
+        objects: [
+            Sphere(center: (0.0, 1.0, -4.0), radius: 1.0, color: "red", material: Shiny(spclr_exp: 100.0, refl_rat: 0.3)),
+            Prism(origin: (-5.0, -1.0, -10.0), width: 10.0, height: 1.0, depth: 10.0, color: "white"),
+        ],
+    )"#;
+
+    fn builder() -> RendererBuilder {
+        RendererBuilder::new().threads(2).screen_width(64).screen_height(40).samples(4).seed(7)
+    }
+
+    #[test]
+    fn job_round_trips() {
+        let config = builder().integrator(Integrator::PathTraced).filter(Filter::Gaussian).firefly_clamp(8.0).max_depth(5).background(0x336699usize).config().clone();
+        let job = encode_job(SCENE, &config);
+        assert_eq!(job[0], TAG_JOB);
+
+        let (scene_src, decoded) = decode_job(&mut &job[1..], 3).unwrap();
+        assert_eq!(scene_src, SCENE);
+        assert_eq!(decoded.num_threads, 3);
+        assert_eq!((decoded.screen_width, decoded.screen_height(), decoded.canvas_unit_size), (config.screen_width, config.screen_height(), config.canvas_unit_size));
+        assert_eq!((decoded.num_samples, decoded.max_depth, decoded.seed), (config.num_samples, config.max_depth, config.seed));
+        assert_eq!((decoded.fov, decoded.exposure, decoded.firefly_clamp), (config.fov, config.exposure, config.firefly_clamp));
+        assert_eq!((decoded.filter, decoded.sample_pattern, decoded.integrator), (config.filter, config.sample_pattern, config.integrator));
+        assert_eq!(decoded.background, config.background);
+    }
+
+    #[test]
+    fn band_and_rows_round_trip() {
+        let mut message = vec![];
+        write_band(&mut message, (16, 32)).unwrap();
+        assert_eq!(message[0], TAG_BAND);
+        assert_eq!(read_band(&mut &message[1..]).unwrap(), (16, 32));
+
+        let pixels: Vec<u32> = (0..6).map(|i| i * 0x010203).collect();
+        let mut message = vec![];
+        write_rows(&mut message, (4, 6), &pixels).unwrap();
+        assert_eq!(message[0], TAG_ROWS);
+        assert_eq!(read_rows(&mut &message[1..], (4, 6), 3).unwrap(), pixels);
+        assert!(matches!(read_rows(&mut &message[1..], (4, 7), 3), Err(DistributedError::Protocol(_))));
+        assert!(matches!(read_rows(&mut &message[1..], (4, 6), 4), Err(DistributedError::Protocol(_))));
+    }
+
+    #[test]
+    fn rejects_bad_messages() {
+        let mut long = vec![];
+        write_u64(&mut long, MAX_STRING_LEN as u64 + 1).unwrap();
+        assert!(matches!(read_string(&mut &long[..]), Err(DistributedError::Protocol(_))));
+
+        let mut job = encode_job(SCENE, builder().config());
+        // max depth follows the scene source and four other fields
+        let max_depth = 1 + 8 + SCENE.len() + 4 * 8;
+        job[max_depth..max_depth + 8].copy_from_slice(&(u32::MAX as u64 + 1).to_le_bytes());
+        assert!(matches!(decode_job(&mut &job[1..], 1), Err(DistributedError::Protocol(_))));
+
+        assert!(matches!(read_hello(&mut &b"GET / HTTP/1.1\r\n"[..]), Err(DistributedError::Protocol(_))));
+        let mut hello = vec![];
+        write_hello(&mut hello).unwrap();
+        assert!(read_hello(&mut &hello[..]).is_ok());
+    }
+
+    #[test]
+    fn rejects_scenes_reading_files() {
+        let scene = SCENE.replace("objects: [", r#"objects: [Mesh(file: "/etc/passwd.ply", origin: (0.0, 0.0, 0.0), color: "white"),"#);
+        assert!(matches!(render(&scene, builder().config(), &[], |_| {}), Err(DistributedError::InvalidJob(_))));
+    }
+
+    #[test]
+    fn matches_local_render() {
+        let workers: Vec<String> = (0..2).map(|_| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap().to_string();
+            thread::spawn(move || serve(listener, 1));
+            addr
+        }).collect();
+
+        let pixels = render(SCENE, builder().config(), &workers, |_| {}).unwrap();
+        let local = builder().render_to_image(Arc::new(scene_file::parse(SCENE).unwrap())).unwrap();
+        assert_eq!((local.width, local.height), (64, 40));
+        assert_eq!(pixels, local.pixels);
+    }
+}
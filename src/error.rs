@@ -0,0 +1,100 @@
+use std::{error::Error, fmt};
+
+use crate::{config::ConfigError, light::LightError};
+
+/*
+
+Renderer Error
+
+Failures that can occur while setting up or running the renderer
+
+*/
+
+#[derive(Debug)]
+pub enum RendererError {
+    // The window could not be opened, e.g. no display is available
+    WindowCreation(Box<dyn Error + Send + Sync>),
+
+    // The video encoder could not be started, e.g. ffmpeg isn't installed
+    VideoEncoder(Box<dyn Error + Send + Sync>),
+
+    // The pixel buffer could not be presented to the display
+    Present(Box<dyn Error + Send + Sync>),
+
+    // The renderer was configured with values it can't work with
+    Config(ConfigError),
+
+    // A render thread panicked while tracing its part of the canvas
+    RenderThread,
+
+    // Rendering was aborted through a cancellation token
+    Cancelled,
+
+    // The scene couldn't be changed because something other than the renderer holds it
+    SceneShared
+}
+
+impl fmt::Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RendererError::WindowCreation(e) => write!(f, "unable to open window: {}", e),
+            RendererError::VideoEncoder(e) => write!(f, "unable to start video encoder: {}", e),
+            RendererError::Present(e) => write!(f, "unable to present frame: {}", e),
+            RendererError::Config(e) => write!(f, "invalid renderer configuration: {}", e),
+            RendererError::RenderThread => write!(f, "a render thread panicked"),
+            RendererError::Cancelled => write!(f, "rendering was cancelled"),
+            RendererError::SceneShared => write!(f, "the scene can't be changed while it's shared")
+        }
+    }
+}
+
+impl Error for RendererError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RendererError::WindowCreation(e) | RendererError::VideoEncoder(e) | RendererError::Present(e) => Some(e.as_ref()),
+            RendererError::Config(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<ConfigError> for RendererError {
+    fn from(e: ConfigError) -> Self {
+        RendererError::Config(e)
+    }
+}
+
+/*
+
+Scene Error
+
+Reasons a scene can't be built from what it was given
+
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneError {
+    // A light, by its index among the scene's lights, and why it can't be used
+    InvalidLight(usize, LightError),
+
+    // A falloff was given before any light to dim
+    FalloffWithoutLight
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::InvalidLight(i, e) => write!(f, "light {} is invalid: {}", i, e),
+            SceneError::FalloffWithoutLight => write!(f, "a falloff needs a point or tube light added before it")
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::InvalidLight(_, e) => Some(e),
+            SceneError::FalloffWithoutLight => None
+        }
+    }
+}
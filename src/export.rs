@@ -0,0 +1,256 @@
+use std::{error::Error, fmt, fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use crate::{color::Color, linalg::Float, object::{Material, Object, Triangles}, Scene};
+
+/*
+
+Scene Export
+
+Writes a scene's objects as triangles, with their colors and materials, for other programs to open, e.g. to compare
+renders with Blender's. Curved surfaces are split into triangles (see Object::triangles), and objects that can't be,
+like point clouds and curves, are left out, as are lights, volumes, decals, fog and the sky
+
+    OBJ (.obj)     with a material file of the same name (.mtl), in the terms the OBJ importer reads (see obj.rs), so a
+                   scene exported and read back keeps its materials
+    glTF (.gltf)   with its geometry in a file of the same name (.bin). Colors become base colors, a shiny material's
+                   reflection ratio its metalness and its specular exponent its roughness, and emissive materials glow
+                   with their intensity as emissive strength
+    GLB (.glb)     the same as a single binary file
+
+Both formats share the tracer's axes, y up with the camera looking down -z
+
+*/
+
+// An object's triangles, named after it, and the index of its look among the scene's
+struct Part {
+    name: String,
+    triangles: Triangles,
+    look: usize
+}
+
+// Every object that can be made of triangles, the distinct colors and materials they have, and how many were left out
+fn parts(scene: &Scene) -> (Vec<Part>, Vec<(usize, Material)>, usize) {
+    let (mut parts, mut looks, mut left_out) = (Vec::new(), Vec::new(), 0);
+    for (i, obj) in scene.objs.iter().enumerate() {
+        let Some(triangles) = obj.triangles().filter(|(vertices, faces)| {
+            !faces.is_empty() && vertices.iter().all(|v| v.x().is_finite() && v.y().is_finite() && v.z().is_finite())
+        }) else {
+            left_out += 1;
+            continue;
+        };
+        let look = (*obj.get_color(), obj.get_material().clone());
+        let look = looks.iter().position(|l| *l == look).unwrap_or_else(|| {
+            looks.push(look);
+            looks.len() - 1
+        });
+        parts.push(Part { name: format!("{}_{}", obj.name().replace(' ', "_"), i), triangles, look });
+    }
+    (parts, looks, left_out)
+}
+
+// Write the scene to path as the format its extension names, and return how many objects were left out
+pub fn export(scene: &Scene, path: impl AsRef<Path>) -> Result<usize, ExportError> {
+    let path = path.as_ref();
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_lowercase();
+    let create = |path: &Path| -> io::Result<BufWriter<File>> { Ok(BufWriter::new(File::create(path)?)) };
+    let file_name = |path: &Path| path.file_name().map_or(String::new(), |name| name.to_string_lossy().into_owned());
+
+    let left_out = match extension.as_str() {
+        "obj" => {
+            let mtl = path.with_extension("mtl");
+            write_obj(scene, create(path)?, create(&mtl)?, &file_name(&mtl))?
+        }
+        "gltf" => {
+            let bin = path.with_extension("bin");
+            write_gltf(scene, create(path)?, create(&bin)?, &file_name(&bin))?
+        }
+        "glb" => write_glb(scene, create(path)?)?,
+        _ => return Err(ExportError::UnknownFormat(extension))
+    };
+    Ok(left_out)
+}
+
+// Write the scene's geometry as OBJ and its materials as MTL, the OBJ naming the MTL file as mtl_file. Returns how many
+// objects were left out
+pub fn write_obj(scene: &Scene, mut obj: impl Write, mut mtl: impl Write, mtl_file: &str) -> io::Result<usize> {
+    let (parts, looks, left_out) = parts(scene);
+
+    writeln!(mtl, "# Materials of the exported scene's objects")?;
+    for (i, (color, material)) in looks.iter().enumerate() {
+        let [r, g, b] = [Color::r(*color), Color::g(*color), Color::b(*color)].map(|c| c as Float / 255.0);
+        writeln!(mtl, "\nnewmtl material_{}\nKd {:.4} {:.4} {:.4}", i, r, g, b)?;
+        match *material {
+            Material::Matte { .. } => writeln!(mtl, "Ks 0 0 0\nillum 1")?,
+            Material::Shiny { spclr_exp, refl_rat, .. } => {
+                writeln!(mtl, "Ks 0.5 0.5 0.5\nNs {}\nillum {}\nPm {}", spclr_exp, if refl_rat > 0.0 { 3 } else { 2 }, refl_rat)?
+            }
+            // Glowing in the color, brightest in its brightest channel
+            Material::Emissive { intensity, .. } => {
+                let brightest = r.max(g).max(b);
+                let [r, g, b] = if brightest > 0.0 { [r, g, b].map(|c| c / brightest * intensity) } else { [intensity; 3] };
+                writeln!(mtl, "Ks 0 0 0\nKe {} {} {}\nillum 1", r, g, b)?
+            }
+        }
+    }
+
+    writeln!(obj, "mtllib {}", mtl_file)?;
+    let mut first = 1;
+    for Part { name, triangles: (vertices, faces), look } in &parts {
+        writeln!(obj, "\no {}", name)?;
+        for v in vertices {
+            writeln!(obj, "v {} {} {}", v.x(), v.y(), v.z())?;
+        }
+        writeln!(obj, "usemtl material_{}", look)?;
+        for [a, b, c] in faces {
+            writeln!(obj, "f {} {} {}", first + a, first + b, first + c)?;
+        }
+        first += vertices.len();
+    }
+
+    obj.flush()?;
+    mtl.flush()?;
+    Ok(left_out)
+}
+
+// Write the scene as glTF, with its geometry in bin, which the glTF names as bin_file. Returns how many objects were
+// left out
+pub fn write_gltf(scene: &Scene, mut gltf: impl Write, mut bin: impl Write, bin_file: &str) -> io::Result<usize> {
+    let (json, data, left_out) = gltf_json(scene, Some(bin_file));
+    gltf.write_all(json.as_bytes())?;
+    bin.write_all(&data)?;
+    gltf.flush()?;
+    bin.flush()?;
+    Ok(left_out)
+}
+
+// Write the scene as a single binary glTF file. Returns how many objects were left out
+pub fn write_glb(scene: &Scene, mut glb: impl Write) -> io::Result<usize> {
+    let (json, data, left_out) = gltf_json(scene, None);
+
+    // Chunks are padded to 4 bytes, JSON with spaces and binary data with zeros
+    let mut json = json.into_bytes();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    let mut data = data;
+    data.resize(data.len().next_multiple_of(4), 0);
+
+    let length = 12 + 8 + json.len() + 8 + data.len();
+    glb.write_all(b"glTF")?;
+    glb.write_all(&2u32.to_le_bytes())?;
+    glb.write_all(&(length as u32).to_le_bytes())?;
+    glb.write_all(&(json.len() as u32).to_le_bytes())?;
+    glb.write_all(b"JSON")?;
+    glb.write_all(&json)?;
+    glb.write_all(&(data.len() as u32).to_le_bytes())?;
+    glb.write_all(b"BIN\0")?;
+    glb.write_all(&data)?;
+    glb.flush()?;
+    Ok(left_out)
+}
+
+// The glTF JSON describing the scene and the binary data of its geometry, a mesh per object with its corners as 32-bit
+// floats and its triangles as 32-bit indices. The data is in the file named bin_file, or follows the JSON in a GLB
+fn gltf_json(scene: &Scene, bin_file: Option<&str>) -> (String, Vec<u8>, usize) {
+    let (parts, looks, left_out) = parts(scene);
+    let mut data: Vec<u8> = Vec::new();
+    let (mut nodes, mut meshes, mut accessors, mut views) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+
+    for (i, Part { name, triangles: (vertices, faces), look }) in parts.iter().enumerate() {
+        #[allow(clippy::unnecessary_cast)] // Only unnecessary when tracing in f32
+        let corners: Vec<[f32; 3]> = vertices.iter().map(|v| [v.x() as f32, v.y() as f32, v.z() as f32]).collect();
+        let (min, max) = corners.iter().fold(([f32::INFINITY; 3], [f32::NEG_INFINITY; 3]), |(min, max), c| {
+            ([0, 1, 2].map(|a| min[a].min(c[a])), [0, 1, 2].map(|a| max[a].max(c[a])))
+        });
+
+        let offset = data.len();
+        data.extend(corners.iter().flatten().flat_map(|c| c.to_le_bytes()));
+        views.push(format!(r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":34962}}"#, offset, data.len() - offset));
+        accessors.push(format!(
+            r#"{{"bufferView":{},"componentType":5126,"count":{},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}}"#,
+            2 * i, corners.len(), min[0], min[1], min[2], max[0], max[1], max[2]
+        ));
+
+        let offset = data.len();
+        data.extend(faces.iter().flatten().flat_map(|&corner| (corner as u32).to_le_bytes()));
+        views.push(format!(r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":34963}}"#, offset, data.len() - offset));
+        accessors.push(format!(r#"{{"bufferView":{},"componentType":5125,"count":{},"type":"SCALAR"}}"#, 2 * i + 1, faces.len() * 3));
+
+        meshes.push(format!(r#"{{"name":"{}","primitives":[{{"attributes":{{"POSITION":{}}},"indices":{},"material":{}}}]}}"#, name, 2 * i, 2 * i + 1, look));
+        nodes.push(format!(r#"{{"name":"{}","mesh":{}}}"#, name, i));
+    }
+
+    let materials: Vec<String> = looks.iter().enumerate().map(|(i, (color, material))| {
+        let [r, g, b] = Color::to_linear(*color);
+        let base = format!(r#""name":"material_{}","doubleSided":{}"#, i, material.double_sided());
+        match *material {
+            Material::Matte { .. } => {
+                format!(r#"{{{},"pbrMetallicRoughness":{{"baseColorFactor":[{},{},{},1],"metallicFactor":0,"roughnessFactor":1}}}}"#, base, r, g, b)
+            }
+            // The roughness of a microfacet surface with about as wide highlights as the specular exponent's
+            Material::Shiny { spclr_exp, refl_rat, .. } => {
+                let roughness = (2.0 / (spclr_exp.max(0.0) + 2.0)).powf(0.25);
+                format!(
+                    r#"{{{},"pbrMetallicRoughness":{{"baseColorFactor":[{},{},{},1],"metallicFactor":{},"roughnessFactor":{}}}}}"#,
+                    base, r, g, b, refl_rat.clamp(0.0, 1.0), roughness
+                )
+            }
+            Material::Emissive { intensity, .. } => format!(
+                r#"{{{},"pbrMetallicRoughness":{{"baseColorFactor":[0,0,0,1]}},"emissiveFactor":[{},{},{}],"extensions":{{"KHR_materials_emissive_strength":{{"emissiveStrength":{}}}}}}}"#,
+                base, r, g, b, intensity
+            )
+        }
+    }).collect();
+    let emissive = looks.iter().any(|(_, material)| matches!(material, Material::Emissive { .. }));
+
+    let buffer = match bin_file {
+        Some(file) => format!(r#"{{"byteLength":{},"uri":"{}"}}"#, data.len(), file.replace('\\', "\\\\").replace('"', "\\\"")),
+        None => format!(r#"{{"byteLength":{}}}"#, data.len())
+    };
+    let json = format!(
+        r#"{{"asset":{{"version":"2.0","generator":"raytracer"}},{}"scene":0,"scenes":[{{"nodes":[{}]}}],"nodes":[{}],"meshes":[{}],"materials":[{}],"accessors":[{}],"bufferViews":[{}],"buffers":[{}]}}"#,
+        if emissive { r#""extensionsUsed":["KHR_materials_emissive_strength"],"# } else { "" },
+        (0..nodes.len()).map(|i| i.to_string()).collect::<Vec<_>>().join(","),
+        nodes.join(","),
+        meshes.join(","),
+        materials.join(","),
+        accessors.join(","),
+        views.join(","),
+        buffer
+    );
+    (json, data, left_out)
+}
+
+/*
+
+Export Error
+
+*/
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    UnknownFormat(String) // The extension of the file asked for
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Io(e) => write!(f, "couldn't write exported scene: {}", e),
+            ExportError::UnknownFormat(ext) => write!(f, "can't export to \"{}\" files, expected obj, gltf or glb", ext)
+        }
+    }
+}
+
+impl Error for ExportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExportError::Io(e) => Some(e),
+            ExportError::UnknownFormat(_) => None
+        }
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(e: io::Error) -> Self {
+        ExportError::Io(e)
+    }
+}
@@ -0,0 +1,91 @@
+use std::{error::Error, fmt};
+
+use crate::linalg::Float;
+
+/*
+
+Falloff
+
+How a point or tube light dims with distance from it. Lights have always been equally bright at any distance here,
+which is easy to light a scene with but flat, so that stays the default. Inverse-square is how light really spreads
+out, for physically based looks; linear fades to nothing at a set range, and a lookup table of brightness at given
+distances can shape any curve a stylized look needs, e.g. a light that stays bright up to a point and then drops off
+
+Brightness is a multiple of the light's intensity. Inverse-square is 1 a unit from the light, growing closer to it up
+to MAX_INVERSE_SQUARE times as bright, so surfaces right next to a light aren't blown out without limit
+
+*/
+
+const MAX_INVERSE_SQUARE: Float = 100.0;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Falloff {
+    #[default]
+    None,
+    Linear { range: Float },    // Fading evenly to nothing at range
+    InverseSquare,
+    Lookup(Vec<(Float, Float)>) // Brightness at increasing distances, blended between them and held past either end
+}
+
+impl Falloff {
+    // How bright a light is at dist from it, as a multiple of its intensity
+    pub fn attenuation(&self, dist: Float) -> Float {
+        match self {
+            Falloff::None => 1.0,
+            Falloff::Linear { range } => (1.0 - dist / range).max(0.0),
+            Falloff::InverseSquare => (1.0 / (dist * dist)).min(MAX_INVERSE_SQUARE),
+            Falloff::Lookup(table) => {
+                let next = table.partition_point(|&(at, _)| at < dist);
+                match (next.checked_sub(1).map(|i| table[i]), table.get(next)) {
+                    (Some((d0, b0)), Some(&(d1, b1))) => b0 + (b1 - b0) * (dist - d0) / (d1 - d0),
+                    (Some((_, brightness)), None) | (None, Some(&(_, brightness))) => brightness,
+                    (None, None) => 1.0
+                }
+            }
+        }
+    }
+
+    // Why the falloff can't be used, if it can't: a linear range must be positive and finite, and a lookup table needs
+    // at least one entry, all finite, with increasing distances and no negative brightness
+    pub fn validate(&self) -> Result<(), FalloffError> {
+        match self {
+            Falloff::Linear { range } if !range.is_finite() || *range <= 0.0 => Err(FalloffError::InvalidRange(*range)),
+            Falloff::Lookup(table) if table.is_empty() => Err(FalloffError::EmptyLookup),
+            Falloff::Lookup(table) if table.iter().any(|&(at, brightness)| !at.is_finite() || !brightness.is_finite()) => {
+                Err(FalloffError::NonFiniteLookup)
+            }
+            Falloff::Lookup(table) if table.windows(2).any(|pair| pair[1].0 <= pair[0].0) => Err(FalloffError::UnorderedLookup),
+            Falloff::Lookup(table) if table.iter().any(|&(_, brightness)| brightness < 0.0) => Err(FalloffError::NegativeLookup),
+            _ => Ok(())
+        }
+    }
+}
+
+/*
+
+Falloff Error
+
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FalloffError {
+    InvalidRange(Float), // A linear falloff's range
+    EmptyLookup,
+    NonFiniteLookup,
+    UnorderedLookup,     // Lookup distances that don't increase
+    NegativeLookup       // Lookup brightness below 0
+}
+
+impl fmt::Display for FalloffError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FalloffError::InvalidRange(range) => write!(f, "linear falloff range must be positive and finite, got {}", range),
+            FalloffError::EmptyLookup => write!(f, "falloff lookup table has no entries"),
+            FalloffError::NonFiniteLookup => write!(f, "falloff lookup table entries must be finite"),
+            FalloffError::UnorderedLookup => write!(f, "falloff lookup table distances must increase"),
+            FalloffError::NegativeLookup => write!(f, "falloff lookup table brightness must be non-negative")
+        }
+    }
+}
+
+impl Error for FalloffError {}
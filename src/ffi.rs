@@ -0,0 +1,407 @@
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    slice,
+    sync::Arc
+};
+
+use crate::{
+    color::Color,
+    falloff::Falloff,
+    light::LightSource,
+    linalg::{Float, Vec3d},
+    object::{self, Primitive, RectangularPrism},
+    Renderer,
+    Scene
+};
+
+/*
+
+C Interface
+
+Lets C and C++ applications embed the renderer (with the ffi feature): a scene is put together from primitives and
+lights, then rendered into an RGBA buffer the caller owns, e.g.
+
+    RtScene *scene = rt_scene_new(NULL, 0x000000);
+    double center[3] = { 0.0, 1.0, -5.0 };
+    RtMaterial shiny = { RT_MATERIAL_KIND_SHINY, 500.0, 0.2, 0.0 };
+    rt_scene_add_sphere(scene, center, 1.0, 0xFF0000, &shiny);
+    rt_scene_add_light(scene, RT_LIGHT_KIND_AMBIENT, 0.2, NULL);
+
+    RtRenderOptions options = rt_render_options_default();
+    uint8_t *rgba = malloc(options.width * options.height * 4);
+    if (rt_render(scene, &options, rgba, options.width * options.height * 4) != RT_STATUS_OK)
+        fprintf(stderr, "%s\n", rt_last_error());
+    rt_scene_free(scene);
+
+Every function that can fail returns a status, and rt_last_error describes the latest failure on the calling thread.
+Vectors are passed as arrays of 3 doubles (whatever precision the tracer is built with), colors as 0xRRGGBB integers.
+Building with the feature generates the header from this file, and include/raytracer.h is a copy of it that the tests
+check is up to date. Link against the library built as a cdylib (libraytracer.so, raytracer.dll or libraytracer.dylib)
+
+*/
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RtStatus {
+    Ok,
+    NullPointer,     // A pointer that must be given was NULL
+    InvalidArgument, // e.g. a negative radius or a zero width
+    BufferTooSmall,  // The RGBA buffer can't hold every pixel of the frame
+    RenderFailed     // The renderer couldn't trace the frame (see rt_last_error)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)] // Only ever made by callers
+pub enum RtMaterialKind {
+    Matte,
+    Shiny,
+    Emissive
+}
+
+// How a surface reflects light. Only the fields of its kind are read
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RtMaterial {
+    pub kind: RtMaterialKind,
+    pub spclr_exp: f64, // Shiny: how sharp specular highlights are
+    pub refl_rat: f64,  // Shiny: fraction of light reflected off the surface
+    pub intensity: f64  // Emissive: how brightly the surface glows
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)] // Only ever made by callers
+pub enum RtLightKind {
+    Ambient,
+    Point,
+    Directional
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RtRenderOptions {
+    pub width: u32,
+    pub height: u32,
+    pub samples: u32,   // Samples per pixel
+    pub max_depth: u32, // Most times a ray is reflected
+    pub threads: u32,   // 0 for as many as the machine has cores
+    pub fov: f64,       // Vertical field of view in degrees, 0 for the default
+    pub seed: u64
+}
+
+// The objects and lights of a scene being put together, built into a scene for the renderer when it's rendered
+pub struct RtScene {
+    camera: Vec3d,
+    background: usize,
+    lights: Vec<LightSource>,
+    shapes: Vec<Shape>,
+    built: Option<Arc<Scene>> // The scene as last rendered, until anything else is added
+}
+
+enum Shape {
+    Sphere { center: Vec3d, radius: Float, color: usize, material: object::Material },
+    Prism { origin: Vec3d, size: [Float; 3], color: usize, material: object::Material },
+    Triangle { points: [Vec3d; 3], color: usize, material: object::Material }
+}
+
+impl Shape {
+    fn primitive(&self) -> Primitive {
+        match self {
+            Shape::Sphere { center, radius, color, material } => object::Sphere::new(center.clone(), *radius, *color, material.clone()).into(),
+            Shape::Prism { origin, size: [width, height, depth], color, material } => {
+                RectangularPrism::new(origin.clone(), *width, *height, *depth, *color, material.clone()).into()
+            }
+            Shape::Triangle { points, color, material } => object::Triangle::new(points.clone(), *color, material.clone()).into()
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+// Record why a call failed, for rt_last_error
+fn fail(status: RtStatus, message: impl Into<String>) -> RtStatus {
+    let message = CString::new(message.into().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    status
+}
+
+// The vector in an array of 3 doubles
+unsafe fn vec3d(v: *const f64) -> Vec3d {
+    let v = slice::from_raw_parts(v, 3);
+    Vec3d::new(v[0] as Float, v[1] as Float, v[2] as Float)
+}
+
+fn material(material: Option<&RtMaterial>) -> object::Material {
+    match material {
+        None => object::Material::default(),
+        Some(RtMaterial { kind: RtMaterialKind::Matte, .. }) => object::Material::default(),
+        Some(&RtMaterial { kind: RtMaterialKind::Shiny, spclr_exp, refl_rat, .. }) => {
+            object::Material::Shiny { spclr_exp: spclr_exp as Float, refl_rat: refl_rat as Float, double_sided: true }
+        },
+        Some(&RtMaterial { kind: RtMaterialKind::Emissive, intensity, .. }) => object::Material::Emissive { intensity: intensity as Float, double_sided: true }
+    }
+}
+
+impl RtScene {
+    fn add(&mut self, shape: Shape) -> RtStatus {
+        self.shapes.push(shape);
+        self.built = None;
+        RtStatus::Ok
+    }
+}
+
+// A new empty scene seen from camera (the origin when NULL) against a background color. Free it with rt_scene_free
+// Safety: camera must be NULL or point to 3 doubles
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_new(camera: *const f64, background: u32) -> *mut RtScene {
+    let camera = if camera.is_null() { Vec3d::new(0.0, 0.0, 0.0) } else { vec3d(camera) };
+    Box::into_raw(Box::new(RtScene { camera, background: (background & 0xFFFFFF) as usize, lights: Vec::new(), shapes: Vec::new(), built: None }))
+}
+
+// Safety: scene must be NULL or come from rt_scene_new, and not have been freed already
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_free(scene: *mut RtScene) {
+    if !scene.is_null() {
+        drop(Box::from_raw(scene));
+    }
+}
+
+// A matte sphere when material is NULL
+// Safety: scene must come from rt_scene_new, center point to 3 doubles and material be NULL or point to a material
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_add_sphere(scene: *mut RtScene, center: *const f64, radius: f64, color: u32, material: *const RtMaterial) -> RtStatus {
+    let (Some(scene), false) = (scene.as_mut(), center.is_null()) else {
+        return fail(RtStatus::NullPointer, "rt_scene_add_sphere was given a NULL scene or center");
+    };
+    if !(radius.is_finite() && radius > 0.0) {
+        return fail(RtStatus::InvalidArgument, format!("sphere radius must be positive, got {}", radius));
+    }
+    scene.add(Shape::Sphere { center: vec3d(center), radius: radius as Float, color: (color & 0xFFFFFF) as usize, material: self::material(material.as_ref()) })
+}
+
+// A box from its minimum corner, its sides along the axes. Matte when material is NULL
+// Safety: scene must come from rt_scene_new, origin point to 3 doubles and material be NULL or point to a material
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_add_prism(scene: *mut RtScene, origin: *const f64, width: f64, height: f64, depth: f64, color: u32, material: *const RtMaterial) -> RtStatus {
+    let (Some(scene), false) = (scene.as_mut(), origin.is_null()) else {
+        return fail(RtStatus::NullPointer, "rt_scene_add_prism was given a NULL scene or origin");
+    };
+    if !(width > 0.0 && height > 0.0 && depth > 0.0) {
+        return fail(RtStatus::InvalidArgument, format!("prism sides must be positive, got {}x{}x{}", width, height, depth));
+    }
+    let size = [width as Float, height as Float, depth as Float];
+    scene.add(Shape::Prism { origin: vec3d(origin), size, color: (color & 0xFFFFFF) as usize, material: self::material(material.as_ref()) })
+}
+
+// A triangle through 3 points given one after another. Matte when material is NULL
+// Safety: scene must come from rt_scene_new, points point to 9 doubles and material be NULL or point to a material
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_add_triangle(scene: *mut RtScene, points: *const f64, color: u32, material: *const RtMaterial) -> RtStatus {
+    let (Some(scene), false) = (scene.as_mut(), points.is_null()) else {
+        return fail(RtStatus::NullPointer, "rt_scene_add_triangle was given a NULL scene or points");
+    };
+    let points = [vec3d(points), vec3d(points.add(3)), vec3d(points.add(6))];
+    scene.add(Shape::Triangle { points, color: (color & 0xFFFFFF) as usize, material: self::material(material.as_ref()) })
+}
+
+// A light of some intensity. Point lights are at vec and directional lights shine from vec, ambient light ignores it
+// Safety: scene must come from rt_scene_new and vec be NULL (for ambient light) or point to 3 doubles
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_add_light(scene: *mut RtScene, kind: RtLightKind, intensity: f64, vec: *const f64) -> RtStatus {
+    let Some(scene) = scene.as_mut() else {
+        return fail(RtStatus::NullPointer, "rt_scene_add_light was given a NULL scene");
+    };
+    if kind != RtLightKind::Ambient && vec.is_null() {
+        return fail(RtStatus::NullPointer, "point and directional lights need a position or direction");
+    }
+    let intensity = intensity as Float;
+    let light = match kind {
+        RtLightKind::Ambient => LightSource::Ambient { intensity },
+        RtLightKind::Point => LightSource::Point { intensity, pos: vec3d(vec), falloff: Falloff::None },
+        RtLightKind::Directional => LightSource::Directional { intensity, dir: vec3d(vec), color: Color::White as usize }
+    };
+    // e.g. an infinite intensity or a directional light shining nowhere
+    if let Err(e) = light.validate() {
+        return fail(RtStatus::InvalidArgument, e.to_string());
+    }
+    scene.lights.push(light);
+    scene.built = None;
+    RtStatus::Ok
+}
+
+// 640x360 with a single sample per pixel, as rendered without settings
+#[no_mangle]
+pub extern "C" fn rt_render_options_default() -> RtRenderOptions {
+    RtRenderOptions { width: 640, height: 360, samples: 1, max_depth: 2, threads: 0, fov: 0.0, seed: 0 }
+}
+
+// Trace a frame of the scene into rgba, 4 bytes per pixel row by row from the top left, with alpha always 255. len is
+// the size of the buffer in bytes, at least width * height * 4
+// Safety: scene must come from rt_scene_new, options point to render options and rgba to len writable bytes
+#[no_mangle]
+pub unsafe extern "C" fn rt_render(scene: *mut RtScene, options: *const RtRenderOptions, rgba: *mut u8, len: usize) -> RtStatus {
+    let (Some(scene), Some(options), false) = (scene.as_mut(), options.as_ref(), rgba.is_null()) else {
+        return fail(RtStatus::NullPointer, "rt_render was given a NULL scene, options or buffer");
+    };
+    let RtRenderOptions { width, height, samples, max_depth, threads, fov, seed } = *options;
+    let (width, height) = (width as usize, height as usize);
+    if width == 0 || height == 0 || samples == 0 {
+        return fail(RtStatus::InvalidArgument, format!("can't render {}x{} pixels with {} samples", width, height, samples));
+    }
+    if len / 4 / width < height {
+        return fail(RtStatus::BufferTooSmall, format!("a {}x{} frame needs {} bytes, the buffer holds {}", width, height, width * height * 4, len));
+    }
+
+    let built = match &scene.built {
+        Some(built) => built,
+        None => {
+            let objs: Vec<Primitive> = scene.shapes.iter().map(Shape::primitive).collect();
+            match Scene::new(scene.camera.clone(), scene.background, scene.lights.clone(), objs) {
+                Ok(built) => scene.built.insert(Arc::new(built)),
+                Err(e) => return fail(RtStatus::InvalidArgument, e.to_string())
+            }
+        }
+    };
+
+    let mut builder = Renderer::builder()
+        .screen_width(width)
+        .screen_height(height)
+        .samples(samples as usize)
+        .max_depth(max_depth)
+        .seed(seed);
+    if threads > 0 {
+        builder = builder.threads(threads as usize);
+    }
+    if fov > 0.0 {
+        builder = builder.fov(fov as Float);
+    }
+
+    // Panics mustn't unwind into the caller's frames
+    let image = match panic::catch_unwind(AssertUnwindSafe(|| builder.render_to_image(Arc::clone(built)))) {
+        Ok(Ok(image)) => image,
+        Ok(Err(e)) => return fail(RtStatus::RenderFailed, e.to_string()),
+        Err(_) => return fail(RtStatus::RenderFailed, "the renderer panicked")
+    };
+
+    let rgba = slice::from_raw_parts_mut(rgba, width * height * 4);
+    for (out, p) in rgba.chunks_exact_mut(4).zip(&image.pixels) {
+        out.copy_from_slice(&[(p >> 16) as u8, (p >> 8) as u8, *p as u8, 255]);
+    }
+    RtStatus::Ok
+}
+
+// Why the latest call that failed on this thread did, or NULL when none has. Valid until the next call that fails
+#[no_mangle]
+pub extern "C" fn rt_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+
+    use super::*;
+
+    // The last error on this thread, as a string
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(rt_last_error()) }.to_string_lossy().into_owned()
+    }
+
+    fn options(width: u32, height: u32) -> RtRenderOptions {
+        RtRenderOptions { width, height, threads: 2, ..rt_render_options_default() }
+    }
+
+    #[test]
+    fn header_is_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/raytracer.h"));
+        assert!(generated == include_str!("../include/raytracer.h"), "include/raytracer.h is out of date, copy it from {}", env!("OUT_DIR"));
+    }
+
+    #[test]
+    fn renders_a_scene() {
+        unsafe {
+            let scene = rt_scene_new(ptr::null(), 0x0000FF);
+            let shiny = RtMaterial { kind: RtMaterialKind::Shiny, spclr_exp: 500.0, refl_rat: 0.2, intensity: 0.0 };
+            assert_eq!(rt_scene_add_sphere(scene, [0.0, 0.0, -5.0].as_ptr(), 1.0, 0xFF0000, &shiny), RtStatus::Ok);
+            assert_eq!(rt_scene_add_prism(scene, [-3.0, -2.0, -8.0].as_ptr(), 6.0, 0.5, 4.0, 0x00FF00, ptr::null()), RtStatus::Ok);
+            let points = [-3.0, 3.0, -8.0, 3.0, 3.0, -8.0, 0.0, 2.0, -8.0];
+            assert_eq!(rt_scene_add_triangle(scene, points.as_ptr(), 0xFFFFFF, ptr::null()), RtStatus::Ok);
+            assert_eq!(rt_scene_add_light(scene, RtLightKind::Ambient, 0.2, ptr::null()), RtStatus::Ok);
+            assert_eq!(rt_scene_add_light(scene, RtLightKind::Point, 0.6, [2.0, 1.0, 0.0].as_ptr()), RtStatus::Ok);
+            assert_eq!(rt_scene_add_light(scene, RtLightKind::Directional, 0.2, [1.0, -4.0, -4.0].as_ptr()), RtStatus::Ok);
+
+            let (width, height) = (32, 18);
+            let mut rgba = vec![0; width * height * 4];
+            assert_eq!(rt_render(scene, &options(width as u32, height as u32), rgba.as_mut_ptr(), rgba.len()), RtStatus::Ok);
+            rt_scene_free(scene);
+
+            assert!(rgba.chunks_exact(4).all(|p| p[3] == 255));
+            // The sphere fills the middle, the background the top left corner
+            let at = |x: usize, y: usize| &rgba[(y * width + x) * 4..][..3];
+            let middle = at(width / 2, height / 2);
+            assert!(middle[0] > middle[1] && middle[0] > middle[2], "the middle is {:?}", middle);
+            assert_eq!(at(0, 0), [0, 0, 255]);
+        }
+    }
+
+    #[test]
+    fn rejects_null_pointers() {
+        unsafe {
+            let center = [0.0, 0.0, 5.0];
+            assert_eq!(rt_scene_add_sphere(ptr::null_mut(), center.as_ptr(), 1.0, 0, ptr::null()), RtStatus::NullPointer);
+            assert!(last_error().contains("NULL"));
+
+            let scene = rt_scene_new(ptr::null(), 0);
+            assert_eq!(rt_scene_add_sphere(scene, ptr::null(), 1.0, 0, ptr::null()), RtStatus::NullPointer);
+            assert_eq!(rt_scene_add_prism(scene, ptr::null(), 1.0, 1.0, 1.0, 0, ptr::null()), RtStatus::NullPointer);
+            assert_eq!(rt_scene_add_triangle(scene, ptr::null(), 0, ptr::null()), RtStatus::NullPointer);
+            assert_eq!(rt_scene_add_light(scene, RtLightKind::Point, 0.5, ptr::null()), RtStatus::NullPointer);
+
+            let mut rgba = vec![0; 4];
+            assert_eq!(rt_render(ptr::null_mut(), &options(1, 1), rgba.as_mut_ptr(), rgba.len()), RtStatus::NullPointer);
+            assert_eq!(rt_render(scene, ptr::null(), rgba.as_mut_ptr(), rgba.len()), RtStatus::NullPointer);
+            assert_eq!(rt_render(scene, &options(1, 1), ptr::null_mut(), rgba.len()), RtStatus::NullPointer);
+            rt_scene_free(scene);
+            rt_scene_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        unsafe {
+            let scene = rt_scene_new(ptr::null(), 0);
+            let v = [0.0, 0.0, 5.0];
+            assert_eq!(rt_scene_add_sphere(scene, v.as_ptr(), -1.0, 0, ptr::null()), RtStatus::InvalidArgument);
+            assert_eq!(rt_scene_add_sphere(scene, v.as_ptr(), f64::NAN, 0, ptr::null()), RtStatus::InvalidArgument);
+            assert_eq!(rt_scene_add_prism(scene, v.as_ptr(), 1.0, 0.0, 1.0, 0, ptr::null()), RtStatus::InvalidArgument);
+            assert_eq!(rt_scene_add_light(scene, RtLightKind::Ambient, f64::INFINITY, ptr::null()), RtStatus::InvalidArgument);
+            assert!(last_error().contains("finite"));
+            assert_eq!(rt_scene_add_light(scene, RtLightKind::Point, f64::NAN, v.as_ptr()), RtStatus::InvalidArgument);
+            assert_eq!(rt_scene_add_light(scene, RtLightKind::Directional, 0.5, [0.0; 3].as_ptr()), RtStatus::InvalidArgument);
+            assert!(last_error().contains("direction"));
+
+            let mut rgba = vec![0; 64];
+            assert_eq!(rt_render(scene, &options(0, 4), rgba.as_mut_ptr(), rgba.len()), RtStatus::InvalidArgument);
+            let no_samples = RtRenderOptions { samples: 0, ..options(4, 4) };
+            assert_eq!(rt_render(scene, &no_samples, rgba.as_mut_ptr(), rgba.len()), RtStatus::InvalidArgument);
+            rt_scene_free(scene);
+        }
+    }
+
+    #[test]
+    fn rejects_small_buffers() {
+        unsafe {
+            let scene = rt_scene_new(ptr::null(), 0);
+            let mut rgba = vec![0; 4 * 4 * 4 - 1];
+            assert_eq!(rt_render(scene, &options(4, 4), rgba.as_mut_ptr(), rgba.len()), RtStatus::BufferTooSmall);
+            assert!(last_error().contains("bytes"));
+            rt_scene_free(scene);
+        }
+    }
+}
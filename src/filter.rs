@@ -0,0 +1,91 @@
+use std::{fmt, str::FromStr};
+
+use crate::linalg::Float;
+
+/*
+
+Reconstruction Filter
+
+How anti-aliasing samples are combined into a canvas unit's color. Samples are spread over the filter's footprint
+around the unit's center and weighted by how far they land from it. Offsets and radii are in canvas units
+
+*/
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Filter {
+    #[default]
+    Box,      // Every sample within the unit counts the same, a straight average
+    Tent,     // Weight falls linearly to zero one unit from the center, softening edges
+    Gaussian  // Weight falls smoothly over one and a half units, the softest and least prone to jagged edges
+}
+
+const GAUSSIAN_ALPHA: Float = 2.0; // How quickly the gaussian falls off
+
+impl Filter {
+    // Distance from the center beyond which samples have no weight
+    pub fn radius(&self) -> Float {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Gaussian => 1.5
+        }
+    }
+
+    // Weight of a sample offset (dx, dy) from the center of a unit
+    pub fn weight(&self, dx: Float, dy: Float) -> Float {
+        let r = self.radius();
+        let falloff = |d: Float| match self {
+            Filter::Box => if d.abs() <= r { 1.0 } else { 0.0 },
+            Filter::Tent => (1.0 - d.abs() / r).max(0.0),
+            // Shifted down so the weight reaches zero at the radius instead of being cut off
+            Filter::Gaussian => ((-GAUSSIAN_ALPHA * d * d).exp() - (-GAUSSIAN_ALPHA * r * r).exp()).max(0.0)
+        };
+        falloff(dx) * falloff(dy)
+    }
+}
+
+// Darken samples, given as (linear RGB, filter weight), that are more than factor times brighter than the median of a
+// unit's samples, so a single stray bright sample doesn't leave a speckle. This also dims bright details thinner than a
+// sample, so it only applies once there are enough samples for the median to mean something
+pub fn clamp_fireflies(samples: &mut [([Float; 3], Float)], factor: Float) {
+    if samples.len() < 3 {
+        return;
+    }
+
+    let luminance = |[r, g, b]: [Float; 3]| 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let mut luminances: Vec<Float> = samples.iter().map(|(rgb, _)| luminance(*rgb)).collect();
+    luminances.sort_by(Float::total_cmp);
+    let median = luminances[luminances.len() / 2];
+    // Against a black median, samples up to factor / 255 bright are still let through
+    let limit = factor * median.max(1.0 / 255.0);
+
+    for (rgb, _) in samples.iter_mut() {
+        let sample_luminance = luminance(*rgb);
+        if sample_luminance > limit {
+            *rgb = rgb.map(|channel| channel * limit / sample_luminance);
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Box => write!(f, "box"),
+            Filter::Tent => write!(f, "tent"),
+            Filter::Gaussian => write!(f, "gaussian")
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "box" => Ok(Filter::Box),
+            "tent" => Ok(Filter::Tent),
+            "gaussian" => Ok(Filter::Gaussian),
+            _ => Err(format!("unknown filter \"{}\", expected box, tent or gaussian", s))
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{color::linear_to_srgb, linalg::Float, post::{Frame, PostEffect}};
+
+/*
+
+FXAA
+
+Fast approximate anti-aliasing smooths jagged edges after a frame is traced, instead of tracing more samples per canvas
+unit, so edges look clean at a single sample for a fraction of supersampling's cost. It's what the interactive viewer
+can afford while the scene is explored
+
+Edges are found where brightness (as displayed) changes sharply between a canvas unit and its neighbours. Each edge is
+followed both ways until it ends, to work out how far along the staircase of a jagged edge the unit is, and the unit is
+blended with the neighbour across the edge by as much as a smooth edge would cover it. Units alone in their
+surroundings (thin lines, speckles) are also blended with their neighbours, by up to subpixel. Fine detail is softened
+a little, as with any post-process anti-aliasing
+
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fxaa {
+    pub edge_threshold: Float,     // The change in brightness, relative to the brightest neighbour, that counts as an edge
+    pub edge_threshold_min: Float, // Changes in brightness smaller than this are never edges, so dark noise is left alone
+    pub subpixel: Float            // How much units standing out from all their neighbours are blended, from 0 to 1
+}
+
+impl Default for Fxaa {
+    fn default() -> Self {
+        Self {
+            edge_threshold: 0.125,
+            edge_threshold_min: 0.0312,
+            subpixel: 0.75
+        }
+    }
+}
+
+// How far each step along an edge goes, in canvas units, taking longer strides the further it goes
+const EDGE_STEPS: [Float; 12] = [1.0, 1.5, 2.0, 2.0, 2.0, 2.0, 2.0, 2.0, 2.0, 4.0, 8.0, 8.0];
+
+impl PostEffect for Fxaa {
+    fn apply(&self, frame: &mut Frame) {
+        let (width, height) = (frame.width, frame.height);
+        let luma = Luma {
+            values: frame.pixels.iter().map(|rgb| linear_to_srgb((0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2]).clamp(0.0, 1.0))).collect(),
+            width,
+            height
+        };
+
+        let pixels: Vec<[Float; 3]> = (0..frame.pixels.len()).map(|i| {
+            let (x, y) = ((i % width) as isize, (i / width) as isize);
+            match self.offset(&luma, x, y) {
+                Some((dx, dy)) => frame.sample(x as Float + 0.5 + dx, y as Float + 0.5 + dy),
+                None => frame.pixels[i]
+            }
+        }).collect();
+        frame.pixels = pixels;
+    }
+}
+
+impl Fxaa {
+    // How far to move the unit at (x, y) towards the neighbour across its edge, in canvas units, or None when it isn't
+    // on an edge
+    fn offset(&self, luma: &Luma, x: isize, y: isize) -> Option<(Float, Float)> {
+        let center = luma.at(x, y);
+        let (north, south, west, east) = (luma.at(x, y - 1), luma.at(x, y + 1), luma.at(x - 1, y), luma.at(x + 1, y));
+        let brightest = center.max(north).max(south).max(west).max(east);
+        let range = brightest - center.min(north).min(south).min(west).min(east);
+        if range < self.edge_threshold_min.max(brightest * self.edge_threshold) {
+            return None;
+        }
+
+        let (north_west, north_east) = (luma.at(x - 1, y - 1), luma.at(x + 1, y - 1));
+        let (south_west, south_east) = (luma.at(x - 1, y + 1), luma.at(x + 1, y + 1));
+
+        // How much the unit stands out from the average of its neighbours, for blending lone units
+        let average = (2.0 * (north + south + west + east) + north_west + north_east + south_west + south_east) / 12.0;
+        let standing_out = ((average - center).abs() / range).clamp(0.0, 1.0);
+        let smoothed = (-2.0 * standing_out + 3.0) * standing_out * standing_out;
+        let subpixel_offset = smoothed * smoothed * self.subpixel;
+
+        // Whether the edge runs horizontally (brightness changes most going up or down) or vertically
+        let horizontal = (north_west - 2.0 * west + south_west).abs()
+            + 2.0 * (north - 2.0 * center + south).abs()
+            + (north_east - 2.0 * east + south_east).abs()
+            >= (north_west - 2.0 * north + north_east).abs()
+            + 2.0 * (west - 2.0 * center + east).abs()
+            + (south_west - 2.0 * south + south_east).abs();
+
+        // Which side of the unit the edge is on, the one with the steeper change
+        let (before, after) = if horizontal { (north, south) } else { (west, east) };
+        let (gradient_before, gradient_after) = ((before - center).abs(), (after - center).abs());
+        let (step, across_luma, gradient) = if gradient_before >= gradient_after {
+            (-1.0, before, gradient_before)
+        } else {
+            (1.0, after, gradient_after)
+        };
+        let edge_luma = (center + across_luma) / 2.0;
+        let threshold = gradient / 4.0;
+
+        // Follow the edge both ways from halfway between the unit and the one across it, until the brightness along it
+        // no longer matches
+        let (start_x, start_y) = if horizontal {
+            (x as Float + 0.5, y as Float + 0.5 + step / 2.0)
+        } else {
+            (x as Float + 0.5 + step / 2.0, y as Float + 0.5)
+        };
+        let along = if horizontal { (1.0, 0.0) } else { (0.0, 1.0) };
+        let walk = |direction: Float| {
+            let mut dist = 0.0;
+            let mut delta = 0.0;
+            for stride in EDGE_STEPS {
+                dist += stride;
+                delta = luma.sample(start_x + along.0 * dist * direction, start_y + along.1 * dist * direction) - edge_luma;
+                if delta.abs() >= threshold {
+                    break;
+                }
+            }
+            (dist, delta)
+        };
+        let ((dist_back, delta_back), (dist_forward, delta_forward)) = (walk(-1.0), walk(1.0));
+
+        // Only the end of the edge nearer the unit matters. Past it, the edge steps towards the unit's side when the
+        // brightness there is on the same side of the edge's as the unit's, which is where the unit is blended
+        let (dist, delta) = if dist_back < dist_forward { (dist_back, delta_back) } else { (dist_forward, delta_forward) };
+        let edge_offset = if (delta < 0.0) != (center - edge_luma < 0.0) {
+            0.5 - dist / (dist_back + dist_forward)
+        } else {
+            0.0
+        };
+
+        let offset = edge_offset.max(subpixel_offset) * step;
+        Some(if horizontal { (0.0, offset) } else { (offset, 0.0) })
+    }
+}
+
+// Displayed brightness of every canvas unit of a frame
+struct Luma {
+    values: Vec<Float>,
+    width: usize,
+    height: usize
+}
+
+impl Luma {
+    fn at(&self, x: isize, y: isize) -> Float {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.values[y * self.width + x]
+    }
+
+    // Blended from the four units around a point in units from the top left
+    fn sample(&self, x: Float, y: Float) -> Float {
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as isize, y0 as isize);
+        (self.at(x0, y0) * (1.0 - fx) + self.at(x0 + 1, y0) * fx) * (1.0 - fy)
+            + (self.at(x0, y0 + 1) * (1.0 - fx) + self.at(x0 + 1, y0 + 1) * fx) * fy
+    }
+}
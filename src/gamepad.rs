@@ -0,0 +1,67 @@
+use gilrs::{Axis, GamepadId, Gilrs};
+use log::warn;
+
+use crate::linalg::Float;
+
+/*
+
+Gamepad
+
+Navigation with a game controller, read through gilrs. The left stick walks the camera and the right stick turns it.
+Speed follows how far a stick is pushed, squared, so small pushes creep up on an object while full pushes sweep across
+the scene. Whichever connected controller was used last is followed
+
+*/
+
+pub struct Gamepad {
+    gilrs: Gilrs,
+    active: Option<GamepadId> // The controller that sent the latest input
+}
+
+// How far each stick is pushed, from -1 to 1 along each axis, with right and up positive
+#[derive(Clone, Copy, Default)]
+pub struct Sticks {
+    pub left: (Float, Float),
+    pub right: (Float, Float)
+}
+
+impl Gamepad {
+    // None when controllers can't be read on this platform
+    pub fn new() -> Option<Self> {
+        match Gilrs::new() {
+            Ok(gilrs) => Some(Self { gilrs, active: None }),
+            Err(e) => {
+                warn!("Gamepad input is unavailable: {}", e);
+                None
+            }
+        }
+    }
+
+    // Where the sticks of the controller used last are now. Centered when no controller is connected
+    pub fn sticks(&mut self) -> Sticks {
+        while let Some(event) = self.gilrs.next_event() {
+            self.active = Some(event.id);
+        }
+        let Some(gamepad) = self.active.and_then(|id| self.gilrs.connected_gamepad(id)) else {
+            return Sticks::default();
+        };
+
+        let axis = |axis| gamepad.value(axis) as Float;
+        Sticks {
+            left: (axis(Axis::LeftStickX), axis(Axis::LeftStickY)),
+            right: (axis(Axis::RightStickX), axis(Axis::RightStickY))
+        }
+    }
+}
+
+impl Sticks {
+    // Whether either stick is pushed at all. gilrs already drops the small drift of a stick at rest
+    pub fn pushed(&self) -> bool {
+        [self.left.0, self.left.1, self.right.0, self.right.1].iter().any(|&v| v != 0.0)
+    }
+}
+
+// Speed for a stick pushed this far, from -1 to 1, keeping its direction
+pub fn response(push: Float) -> Float {
+    push * push.abs()
+}
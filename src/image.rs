@@ -0,0 +1,151 @@
+use std::io::{self, Read, Write};
+
+/*
+
+Image Input and Output
+
+Encoding of rendered frames, e.g. to save a render or send it over the network, and decoding of images, e.g. textures
+
+Channels that don't fit in 8 bits, like depth, are written as 16-bit gray PNGs or as OpenEXR files of 32-bit floats.
+The EXR files are the simplest the format allows: uncompressed, one scanline at a time
+
+*/
+
+// A rendered frame, as packed 0RGB pixels row by row from the top left
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub pixels: Vec<u32>,
+    pub width: usize,
+    pub height: usize
+}
+
+impl Image {
+    pub fn pixel(&self, x: usize, y: usize) -> u32 {
+        self.pixels[y * self.width + x]
+    }
+
+    // The pixels as RGB bytes, e.g. for other image libraries
+    pub fn to_rgb(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|p| [(p >> 16) as u8, (p >> 8) as u8, *p as u8]).collect()
+    }
+
+    pub fn write_png(&self, writer: impl Write) -> Result<(), png::EncodingError> {
+        write_png(writer, &self.pixels, self.width, self.height)
+    }
+
+    // Read a PNG of any color type and bit depth. Transparency is dropped, and gray images become gray pixels
+    pub fn read_png(reader: impl Read) -> Result<Self, png::DecodingError> {
+        Self::read_png_with_alpha(reader).map(|(image, _)| image)
+    }
+
+    // Read a PNG along with how opaque each pixel is, from 0 (transparent) to 255, if the PNG has transparency
+    pub fn read_png_with_alpha(reader: impl Read) -> Result<(Self, Option<Vec<u8>>), png::DecodingError> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data)?;
+
+        let channels = info.color_type.samples();
+        let texels = data[..info.buffer_size()].chunks_exact(channels);
+        let pixels = texels.clone().map(|px| {
+            let (r, g, b) = if channels < 3 { (px[0], px[0], px[0]) } else { (px[0], px[1], px[2]) };
+            (r as u32) << 16 | (g as u32) << 8 | b as u32
+        }).collect();
+        // Gray and alpha, or RGB and alpha, keep alpha last
+        let alpha = (channels % 2 == 0).then(|| texels.map(|px| px[channels - 1]).collect());
+
+        Ok((Self { pixels, width: info.width as usize, height: info.height as usize }, alpha))
+    }
+}
+
+// Write packed 0RGB pixels as an 8-bit RGB PNG
+pub fn write_png(writer: impl Write, buffer: &[u32], width: usize, height: usize) -> Result<(), png::EncodingError> {
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let data: Vec<u8> = buffer.iter().flat_map(|p| [(p >> 16) as u8, (p >> 8) as u8, *p as u8]).collect();
+    encoder.write_header()?.write_image_data(&data)
+}
+
+// Write gray levels as a 16-bit grayscale PNG
+pub fn write_png16(writer: impl Write, levels: &[u16], width: usize, height: usize) -> Result<(), png::EncodingError> {
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Sixteen);
+
+    let data: Vec<u8> = levels.iter().flat_map(|level| level.to_be_bytes()).collect();
+    encoder.write_header()?.write_image_data(&data)
+}
+
+const EXR_MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+const EXR_VERSION: u32 = 2;    // Single part, scanlines
+const EXR_FLOAT: i32 = 2;      // 32-bit float pixels
+const EXR_NO_COMPRESSION: u8 = 0;
+const EXR_INCREASING_Y: u8 = 0; // Scanlines stored top to bottom
+
+// Write named channels of 32-bit floats, each a value per pixel row by row from the top left, as an OpenEXR file
+pub fn write_exr(mut writer: impl Write, channels: &[(&str, &[f32])], width: usize, height: usize) -> io::Result<()> {
+    // Channels are listed and stored in alphabetical order
+    let mut channels = channels.to_vec();
+    channels.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut header = Vec::new();
+    header.extend(EXR_MAGIC);
+    header.extend(EXR_VERSION.to_le_bytes());
+
+    let mut chlist = Vec::new();
+    for (name, _) in &channels {
+        chlist.extend(name.as_bytes());
+        chlist.push(0);
+        chlist.extend(EXR_FLOAT.to_le_bytes());
+        chlist.extend([0; 4]); // Not perceptually linear, and reserved
+        chlist.extend(1i32.to_le_bytes()); // Sampled at every pixel across and down
+        chlist.extend(1i32.to_le_bytes());
+    }
+    chlist.push(0);
+
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1].iter().flat_map(|v| v.to_le_bytes()).collect();
+    let attributes: [(&str, &str, Vec<u8>); 8] = [
+        ("channels", "chlist", chlist),
+        ("compression", "compression", vec![EXR_NO_COMPRESSION]),
+        ("dataWindow", "box2i", window.clone()),
+        ("displayWindow", "box2i", window),
+        ("lineOrder", "lineOrder", vec![EXR_INCREASING_Y]),
+        ("pixelAspectRatio", "float", 1f32.to_le_bytes().to_vec()),
+        ("screenWindowCenter", "v2f", [0f32, 0f32].iter().flat_map(|v| v.to_le_bytes()).collect()),
+        ("screenWindowWidth", "float", 1f32.to_le_bytes().to_vec())
+    ];
+    for (name, kind, value) in attributes {
+        for text in [name, kind] {
+            header.extend(text.as_bytes());
+            header.push(0);
+        }
+        header.extend((value.len() as i32).to_le_bytes());
+        header.extend(value);
+    }
+    header.push(0);
+
+    // A table of where every scanline starts follows the header, then the scanlines, each its row number, its size and
+    // then every channel's values along the row in turn
+    let line_size = channels.len() * width * 4;
+    let lines_start = header.len() + height * 8;
+    for row in 0..height {
+        let offset = (lines_start + row * (8 + line_size)) as u64;
+        header.extend(offset.to_le_bytes());
+    }
+    writer.write_all(&header)?;
+
+    let mut line = Vec::with_capacity(8 + line_size);
+    for row in 0..height {
+        line.clear();
+        line.extend((row as i32).to_le_bytes());
+        line.extend((line_size as i32).to_le_bytes());
+        for (_, values) in &channels {
+            line.extend(values[row * width..(row + 1) * width].iter().flat_map(|v| v.to_le_bytes()));
+        }
+        writer.write_all(&line)?;
+    }
+    Ok(())
+}
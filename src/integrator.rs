@@ -0,0 +1,432 @@
+use std::{fmt, str::FromStr};
+
+use rand::Rng;
+
+use crate::{
+    color::Color,
+    linalg::{consts::PI, Float, Ray, Vec3d},
+    object::{HitRecord, Material, Object, RayKind},
+    utils::Range,
+    Scene
+};
+
+/*
+
+Integrator
+
+How the light arriving along a camera ray is found. The Whitted integrator lights surfaces directly from every source
+and follows mirror reflections. The path tracer follows one random path of bounces per sample instead, so it also picks
+up light reflected between surfaces (color bleeding, soft occlusion of ambient light) and blurry glossy reflections,
+at the cost of noise that averages away as samples are added
+
+Bounce directions are importance sampled from the surface's BRDF: cosine weighted for matte surfaces and from the GGX
+microfacet distribution for the glossy part of shiny ones, so the directions that reflect the most light are the ones
+sampled most often
+
+Glowing objects are also sampled directly at every bounce (next event estimation), so small bright lamps don't depend
+on bounces happening to hit them. Light found both ways is weighted by multiple importance sampling (power heuristic),
+so each technique counts most where it's least noisy: light sampling for small lamps, BRDF sampling for glossy surfaces
+
+The ambient occlusion integrator ignores lights and materials altogether. Every surface is white, darkened by how much
+of the hemisphere above it is blocked by nearby geometry, which is a quick way to check a scene's layout
+
+*/
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Integrator {
+    #[default]
+    Whitted,
+    PathTraced,
+    AmbientOcclusion
+}
+
+const AO_RAYS: usize = 16;      // Rays sent over the hemisphere above every surface seen
+const AO_DISTANCE: Float = 4.0; // Geometry further away than this doesn't occlude
+
+impl Scene {
+    // Trace a random path of up to max_bounces bounces starting along a camera ray, and return the light it sees, as
+    // linear RGB that isn't limited to 1
+    pub fn trace_path(&self, ray: &Ray, t_range: &Range<Float>, max_bounces: u32, rng: &mut impl Rng) -> [Float; 3] {
+        self.trace_path_hit(ray, t_range, max_bounces, rng, self.closest_intersection(ray, t_range, RayKind::Camera))
+    }
+
+    // trace_path, given what the camera ray hits first
+    pub(crate) fn trace_path_hit(&self, ray: &Ray, t_range: &Range<Float>, max_bounces: u32, rng: &mut impl Rng, hit: Option<(&dyn Object, HitRecord)>) -> [Float; 3] {
+        match hit {
+            Some((obj, hit)) if obj.render_flags().shadow_catcher => {
+                // Seen through, darkened by the shadows falling on it
+                let dist = hit.t * ray.dir().magnitude();
+                let behind_range = Range::new(hit.t + self.bias(dist, ray.dir()), t_range.max);
+                let behind_hit = self.closest_intersection(ray, &behind_range, RayKind::Through);
+                let behind = self.trace_path_hit(ray, &behind_range, max_bounces, rng, behind_hit);
+                let lit = self.lit_fraction(&hit.point, dist, ray.view());
+                behind.map(|c| c * lit)
+            }
+            Some((obj, hit)) => {
+                let dist = (&hit.point - ray.origin()).magnitude();
+                let radiance = self.path_radiance(ray, obj, hit, t_range.max, max_bounces, rng);
+                self.apply_media_to_radiance(ray, t_range, dist, radiance)
+            }
+            // The background lights the scene as much as it's seen, like the light outside a path escapes into
+            None => self.apply_media_to_radiance(ray, t_range, Float::INFINITY, Color::decode(self.background(ray)))
+        }
+    }
+
+    // apply_media for linear light. Volumes, fog and haze blend encoded colors, so the light is only encoded for them
+    // when the scene has any
+    fn apply_media_to_radiance(&self, ray: &Ray, t_range: &Range<Float>, dist: Float, radiance: [Float; 3]) -> [Float; 3] {
+        if self.volumes.is_empty() && self.fog.is_none() && self.atmosphere.is_none() {
+            return radiance;
+        }
+        Color::decode(self.apply_media(ray, t_range, dist, Color::encode(radiance)))
+    }
+
+    // How much of the hemisphere above the surface seen along a camera ray is open, as a linear shade of gray
+    pub fn trace_occlusion(&self, ray: &Ray, t_range: &Range<Float>, rng: &mut impl Rng) -> [Float; 3] {
+        self.trace_occlusion_hit(ray, rng, self.closest_intersection(ray, t_range, RayKind::Camera))
+    }
+
+    // trace_occlusion, given what the camera ray hits first
+    pub(crate) fn trace_occlusion_hit(&self, ray: &Ray, rng: &mut impl Rng, hit: Option<(&dyn Object, HitRecord)>) -> [Float; 3] {
+        let Some((_, hit)) = hit else {
+            return [1.0; 3];
+        };
+        let (norm, HitRecord { point: intxp, t, .. }) = (hit.facing_normal(), hit);
+        let (tangent, bitangent) = basis(&norm);
+
+        // Cosine weighted, so rays closer to the normal count for more as they would for a matte surface
+        let open = (0..AO_RAYS).filter(|_| {
+            let (u1, u2): (Float, Float) = (rng.random(), rng.random());
+            let (r, phi) = (u1.sqrt(), 2.0 * PI * u2);
+            let dir = &(&(&tangent * (r * phi.cos())) + &(&bitangent * (r * phi.sin()))) + &(&norm * (1.0 - u1).sqrt());
+            let t_min = self.bias(t * ray.dir().magnitude(), &dir);
+            self.closest_intersection(&Ray::new(intxp.clone(), dir).with_view(ray.view().clone()), &Range::new(t_min, AO_DISTANCE), RayKind::Shadow).is_none()
+        }).count();
+
+        [open as Float / AO_RAYS as Float; 3]
+    }
+
+    // Light leaving the first surface a path hits towards where the path came from
+    // Volumes and fog are only accounted for along the camera ray, not the bounces
+    fn path_radiance(&self, ray: &Ray, obj: &dyn Object, hit: HitRecord, t_max: Float, max_bounces: u32, rng: &mut impl Rng) -> [Float; 3] {
+        let mut radiance = [0.0; 3];
+        let mut throughput = [1.0; 3]; // Fraction of the light at the current surface that makes it back along the path
+        // Paths shade the side of each surface they arrive on
+        let (mut norm, mut color) = (hit.facing_normal(), self.surface_color(obj, &hit));
+        let (mut obj, mut p, mut wo) = (obj, hit.point, (ray.dir() * -1.0).normalize());
+        let view = ray.view(); // Every ray along the path is seen from the camera's view
+        let mut dist = hit.t * ray.dir().magnitude(); // How far the current surface is along the ray that found it
+        let mut bounce_from: Option<(Vec3d, Float)> = None; // Where the last bounce left from and the BRDF's density for it
+
+        // The path goes one step past its last bounce, only to see whether the BRDF's last direction finds a lamp or
+        // escapes, as that light is shared with light and portal sampling there and would otherwise only count in part
+        for bounce in 0..=max_bounces.saturating_add(1) {
+            if let Material::Emissive { intensity, .. } = obj.get_material() {
+                // Lamps seen straight from the camera count fully, ones found by a bounce share with light sampling
+                let weight = match &bounce_from {
+                    Some((from, brdf_pdf)) => power_heuristic(*brdf_pdf, obj.direction_pdf(from, view, &(&wo * -1.0)) / self.emitters.len() as Float),
+                    None => 1.0
+                };
+                let emitted = Color::to_linear(color);
+                for c in 0..3 {
+                    radiance[c] += throughput[c] * emitted[c] * intensity * weight;
+                }
+                break;
+            }
+            if bounce > max_bounces {
+                break;
+            }
+
+            let brdf = Brdf::new(color, obj.get_material());
+
+            // Point, directional and tube lights aren't objects, so a path can never hit one by chance. Their light is
+            // added at every surface instead
+            for (wi, light) in self.visible_lights(&p, dist, view, rng) {
+                let cos = &norm * &wi;
+                if cos > 0.0 {
+                    let f = brdf.eval(&norm, &wo, &wi);
+                    for c in 0..3 {
+                        radiance[c] += throughput[c] * f[c] * PI * light[c] * cos;
+                    }
+                }
+            }
+
+            // Light from one glowing object, chosen at random
+            if let Some(light) = self.sample_emitter(&p, dist, view, &brdf, &norm, &wo, rng) {
+                for c in 0..3 {
+                    radiance[c] += throughput[c] * light[c];
+                }
+            }
+
+            // Light from outside, through a window marked by a portal chosen at random, if nothing is in the way
+            if let Some((wi, portal_pdf)) = self.sample_portal(&p, rng).filter(|(wi, _)| &norm * wi > 0.0) {
+                let portal_ray = Ray::new(p.clone(), wi.clone()).with_view(view.clone());
+                if self.closest_intersection(&portal_ray, &Range::new(self.bias(dist, &wi), t_max), RayKind::Reflection).is_none() {
+                    let f = brdf.eval(&norm, &wo, &wi);
+                    let outside = self.outside_light(&portal_ray);
+                    let scale = (&norm * &wi) * power_heuristic(portal_pdf, brdf.pdf(&norm, &wo, &wi)) / portal_pdf;
+                    for c in 0..3 {
+                        radiance[c] += throughput[c] * f[c] * outside[c] * scale;
+                    }
+                }
+            }
+
+            let Some((wi, weight, pdf)) = brdf.sample(&norm, &wo, rng) else {
+                break;
+            };
+            for c in 0..3 {
+                throughput[c] *= weight[c];
+            }
+
+            let bounce_ray = Ray::new(p.clone(), wi.clone()).with_view(view.clone());
+            match self.closest_intersection(&bounce_ray, &Range::new(self.bias(dist, &wi), t_max), RayKind::Reflection) {
+                Some((next_obj, next_hit)) => {
+                    bounce_from = Some((p, pdf));
+                    dist = next_hit.t * wi.magnitude();
+                    (norm, color) = (next_hit.facing_normal(), self.surface_color(next_obj, &next_hit));
+                    (obj, p, wo) = (next_obj, next_hit.point, &wi * -1.0);
+                }
+                None => {
+                    // Paths escaping through a portal share the light outside with portal sampling
+                    let weight = power_heuristic(pdf, self.portal_pdf(&p, &wi));
+                    let outside = self.outside_light(&bounce_ray);
+                    for c in 0..3 {
+                        radiance[c] += throughput[c] * outside[c] * weight;
+                    }
+                    break;
+                }
+            }
+        }
+
+        radiance
+    }
+
+    // Light reflected from p (hit_dist along the ray that found it, seen from its view) towards wo that arrives straight
+    // from a randomly chosen glowing object, weighted against finding the same light by sampling the BRDF
+    #[allow(clippy::too_many_arguments)]
+    fn sample_emitter(&self, p: &Vec3d, hit_dist: Float, view: &Vec3d, brdf: &Brdf, norm: &Vec3d, wo: &Vec3d, rng: &mut impl Rng) -> Option<[Float; 3]> {
+        if self.emitters.is_empty() {
+            return None;
+        }
+
+        let emitter = &self.objs[self.emitters[rng.random_range(0..self.emitters.len())]];
+        let Material::Emissive { intensity, .. } = emitter.get_material() else {
+            return None;
+        };
+        let (wi, pdf) = emitter.sample_direction(p, view, (rng.random(), rng.random()))?;
+        let light_pdf = pdf / self.emitters.len() as Float;
+
+        let cos = norm * &wi;
+        if cos <= 0.0 {
+            return None;
+        }
+
+        // The light only arrives if nothing else is in the way
+        let light_ray = Ray::new(p.clone(), wi.clone()).with_view(view.clone());
+        let t_min = self.bias(hit_dist, &wi);
+        let emitter_hit = emitter.get_closest_intersection(&light_ray, &Range::new(t_min, Float::INFINITY))?;
+        let dist = emitter_hit.t;
+        let shdw_range = Range::new(t_min, dist * (1.0 - 1e-6));
+        if self.closest_intersection(&light_ray, &shdw_range, RayKind::Shadow).is_some() {
+            return None;
+        }
+
+        let f = brdf.eval(norm, wo, &wi);
+        let emitted = Color::to_linear(self.surface_color(emitter, &emitter_hit));
+        let scale = intensity * self.volume_transmittance(&light_ray, &shdw_range) * cos
+            * power_heuristic(light_pdf, brdf.pdf(norm, wo, &wi)) / light_pdf;
+
+        Some([0, 1, 2].map(|c| f[c] * emitted[c] * scale))
+    }
+
+    // Light arriving along a ray that escapes the scene: the background, and ambient light arriving evenly from every
+    // direction
+    fn outside_light(&self, ray: &Ray) -> [Float; 3] {
+        let ambient = self.ambient_intensity();
+        Color::decode(self.background(ray)).map(|c| c + ambient)
+    }
+
+    // A unit direction from p through a random point of a portal chosen at random, and the density of choosing it over
+    // every portal, as a window can be seen through another
+    fn sample_portal(&self, p: &Vec3d, rng: &mut impl Rng) -> Option<(Vec3d, Float)> {
+        if self.portals.is_empty() {
+            return None;
+        }
+        let portal = &self.portals[rng.random_range(0..self.portals.len())];
+        let (wi, _) = portal.sample_direction(p, (rng.random(), rng.random()))?;
+        let pdf = self.portal_pdf(p, &wi);
+        (pdf > 0.0).then_some((wi, pdf))
+    }
+
+    // The density sample_portal chooses a unit direction from p with
+    fn portal_pdf(&self, p: &Vec3d, dir: &Vec3d) -> Float {
+        if self.portals.is_empty() {
+            return 0.0;
+        }
+        self.portals.iter().map(|portal| portal.direction_pdf(p, dir)).sum::<Float>() / self.portals.len() as Float
+    }
+
+    // Directions towards the point, directional and tube lights that reach a point dist along the ray that found it, and
+    // the linear light arriving from each (its intensity tinted by its color), seen from that ray's view. Tube lights are
+    // reached at a random place along them
+    fn visible_lights(&self, p: &Vec3d, dist: Float, view: &Vec3d, rng: &mut impl Rng) -> Vec<(Vec3d, [Float; 3])> {
+        self.lights.iter().filter_map(|light| {
+            let (light_dir, intensity, t_max) = light.sample_towards(p, rng)?;
+            let shdw_range = Range::new(self.bias(dist, &light_dir), t_max);
+
+            let light_ray = Ray::new(p.clone(), light_dir).with_view(view.clone());
+            if self.closest_intersection(&light_ray, &shdw_range, RayKind::Shadow).is_some() {
+                return None;
+            }
+
+            let intensity = intensity * self.volume_transmittance(&light_ray, &shdw_range);
+            Some((light_ray.dir().normalize(), Color::to_linear(light.color()).map(|c| c * intensity)))
+        }).collect()
+    }
+}
+
+/*
+
+BRDF
+
+How much light arriving at a surface from one direction is reflected towards another. Matte surfaces reflect evenly
+in every direction (Lambertian). Shiny surfaces reflect their reflection ratio of light in a glossy GGX lobe around
+the mirror direction and the rest like matte surfaces, with the specular exponent setting how tight the lobe is
+
+*/
+
+struct Brdf {
+    albedo: [Float; 3], // Fraction of red, green and blue light reflected diffusely
+    specular: Float,    // Fraction of light reflected by the glossy lobe instead
+    alpha: Float        // GGX roughness, 0 is a perfect mirror
+}
+
+impl Brdf {
+    fn new(color: usize, material: &Material) -> Self {
+        let (specular, alpha) = match material {
+            Material::Matte { .. } | Material::Emissive { .. } => (0.0, 1.0),
+            // The roughness whose highlight best matches a Phong highlight with this exponent
+            Material::Shiny { spclr_exp, refl_rat, .. } => (*refl_rat, (2.0 / (spclr_exp + 2.0)).sqrt())
+        };
+
+        Self {
+            albedo: Color::to_linear(color),
+            specular,
+            alpha
+        }
+    }
+
+    // Reflected light per unit of light arriving from wi, when seen from wo
+    fn eval(&self, norm: &Vec3d, wo: &Vec3d, wi: &Vec3d) -> [Float; 3] {
+        let diffuse = self.albedo.map(|a| a * (1.0 - self.specular) / PI);
+        if self.specular <= 0.0 {
+            return diffuse;
+        }
+
+        let (cos_o, cos_i) = (norm * wo, norm * wi);
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return diffuse;
+        }
+
+        let h = (wo + wi).normalize();
+        let glossy = self.specular * self.ggx_d(norm * &h) * self.smith_g1(cos_o) * self.smith_g1(cos_i) / (4.0 * cos_o * cos_i);
+        diffuse.map(|d| d + glossy)
+    }
+
+    // Probability density of sample choosing wi when seen from wo
+    fn pdf(&self, norm: &Vec3d, wo: &Vec3d, wi: &Vec3d) -> Float {
+        let cos_i = norm * wi;
+        if cos_i <= 0.0 {
+            return 0.0;
+        }
+
+        let diffuse_pdf = cos_i / PI;
+        if self.specular <= 0.0 {
+            return diffuse_pdf;
+        }
+
+        let h = (wo + wi).normalize();
+        let glossy_pdf = self.ggx_d(norm * &h) * (norm * &h) / (4.0 * (wo * &h).abs());
+        (1.0 - self.specular) * diffuse_pdf + self.specular * glossy_pdf
+    }
+
+    // Choose a direction for light to arrive from, in proportion to how much it reflects towards wo
+    // Returns the direction, the BRDF times the cosine over the probability of choosing it, and that probability
+    fn sample(&self, norm: &Vec3d, wo: &Vec3d, rng: &mut impl Rng) -> Option<(Vec3d, [Float; 3], Float)> {
+        let (tangent, bitangent) = basis(norm);
+        let local = |x: Float, y: Float, z: Float| &(&(&tangent * x) + &(&bitangent * y)) + &(norm * z);
+        let (u1, u2): (Float, Float) = (rng.random(), rng.random());
+        let phi = 2.0 * PI * u2;
+
+        let wi = if rng.random::<Float>() < self.specular {
+            // Microfacet normal from the GGX distribution, mirrored around to get the light direction
+            let cos_theta = ((1.0 - u1) / (1.0 + (self.alpha * self.alpha - 1.0) * u1)).sqrt();
+            let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+            let h = local(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+            wo.reflect(&h)
+        } else {
+            // Cosine weighted direction in the hemisphere
+            let r = u1.sqrt();
+            local(r * phi.cos(), r * phi.sin(), (1.0 - u1).sqrt())
+        };
+
+        let pdf = self.pdf(norm, wo, &wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        let cos_i = norm * &wi;
+        let weight = self.eval(norm, wo, &wi).map(|f| f * cos_i / pdf);
+        Some((wi, weight, pdf))
+    }
+
+    // GGX distribution of microfacet normals
+    fn ggx_d(&self, cos_h: Float) -> Float {
+        let a2 = self.alpha * self.alpha;
+        let d = cos_h * cos_h * (a2 - 1.0) + 1.0;
+        a2 / (PI * d * d)
+    }
+
+    // Fraction of microfacets facing a direction that aren't hidden by others (Smith)
+    fn smith_g1(&self, cos: Float) -> Float {
+        let a2 = self.alpha * self.alpha;
+        2.0 * cos / (cos + (a2 + (1.0 - a2) * cos * cos).sqrt())
+    }
+}
+
+// Weight of a sample from a technique with density pdf, when another technique could have found it with density other_pdf
+fn power_heuristic(pdf: Float, other_pdf: Float) -> Float {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b > 0.0 { a / (a + b) } else { 0.0 }
+}
+
+// Two unit vectors perpendicular to a unit normal and each other
+fn basis(norm: &Vec3d) -> (Vec3d, Vec3d) {
+    let up = if norm.x().abs() > 0.9 { Vec3d::new(0.0, 1.0, 0.0) } else { Vec3d::new(1.0, 0.0, 0.0) };
+    let tangent = up.cross(norm).normalize();
+    let bitangent = norm.cross(&tangent);
+    (tangent, bitangent)
+}
+
+impl fmt::Display for Integrator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Integrator::Whitted => write!(f, "whitted"),
+            Integrator::PathTraced => write!(f, "path"),
+            Integrator::AmbientOcclusion => write!(f, "ao")
+        }
+    }
+}
+
+impl FromStr for Integrator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "whitted" => Ok(Integrator::Whitted),
+            "path" => Ok(Integrator::PathTraced),
+            "ao" => Ok(Integrator::AmbientOcclusion),
+            _ => Err(format!("unknown integrator \"{}\", expected whitted, path or ao", s))
+        }
+    }
+}
@@ -1,17 +1,92 @@
+pub mod aov;
+pub mod atmosphere;
+pub mod bloom;
+pub mod cas;
 pub mod color;
+pub mod compare;
+pub mod config;
+pub mod controls;
+pub mod decal;
+pub mod display;
+pub mod distributed;
+pub mod error;
+pub mod export;
+pub mod falloff;
+#[cfg(feature = "ffi")]
+#[allow(clippy::missing_safety_doc)] // Each function says what it needs of its pointers in its comment
+mod ffi;
+pub mod filter;
+pub mod fxaa;
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+pub mod image;
+pub mod integrator;
 pub mod linalg;
+pub mod material;
+mod motion;
+pub mod obj;
 pub mod object;
+pub mod pipeline;
+pub mod portal;
+pub mod post;
+#[cfg(feature = "egui")]
+pub mod overlay;
 pub mod light;
+pub mod ply;
+pub mod prelude;
+pub mod progress;
+#[cfg(feature = "python")]
+mod python;
+pub mod sample_pattern;
+pub mod scene_builder;
+pub mod scene_file;
+pub mod stereo;
+pub mod text;
+pub mod texture;
+pub mod time_of_day;
+pub mod turntable;
+pub mod upscale;
 pub mod utils;
+pub mod volume;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
-use std::{f64::{EPSILON, INFINITY}, sync::{Arc, Mutex, RwLock}, thread};
+use std::{sync::{mpsc, Arc, RwLock}, thread};
 
+use aov::Aov;
+use atmosphere::Atmosphere;
+use cas::Cas;
 use color::Color;
-use linalg::{Mat3, Ray, Vec3d};
-use object::{Material, Object, closest_intersection};
+use compare::Comparison;
+use config::{RendererBuilder, RendererConfig, MAX_DEPTH};
+use controls::Controls;
+use decal::Decal;
+use display::{Display, Key};
+use error::{RendererError, SceneError};
+use filter::Filter;
+use fxaa::Fxaa;
+use image::Image;
+use integrator::Integrator;
+use linalg::{Float, Mat3, Ray, Vec3d, RAY_EPSILON};
+use material::presets;
+use motion::{Motion, View};
+use object::{HitRecord, Material, Object, Primitive, RayKind, Tlas, TraversalContext, PACKET_SIZE};
+use pipeline::Pipeline;
+use portal::Portal;
+use post::{Frame, PostEffect};
 use light::LightSource;
+use log::{debug, info};
+use progress::{CancelToken, Progress};
+use sample_pattern::SamplePattern;
+use scene_builder::SceneBuilder;
+use stereo::{Eye, Stereo};
+use time_of_day::TimeOfDay;
+use turntable::Turntable;
+use upscale::Upscale;
 use rand::Rng;
-use utils::Range;
+use rand_pcg::Pcg32;
+use utils::{frame_seed, Instant, Range};
+use volume::Volume;
 
 /*
 
@@ -22,36 +97,29 @@ The underlying structure supporting the drawable canvas
 */
 
 struct Screen {
-    window: minifb::Window,
+    display: Box<dyn Display>,
     buffer: Vec<u32>,
     width: usize,
     height: usize
 }
 
 impl Screen {
-    fn build(screen_width: usize, screen_height: usize) -> Self {
-        let mut window = minifb::Window::new(
-            "Press ESC to exit",
-            screen_width,
-            screen_height,
-            minifb::WindowOptions::default(),
-        )
-        .unwrap_or_else(|e| {
-            panic!("Unable to open window: {}", e);
-        });
-    
-        window.set_target_fps(60);
-
+    fn new(display: Box<dyn Display>, screen_width: usize, screen_height: usize) -> Self {
         Self {
-            window,
+            display,
             buffer: vec![0; screen_width * screen_height],
             width: screen_width,
             height: screen_height
         }
     }
 
-    fn render_buffer(&mut self) {
-        self.window.update_with_buffer(&self.buffer, self.width, self.height).unwrap();
+    fn resize(&mut self, width: usize, height: usize) {
+        (self.width, self.height) = (width, height);
+        self.buffer = vec![0; width * height];
+    }
+
+    fn render_buffer(&mut self) -> Result<(), RendererError> {
+        self.display.present(&self.buffer, self.width, self.height)
     }
 }
 
@@ -59,31 +127,71 @@ impl Screen {
 
 Canvas
 
+Double buffered, so a finished frame can be presented while worker threads trace the next one into the other buffer
+
+The canvas is traced with fewer, larger units while the camera moves, so the two buffers can differ in size
+
 */
 
+// Buffers hold packed 0RGB canvas units, row by row from the top left, alongside the linear light they were encoded from
 struct Canvas {
-    buffer: Mutex<Vec<Vec<usize>>>,
+    buffer: Vec<u32>,    // The frame being traced. The worker threads are given it while they trace
+    front: Vec<u32>,     // The last finished frame, which is presented
+    linear: Vec<[Float; 3]>, // Linear RGB of the frame being traced, which its samples are accumulated in
+    front_linear: Vec<[Float; 3]>, // Linear RGB of the last finished frame
+    aovs: Vec<Vec<[Float; 3]>>, // Every AOV of the frame being traced, in the order the renderer records them
+    front_aovs: Vec<Vec<[Float; 3]>>, // Every AOV of the last finished frame
+    row_costs: Vec<f64>, // Seconds each row took to trace last time, used to share out the next frame's rows evenly
     width: usize, 
-    height: usize
+    height: usize,
+    front_width: usize,  // Size of the last finished frame
+    front_height: usize
 }
 
 impl Canvas {
-    fn new(screen_width: usize, screen_height: usize, canvas_unit_size: usize) -> Self {
+    fn new(width: usize, height: usize, num_aovs: usize) -> Self {
         Self {
-            buffer: Mutex::new(vec![vec![0; screen_width]; screen_height]),
-            width: screen_width / canvas_unit_size,
-            height: screen_height / canvas_unit_size,
+            buffer: vec![0; width * height],
+            front: vec![0; width * height],
+            linear: vec![[0.0; 3]; width * height],
+            front_linear: vec![[0.0; 3]; width * height],
+            aovs: vec![vec![[0.0; 3]; width * height]; num_aovs],
+            front_aovs: vec![vec![[0.0; 3]; width * height]; num_aovs],
+            row_costs: vec![1.0; height],
+            width,
+            height,
+            front_width: width,
+            front_height: height
         }
     }
 
-    fn clear(&self) {
-        let mut buffer = self.buffer.lock().unwrap(); 
-        for row in buffer.iter_mut() {
-            for p in row {
-                *p = 0;
-            }
+    // Present the frame just traced, and trace the next one over the frame that was presented
+    fn swap(&mut self) {
+        std::mem::swap(&mut self.buffer, &mut self.front);
+        std::mem::swap(&mut self.linear, &mut self.front_linear);
+        std::mem::swap(&mut self.aovs, &mut self.front_aovs);
+        (self.front_width, self.front_height) = (self.width, self.height);
+    }
+
+    fn clear(&mut self) {
+        self.buffer.clear();
+        self.buffer.resize(self.width * self.height, 0);
+        self.linear.clear();
+        self.linear.resize(self.width * self.height, [0.0; 3]);
+        for aov in self.aovs.iter_mut() {
+            aov.clear();
+            aov.resize(self.width * self.height, [0.0; 3]);
         }
     }
+
+    // Trace the next frame at a different size, leaving the last finished frame to be presented until then
+    fn resize(&mut self, width: usize, height: usize) {
+        (self.width, self.height) = (width, height);
+        self.buffer = vec![0; width * height];
+        self.linear = vec![[0.0; 3]; width * height];
+        self.aovs = vec![vec![[0.0; 3]; width * height]; self.aovs.len()];
+        self.row_costs = vec![1.0; height];
+    }
 }
 
 /*
@@ -92,32 +200,93 @@ Camera / Viewport
 
 */
 
+#[derive(Clone)]
 struct Camera {
     origin: Vec3d,      // The eye point. Rays are traced from this point.
 
     // The viewport is the rectangle through which the camera looks through, i.e. emits rays through
-    vp_width: f64,
-    vp_height: f64,
+    vp_width: Float,
+    vp_height: Float,
     vp_depth: isize,    // Depth of viewport location in z+ direction from camera. Absolute of this value is the focal length.
 
-    y_rot: f64,         // Current horizontal rotation (deg)
-    x_rot: f64,         // Current vertical rotation (deg)
+    fov: Float,         // Vertical field of view (deg)
+
+    y_rot: Float,       // Current horizontal rotation (deg)
+    x_rot: Float,       // Current vertical rotation (deg)
     rot_m: Mat3         // Matrix holds camera transformations to apply on rays being traced
 }
 
 impl Camera {
-    fn new(origin: Vec3d, aspect_ratio: f64) -> Self {
-        let viewport_height = 1.0;
-        Self {
+    fn new(origin: Vec3d, aspect_ratio: Float, fov: Float) -> Self {
+        let mut camera = Self {
             origin,
-            vp_width: viewport_height * aspect_ratio,
-            vp_height: viewport_height,
-            vp_depth: viewport_height as isize * -1,
+            vp_width: 0.0,
+            vp_height: 0.0,
+            vp_depth: -1,
+            fov,
             y_rot: 0.0,
             x_rot: 0.0,
             rot_m: Mat3::identity()
+        };
+        camera.set_fov(fov, aspect_ratio);
+        camera
+    }
+
+    // Resize the viewport so it spans the given vertical field of view (deg)
+    fn set_fov(&mut self, fov: Float, aspect_ratio: Float) {
+        self.fov = fov;
+        self.vp_height = 2.0 * (fov.to_radians() / 2.0).tan() * (self.vp_depth as Float).abs();
+        self.vp_width = self.vp_height * aspect_ratio;
+    }
+
+    // Move along the ground in a direction relative to where the camera faces, e.g. (0, 0, -1) is forward
+    fn walk(&mut self, dir: Vec3d, dist: Float) {
+        let step = &(&self.rot_m * &dir).normalize() * dist;
+        self.origin = &self.origin + &Vec3d::new(step.x(), 0.0, step.z());
+    }
+
+    // Turn the camera, keeping it from looking past straight up or too far down
+    fn turn(&mut self, y_deg: Float, x_deg: Float) {
+        self.y_rot += y_deg;
+        self.x_rot = (self.x_rot + x_deg).clamp(-35.0, 89.0);
+    }
+
+    // Distances along a primary ray at which objects are seen, from the viewport out
+    fn view_range(&self) -> Range<Float> {
+        Range { min: self.vp_depth.abs() as Float, max: VIEW_DISTANCE }
+    }
+
+    // The ray through the center of the view
+    fn center_ray(&self) -> Ray {
+        Ray::new(self.origin.clone(), &self.rot_m * &Vec3d::new(0.0, 0.0, self.vp_depth as Float))
+    }
+
+    // Where rays are traced from, the origin moved sideways to an eye when rendering in stereo
+    fn eye_origin(&self, eye: Option<Eye>, interocular: Float) -> Vec3d {
+        match eye {
+            Some(eye) => &self.origin + &(&self.rot_m * &Vec3d::new(eye.offset(interocular), 0.0, 0.0)),
+            None => self.origin.clone()
         }
     }
+
+    // The (unrotated) rays from the camera through the center of every canvas unit on the viewport
+    // With several views side by side, each gets an equal share of the canvas's width and sees the full viewport height
+    fn primary_rays(&self, canvas_width: usize, canvas_height: usize, views: usize) -> Vec<Vec<Ray>> {
+        let view_width = canvas_width / views;
+        let vp_width = self.vp_width / views as Float;
+        (0..canvas_height).map(|row|
+            (0..canvas_width).map(|col|
+                Ray::new(
+                    self.origin.clone(),
+                    Vec3d::new(
+                        ((col % view_width) as isize - view_width as isize / 2) as Float * vp_width / view_width as Float,
+                        (canvas_height as isize / 2 - row as isize) as Float * self.vp_height / canvas_height as Float,
+                        self.vp_depth as Float
+                    )
+                )
+            ).collect()
+        ).collect()
+    }
 }
 
 /*
@@ -133,342 +302,1974 @@ pub struct Scene {
     camera_origin: Vec3d,
     bg_col: usize,
     lights: Vec<LightSource>,
-    objs: Vec<Box<dyn Object>>,
+    objs: Vec<Primitive>,
+    emitters: Vec<usize>, // Indices of the glowing objects, which the path tracer samples as lights
+    tlas: Tlas, // Finds the objects near a ray's path
+    volumes: Vec<Volume>,
+    decals: Vec<Decal>, // Painted over surfaces in order, so later decals cover earlier ones
+    portals: Vec<Portal>, // Openings light from outside comes in through, which the path tracer samples
+    fog: Option<Fog>,
+    atmosphere: Option<Atmosphere>, // Replaces the background color with a sky
+    ray_bias: Float // How far rays leaving a surface start from it, per unit of distance the surface is from the ray that found it
+}
+
+// Indices of the glowing objects
+fn emitters(objs: &[Primitive]) -> Vec<usize> {
+    objs.iter().enumerate()
+        .filter(|(_, obj)| matches!(obj.get_material(), Material::Emissive { .. }))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+// Homogeneous fog filling the scene. The fraction of a surface's color that survives a distance d through the fog is
+// e^(-density * d), the rest is replaced by the fog color
+// With scattering above 0 the fog doesn't glow on its own but is lit by the scene's lights instead: that fraction of the
+// density scatters light towards the camera (the rest absorbs it), so beams show up where light passes between objects
+#[derive(Clone, Debug)]
+pub struct Fog {
+    pub color: usize,
+    pub density: Float,
+    pub scattering: Float
+}
+
+// Where a ray cast into a scene hits it
+#[derive(Clone)]
+pub struct Hit {
+    pub object: usize,        // Index of the object hit, in the order the scene was given its objects
+    pub t: Float,             // How far along the ray, where the ray's direction is one step
+    pub point: Vec3d,
+    pub normal: Vec3d,        // Unit normal of the surface, facing back along the ray
+    pub front_face: bool      // Whether the ray hit the outside of the surface
 }
 
 impl Scene {
-    pub fn new(camera_origin: Vec3d, bg_col: usize, lights: Vec<LightSource>, objs: Vec<Box<dyn Object>>) -> Self {
-        Self {
+    // Objects can be given as primitives or boxed objects, or as the shapes a primitive can hold. Fails when a light
+    // can't be used, e.g. a tube light without an axis
+    pub fn new(camera_origin: Vec3d, bg_col: usize, lights: Vec<LightSource>, objs: impl IntoIterator<Item = impl Into<Primitive>>) -> Result<Self, SceneError> {
+        if let Some((i, e)) = lights.iter().enumerate().find_map(|(i, light)| light.validate().err().map(|e| (i, e))) {
+            return Err(SceneError::InvalidLight(i, e));
+        }
+        let objs: Vec<Primitive> = objs.into_iter().map(Into::into).collect();
+        Ok(Self {
             camera_origin,
             bg_col,
             lights,
-            objs
+            emitters: emitters(&objs),
+            tlas: Tlas::new(&objs),
+            objs,
+            volumes: Vec::new(),
+            decals: Vec::new(),
+            portals: Vec::new(),
+            fog: None,
+            atmosphere: None,
+            ray_bias: RAY_EPSILON
+        })
+    }
+
+    pub fn builder() -> SceneBuilder {
+        SceneBuilder::new()
+    }
+
+    pub fn with_volume(mut self, volume: Volume) -> Self {
+        self.volumes.push(volume);
+        self
+    }
+
+    pub fn with_decal(mut self, decal: Decal) -> Self {
+        self.decals.push(decal);
+        self
+    }
+
+    pub fn with_portal(mut self, portal: Portal) -> Self {
+        self.portals.push(portal);
+        self
+    }
+
+    pub fn with_fog(mut self, fog: Fog) -> Self {
+        self.fog = Some(fog);
+        self
+    }
+
+    pub fn with_atmosphere(mut self, atmosphere: Atmosphere) -> Self {
+        self.atmosphere = Some(atmosphere);
+        self
+    }
+
+    // Start shadow, reflection and bounce rays further from the surfaces they leave, for scenes so large the default
+    // leaves speckles of surfaces shadowing themselves
+    pub fn with_ray_bias(mut self, ray_bias: Float) -> Self {
+        self.ray_bias = ray_bias;
+        self
+    }
+
+    pub fn ray_bias(&self) -> Float {
+        self.ray_bias
+    }
+
+    // The color of an object where it's hit, with any decals covering that point painted over it
+    pub(crate) fn surface_color(&self, obj: &dyn Object, hit: &HitRecord) -> usize {
+        self.decals.iter().fold(obj.color_at(hit), |color, decal| decal.apply(hit, color))
+    }
+
+    // The t that rays leaving a surface in a direction start from, so they don't hit the surface they leave. Rounding
+    // errors in where the surface was hit grow with how far along the ray that found it (dist) it is
+    pub(crate) fn bias(&self, dist: Float, dir: &Vec3d) -> Float {
+        self.ray_bias * dist.max(1.0) / dir.magnitude()
+    }
+
+    pub fn camera_origin(&self) -> &Vec3d {
+        &self.camera_origin
+    }
+
+    // Replace the object at an index, e.g. with an instance of the same mesh somewhere else to move it
+    pub fn set_object(&mut self, index: usize, obj: impl Into<Primitive>) {
+        self.set_objects([(index, obj)]);
+    }
+
+    // Replace the objects at several indices at once, e.g. everything that moves in a frame of an animation. The
+    // hierarchy over the objects is refit once afterwards rather than rebuilt
+    pub fn set_objects(&mut self, objs: impl IntoIterator<Item = (usize, impl Into<Primitive>)>) {
+        for (index, obj) in objs {
+            self.objs[index] = obj.into();
+        }
+        self.emitters = emitters(&self.objs);
+        self.tlas.refit(&self.objs);
+    }
+
+    // Color seen where rays hit nothing, when the scene has no atmosphere
+    pub fn set_background(&mut self, color: usize) {
+        self.bg_col = color;
+    }
+
+    // Add an object to the scene, returning its index. The hierarchy over the objects is refit to include it, which
+    // rebuilds it since the number of objects changed (see Tlas::refit)
+    pub fn add_object(&mut self, obj: impl Into<Primitive>) -> usize {
+        self.objs.push(obj.into());
+        self.emitters = emitters(&self.objs);
+        self.tlas.refit(&self.objs);
+        self.objs.len() - 1
+    }
+
+    pub fn object(&self, index: usize) -> Option<&dyn Object> {
+        self.objs.get(index).map(|obj| obj as &dyn Object)
+    }
+
+    // What a kind of ray hits first, checking the points (ray at t) within the t range (excluding its far end), found the
+    // same way the renderer finds it, e.g. a camera ray to pick the object under the cursor, or a shadow ray to check
+    // whether one point can see another. Objects whose render flags hide them from that kind of ray aren't hit
+    pub fn cast_ray(&self, ray: &Ray, t_range: &Range<Float>, kind: RayKind) -> Option<Hit> {
+        let (object, hit) = self.tlas.closest_intersection(&self.objs, ray, t_range, kind, &mut TraversalContext::default())?;
+        Some(Hit { object, t: hit.t, normal: hit.facing_normal(), point: hit.point, front_face: hit.front_face })
+    }
+
+    // The closest object a kind of ray sees along it and where the ray hits it, checking the points (ray at t) within the
+    // t range
+    fn closest_intersection(&self, ray: &Ray, t_range: &Range<Float>, kind: RayKind) -> Option<(&dyn Object, HitRecord<'_>)> {
+        self.first_hit(ray, t_range, kind, &mut TraversalContext::default())
+    }
+
+    // closest_intersection, for a ray traced with a traversal context, e.g. one of a render thread's primary rays
+    fn first_hit(&self, ray: &Ray, t_range: &Range<Float>, kind: RayKind, context: &mut TraversalContext) -> Option<(&dyn Object, HitRecord<'_>)> {
+        self.tlas.closest_intersection(&self.objs, ray, t_range, kind, context).map(|(i, hit)| (&self.objs[i] as &dyn Object, hit))
+    }
+
+    // The light seen along a ray, as linear RGB that isn't limited to 1
+    // The colors of what it sees are lit and blended as they're encoded for display, the way this tracer always has,
+    // and only turned into linear light at the end, without being rounded or limited to full brightness on the way
+    pub fn trace_ray(&self, ray: &Ray, t_range: &Range<Float>, ray_refl_limit: u32) -> [Float; 3] {
+        Color::decode(self.trace(ray, t_range, ray_refl_limit, RayKind::Camera))
+    }
+
+    // The part of what trace_ray sees that's lit straight from the lights, leaving out what the first surface it hits
+    // reflects, given what that is
+    fn trace_direct(&self, ray: &Ray, t_range: &Range<Float>, ray_refl_limit: u32, hit: Option<(&dyn Object, HitRecord)>) -> [Float; 3] {
+        match hit {
+            Some((obj, hit)) if !obj.render_flags().shadow_catcher => {
+                let reflectivity = match obj.get_material() {
+                    Material::Shiny { refl_rat, .. } if ray_refl_limit > 0 => refl_rat.max(0.0),
+                    _ => 0.0
+                };
+                let dist = (&hit.point - ray.origin()).magnitude();
+                let color = scaled(self.shade(ray, obj, hit, t_range, 0), 1.0 - reflectivity);
+                Color::decode(self.apply_media(ray, t_range, dist, color))
+            },
+            hit => self.trace_hit(ray, t_range, ray_refl_limit, hit)
         }
     }
 
-    fn trace_ray(&self, ray: &Ray, t_range: &Range<f64>, ray_refl_limit: u32) -> usize {
+    // The color seen along a ray given what it hits first, e.g. for hits found ahead of shading them, as trace_ray
+    // returns it
+    pub(crate) fn trace_hit(&self, ray: &Ray, t_range: &Range<Float>, ray_refl_limit: u32, hit: Option<(&dyn Object, HitRecord)>) -> [Float; 3] {
+        Color::decode(self.hit_color(ray, t_range, ray_refl_limit, hit))
+    }
+
+    fn trace(&self, ray: &Ray, t_range: &Range<Float>, ray_refl_limit: u32, kind: RayKind) -> [Float; 3] {
         // Trace a ray and if we encounter an object, return its color
         // Check all points along the ray, where the ray at t is within a given range (inclusive)
         // Set a limit on the number of times a ray is aloud to reflect
     
-        match closest_intersection(&self.objs, ray, t_range) {
-            Some((obj, intxp)) => {               
-                // Find the sum of the intensities of light contributed by all sources on the intersection point
+        self.hit_color(ray, t_range, ray_refl_limit, self.closest_intersection(ray, t_range, kind))
+    }
 
-                let mut direct_light_intensity = 0.0;
+    // trace_hit, as sRGB encoded channels
+    fn hit_color(&self, ray: &Ray, t_range: &Range<Float>, ray_refl_limit: u32, hit: Option<(&dyn Object, HitRecord)>) -> [Float; 3] {
+        match hit {
+            Some((obj, hit)) if obj.render_flags().shadow_catcher => {
+                // Seen through, darkened by the shadows falling on it
+                let dist = hit.t * ray.dir().magnitude();
+                let behind = self.trace(ray, &Range::new(hit.t + self.bias(dist, ray.dir()), t_range.max), ray_refl_limit, RayKind::Through);
+                scaled(behind, self.lit_fraction(&hit.point, dist, ray.view()))
+            },
 
-                // Light contributed by sources directly on object 
+            Some((obj, hit)) => {
+                let dist = (&hit.point - ray.origin()).magnitude();
+                let color = self.shade(ray, obj, hit, t_range, ray_refl_limit);
+                self.apply_media(ray, t_range, dist, color)
+            },
+
+            _ => {
+                // No light along ray
+                self.apply_media(ray, t_range, Float::INFINITY, self.background(ray))
+            }
+        }
+    }
 
-                for light in self.lights.iter() {
-                    if let LightSource::Ambient { intensity } = light {
-                        // Ambient source
-                        direct_light_intensity += intensity;
+    // Color seen along a ray that doesn't hit anything, as sRGB encoded channels
+    fn background(&self, ray: &Ray) -> [Float; 3] {
+        match &self.atmosphere {
+            Some(atmosphere) => atmosphere.sky_color(ray.dir()),
+            None => Color::to_rgb(self.bg_col)
+        }
+    }
 
-                    } else {
-                        // Point or directional source
-                        let (intxp_light_dir, light_intensity, ) = if let LightSource::Point { intensity, pos } = light {
-                            (pos - &intxp, *intensity)
-                        } else if let LightSource::Directional { intensity, dir } = light {
-                            (dir * -1.0, *intensity)
-                        } else {
-                            (Vec3d::new(0.0, 0.0, 0.0), 0.0)
-                        };
-                        
-                        let intxp_light_ray = Ray::new (
-                            intxp.clone(),
-                            intxp_light_dir.clone()
-                        );
-
-                        // Check for objects that exist along the ray from the intersection point to the light source.
-                        // If this is the case, the point is shadowed, and the source contributes no direct light.
-                        if let LightSource::Point { intensity: _, pos } = light {
-                            if let Some((_, shdw_intxp)) = closest_intersection(&self.objs, &intxp_light_ray, &Range{min: EPSILON * 1000000.0, max: INFINITY}) {
-                                if (&intxp - &shdw_intxp).magnitude() < (&intxp - pos).magnitude() {
-                                    continue;
-                                }
-                            }
-                        } else if let LightSource::Directional { intensity: _, dir } = light {
-                            let ray = Ray::new(intxp.clone(), dir * -1.0);
-                            if let Some(_) = closest_intersection(&self.objs, &ray, &Range{min: EPSILON * 1000000.0, max: INFINITY}) {
-                                continue;
-                            }
-                        }
+    // The color seen through the atmosphere, volumes and fog along a ray, given the color of what it hit dist away, as
+    // sRGB encoded channels
+    fn apply_media(&self, ray: &Ray, t_range: &Range<Float>, dist: Float, color: [Float; 3]) -> [Float; 3] {
+        if dist.is_infinite() {
+            let color = self.apply_volumes(ray, t_range, color);
+            return self.apply_fog(ray, t_range.min, dist, color);
+        }
+
+        let color = match &self.atmosphere {
+            Some(atmosphere) => atmosphere.apply_haze(color, ray.dir(), dist),
+            None => color
+        };
+        let color = self.apply_volumes(ray, &Range::new(t_range.min, dist / ray.dir().magnitude()), color);
+        self.apply_fog(ray, t_range.min, dist, color)
+    }
+
+    // The color seen through every volume along the ray segment, given the color of what lies behind them
+    fn apply_volumes(&self, ray: &Ray, t_range: &Range<Float>, behind: [Float; 3]) -> [Float; 3] {
+        let mut segments: Vec<(&Volume, Range<Float>)> = self.volumes.iter()
+            .filter_map(|volume| volume.interval(ray, t_range).map(|inside| (volume, inside)))
+            .collect();
+        segments.sort_by(|a, b| a.1.min.total_cmp(&b.1.min));
+
+        // Composite from the back, each volume lets through part of what's behind it and adds the light it scatters
+        let ambient = self.ambient_intensity();
+        segments.iter().rev().fold(behind, |color, (volume, inside)| {
+            let (transmittance, in_scattered) = self.march_medium(ray, inside, volume.scattering(), volume.extinction(), ambient, 32);
+            blend(color, transmittance, Color::to_rgb(volume.color()), in_scattered)
+        })
+    }
+
+    // Step through a homogeneous medium along part of a ray, collecting light scattered towards the ray's origin
+    // Ambient light is scattered evenly throughout the medium, direct light only where it isn't shadowed
+    // Returns the fraction of light from behind that makes it through, and the amount of light scattered in
+    fn march_medium(&self, ray: &Ray, t_range: &Range<Float>, scattering: Float, extinction: Float, ambient: Float, steps: usize) -> (Float, Float) {
+        let dt = t_range.size() / steps as Float;
+        let step_dist = dt * ray.dir().magnitude();
+        let step_transmittance = (-extinction * step_dist).exp();
+
+        let mut transmittance = 1.0;
+        let mut in_scattered = 0.0;
+
+        // Sample the light at the middle of every step, the shadow rays of neighbouring steps traced together
+        for first in (0..steps).step_by(PACKET_SIZE) {
+            let points: Vec<(Vec3d, Float)> = (first..steps.min(first + PACKET_SIZE)).map(|i| {
+                let t = t_range.min + (i as Float + 0.5) * dt;
+                (ray.at(t), t * ray.dir().magnitude())
+            }).collect();
+            for light in &self.direct_light_at_points(&points, ray.view())[..points.len()] {
+                in_scattered += transmittance * scattering * (ambient + light) * step_dist;
+                transmittance *= step_transmittance;
+            }
+        }
+
+        (transmittance, in_scattered)
+    }
+
+    fn ambient_intensity(&self) -> Float {
+        self.lights.iter().map(|light| if let LightSource::Ambient { intensity } = light { *intensity } else { 0.0 }).sum()
+    }
+
+    // Intensity of light arriving at a point in space from point, directional and tube sources, after shadows and volumes in the
+    // way, for a point dist along the ray that found it, seen from that ray's view
+    fn direct_light_at(&self, p: &Vec3d, dist: Float, view: &Vec3d) -> Float {
+        self.direct_light_at_points(&[(p.clone(), dist)], view)[0]
+    }
+
+    // direct_light_at for each of up to PACKET_SIZE points and how far along the ray that found them they are, the
+    // shadow rays towards each light traced together as a packet
+    fn direct_light_at_points(&self, points: &[(Vec3d, Float)], view: &Vec3d) -> [Float; PACKET_SIZE] {
+        let mut lit = [0.0; PACKET_SIZE];
+        for (light, source) in self.lights.iter().flat_map(|light| (0..light.sources()).map(move |source| (light, source))) {
+            let (light_rays, shdw_ranges, intensities): (Vec<Ray>, Vec<Range<Float>>, Vec<Float>) = points.iter().filter_map(|(p, dist)| {
+                let (light_dir, intensity, t_max) = light.towards(p, source)?;
+                let shdw_range = Range::new(self.bias(*dist, &light_dir), t_max);
+                Some((Ray::new(p.clone(), light_dir).with_view(view.clone()), shdw_range, intensity))
+            }).collect();
+
+            let occluded = self.tlas.packet_occluded(&self.objs, &light_rays, &shdw_ranges, RayKind::Shadow);
+            for (lit, (((light_ray, shdw_range), intensity), occluded)) in lit.iter_mut().zip(light_rays.iter().zip(&shdw_ranges).zip(intensities).zip(occluded)) {
+                if !occluded {
+                    *lit += intensity * self.volume_transmittance(light_ray, shdw_range);
+                }
+            }
+        }
+        lit
+    }
+
+    // Fraction of the light from point, directional and tube sources that reaches a point, for a point dist along the ray that
+    // found it (seen from its view), e.g. to darken what's seen through a shadow catcher by the shadows falling on it
+    fn lit_fraction(&self, p: &Vec3d, dist: Float, view: &Vec3d) -> Float {
+        let total: Float = self.lights.iter()
+            .flat_map(|light| (0..light.sources()).filter_map(move |source| light.towards(p, source)))
+            .map(|(_, intensity, _)| intensity)
+            .sum();
+        if total <= 0.0 { 1.0 } else { self.direct_light_at(p, dist, view) / total }
+    }
+
+    // Fraction of light that makes it along a ray segment through the volumes it crosses
+    fn volume_transmittance(&self, ray: &Ray, t_range: &Range<Float>) -> Float {
+        let optical_depth: Float = self.volumes.iter()
+            .filter_map(|volume| volume.interval(ray, t_range).map(|inside| volume.extinction() * inside.size()))
+            .sum();
+        (-optical_depth * ray.dir().magnitude()).exp()
+    }
+
+    // The color of an object where a ray intersects it, as sRGB encoded channels
+    fn shade(&self, ray: &Ray, obj: &dyn Object, hit: HitRecord, t_range: &Range<Float>, ray_refl_limit: u32) -> [Float; 3] {
+        // Find the sum of the intensities of light contributed by all sources on the intersection point
+        let (norm, color) = (hit.facing_normal(), Color::to_rgb(self.surface_color(obj, &hit)));
+        let HitRecord { point: intxp, t, .. } = hit;
+        let dist = t * ray.dir().magnitude();
+
+        // Glowing objects aren't lit, they only show their own light
+        if let Material::Emissive { intensity, .. } = obj.get_material() {
+            return scaled(color, *intensity);
+        }
+
+        let mut direct_light = [0.0; 3];
 
-                        // Get the normal vector of the object going through the intersection point. This method will be defined differently for every object type
-                        if let Some(mut norm) = obj.get_normal(&intxp) {
-                            
-                            if &norm * &intxp_light_dir < 0.0 { // Ensure norm and ray from intersection point to light are in the same direction. Important to do this because of triangles.
-                                norm = &norm * -1.0;
-                            }
-
-                            // Diffuse reflection
-                            let n_dot_il: f64 = &norm * &intxp_light_dir;
-                            if n_dot_il > 0.0 { // Don't account for lights behind surfaces (will have negative dot product)
-                                direct_light_intensity += light_intensity * n_dot_il / (norm.magnitude() * intxp_light_dir.magnitude()); // cos(angle between norm and ray from intersection point to light source) * intensity
-                            }
-
-                            // Specular reflection
-                            if let Material::Shiny { spclr_exp, refl_rat: _} = obj.get_material() {
-                                let intxp_light_refl_dir = intxp_light_dir.reflect(&norm);
-                                let intxp_o_dir = ray.origin() - &intxp;
-                                let ilr_dot_io = &intxp_light_refl_dir * &intxp_o_dir;
-                                if ilr_dot_io > 0.0 { // Don't account for lights when angle between reflected vector of intersection point to light source and intersection point to ray origin is > 90 (will have negative dot product)
-                                    direct_light_intensity += light_intensity * (ilr_dot_io / (intxp_light_refl_dir.magnitude() * intxp_o_dir.magnitude())).powf(*spclr_exp); // cos (angle between reflected ray from intersection point to light source and vectory from intersection point to ray origin) ^ spec_exp * intensity
-                                }
-                            }
+        // Light contributed by sources directly on object 
+
+        for light in self.lights.iter() {
+            if let LightSource::Ambient { intensity } = light {
+                // Ambient source
+                direct_light = direct_light.map(|c| c + intensity);
+
+            } else {
+                // Point, directional or tube source, a tube's light coming from several places along it, tinted by its color
+                let mut light_received = 0.0;
+                for (intxp_light_dir, light_intensity, t_max) in (0..light.sources()).filter_map(|source| light.towards(&intxp, source)) {
+                
+                    let intxp_light_ray = Ray::new (
+                        intxp.clone(),
+                        intxp_light_dir.clone()
+                    ).with_view(ray.view().clone());
+
+                    // Check for objects that exist along the ray from the intersection point to the light source.
+                    // If this is the case, the point is shadowed, and the source contributes no direct light.
+                    // A point or tube light sits at t = 1 along this ray, anything past it can't cast a shadow
+                    let shdw_range = Range::new(self.bias(dist, &intxp_light_dir), t_max);
+                    if self.closest_intersection(&intxp_light_ray, &shdw_range, RayKind::Shadow).is_some() {
+                        continue;
+                    }
+
+                    // Volumes between the point and the light dim it
+                    let light_intensity = light_intensity * self.volume_transmittance(&intxp_light_ray, &shdw_range);
+
+                    // Lights on the other side of the surface from the ray don't light the side that's seen
+                    let n_dot_il: Float = &norm * &intxp_light_dir;
+                    if n_dot_il <= 0.0 {
+                        continue;
+                    }
+
+                    // Diffuse reflection
+                    light_received += light_intensity * n_dot_il / (norm.magnitude() * intxp_light_dir.magnitude()); // cos(angle between norm and ray from intersection point to light source) * intensity
+
+                    // Specular reflection
+                    if let Material::Shiny { spclr_exp, .. } = obj.get_material() {
+                        let intxp_light_refl_dir = intxp_light_dir.reflect(&norm);
+                        let intxp_o_dir = ray.origin() - &intxp;
+                        let ilr_dot_io = &intxp_light_refl_dir * &intxp_o_dir;
+                        if ilr_dot_io > 0.0 { // Don't account for lights when angle between reflected vector of intersection point to light source and intersection point to ray origin is > 90 (will have negative dot product)
+                            light_received += light_intensity * (ilr_dot_io / (intxp_light_refl_dir.magnitude() * intxp_o_dir.magnitude())).powf(*spclr_exp); // cos (angle between reflected ray from intersection point to light source and vectory from intersection point to ray origin) ^ spec_exp * intensity
                         }
                     }
                 }
-                let direct_color = Color::scale(*obj.get_color() as usize, direct_light_intensity);
-    
-                // Light contributed by sources indirectly through reflections. Only shiny objects reflect light.
+                direct_light = blend(direct_light, 1.0, Color::to_rgb(light.color()), light_received);
+            }
+        }
+        let direct_color = [0, 1, 2].map(|c| color[c] * direct_light[c]);
 
-                match obj.get_material() {
-                    Material::Shiny { spclr_exp: _, refl_rat } => {
-                        if ray_refl_limit <= 0 || *refl_rat <= 0.0 {
-                            return direct_color;
-                        }
-                        
-                        if let Some(mut norm) = obj.get_normal(&intxp) {
-                            if &norm * ray.dir() < 0.0 {
-                                norm = &norm * -1.0;
-                            }
-                            
-                            let refl_ray = Ray::new (
-                                intxp,
-                                (ray.dir() * -1.0).reflect(&norm)
-                            );
-                            
-                            let reflected_color = self.trace_ray(&refl_ray, &Range{min: EPSILON * 1000000.0, max: t_range.max}, ray_refl_limit - 1);
-                            
-                            // Add direct and indirect colors
-                            Color::add(Color::scale(direct_color, 1.0 - *refl_rat), Color::scale(reflected_color, *refl_rat))
-                        } else {
-                            direct_color
-                        }
-                    },
-                    _ => direct_color
+        // Light contributed by sources indirectly through reflections. Only shiny objects reflect light.
+
+        match obj.get_material() {
+            Material::Shiny { refl_rat, .. } => {
+                if ray_refl_limit == 0 || *refl_rat <= 0.0 {
+                    return direct_color;
                 }
+
+                let refl_ray = Ray::new (
+                    intxp,
+                    (ray.dir() * -1.0).reflect(&norm)
+                ).with_view(ray.view().clone());
+                
+                let reflected_color = self.trace(&refl_ray, &Range{min: self.bias(dist, refl_ray.dir()), max: t_range.max}, ray_refl_limit - 1, RayKind::Reflection);
+                
+                // Add direct and indirect colors
+                blend(direct_color, 1.0 - *refl_rat, reflected_color, *refl_rat)
             },
+            _ => direct_color
+        }
+    }
+
+    // Fade a color towards the fog color the further light travelled through it, from t_min along the ray to dist away
+    fn apply_fog(&self, ray: &Ray, t_min: Float, dist: Float, color: [Float; 3]) -> [Float; 3] {
+        let Some(fog) = self.fog.as_ref().filter(|fog| fog.density > 0.0) else {
+            return color;
+        };
 
-            _ => self.bg_col // No light along ray
+        let transmittance = (-fog.density * dist).exp();
+        if fog.scattering <= 0.0 {
+            return blend(color, transmittance, Color::to_rgb(fog.color), 1.0 - transmittance);
         }
+
+        // Past the distance where the fog lets through less than 1% of light, nothing more can be seen
+        let dist = dist.min(-Float::ln(0.01) / fog.density);
+        let t_range = Range::new(t_min, dist / ray.dir().magnitude());
+        let scattering = fog.scattering * fog.density;
+        let (_, in_scattered) = self.march_medium(ray, &t_range, scattering, fog.density, self.ambient_intensity(), 64);
+
+        blend(color, transmittance, Color::to_rgb(fog.color), in_scattered)
     }
 }
 
+// The channels of a color, each multiplied by factor
+fn scaled(rgb: [Float; 3], factor: Float) -> [Float; 3] {
+    rgb.map(|c| c * factor)
+}
+
+// The sum of two colors, each weighted
+fn blend(a: [Float; 3], a_weight: Float, b: [Float; 3], b_weight: Float) -> [Float; 3] {
+    [0, 1, 2].map(|c| a[c] * a_weight + b[c] * b_weight)
+}
+
 /*
 
 Ray Tracing 3D Renderer
 
 */
 
+type ProgressCallback = Box<dyn FnMut(&Progress)>;
+
+// Canvas rows being traced by worker threads in the background
+struct Tracing {
+    traced: Traced,
+    tiles: mpsc::Receiver<()>, // Receives a message as each tile finishes
+    tiles_total: usize,
+    frame: u64,
+    start: Instant
+}
+
+// What the worker threads are lent while they trace, given back once they're done
+struct Lent {
+    buffer: Vec<u32>,
+    linear: Vec<[Float; 3]>,
+    aovs: Vec<Vec<[Float; 3]>>,
+    row_costs: Vec<f64>,
+    scratch: Vec<Scratch>
+}
+
+// A run of canvas rows a worker thread traces, borrowed from what was lent
+struct Band<'a> {
+    units: &'a mut [u32],
+    linear: &'a mut [[Float; 3]],
+    aovs: Vec<&'a mut [[Float; 3]]>,
+    costs: &'a mut [f64]
+}
+
+impl<'a> Band<'a> {
+    // The first rows of the band, and the rest of it
+    fn split_at(self, rows: usize, width: usize) -> (Self, Self) {
+        let (units, rest_units) = self.units.split_at_mut(rows * width);
+        let (linear, rest_linear) = self.linear.split_at_mut(rows * width);
+        let (aovs, rest_aovs) = self.aovs.into_iter().map(|aov| aov.split_at_mut(rows * width)).unzip();
+        let (costs, rest_costs) = self.costs.split_at_mut(rows);
+        (
+            Self { units, linear, aovs, costs },
+            Self { units: rest_units, linear: rest_linear, aovs: rest_aovs, costs: rest_costs }
+        )
+    }
+}
+
+enum Traced {
+    Running(thread::JoinHandle<Lent>),
+    Done(Lent) // Traced on this thread, where threads can't be spawned
+}
+
+pub const VIEW_DISTANCE: Float = 100.0; // How far past the camera objects are seen
+const SCROLL_ZOOM_STEP: Float = 1.1;  // How much a notch of the mouse wheel narrows the field of view
+const SCROLL_SPEED_STEP: Float = 1.25; // How much a notch of the mouse wheel with shift held speeds up walking
+const MIN_FOV: Float = 5.0;
+const MAX_FOV: Float = 150.0;
+const MIN_MOVE_SPEED: Float = 0.001;
+const MAX_MOVE_SPEED: Float = 100.0;
+const CROSSHAIR_SIZE: usize = 8; // Length of each arm of the crosshair in pixels, from the center
+const CROSSHAIR_GAP: usize = 2;  // Pixels left out at the center of the crosshair, so what's under it can be seen
+const DIVIDER_WIDTH: usize = 2;  // Width of the divider between the sides of a comparison in pixels
+const DIVIDER_GRAB: f32 = 8.0;   // How far from the divider, in pixels, pressing the mouse grabs it
+const SPAWN_STREAM: u64 = u64::MAX; // Stream of the spawned spheres' generator, apart from those of the pixels
+const PROGRESSIVE_SCALE: usize = 8; // How many times larger canvas units are at the coarsest level of progressive tracing
+const QUALITY_SAMPLES: [usize; 4] = [1, 2, 4, 8]; // Samples per canvas unit chosen by each of the sample keys
+const SUNRISE_HOUR: Float = 7.0; // Where the time of day starts when it's turned on, early enough to see the sun rise
+#[cfg(not(target_arch = "wasm32"))]
+const PAUSED_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
 pub struct Renderer {
     screen: Screen,
-    canvas: Arc<Canvas>,
+    canvas: Canvas,
     camera: Arc<RwLock<Camera>>,
     scene: Arc<Scene>,
     canvas_unit_size: usize, // The square length of pixels that a canvas unit will take up, e.g. a value of 2 means one canvas unit will take up a 2x2 square of pixels
+    render_scale: Float, // Canvas units per pixel along each side, after canvas_unit_size, e.g. 0.5 for half resolution or 2 to supersample
+    upscale: Upscale, // How a canvas smaller than the screen is stretched over it
+    fullscreen: bool, // Whether the display covers the screen, rather than being a window
+    preview_scale: usize, // How many times larger canvas units are while the camera moves
+    progressive: bool, // Whether every change is traced coarse to fine, from an eighth of the resolution up
+    controls: Controls, // Keys that move the camera and how fast they move it
+    drag_from: Option<(f32, f32)>, // Where the mouse was last frame while dragging to look around
+    paused: bool, // Whether tracing is stopped, leaving the last frame on screen
+    selected_light: Option<usize>, // The light moved by the light keys
+    spawn_rng: Pcg32, // Chooses where spawned spheres go and how they look
+    time_of_day: Option<TimeOfDay>, // Moves a directional light along a sun arc while on
+    day_length: Float, // Seconds a whole day takes to pass while the time of day is on
+    turntable: Option<Turntable>, // Moves the camera round a point in front of it while on
+    orbit_radius: Float, // Distance from the camera to the point it moves round, when the turntable is turned on
+    orbit_speed: Float, // Degrees the camera moves round that point every frame
+    last_step: Instant, // When the last frame was stepped, so the time of day passes with real time
+    crosshair: bool, // Whether the crosshair and the readout of the object under it are shown
+    hovered: Option<String>, // The readout of the object under the crosshair
+    title: String, // Shown in the title bar, last set to this
+    keys_were_down: Vec<Key>, // Keys held last frame, so holding a key down only acts on it once
+    #[cfg(feature = "gamepad")]
+    gamepad_sensitivity: Float, // Camera speed with a gamepad's sticks fully pushed, relative to the keys
+    scale: usize, // How many times larger canvas units are in the frame being traced, halved every frame after the camera stops
     num_threads: usize,
     num_samples: usize, // Number of samples used when performing anti-aliasing
+    filter: Filter, // How anti-aliasing samples are weighted when combined
+    sample_pattern: SamplePattern, // Where anti-aliasing samples are placed within a canvas unit
+    firefly_clamp: Option<Float>, // Limit samples to this many times the median brightness of their canvas unit
+    max_depth: u32, // Number of times a ray may be reflected
+    integrator: Integrator, // How the light along each ray is found
+    exposure: Float, // Brightness multiplier applied to every traced canvas unit
+    fxaa: bool, // Whether jagged edges are smoothed after tracing, before the other post effects
+    cas: Option<Cas>, // Sharpening after FXAA, before the other post effects
+    post_effects: Vec<Arc<dyn PostEffect>>, // Applied to every finished frame in order, bloom first
+    seed: u64, // Seeds the random numbers used while tracing, so renders are reproducible
+    stereo: Stereo, // Whether a view is traced for each eye, and how the views are combined
+    interocular: Float, // Distance between the eyes when rendering in stereo
+    eye: Option<Eye>, // The eye the frame is being traced from, when tracing each eye's view separately
+    eye_buffer: Vec<u32>, // The left eye's view, kept while the right eye's is traced
+    comparison: Option<Comparison>, // Settings changed for the right side of the window, when comparing two renders
+    compared_buffer: Vec<u32>, // The right side's frame, kept while the left side's is traced
+    split: Float, // Where the divider between the sides of a comparison is, as a fraction of the screen's width
+    dragging_split: bool, // Whether the divider is being dragged with the mouse
+    pipeline: Pipeline, // The order the samples of a tile are traced in
+    aovs: Vec<Aov>, // Channels recorded for every canvas unit as well as its color
+    motion: Option<Arc<Motion>>, // Where the camera and objects were the frame before the one being traced, and are now
+    frame: u64, // Number of frames traced so far
+    dirty: bool, // Whether the frame needs tracing again, because the camera moved or a setting changed since the last one
+    tracing: Option<Tracing>, // The next frame, traced while the last one is presented
+    progress_callback: Option<ProgressCallback>, // Notified as tiles of a frame finish
+    cancel_token: CancelToken, // Aborts tracing when cancelled
     rays: Arc<Vec<Vec<Ray>>>, // The rays that are traced into the scene
-    thread_buffers: Vec<Arc<Mutex<Vec<Vec<usize>>>>> // The canvas is split into buffers for each thread to own and operate on
+    scratch: Vec<Scratch>, // Working memory for each worker thread, kept between frames
+    #[cfg(feature = "gamepad")]
+    gamepad: Option<gamepad::Gamepad>, // The controller the camera is also driven with, if controllers can be read
+    #[cfg(feature = "egui")]
+    overlay: overlay::Overlay // Settings panel drawn over the canvas
 }
 
 impl Renderer {
-    pub fn new(num_threads: usize, screen_width: usize, aspect_ratio: f64, canvas_unit_size: usize, scene: Arc<Scene>, num_samples: usize) -> Self {
-        let screen_height = (screen_width as f64 / aspect_ratio) as usize;
+    pub fn builder() -> RendererBuilder {
+        RendererBuilder::new()
+    }
+
+    // Create a renderer presenting to the default display (a window, if a windowing backend is enabled)
+    pub fn new(config: RendererConfig, scene: Arc<Scene>) -> Result<Self, RendererError> {
+        config.validate()?;
+        let display = display::default_display(config.screen_width, config.screen_height())?;
+        Self::with_display(config, scene, display)
+    }
 
-        if screen_width % canvas_unit_size != 0 || screen_height % canvas_unit_size != 0 {
-            panic!("Window dimensions must be a multiple of pixel size")
+    // Trace a single frame of a scene at the given size without a window, with every other setting left at its default
+    pub fn render_to_image(scene: Arc<Scene>, width: usize, height: usize, samples: usize) -> Result<Image, RendererError> {
+        Self::builder()
+            .screen_width(width)
+            .screen_height(height)
+            .samples(samples)
+            .render_to_image(scene)
+    }
+
+    pub fn with_display(config: RendererConfig, mut scene: Arc<Scene>, display: Box<dyn Display>) -> Result<Self, RendererError> {
+        config.validate()?;
+        if let Some(color) = config.background.filter(|&color| color != scene.bg_col) {
+            Arc::get_mut(&mut scene).ok_or(RendererError::SceneShared)?.set_background(color);
         }
 
-        let canvas = Canvas::new(screen_width, screen_height, canvas_unit_size);
+        let RendererConfig { num_threads, screen_width, canvas_unit_size, num_samples, filter, sample_pattern, firefly_clamp, max_depth, integrator, fov, exposure, seed, stereo, interocular, preview_scale, pipeline, .. } = config;
+        let screen_height = config.screen_height();
 
-        let camera = Camera::new(scene.camera_origin.clone(), screen_width as f64 / screen_height as f64);
+        // Each AOV is recorded once, however many times it was asked for
+        let aovs: Vec<Aov> = config.aovs.iter().enumerate()
+            .filter(|(i, aov)| !config.aovs[..*i].contains(aov))
+            .map(|(_, &aov)| aov)
+            .collect();
+        let (canvas_width, canvas_height) = canvas_dimensions(screen_width, screen_height, canvas_unit_size, config.render_scale, stereo.views());
+        let canvas = Canvas::new(canvas_width, canvas_height, aovs.len());
 
-        let rays = (0..canvas.height).map(|row|
-                (0..canvas.width).map(|col|
-                    Ray::new(
-                        camera.origin.clone(),
-                        Vec3d::new(
-                            (col as isize - canvas.width as isize / 2) as f64 * camera.vp_width / canvas.width as f64,
-                            (canvas.height as isize / 2 - row as isize) as f64 * camera.vp_height / canvas.height as f64,
-                            camera.vp_depth as f64
-                        )
-                    )
-                ).collect()
-            ).collect();
+        info!("Loaded scene with {} objects and {} lights", scene.objs.len(), scene.lights.len());
+        info!(
+            "Rendering {}x{} canvas to a {}x{} window with {} threads and {} samples per unit",
+            canvas.width, canvas.height, screen_width, screen_height, num_threads, num_samples
+        );
 
-        let thread_buffers = (0..num_threads).map(|_| 
-            Arc::new(Mutex::new(vec![vec![0; canvas.width]; canvas.height]))
-        ).collect();
+        let camera = Camera::new(scene.camera_origin.clone(), screen_width as Float / screen_height as Float, fov);
 
-        Self {
+        let rays = camera.primary_rays(canvas.width, canvas.height, stereo.views());
+        let turntable = config.turntable.then(|| Turntable::new(&camera.origin, camera.y_rot, config.orbit_radius, config.orbit_speed));
+
+        // The canvas is sized to the fullscreen display once it reports its size, on the first step
+        let mut screen = Screen::new(display, screen_width, screen_height);
+        if config.fullscreen {
+            screen.display.set_fullscreen(true)?;
+        }
+
+        Ok(Self {
             camera: Arc::new(RwLock::new(camera)),
             scene,
-            canvas: Arc::new(canvas),
-            screen: Screen::build(screen_width, screen_height),
+            canvas,
+            screen,
             canvas_unit_size,
+            render_scale: config.render_scale,
+            upscale: config.upscale,
+            fullscreen: config.fullscreen,
+            preview_scale,
+            progressive: config.progressive,
+            controls: config.controls.clone(),
+            drag_from: None,
+            paused: false,
+            selected_light: None,
+            spawn_rng: Pcg32::new(frame_seed(seed, 0), SPAWN_STREAM),
+            time_of_day: None,
+            day_length: config.day_length,
+            turntable,
+            orbit_radius: config.orbit_radius,
+            orbit_speed: config.orbit_speed,
+            last_step: Instant::now(),
+            crosshair: false,
+            hovered: None,
+            title: display::WINDOW_TITLE.to_string(),
+            keys_were_down: Vec::new(),
+            #[cfg(feature = "gamepad")]
+            gamepad_sensitivity: config.gamepad_sensitivity,
+            scale: 1,
             num_threads,
             num_samples,
+            filter,
+            sample_pattern,
+            firefly_clamp,
+            max_depth,
+            integrator,
+            exposure,
+            fxaa: config.fxaa,
+            cas: config.cas.map(|sharpness| Cas { sharpness }),
+            post_effects: config.bloom.map(|bloom| Arc::new(bloom) as Arc<dyn PostEffect>).into_iter().chain(config.post_effects.iter().cloned()).collect(),
+            seed,
+            stereo,
+            interocular,
+            eye: None,
+            eye_buffer: Vec::new(),
+            comparison: config.comparison,
+            compared_buffer: Vec::new(),
+            split: 0.5,
+            dragging_split: false,
+            pipeline,
+            aovs,
+            motion: None,
+            frame: 0,
+            dirty: true,
+            tracing: None,
+            progress_callback: None,
+            cancel_token: CancelToken::new(),
             rays: Arc::new(rays),
-            thread_buffers
+            scratch: (0..num_threads).map(|_| Scratch::new(num_samples)).collect(),
+            #[cfg(feature = "gamepad")]
+            gamepad: gamepad::Gamepad::new(),
+            #[cfg(feature = "egui")]
+            overlay: overlay::Overlay::new()
+        })
+    }
+
+    // Report progress while tracing, e.g. to drive a progress bar during long offline renders
+    pub fn set_progress_callback(&mut self, callback: impl FnMut(&Progress) + 'static) {
+        self.progress_callback = Some(Box::new(callback));
+    }
+
+    // A token that can be cancelled (from any thread) to abort the frame being traced and stop the render loop
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel_token.clone()
+    }
+
+    // Change the scene between frames, e.g. to move instances around in an animation. The frame being traced in the
+    // background is finished first, and the next frame is traced with the changes
+    pub fn edit_scene(&mut self, edit: impl FnOnce(&mut Scene)) -> Result<(), RendererError> {
+        if let Some(tracing) = self.tracing.take() {
+            self.finish_frame(tracing)?;
+            self.canvas.swap();
         }
+
+        let scene = Arc::get_mut(&mut self.scene).ok_or(RendererError::SceneShared)?;
+        edit(scene);
+        self.dirty = true;
+        Ok(())
+    }
+
+    // The most recently presented frame as packed 0RGB pixels, and its width and height
+    pub fn frame_buffer(&self) -> (&[u32], usize, usize) {
+        (&self.screen.buffer, self.screen.width, self.screen.height)
+    }
+
+    // Linear RGB of every canvas unit of the last finished frame, as accumulated from its samples before being encoded for
+    // display, and its width and height in canvas units. Exposure can take channels past 1, as they're left unclamped
+    // for exporting. In anaglyph stereo this is the right eye's view
+    pub fn accumulation_buffer(&self) -> (&[[Float; 3]], usize, usize) {
+        (&self.canvas.front_linear, self.canvas.front_width, self.canvas.front_height)
+    }
+
+    // An AOV of every canvas unit of the last finished frame, and its width and height in canvas units. None unless the
+    // AOV was asked for in the config. In anaglyph stereo this is the right eye's view
+    pub fn aov_buffer(&self, aov: Aov) -> Option<(&[[Float; 3]], usize, usize)> {
+        let index = self.aovs.iter().position(|&recorded| recorded == aov)?;
+        Some((&self.canvas.front_aovs[index], self.canvas.front_width, self.canvas.front_height))
+    }
+
+    pub fn run(&mut self) -> Result<(), RendererError> {
+        while self.step()? {}
+        Ok(())
+    }
+
+    // Sharpen frames after tracing with contrast-adaptive sharpening of the given sharpness (0 to 1), or stop with None.
+    // The next frame is traced with it
+    pub fn set_sharpening(&mut self, sharpness: Option<Float>) {
+        self.cas = sharpness.map(|sharpness| Cas { sharpness: sharpness.clamp(0.0, 1.0) });
+        self.dirty = true;
     }
 
-    pub fn run(&mut self) {
-        while self.screen.window.is_open() && !self.screen.window.is_key_down(minifb::Key::Escape) {
-            self.update_camera();
-            self.canvas.clear();
-            self.trace_rays();
-            self.render_canvas();
+    // Switch the display between a window and fullscreen, for displays that can. Frames are traced at its new size from
+    // the next step on, with everything else carrying on as it was
+    pub fn set_fullscreen(&mut self, fullscreen: bool) -> Result<(), RendererError> {
+        self.screen.display.set_fullscreen(fullscreen)?;
+        self.fullscreen = fullscreen;
+        Ok(())
+    }
+
+    // Trace the next frame again, even if nothing has changed since the last one, e.g. after editing the scene
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    // Handle input, then present a single frame
+    // Frames are traced in the background while the one before is presented, so what's presented lags input by a
+    // frame. When nothing has changed since the last frame was traced, it's presented again without tracing
+    // Returns false once the display is closed, escape is pressed or rendering is cancelled
+    pub fn step(&mut self) -> Result<bool, RendererError> {
+        if !self.screen.display.is_open() || self.screen.display.keys_down().contains(&Key::Escape) {
+            return Ok(false);
+        }
+
+        // Finish the frame traced while the last one was presented. The first frame has nothing to be presented over
+        let finished = match self.tracing.take() {
+            Some(tracing) => self.finish_frame(tracing).map(|_| self.canvas.swap()),
+            None if self.dirty => {
+                // Traced progressively, a change shows up at the coarsest level straight away
+                if self.progressive {
+                    self.set_scale(PROGRESSIVE_SCALE);
+                }
+                self.trace_frame()
+            }
+            None => Ok(())
+        };
+        match finished {
+            Err(RendererError::Cancelled) => return Ok(false),
+            result => result?
+        }
+
+        let keys_down = self.screen.display.keys_down();
+        let pressed: Vec<Key> = keys_down.iter().copied().filter(|key| !self.keys_were_down.contains(key)).collect();
+        self.keys_were_down = keys_down;
+        if pressed.contains(&self.controls.keys.fullscreen) {
+            self.set_fullscreen(!self.fullscreen)?;
+            info!("{}", if self.fullscreen { "Fullscreen" } else { "Windowed" });
+        }
+        let resized = self.update_size();
+        self.update_split();
+
+        // While paused, the last frame stays on screen and nothing more is traced. Input is still polled, but only every
+        // so often, so the renderer sits idle
+        if self.update_pause(&pressed) {
+            self.last_step = Instant::now(); // The time of day stands still while paused
+            self.render_canvas()?;
+            #[cfg(not(target_arch = "wasm32"))]
+            thread::sleep(PAUSED_POLL_INTERVAL);
+            return Ok(true);
+        }
+        self.update_quality(&pressed);
+        let light_moved = self.update_lights(&pressed) | self.update_time_of_day(&pressed);
+        if pressed.contains(&self.controls.keys.spawn) {
+            self.spawn_sphere();
+        }
+
+        // The turntable traces every frame at full size, so it shows what a still would
+        if self.update_turntable(&pressed) {
+            self.dirty = true;
         }
+
+        // Moving traces quick, coarse frames, which sharpen over the frames after the camera (or a light) stops. Traced
+        // progressively, any change starts over from the coarsest level
+        let moved = self.update_camera() || light_moved || resized;
+        if moved || (self.progressive && self.dirty) {
+            self.set_scale(if self.progressive { PROGRESSIVE_SCALE } else { self.preview_scale });
+            self.dirty = true;
+        } else if self.scale > 1 {
+            self.set_scale(self.scale / 2);
+            self.dirty = true;
+        }
+        if pressed.contains(&self.controls.keys.crosshair) {
+            self.crosshair = !self.crosshair;
+        }
+        self.update_title();
+        #[cfg(feature = "egui")]
+        self.update_overlay();
+
+        // The eyes of an anaglyph, and the sides of a comparison, are traced one after the other into the same canvas, so
+        // aren't traced in the background
+        if self.dirty {
+            if self.stereo == Stereo::Anaglyph || self.comparison.is_some() {
+                match self.trace_frame() {
+                    Err(RendererError::Cancelled) => return Ok(false),
+                    result => result?
+                }
+            } else {
+                self.canvas.clear();
+                self.tracing = Some(self.start_frame());
+                self.dirty = false;
+            }
+        }
+
+        self.render_canvas()?;
+        Ok(true)
     }
 
-    fn update_camera(&self) {       
-        let mut camera  = self.camera.write().unwrap(); 
+    // Trace a frame, then present it from now on
+    fn trace_frame(&mut self) -> Result<(), RendererError> {
+        self.canvas.clear();
+        match (self.stereo, self.comparison) {
+            (Stereo::Anaglyph, _) => self.trace_eyes()?,
+            (_, Some(comparison)) => self.trace_compared(comparison)?,
+            _ => self.trace_rays()?
+        }
+        self.canvas.swap();
+        self.dirty = false;
+        Ok(())
+    }
 
-        let x_speed = 0.3;
-        let z_speed = 0.3;
+    // Move and turn the camera with the keys held down, by dragging the mouse, and with a gamepad's sticks. Returns
+    // whether it moved
+    fn update_camera(&mut self) -> bool {
+        let mut camera  = self.camera.write().unwrap(); 
+        let mut moved = false;
 
-        let y_rot_speed = 5.0;
-        let x_rot_speed = 3.0;
+        let Controls { keys, move_speed, turn_speed, tilt_speed, mouse_sensitivity } = &self.controls;
+        let (move_speed, turn_speed, tilt_speed, mouse_sensitivity) = (*move_speed, *turn_speed, *tilt_speed, *mouse_sensitivity);
 
-        for key in self.screen.window.get_keys() {
+        let keys_down = self.screen.display.keys_down();
+        for &key in &keys_down {
             match key {
                 
                 // Move left, right, forward, backward
-                minifb::Key::A => {
-                    let step = &(&camera.rot_m * &Vec3d::new(-1.0, 0.0, 0.0)).normalize() * x_speed;
-                    camera.origin = &camera.origin + &Vec3d::new(step.x(), 0.0, step.z());
-                }
-                minifb::Key::D => {
-                    let step = &(&camera.rot_m * &Vec3d::new(1.0, 0.0, 0.0)).normalize() * x_speed;
-                    camera.origin = &camera.origin + &Vec3d::new(step.x(), 0.0, step.z());
-                }
-                minifb::Key::W => {
-                    let step = &(&camera.rot_m * &Vec3d::new(0.0, 0.0, -1.0)).normalize() * z_speed;
-                    camera.origin = &camera.origin + &Vec3d::new(step.x(), 0.0, step.z());
-                }
-                minifb::Key::S => {
-                    let step = &(&camera.rot_m * &Vec3d::new(0.0, 0.0, 1.0)).normalize() * z_speed;
-                    camera.origin = &camera.origin + &Vec3d::new(step.x(), 0.0, step.z());
-                }
+                key if key == keys.left => camera.walk(Vec3d::new(-1.0, 0.0, 0.0), move_speed),
+                key if key == keys.right => camera.walk(Vec3d::new(1.0, 0.0, 0.0), move_speed),
+                key if key == keys.forward => camera.walk(Vec3d::new(0.0, 0.0, -1.0), move_speed),
+                key if key == keys.back => camera.walk(Vec3d::new(0.0, 0.0, 1.0), move_speed),
                 
                 // Look left, right, up, down
-                minifb::Key::Left => {
-                    camera.y_rot += y_rot_speed;
-                }
-                minifb::Key::Right => {
-                    camera.y_rot -= y_rot_speed;
-                }
-                minifb::Key::Up => {
-                    camera.x_rot = (camera.x_rot + x_rot_speed).min(89.0);
-                }
-                minifb::Key::Down => {
-                    camera.x_rot = (camera.x_rot - x_rot_speed).max(-35.0);
-                }
+                key if key == keys.look_left => camera.turn(turn_speed, 0.0),
+                key if key == keys.look_right => camera.turn(-turn_speed, 0.0),
+                key if key == keys.look_up => camera.turn(0.0, tilt_speed),
+                key if key == keys.look_down => camera.turn(0.0, -tilt_speed),
+
+                _ => continue
+            }
+            moved = true;
+        }
 
-                _ => {}
+        // Dragging turns the camera the way the mouse moves, unless the drag started on the settings panel or the divider
+        // of a comparison
+        let dragging = self.screen.display.mouse_down() && !self.dragging_split && (self.drag_from.is_some() || !self.pointer_on_overlay());
+        let drag = if dragging { self.screen.display.mouse_pos() } else { None };
+        if let (Some((x, y)), Some((from_x, from_y))) = (drag, self.drag_from) {
+            let (dx, dy) = ((x - from_x) as Float, (y - from_y) as Float);
+            if dx != 0.0 || dy != 0.0 {
+                camera.turn(-dx * mouse_sensitivity, -dy * mouse_sensitivity);
+                moved = true;
+            }
+        }
+        self.drag_from = drag;
+
+        // Scrolling zooms in and out, or with shift held, changes how fast the camera walks
+        let scroll = self.screen.display.scroll() as Float;
+        if scroll != 0.0 && !self.pointer_on_overlay() {
+            if keys_down.contains(&Key::LeftShift) || keys_down.contains(&Key::RightShift) {
+                self.controls.move_speed = (self.controls.move_speed * SCROLL_SPEED_STEP.powf(scroll)).clamp(MIN_MOVE_SPEED, MAX_MOVE_SPEED);
+            } else {
+                let fov = (camera.fov / SCROLL_ZOOM_STEP.powf(scroll)).clamp(MIN_FOV, MAX_FOV);
+                camera.set_fov(fov, self.screen.width as Float / self.screen.height as Float);
+                self.rays = Arc::new(camera.primary_rays(self.canvas.width, self.canvas.height, self.stereo.views()));
+                moved = true;
             }
         }
 
+        // Sticks move the camera as fast as the keys when fully pushed, scaled by the sensitivity
+        #[cfg(feature = "gamepad")]
+        if let Some(sticks) = self.gamepad.as_mut().map(gamepad::Gamepad::sticks).filter(gamepad::Sticks::pushed) {
+            let speed = |push| gamepad::response(push) * self.gamepad_sensitivity;
+            camera.walk(Vec3d::new(1.0, 0.0, 0.0), move_speed * speed(sticks.left.0));
+            camera.walk(Vec3d::new(0.0, 0.0, -1.0), move_speed * speed(sticks.left.1));
+            camera.turn(-turn_speed * speed(sticks.right.0), tilt_speed * speed(sticks.right.1));
+            moved = true;
+        }
+
         let y_rot_matrix = Mat3::rotation_y(camera.y_rot);
         let x_rot_matrix = Mat3::rotation_matrix(&(&y_rot_matrix * &Vec3d::new(1.0, 0.0, 0.0)), camera.x_rot);
         camera.rot_m = &x_rot_matrix * &y_rot_matrix;
+        moved
     }
 
-    pub fn trace_rays(&self) {
-        let mut handles = vec![];
-        
-        let chunk_size = self.canvas.height / self.num_threads; // Each thread renders this many rows
-        
-        for thread_i in 0..self.num_threads {
-            let scene = Arc::clone(&self.scene);
-            let canvas = Arc::clone(&self.canvas);
-            let camera = Arc::clone(&self.camera);
-            let rays = Arc::clone(&self.rays);
-            let thread_buffer = Arc::clone(&self.thread_buffers[thread_i]);
+    // Move the divider of a comparison with the mouse, when pressed on it. Both sides are traced whole, so the frame
+    // isn't traced again
+    fn update_split(&mut self) {
+        if self.comparison.is_none() || !self.screen.display.mouse_down() {
+            self.dragging_split = false;
+            return;
+        }
+        let Some((x, _)) = self.screen.display.mouse_pos() else {
+            return;
+        };
+
+        // Only grabbed when the mouse is pressed, not dragged onto it while turning the camera
+        let divider = self.divider_x() as f32;
+        if !self.dragging_split && self.drag_from.is_none() && (x - divider).abs() <= DIVIDER_GRAB {
+            self.dragging_split = true;
+        }
+        if self.dragging_split {
+            self.split = (x as Float / self.screen.width as Float).clamp(0.0, 1.0);
+        }
+    }
 
-            let row_start = (thread_i * chunk_size) as usize;
-            let row_end = if thread_i == self.num_threads - 1 { canvas.height } else { row_start + chunk_size };
+    // The screen column the divider of a comparison is drawn at
+    fn divider_x(&self) -> usize {
+        ((self.split * self.screen.width as Float).round() as usize).min(self.screen.width)
+    }
 
-            let num_samples = self.num_samples;
+    // Pause or resume when the pause key is pressed. Returns whether rendering is paused
+    fn update_pause(&mut self, pressed: &[Key]) -> bool {
+        if pressed.contains(&self.controls.keys.pause) {
+            self.paused = !self.paused;
+            info!("{}", if self.paused { "Paused" } else { "Resumed" });
+        }
+        self.paused
+    }
 
-            let handle = thread::spawn(move || {
-                let camera = camera.read().unwrap();
-                let mut thread_buffer = thread_buffer.lock().unwrap();
-                let mut rng = rand::rng();
+    // Change the number of samples and bounces, and turn FXAA on and off, with the keys bound to them, e.g. to look
+    // around at low quality and raise it to take a still
+    fn update_quality(&mut self, pressed: &[Key]) {
+        let keys = &self.controls.keys;
+        let num_samples = keys.samples.iter().position(|key| pressed.contains(key)).map_or(self.num_samples, |i| QUALITY_SAMPLES[i]);
+        let max_depth = if pressed.contains(&keys.more_bounces) {
+            (self.max_depth + 1).min(MAX_DEPTH)
+        } else if pressed.contains(&keys.fewer_bounces) {
+            self.max_depth.saturating_sub(1)
+        } else {
+            self.max_depth
+        };
+
+        if (num_samples, max_depth) != (self.num_samples, self.max_depth) {
+            info!("Tracing with {} samples per unit and {} bounces", num_samples, max_depth);
+            (self.num_samples, self.max_depth) = (num_samples, max_depth);
+            self.dirty = true;
+        }
+        if pressed.contains(&keys.fxaa) {
+            self.fxaa = !self.fxaa;
+            info!("FXAA {}", if self.fxaa { "on" } else { "off" });
+            self.dirty = true;
+        }
+    }
 
-                // Render a canvas unit at (col, row)
-                // Sample to perform anti-aliasing
-                
-                for row in row_start..row_end {
-                    for col in 0..canvas.width {
-                        let mut total_color = (0, 0, 0);
-
-                        for _ in 0..num_samples {
-                            let jitter_x: f64 = if num_samples > 1 {rng.random::<f64>() - 0.5} else {0.0};
-                            let jitter_y: f64 = if num_samples > 1 {rng.random::<f64>() - 0.5} else {0.0};
-                            
-                            let ray = &rays[row][col];
-                            
-                            // Use rotation matrix to rotate each ray (gives effect of changing camera orientation)
-                            // Add random jitter for anti-aliasing
-                            
-                            let transformed_ray = Ray::new(
-                                camera.origin.clone(),
-                                &camera.rot_m * &(ray.dir() + &(&Vec3d::new(jitter_x, jitter_y, 0.0) * 0.0005))
-                            );
-                            
-                            let color = scene.trace_ray(
-                                &transformed_ray, 
-                                &Range{min: camera.vp_depth.abs() as f64, max: 100.0},
-                                2
-                            );
-
-                            total_color.0 += Color::r(color);
-                            total_color.1 += Color::g(color);
-                            total_color.2 += Color::b(color);
-                        }
+    // Follow the display's size when it's been resized, e.g. by dragging the window's edges: the screen, the camera's
+    // viewport and the canvas are sized to fit it, and the last frame is stretched over it until the next is traced.
+    // Returns whether it was resized
+    fn update_size(&mut self) -> bool {
+        let Some((width, height)) = self.screen.display.size().filter(|&size| size != (self.screen.width, self.screen.height)) else {
+            return false;
+        };
+        // Minimized windows have no size
+        if width == 0 || height == 0 {
+            return false;
+        }
 
-                        thread_buffer[row][col] = (total_color.0 / num_samples).min(255) << 16 | (total_color.1 / num_samples).min(255) << 8 | (total_color.2 / num_samples).min(255);
-                    }
+        info!("Resized to {}x{}", width, height);
+        self.screen.resize(width, height);
+        {
+            let mut camera = self.camera.write().unwrap();
+            let fov = camera.fov;
+            camera.set_fov(fov, width as Float / height as Float);
+        }
+        self.resize_canvas();
+        self.dirty = true;
+        true
+    }
+
+    // Select the next light that can be moved, and move (or turn) the selected light with the keys held, relative to
+    // where the camera faces. Lights can only be moved while the renderer is the scene's sole owner. Returns whether a
+    // light moved
+    fn update_lights(&mut self, pressed: &[Key]) -> bool {
+        let keys = &self.controls.keys;
+        let Some(scene) = Arc::get_mut(&mut self.scene) else {
+            return false;
+        };
+
+        // Cycle through the lights that can be moved, selecting none after the last
+        if pressed.contains(&keys.next_light) {
+            let after = self.selected_light.map_or(0, |i| i + 1);
+            self.selected_light = (after..scene.lights.len()).find(|&i| scene.lights[i].is_movable());
+        }
+        let Some(light) = self.selected_light.and_then(|i| scene.lights.get_mut(i)) else {
+            return false;
+        };
+
+        let keys_down = self.screen.display.keys_down();
+        let held = |key: Key| if keys_down.contains(&key) { 1.0 } else { 0.0 };
+        let (right, up, forward) = (
+            held(keys.light_right) - held(keys.light_left),
+            held(keys.light_up) - held(keys.light_down),
+            held(keys.light_forward) - held(keys.light_back)
+        );
+        if (right, up, forward) == (0.0, 0.0, 0.0) {
+            return false;
+        }
+
+        // Point lights move along the ground like the camera walks, and up and down. Directional lights turn left and
+        // right about the vertical, and tilt towards and away from the camera
+        let camera = self.camera.read().unwrap();
+        let flat = |dir: Vec3d| {
+            let dir = &camera.rot_m * &dir;
+            Vec3d::new(dir.x(), 0.0, dir.z()).normalize()
+        };
+        let camera_right = flat(Vec3d::new(1.0, 0.0, 0.0));
+        let offset = &(&(&camera_right * right) + &(&flat(Vec3d::new(0.0, 0.0, -1.0)) * forward)) + &Vec3d::new(0.0, up, 0.0);
+        let rotation = &Mat3::rotation_matrix(&camera_right, -forward * self.controls.tilt_speed) * &Mat3::rotation_y(-right * self.controls.turn_speed);
+        light.nudge(&(&offset * self.controls.move_speed), &rotation);
+
+        debug!("Moved {}", light.describe());
+        true
+    }
+
+    // Turn the time of day on or off, and while on, move the first directional light (and the sky's sun) along the sun
+    // arc by the time since the last frame, dimming and coloring it for how high the sun is. Returns whether the light
+    // moved
+    fn update_time_of_day(&mut self, pressed: &[Key]) -> bool {
+        let elapsed = self.last_step.elapsed().as_secs_f64() as Float;
+        self.last_step = Instant::now();
+        let Some(scene) = Arc::get_mut(&mut self.scene) else {
+            return false;
+        };
+
+        if pressed.contains(&self.controls.keys.time_of_day) {
+            self.time_of_day = match self.time_of_day {
+                Some(_) => None,
+                None => scene.lights.iter().position(|light| matches!(light, LightSource::Directional { .. })).map(|i| {
+                    TimeOfDay::new(i, SUNRISE_HOUR, self.day_length, scene.lights[i].intensity())
+                })
+            };
+        }
+        let Some(time_of_day) = &mut self.time_of_day else {
+            return false;
+        };
+
+        time_of_day.advance(elapsed);
+        let sun_dir = time_of_day.sun_dir();
+        if let Some(LightSource::Directional { intensity, dir, color }) = scene.lights.get_mut(time_of_day.light()) {
+            (*intensity, *dir, *color) = (time_of_day.intensity(), &sun_dir * -1.0, time_of_day.color());
+        }
+        if let Some(atmosphere) = &mut scene.atmosphere {
+            atmosphere.set_sun_dir(&sun_dir);
+        }
+        true
+    }
+
+    // Turn the turntable on or off, and while on, move the camera on round the point it orbits. Returns whether the
+    // camera moved
+    fn update_turntable(&mut self, pressed: &[Key]) -> bool {
+        let mut camera = self.camera.write().unwrap();
+        if pressed.contains(&self.controls.keys.turntable) {
+            self.turntable = match self.turntable {
+                Some(_) => None,
+                None => Some(Turntable::new(&camera.origin, camera.y_rot, self.orbit_radius, self.orbit_speed))
+            };
+            info!("Turntable {}", if self.turntable.is_some() { "on" } else { "off" });
+        }
+        let Some(turntable) = &self.turntable else {
+            return false;
+        };
+
+        // The target stays ahead, so turning the camera by hand moves it round the target too
+        (camera.origin, camera.y_rot) = turntable.advance(camera.y_rot);
+        true
+    }
+
+    // Drop a sphere of random size, color and material in front of the camera, to stress the intersection code and the
+    // hierarchy over the objects while exploring. Objects can only be added while the renderer is the scene's sole owner
+    fn spawn_sphere(&mut self) {
+        let Some(scene) = Arc::get_mut(&mut self.scene) else {
+            return;
+        };
+        let rng = &mut self.spawn_rng;
+        let camera = self.camera.read().unwrap();
+
+        let offset = Vec3d::new(rng.random_range(-1.5..1.5), rng.random_range(-1.0..1.0), -rng.random_range(3.0..8.0));
+        let center = &camera.origin + &(&camera.rot_m * &offset);
+        let color = Color::from_rgb(rng.random_range(0.0..255.0), rng.random_range(0.0..255.0), rng.random_range(0.0..255.0));
+        let materials = [Material::default(), presets::plastic(), presets::rubber(), presets::chrome(), presets::gold(), presets::glass()];
+        let material = materials[rng.random_range(0..materials.len())].clone();
+
+        let index = scene.add_object(object::Sphere::new(center, rng.random_range(0.2..1.0), color, material));
+        debug!("Spawned sphere #{}", index);
+        self.dirty = true;
+    }
+
+    // Show what's being inspected in the title bar: the object under the crosshair while it's shown, which helps debug
+    // scenes, and the light selected for moving
+    fn update_title(&mut self) {
+        self.hovered = self.crosshair.then(|| {
+            let camera = self.camera.read().unwrap();
+            match self.scene.cast_ray(&camera.center_ray(), &camera.view_range(), RayKind::Camera) {
+                Some(hit) => {
+                    let object = self.scene.object(hit.object).expect("hits are on objects in the scene");
+                    format!("{} #{}, {:.2} away, {}", object.name(), hit.object, hit.t, object.get_material())
+                }
+                None => "nothing".to_string()
+            }
+        });
+        let selected = self.selected_light.and_then(|i| self.scene.lights.get(i)).map(|light| format!("moving {}", light.describe()));
+        let time = self.time_of_day.as_ref().map(|time_of_day| {
+            let minutes = (time_of_day.hour() * 60.0) as u32;
+            format!("{:02}:{:02}", minutes / 60, minutes % 60)
+        });
+
+        let title = [self.hovered.clone(), selected, time, Some(display::WINDOW_TITLE.to_string())].into_iter().flatten().collect::<Vec<_>>().join(" - ");
+        if title != self.title {
+            self.screen.display.set_title(&title);
+            self.title = title;
+        }
+    }
+
+    // Whether the pointer is over the settings panel, so clicks there are left to the panel
+    fn pointer_on_overlay(&self) -> bool {
+        #[cfg(feature = "egui")]
+        {
+            self.overlay.wants_pointer()
+        }
+        #[cfg(not(feature = "egui"))]
+        {
+            false
+        }
+    }
+
+    // Trace with canvas units this many times larger than configured. Only called between frames
+    fn set_scale(&mut self, scale: usize) {
+        if scale == self.scale {
+            return;
+        }
+        self.scale = scale;
+        self.resize_canvas();
+    }
+
+    // Size the canvas of the next frame to the screen at the current scale, with rays to match
+    fn resize_canvas(&mut self) {
+        let views = self.stereo.views();
+        let (width, height) = canvas_dimensions(self.screen.width, self.screen.height, self.canvas_unit_size * self.scale, self.render_scale, views);
+        self.canvas.resize(width, height);
+        self.rays = Arc::new(self.camera.read().unwrap().primary_rays(width, height, views));
+    }
+
+    // Samples per canvas unit at the current scale. Traced progressively, each level coarser than full size takes half
+    // the samples of the one after it
+    fn level_samples(&self) -> usize {
+        if self.progressive {
+            (self.num_samples / self.scale).max(1)
+        } else {
+            self.num_samples
+        }
+    }
+
+    pub fn trace_rays(&mut self) -> Result<(), RendererError> {
+        let tracing = self.start_frame();
+        self.finish_frame(tracing)
+    }
+
+    // Start tracing the next frame on the worker threads
+    fn start_frame(&mut self) -> Tracing {
+        // Both eyes' views of a frame move from the frame before
+        if self.aovs.contains(&Aov::Motion) && self.eye != Some(Eye::Right) {
+            let now = View::new(&self.camera.read().unwrap(), &self.scene);
+            let before = self.motion.as_ref().map_or_else(|| now.clone(), |motion| motion.now.clone());
+            self.motion = Some(Arc::new(Motion {
+                before,
+                now,
+                canvas_width: self.canvas.width,
+                canvas_height: self.canvas.height,
+                views: self.stereo.views(),
+                interocular: self.interocular
+            }));
+        }
+
+        let tracing = self.start_rows(0, self.canvas.height);
+        self.frame += 1;
+        tracing
+    }
+
+    fn finish_frame(&mut self, tracing: Tracing) -> Result<(), RendererError> {
+        let (frame, start) = (tracing.frame, tracing.start);
+
+        let result = self.finish_rows(tracing);
+        if let Err(RendererError::Cancelled) = result {
+            info!("Frame {} cancelled after {:.2?}", frame, start.elapsed());
+        }
+        result?;
+
+        self.post_process();
+
+        debug!("Frame {} traced in {:.2?}", frame, start.elapsed());
+        Ok(())
+    }
+
+    // Apply the post-processing effects to the frame just traced (FXAA then sharpening first, when they're on), a view
+    // at a time, and encode it again for display. The linear light it was traced to is kept as it was
+    fn post_process(&mut self) {
+        if !self.fxaa && self.cas.is_none() && self.post_effects.is_empty() {
+            return;
+        }
+
+        let (width, height) = (self.canvas.width, self.canvas.height);
+        let view_width = width / self.stereo.views();
+        for view in (0..width).step_by(view_width.max(1)).filter(|start| start + view_width <= width) {
+            let units = |row: usize| row * width + view..row * width + view + view_width;
+            let mut frame = Frame {
+                pixels: (0..height).flat_map(|row| self.canvas.linear[units(row)].to_vec()).collect(),
+                width: view_width,
+                height,
+                seed: frame_seed(self.seed, self.frame)
+            };
+            if self.fxaa {
+                Fxaa::default().apply(&mut frame);
+            }
+            if let Some(cas) = &self.cas {
+                cas.apply(&mut frame);
+            }
+            for effect in &self.post_effects {
+                effect.apply(&mut frame);
+            }
+            for (row, pixels) in frame.pixels.chunks(view_width).enumerate() {
+                for (unit, &rgb) in self.canvas.buffer[units(row)].iter_mut().zip(pixels) {
+                    *unit = Color::from_linear(rgb) as u32;
                 }
+            }
+        }
+    }
+
+    // Trace the frame from the left eye, keeping its view, then from the right eye into the canvas
+    fn trace_eyes(&mut self) -> Result<(), RendererError> {
+        self.eye = Some(Eye::Left);
+        let left = self.trace_rays();
+        self.eye_buffer = self.canvas.buffer.clone();
+
+        self.eye = Some(Eye::Right);
+        let right = left.and_then(|_| self.trace_rays());
+
+        self.eye = None;
+        right
+    }
 
-                // Merge thread buffers into canvas buffer
+    // Trace the frame with the comparison's settings, keeping it for the right side, then with the renderer's own
+    fn trace_compared(&mut self, comparison: Comparison) -> Result<(), RendererError> {
+        let own = (self.integrator, self.num_samples, self.max_depth, self.exposure, self.fxaa, self.cas, self.post_effects.clone());
+        self.integrator = comparison.integrator.unwrap_or(self.integrator);
+        self.num_samples = comparison.num_samples.unwrap_or(self.num_samples);
+        self.max_depth = comparison.max_depth.unwrap_or(self.max_depth);
+        self.exposure = comparison.exposure.unwrap_or(self.exposure);
+        if comparison.raw {
+            (self.fxaa, self.cas) = (false, None);
+            self.post_effects.clear();
+        }
+        let right = self.trace_rays();
+        self.compared_buffer = self.canvas.buffer.clone();
+
+        (self.integrator, self.num_samples, self.max_depth, self.exposure, self.fxaa, self.cas, self.post_effects) = own;
+        right.and_then(|_| self.trace_rays())
+    }
+
+    // Trace a band of canvas rows of the current frame and return them as packed 0RGB canvas units, row by row
+    // Rows come out the same as when the whole frame is traced, so bands can be traced separately (e.g. on other machines)
+    pub fn render_rows(&mut self, row_start: usize, row_end: usize) -> Result<Vec<u32>, RendererError> {
+        let row_end = row_end.min(self.canvas.height);
+        let row_start = row_start.min(row_end);
 
-                let mut buffer = canvas.buffer.lock().unwrap();
+        self.trace_rows(row_start, row_end)?;
 
-                for row in row_start..row_end {
-                    for col in 0..canvas.width {
-                        buffer[row][col] = thread_buffer[row][col] as usize;
+        Ok(self.canvas.buffer[row_start * self.canvas.width..row_end * self.canvas.width].to_vec())
+    }
+
+    pub fn canvas_size(&self) -> (usize, usize) {
+        (self.canvas.width, self.canvas.height)
+    }
+
+    fn trace_rows(&mut self, rows_start: usize, rows_end: usize) -> Result<(), RendererError> {
+        let tracing = self.start_rows(rows_start, rows_end);
+        self.finish_rows(tracing)
+    }
+
+    // Start tracing a band of canvas rows into the canvas, without waiting for it to finish
+    // A coordinating thread takes the canvas buffer and splits the band between the worker threads, which each write
+    // their own rows of it directly
+    fn start_rows(&mut self, rows_start: usize, rows_end: usize) -> Tracing {
+        let start = Instant::now();
+
+        // Each canvas row is a tile. Threads report every finished tile so progress can be tracked from this thread
+        let (tile_tx, tile_rx) = mpsc::channel();
+
+        let tracer = RowTracer {
+            scene: Arc::clone(&self.scene),
+            camera: Arc::clone(&self.camera),
+            rays: Arc::clone(&self.rays),
+            cancel_token: self.cancel_token.clone(),
+            canvas_width: self.canvas.width,
+            canvas_height: self.canvas.height,
+            num_samples: self.level_samples(),
+            filter: self.filter,
+            sample_pattern: self.sample_pattern,
+            firefly_clamp: self.firefly_clamp,
+            max_depth: self.max_depth,
+            integrator: self.integrator,
+            exposure: self.exposure,
+            seed: self.seed,
+            frame: self.frame,
+            stereo: self.stereo,
+            interocular: self.interocular,
+            eye: self.eye,
+            pipeline: self.pipeline,
+            aovs: self.aovs.clone(),
+            material_ids: if self.aovs.contains(&Aov::MaterialId) { aov::material_ids(&self.scene.objs) } else { Vec::new() },
+            motion: self.motion.clone()
+        };
+        let num_threads = self.num_threads;
+        let mut lent = Lent {
+            buffer: std::mem::take(&mut self.canvas.buffer),
+            linear: std::mem::take(&mut self.canvas.linear),
+            aovs: std::mem::take(&mut self.canvas.aovs),
+            row_costs: std::mem::take(&mut self.canvas.row_costs),
+            scratch: std::mem::take(&mut self.scratch)
+        };
+
+        let work = move || {
+            let width = tracer.canvas_width;
+            let Lent { buffer, linear, aovs, row_costs, scratch } = &mut lent;
+
+            // Rows that were slow last frame (e.g. full of reflections) are likely slow again, so each thread gets a
+            // run of rows that took about as long as every other thread's
+            let counts = balance_rows(&row_costs[rows_start..rows_end], num_threads);
+            let mut chunks = Vec::with_capacity(counts.len());
+            let units = rows_start * width..rows_end * width;
+            let mut rest = Band {
+                units: &mut buffer[units.clone()],
+                linear: &mut linear[units.clone()],
+                aovs: aovs.iter_mut().map(|aov| &mut aov[units.clone()]).collect(),
+                costs: &mut row_costs[rows_start..rows_end]
+            };
+            let mut row_start = rows_start;
+            for (count, scratch) in counts.into_iter().zip(scratch.iter_mut()) {
+                let (band, after) = rest.split_at(count, width);
+                chunks.push((row_start, band, scratch));
+                (rest, row_start) = (after, row_start + count);
+            }
+
+            // Threads can't be spawned in the browser, so the chunks are traced one after another there
+            if cfg!(target_arch = "wasm32") {
+                for (row_start, band, scratch) in chunks {
+                    tracer.trace(row_start, band, scratch, &tile_tx);
+                }
+            } else {
+                let tracer = &tracer;
+                thread::scope(|scope| {
+                    for (row_start, band, scratch) in chunks {
+                        let tile_tx = tile_tx.clone();
+                        scope.spawn(move || tracer.trace(row_start, band, scratch, &tile_tx));
                     }
+                });
+            }
+            lent
+        };
+
+        let traced = if cfg!(target_arch = "wasm32") {
+            Traced::Done(work())
+        } else {
+            Traced::Running(thread::spawn(work))
+        };
+
+        Tracing {
+            traced,
+            tiles: tile_rx,
+            tiles_total: rows_end - rows_start,
+            frame: self.frame,
+            start
+        }
+    }
+
+    // Report progress as the worker threads finish tiles, and wait for them all to finish
+    fn finish_rows(&mut self, tracing: Tracing) -> Result<(), RendererError> {
+        let Tracing { traced, tiles, tiles_total, start, .. } = tracing;
+
+        // Receiving stops once every thread has finished (dropped its sender)
+        for (tiles_finished, _) in tiles.into_iter().enumerate() {
+            let tiles_done = tiles_finished + 1;
+            if let Some(callback) = self.progress_callback.as_mut() {
+                callback(&Progress { tiles_done, tiles_total, elapsed: start.elapsed() });
+            }
+        }
+
+        let lent = match traced {
+            Traced::Running(handle) => match handle.join() {
+                Ok(lent) => lent,
+                Err(_) => {
+                    // What was lent went down with the thread, so start over with new ones
+                    self.canvas.buffer = vec![0; self.canvas.width * self.canvas.height];
+                    self.canvas.linear = vec![[0.0; 3]; self.canvas.width * self.canvas.height];
+                    self.canvas.aovs = vec![vec![[0.0; 3]; self.canvas.width * self.canvas.height]; self.aovs.len()];
+                    self.canvas.row_costs = vec![1.0; self.canvas.height];
+                    self.scratch = (0..self.num_threads).map(|_| Scratch::new(self.num_samples)).collect();
+                    return Err(RendererError::RenderThread);
+                }
+            },
+            Traced::Done(lent) => lent
+        };
+        self.canvas.buffer = lent.buffer;
+        self.canvas.linear = lent.linear;
+        self.canvas.aovs = lent.aovs;
+        self.canvas.row_costs = lent.row_costs;
+        self.scratch = lent.scratch;
+
+        if self.cancel_token.is_cancelled() {
+            return Err(RendererError::Cancelled);
+        }
+
+        Ok(())
+    }
+
+    // Stretch the last finished frame over the screen, or shrink it when it has more units than the screen has pixels
+    fn render_canvas(&mut self) -> Result<(), RendererError> {
+        let (width, height) = (self.canvas.front_width, self.canvas.front_height);
+        let anaglyph: Vec<u32>;
+        let units = match self.stereo {
+            Stereo::Mono | Stereo::SideBySide | Stereo::CrossEyed => &self.canvas.front,
+            Stereo::Anaglyph => {
+                anaglyph = self.eye_buffer.iter().zip(&self.canvas.front).map(|(&left, &right)| stereo::anaglyph(left as usize, right as usize) as u32).collect();
+                &anaglyph
+            }
+        };
+        self.upscale.resample(units, width, height, &mut self.screen.buffer, self.screen.width, self.screen.height);
+        if self.comparison.is_some() && self.compared_buffer.len() == width * height {
+            let mut compared = vec![0; self.screen.buffer.len()];
+            self.upscale.resample(&self.compared_buffer, width, height, &mut compared, self.screen.width, self.screen.height);
+            self.draw_comparison(&compared);
+        }
+
+        if self.crosshair {
+            self.draw_crosshair();
+        }
+        #[cfg(feature = "egui")]
+        self.overlay.paint(&mut self.screen.buffer, self.screen.width, self.screen.height);
+
+        self.screen.render_buffer()
+    }
+
+    // The right side of a comparison over the screen past the divider, and the divider, inverting the pixels under it
+    fn draw_comparison(&mut self, compared: &[u32]) {
+        let width = self.screen.width;
+        let divider = self.divider_x();
+        let line = divider.saturating_sub(DIVIDER_WIDTH / 2)..(divider + DIVIDER_WIDTH.div_ceil(2)).min(width);
+        for (row, compared_row) in self.screen.buffer.chunks_mut(width).zip(compared.chunks(width)) {
+            row[divider..].copy_from_slice(&compared_row[divider..]);
+            for pixel in &mut row[line.clone()] {
+                *pixel ^= 0xFFFFFF;
+            }
+        }
+    }
+
+    // A small cross at the center of the screen, inverting the pixels under it so it shows up on any background
+    fn draw_crosshair(&mut self) {
+        let (width, height) = (self.screen.width, self.screen.height);
+        let (center_x, center_y) = (width / 2, height / 2);
+        for offset in CROSSHAIR_GAP..=CROSSHAIR_SIZE {
+            let pixels = [
+                (center_x.checked_sub(offset), Some(center_y)),
+                (Some(center_x + offset), Some(center_y)),
+                (Some(center_x), center_y.checked_sub(offset)),
+                (Some(center_x), Some(center_y + offset))
+            ];
+            for (x, y) in pixels {
+                if let (Some(x), Some(y)) = (x.filter(|&x| x < width), y.filter(|&y| y < height)) {
+                    self.screen.buffer[y * width + x] ^= 0xFFFFFF;
                 }
-            });
-            handles.push(handle);
+            }
+        }
+    }
+
+    // Run the settings panel and apply any values that were changed through it
+    #[cfg(feature = "egui")]
+    fn update_overlay(&mut self) {
+        let fov = self.camera.read().unwrap().fov;
+        let mut settings = overlay::OverlaySettings {
+            num_samples: self.num_samples,
+            fixed_sample_pattern: self.sample_pattern == SamplePattern::RotatedGrid,
+            max_depth: self.max_depth,
+            fov,
+            exposure: self.exposure,
+            fxaa: self.fxaa,
+            sharpen: self.cas.is_some(),
+            sharpness: self.cas.map_or(Cas::default().sharpness, |cas| cas.sharpness),
+            lights: self.scene.lights.iter().map(|light| (light.name(), light.intensity())).collect(),
+            hovered: self.hovered.clone()
+        };
+
+        let before = settings.clone();
+        self.overlay.update(self.screen.display.as_ref(), self.screen.width, self.screen.height, &mut settings);
+        if settings == before {
+            return;
+        }
+        self.dirty = true;
+
+        self.num_samples = settings.num_samples;
+        self.sample_pattern = if settings.fixed_sample_pattern { SamplePattern::RotatedGrid } else { SamplePattern::Random };
+        self.max_depth = settings.max_depth;
+        self.exposure = settings.exposure;
+        self.fxaa = settings.fxaa;
+        self.cas = settings.sharpen.then_some(Cas { sharpness: settings.sharpness });
+
+        if settings.fov != fov {
+            let mut camera = self.camera.write().unwrap();
+            camera.set_fov(settings.fov, self.screen.width as Float / self.screen.height as Float);
+            self.rays = Arc::new(camera.primary_rays(self.canvas.width, self.canvas.height, self.stereo.views()));
         }
 
-        for handle in handles {
-            handle.join().unwrap();
+        // Lights can only be edited while the renderer is the scene's sole owner
+        if let Some(scene) = Arc::get_mut(&mut self.scene) {
+            for (light, (_, intensity)) in scene.lights.iter_mut().zip(settings.lights) {
+                light.set_intensity(intensity);
+            }
         }
     }
+}
+/*
+
+Scratch
 
-    fn render_canvas(&mut self) {
-        let canvas_buffer = &self.canvas.buffer.lock().unwrap();
+Working memory a worker thread reuses for every canvas unit it traces, kept from frame to frame so tracing doesn't
+allocate. The random number generator is reseeded at every canvas unit, so renders stay the same however the canvas
+is split between threads
 
-        for canvas_row in 0..self.canvas.height {
-            for canvas_col in 0..self.canvas.width {
-                let screen_row_start = canvas_row * self.canvas_unit_size;
-                let screen_col_start = canvas_col * self.canvas_unit_size;
-                for screen_row in screen_row_start .. screen_row_start + self.canvas_unit_size {
-                    for screen_col in screen_col_start .. screen_col_start + self.canvas_unit_size {
-                        self.screen.buffer[screen_row * self.screen.width + screen_col] = canvas_buffer[canvas_row][canvas_col] as u32;
+*/
+
+struct Scratch {
+    samples: Vec<([Float; 3], Float)>, // Linear RGB and filter weight of every sample of the canvas unit being traced
+    aov_samples: Vec<(aov::Sample, Float)>, // What the AOVs need from every sample, when any are recorded
+    aov_values: Vec<[Float; 3]>, // The value of every AOV of the canvas unit being traced
+    tile_rays: Vec<Ray>, // Camera ray of every sample of the tile being traced sorted
+    tile_weights: Vec<Float>, // Filter weight of every sample of the tile
+    tile_units: Vec<std::ops::Range<usize>>, // Which of the tile's samples belong to each of its units
+    tile_colors: Vec<[Float; 3]>, // Light seen by every sample of the tile
+    tile_order: Vec<usize>, // The tile's samples in the order they're shaded
+    traversal: TraversalContext, // What the thread's camera rays pass on to each other as they're traced
+    rng: Pcg32
+}
+
+impl Scratch {
+    fn new(num_samples: usize) -> Self {
+        Self {
+            samples: Vec::with_capacity(num_samples),
+            aov_samples: Vec::new(),
+            aov_values: Vec::new(),
+            tile_rays: Vec::new(),
+            tile_weights: Vec::new(),
+            tile_units: Vec::new(),
+            tile_colors: Vec::new(),
+            tile_order: Vec::new(),
+            traversal: TraversalContext::primary(),
+            rng: Pcg32::new(0, 0)
+        }
+    }
+}
+
+/*
+
+Row Tracer
+
+Everything the worker threads need to trace canvas rows of a frame, shared between them
+
+*/
+
+const TILE_SIZE: usize = 8; // Width and height of the tiles rows are traced in, in canvas units
+
+struct RowTracer {
+    scene: Arc<Scene>,
+    camera: Arc<RwLock<Camera>>,
+    rays: Arc<Vec<Vec<Ray>>>, // The rays that are traced into the scene
+    cancel_token: CancelToken,
+    canvas_width: usize,
+    canvas_height: usize,
+    num_samples: usize,
+    filter: Filter,
+    sample_pattern: SamplePattern,
+    firefly_clamp: Option<Float>,
+    max_depth: u32,
+    integrator: Integrator,
+    exposure: Float,
+    seed: u64,
+    frame: u64,
+    stereo: Stereo,
+    interocular: Float,
+    eye: Option<Eye>,
+    pipeline: Pipeline,
+    aovs: Vec<Aov>,
+    material_ids: Vec<usize>, // The material ID of every object, when material IDs are recorded
+    motion: Option<Arc<Motion>> // Where the camera and objects were the frame before, when motion vectors are recorded
+}
+
+impl RowTracer {
+    // Trace consecutive canvas rows, starting at row_start, into their part of the canvas buffer (and the linear light and
+    // AOVs of each unit into their buffers), and record how long each took. A message is sent as each row finishes
+    //
+    // Rows are traced a strip of TILE_SIZE at a time, a square tile after another, and the units of a tile in Z-order
+    // (Morton order). Rays traced one after another then start close together and pass through the same boxes and
+    // objects, which are still in the cache, instead of crossing the whole scene along every row
+    fn trace(&self, row_start: usize, band: Band, scratch: &mut Scratch, tile_tx: &mpsc::Sender<()>) {
+        let camera = self.camera.read().unwrap();
+        let row_len = self.canvas_width;
+
+        let Band { units, linear, mut aovs, costs } = band;
+        scratch.aov_values.resize(self.aovs.len(), [0.0; 3]);
+
+        let strips = units.chunks_mut(row_len * TILE_SIZE).zip(linear.chunks_mut(row_len * TILE_SIZE)).zip(costs.chunks_mut(TILE_SIZE));
+        for (strip, ((units, linear), costs)) in strips.enumerate() {
+            if self.cancel_token.is_cancelled() {
+                break;
+            }
+            let started = Instant::now();
+            let strip_start = row_start + strip * TILE_SIZE;
+            let strip_rows = costs.len();
+
+            for tile_col in (0..row_len).step_by(TILE_SIZE) {
+                let tile = (0..TILE_SIZE * TILE_SIZE).map(morton)
+                    .map(|(x, y)| (tile_col + x, y))
+                    .filter(|&(col, row)| col < row_len && row < strip_rows);
+                if self.sorts() {
+                    self.trace_tile_sorted(&camera, tile.clone().map(|(col, row)| (col, strip_start + row)), scratch);
+                }
+
+                for (n, (col, row)) in tile.enumerate() {
+                    let i = row * row_len + col;
+                    linear[i] = if self.sorts() {
+                        self.resolve_sorted_unit(&camera, n, scratch)
+                    } else {
+                        self.trace_unit(&camera, col, strip_start + row, scratch)
+                    };
+                    units[i] = Color::from_linear(linear[i]) as u32;
+
+                    // The AOVs of the whole band are indexed from its first row
+                    for (aov, (buffer, value)) in self.aovs.iter().zip(aovs.iter_mut().zip(&scratch.aov_values)) {
+                        buffer[strip * row_len * TILE_SIZE + i] = match (aov, &self.motion) {
+                            (Aov::Motion, Some(motion)) => self.trace_motion(&camera, motion, col, strip_start + row),
+                            _ => *value
+                        };
                     }
                 }
             }
+
+            // The strip's rows took about as long as each other
+            let elapsed = started.elapsed().as_secs_f64();
+            for cost in costs.iter_mut() {
+                *cost = elapsed / strip_rows as f64;
+                let _ = tile_tx.send(());
+            }
         }
-        
-        self.screen.render_buffer();
     }
-}
\ No newline at end of file
+
+    // The motion vector of the canvas unit at (col, row), found along the ray through its center
+    fn trace_motion(&self, camera: &Camera, motion: &Motion, col: usize, row: usize) -> [Float; 3] {
+        let eye = self.stereo.eye_at(col, self.canvas_width, self.eye);
+        let ray = Ray::new(camera.eye_origin(eye, self.interocular), &camera.rot_m * self.rays[row][col].dir());
+        let [x, y] = motion.at(&self.scene, &ray, eye, (col, row));
+        [x, y, 0.0]
+    }
+
+    // Trace the canvas unit at (col, row), sampling it several times to anti-alias it, and return its linear RGB
+    // The unit's AOVs other than motion are left in the scratch's AOV values
+    fn trace_unit(&self, camera: &Camera, col: usize, row: usize, scratch: &mut Scratch) -> [Float; 3] {
+        let needs_direct = self.aovs.iter().any(Aov::needs_direct);
+        let Scratch { samples, aov_samples, traversal, rng, .. } = scratch;
+
+        *rng = Pcg32::new(frame_seed(self.seed, self.frame), (row * self.canvas_width + col) as u64);
+        samples.clear();
+        aov_samples.clear();
+
+        for sample in 0..self.num_samples {
+            let Some((ray, weight)) = self.sample_ray(camera, col, row, sample, rng) else {
+                continue;
+            };
+
+            let t_range = camera.view_range();
+            let sample_rng = needs_direct.then(|| rng.clone());
+            let hit = self.scene.first_hit(&ray, &t_range, RayKind::Camera, traversal);
+            let color = match self.integrator {
+                Integrator::Whitted => self.scene.trace_hit(&ray, &t_range, self.max_depth, hit),
+                Integrator::PathTraced => self.scene.trace_path_hit(&ray, &t_range, self.max_depth, rng, hit),
+                Integrator::AmbientOcclusion => self.scene.trace_occlusion_hit(&ray, rng, hit)
+            };
+
+            samples.push((color, weight));
+            if let Some(aov_sample) = self.aov_sample(camera, &ray, &t_range, color, sample_rng, traversal) {
+                aov_samples.push((aov_sample, weight));
+            }
+        }
+
+        self.resolve_unit(scratch)
+    }
+
+    // The camera ray of a sample of the canvas unit at (col, row), and its filter weight. None when the sample lands
+    // where the filter has no weight
+    fn sample_ray(&self, camera: &Camera, col: usize, row: usize, sample: usize, rng: &mut Pcg32) -> Option<(Ray, Float)> {
+        let (num_samples, filter) = (self.num_samples, self.filter);
+
+        // Spread samples over the filter's footprint, in canvas units from the unit's center
+        let (jitter_x, jitter_y) = if num_samples > 1 {
+            let (x, y) = self.sample_pattern.offset(sample, num_samples, rng);
+            (x * filter.radius(), y * filter.radius())
+        } else {
+            (0.0, 0.0)
+        };
+        let weight = filter.weight(jitter_x, jitter_y);
+        if weight <= 0.0 {
+            return None;
+        }
+
+        // Size of a canvas unit on the viewport, which sample offsets are scaled by
+        let unit_size = camera.vp_height / self.canvas_height as Float;
+        let origin = camera.eye_origin(self.stereo.eye_at(col, self.canvas_width, self.eye), self.interocular);
+        let ray = &self.rays[row][col];
+
+        // Use rotation matrix to rotate each ray (gives effect of changing camera orientation)
+        // Add random jitter for anti-aliasing
+
+        let transformed_ray = Ray::new(
+            origin,
+            &camera.rot_m * &(ray.dir() + &(&Vec3d::new(jitter_x, -jitter_y, 0.0) * unit_size))
+        );
+        Some((transformed_ray, weight))
+    }
+
+    // Combine the samples of a canvas unit left in the scratch into its linear RGB, and its AOVs other than motion
+    fn resolve_unit(&self, scratch: &mut Scratch) -> [Float; 3] {
+        let Scratch { samples, aov_samples, aov_values, .. } = scratch;
+
+        if let Some(factor) = self.firefly_clamp {
+            filter::clamp_fireflies(samples, factor);
+        }
+
+        // Samples are averaged (and exposed) as light adds up, in linear space, rather than as the encoded values shown
+        let mut total_color: [Float; 3] = [0.0; 3];
+        let mut total_weight = 0.0;
+        for &(color, weight) in samples.iter() {
+            for (total, channel) in total_color.iter_mut().zip(color) {
+                *total += channel * weight;
+            }
+            total_weight += weight;
+        }
+
+        // Every sample may have landed where the filter has no weight
+        let total_weight = if total_weight > 0.0 { total_weight } else { 1.0 };
+        let color = total_color.map(|total| total / total_weight * self.exposure);
+
+        aov::resolve(&self.aovs, aov_samples, color, self.exposure, aov_values);
+        color
+    }
+
+    // Trace the samples of a tile's canvas units a stage at a time, shading them sorted (see pipeline.rs). Every unit's
+    // samples are left in the scratch, in the order the units are given, for resolve_sorted_unit
+    fn trace_tile_sorted(&self, camera: &Camera, units: impl Iterator<Item = (usize, usize)>, scratch: &mut Scratch) {
+        let Scratch { rng, tile_rays, tile_weights, tile_units, tile_colors, tile_order, .. } = scratch;
+        tile_rays.clear();
+        tile_weights.clear();
+        tile_units.clear();
+
+        // Camera rays, drawing the same random numbers for each unit as tracing it immediately does
+        for (col, row) in units {
+            *rng = Pcg32::new(frame_seed(self.seed, self.frame), (row * self.canvas_width + col) as u64);
+            let first = tile_rays.len();
+            for (ray, weight) in (0..self.num_samples).filter_map(|sample| self.sample_ray(camera, col, row, sample, rng)) {
+                tile_rays.push(ray);
+                tile_weights.push(weight);
+            }
+            tile_units.push(first..tile_rays.len());
+        }
+
+        // What every ray hits, found for neighbouring rays together as packets, then where its shading goes in the
+        // sorted order
+        let t_range = camera.view_range();
+        let t_ranges = [t_range; PACKET_SIZE];
+        let mut hits = Vec::with_capacity(tile_rays.len());
+        for rays in tile_rays.chunks(PACKET_SIZE) {
+            hits.extend(self.scene.tlas.packet_intersections(&self.scene.objs, rays, &t_ranges, RayKind::Camera).into_iter().take(rays.len()));
+        }
+        tile_order.clear();
+        tile_order.extend(0..tile_rays.len());
+        tile_order.sort_by_key(|&i| pipeline::shading_key(hits[i].as_ref().map(|(obj, hit)| (hit.material, *obj)), tile_rays[i].dir()));
+
+        tile_colors.clear();
+        tile_colors.resize(tile_rays.len(), [0.0; 3]);
+        for &i in tile_order.iter() {
+            let ray = &tile_rays[i];
+            let hit = hits[i].take().map(|(obj, hit)| (&self.scene.objs[obj] as &dyn Object, hit));
+            tile_colors[i] = self.scene.trace_hit(ray, &t_range, self.max_depth, hit);
+        }
+    }
+
+    // Combine the samples of the nth unit given to trace_tile_sorted into its linear RGB, and its AOVs other than motion
+    fn resolve_sorted_unit(&self, camera: &Camera, n: usize, scratch: &mut Scratch) -> [Float; 3] {
+        let Scratch { samples, aov_samples, tile_rays, tile_weights, tile_units, tile_colors, traversal, .. } = scratch;
+        samples.clear();
+        aov_samples.clear();
+
+        let t_range = camera.view_range();
+        for i in tile_units[n].clone() {
+            let (ray, weight, color) = (&tile_rays[i], tile_weights[i], tile_colors[i]);
+            samples.push((color, weight));
+            if let Some(aov_sample) = self.aov_sample(camera, ray, &t_range, color, None, traversal) {
+                aov_samples.push((aov_sample, weight));
+            }
+        }
+        self.resolve_unit(scratch)
+    }
+
+    // Whether a tile's samples are shaded sorted. Only samples that don't draw random numbers can be
+    fn sorts(&self) -> bool {
+        self.pipeline == Pipeline::Sorted && self.integrator == Integrator::Whitted
+    }
+
+    // What the AOVs need from a sample, if any are recorded that need it, given its camera ray and color and the random
+    // numbers (rng) its color was traced with, tracing it again with the thread's traversal context
+    fn aov_sample(&self, camera: &Camera, ray: &Ray, t_range: &Range<Float>, color: [Float; 3], rng: Option<Pcg32>, traversal: &mut TraversalContext) -> Option<aov::Sample> {
+        let needs_surface = self.aovs.iter().any(Aov::needs_surface);
+        let needs_direct = self.aovs.iter().any(Aov::needs_direct);
+        if !needs_surface && !needs_direct {
+            return None;
+        }
+
+        let surface = needs_surface.then(|| self.trace_surface(camera, ray, t_range, traversal)).flatten();
+        let direct = if needs_direct { self.trace_direct(ray, t_range, color, rng, traversal) } else { [0.0; 3] };
+        Some(aov::Sample { surface, direct })
+    }
+
+    // The first surface a sample's camera ray hits
+    fn trace_surface(&self, camera: &Camera, ray: &Ray, t_range: &Range<Float>, traversal: &mut TraversalContext) -> Option<aov::Surface> {
+        let forward = camera.center_ray().dir().normalize();
+        let (object, hit) = self.scene.tlas.closest_intersection(&self.scene.objs, ray, t_range, RayKind::Camera, traversal)?;
+        Some(aov::Surface {
+            object,
+            material: self.material_ids.get(object).copied().unwrap_or(0),
+            normal: hit.facing_normal(),
+            depth: hit.t * (ray.dir() * &forward),
+            albedo: Color::to_linear(self.scene.surface_color(&self.scene.objs[object], &hit))
+        })
+    }
+
+    // The part of a sample's color that came straight from the lights off the first surface its camera ray hits, traced
+    // with the random numbers (rng) its color was, as linear RGB
+    fn trace_direct(&self, ray: &Ray, t_range: &Range<Float>, color: [Float; 3], rng: Option<Pcg32>, traversal: &mut TraversalContext) -> [Float; 3] {
+        let mut hit = || self.scene.first_hit(ray, t_range, RayKind::Camera, traversal);
+        match (self.integrator, rng) {
+            (Integrator::Whitted, _) => self.scene.trace_direct(ray, t_range, self.max_depth, hit()),
+            (Integrator::PathTraced, Some(mut rng)) => self.scene.trace_path_hit(ray, t_range, 0, &mut rng, hit()),
+            (Integrator::PathTraced, None) => color,
+            (Integrator::AmbientOcclusion, _) => color
+        }
+    }
+}
+
+// The width and height of a canvas whose units each cover unit pixels of the screen along each side, with render_scale
+// times as many units along each side. Views side by side each keep the same number of columns
+fn canvas_dimensions(screen_width: usize, screen_height: usize, unit: usize, render_scale: Float, views: usize) -> (usize, usize) {
+    let width = ((screen_width as Float * render_scale / (unit * views) as Float) as usize).max(1) * views;
+    let height = ((screen_height as Float * render_scale / unit as Float) as usize).max(1);
+    (width, height)
+}
+
+// Split rows into consecutive runs, one for each thread, that are each about as costly. Returns the number of rows in
+// every run
+fn balance_rows(costs: &[f64], num_threads: usize) -> Vec<usize> {
+    // Rows that haven't been timed yet (or traced too fast to time) are shared out evenly
+    let total: f64 = costs.iter().sum();
+    let cost = |c: f64| if total > 0.0 { c } else { 1.0 };
+    let total = if total > 0.0 { total } else { costs.len() as f64 };
+
+    let mut counts = Vec::with_capacity(num_threads);
+    let (mut run_start, mut spent) = (0, 0.0);
+    for (row, &c) in costs.iter().enumerate() {
+        spent += cost(c);
+        // The run ends once the rows so far have reached every thread's share up to this one
+        if counts.len() + 1 < num_threads && spent >= total * (counts.len() + 1) as f64 / num_threads as f64 {
+            counts.push(row + 1 - run_start);
+            run_start = row + 1;
+        }
+    }
+    counts.push(costs.len() - run_start);
+    counts
+}
+
+// The ith position along a Z-order curve, which visits a square as four quarters, each of those as four quarters, and
+// so on. Columns are the even bits of i, and rows the odd bits
+fn morton(i: usize) -> (usize, usize) {
+    let compact = |bits: usize| (0..usize::BITS / 2).fold(0, |n, b| n | ((bits >> (2 * b)) & 1) << b);
+    (compact(i), compact(i >> 1))
+}
@@ -1,17 +1,215 @@
-use crate::linalg::Vec3d;
+use std::{error::Error, fmt};
 
+use rand::Rng;
+
+use crate::{color::Color, falloff::{Falloff, FalloffError}, linalg::{consts::PI, Float, Mat3, Vec3d}};
+
+const TUBE_POINTS: usize = 16; // Points along a tube light its light is split between, when not sampled at random
+
+#[derive(Clone)]
 pub enum LightSource {
     // A light source contributes some intensity of light (a fraction) to the scene
     // The sum of all light sources should equal to 1.0
     
     // In the real world, points in space are hit by scattered rays. 
     // To attempt to simulate this phenomena, we use an ambient source, which adds some light to every point
-    Ambient { intensity: f64 },
+    Ambient { intensity: Float },
 
-    // Emit light equally in all directions from a position, e.g. a lightbulb
-    Point { intensity: f64, pos: Vec3d },
+    // Emit light equally in all directions from a position, e.g. a lightbulb, dimming with distance by its falloff
+    Point { intensity: Float, pos: Vec3d, falloff: Falloff },
 
     // Light travelling along any vector with a given direction. Every point in space can be struck by these rays
     // This type of source can model the sun's rays on the earth because of the large difference in size
-    Directional { intensity: f64, dir: Vec3d },
-}
\ No newline at end of file
+    // Its color tints the light it casts, e.g. orange as the sun sets
+    Directional { intensity: Float, dir: Vec3d, color: usize },
+
+    // Emit light from all along a line segment centred on a position, e.g. a fluorescent tube or LED strip, for
+    // highlights and shadows stretched along it. The radius thickens it, softening shadows across it too
+    Tube { intensity: Float, pos: Vec3d, axis: Vec3d, length: Float, radius: Float, falloff: Falloff },
+}
+
+impl LightSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LightSource::Ambient { .. } => "Ambient",
+            LightSource::Point { .. } => "Point",
+            LightSource::Directional { .. } => "Directional",
+            LightSource::Tube { .. } => "Tube"
+        }
+    }
+
+    pub fn intensity(&self) -> Float {
+        match self {
+            LightSource::Ambient { intensity } | LightSource::Point { intensity, .. } | LightSource::Directional { intensity, .. }
+                | LightSource::Tube { intensity, .. } => *intensity
+        }
+    }
+
+    pub fn set_intensity(&mut self, new_intensity: Float) {
+        match self {
+            LightSource::Ambient { intensity } | LightSource::Point { intensity, .. } | LightSource::Directional { intensity, .. }
+                | LightSource::Tube { intensity, .. } => *intensity = new_intensity
+        }
+    }
+
+    // The color of the light, white except for directional lights, which can be tinted
+    pub fn color(&self) -> usize {
+        match self {
+            LightSource::Directional { color, .. } => *color,
+            _ => Color::White as usize
+        }
+    }
+
+    // Whether the light has a position or direction that can be changed
+    pub fn is_movable(&self) -> bool {
+        !matches!(self, LightSource::Ambient { .. })
+    }
+
+    // Move a point or tube light by an offset, or turn a directional light's direction by a rotation. Ambient light
+    // reaches everywhere, so is left as it is
+    pub fn nudge(&mut self, offset: &Vec3d, rotation: &Mat3) {
+        match self {
+            LightSource::Point { pos, .. } | LightSource::Tube { pos, .. } => *pos = &*pos + offset,
+            LightSource::Directional { dir, .. } => *dir = rotation * &*dir,
+            LightSource::Ambient { .. } => {}
+        }
+    }
+
+    // Where the light is, or which way it shines, for showing while it's being moved
+    pub fn describe(&self) -> String {
+        match self {
+            LightSource::Ambient { intensity } => format!("ambient light of intensity {}", intensity),
+            LightSource::Point { pos, .. } => format!("point light at ({:.2}, {:.2}, {:.2})", pos.x(), pos.y(), pos.z()),
+            LightSource::Directional { dir, .. } => format!("directional light shining along ({:.2}, {:.2}, {:.2})", dir.x(), dir.y(), dir.z()),
+            LightSource::Tube { pos, length, .. } => format!("tube light {:.2} long at ({:.2}, {:.2}, {:.2})", length, pos.x(), pos.y(), pos.z())
+        }
+    }
+
+    // Change how a point or tube light dims with distance, or why it can't be: the falloff isn't valid, or the light is
+    // ambient or directional, which reach everywhere equally and so have no distance to dim with
+    pub fn set_falloff(&mut self, new_falloff: Falloff) -> Result<(), LightError> {
+        new_falloff.validate().map_err(LightError::InvalidFalloff)?;
+        match self {
+            LightSource::Point { falloff, .. } | LightSource::Tube { falloff, .. } => {
+                *falloff = new_falloff;
+                Ok(())
+            }
+            _ => Err(LightError::NoFalloff)
+        }
+    }
+
+    // How many places the light's rays come from, which shadow rays are sent towards: none for ambient light, which
+    // comes from everywhere, TUBE_POINTS along a tube light and one otherwise
+    pub fn sources(&self) -> usize {
+        match self {
+            LightSource::Ambient { .. } => 0,
+            LightSource::Point { .. } | LightSource::Directional { .. } => 1,
+            LightSource::Tube { .. } => TUBE_POINTS
+        }
+    }
+
+    // The direction from p towards the ith place the light's rays come from, the intensity arriving along it after
+    // falloff, and how far along the direction the light is (infinitely far for directional lights). A tube light's
+    // places are spread evenly along it and round it, sharing its intensity. None for ambient light
+    pub fn towards(&self, p: &Vec3d, i: usize) -> Option<(Vec3d, Float, Float)> {
+        match self {
+            LightSource::Ambient { .. } => None,
+            LightSource::Point { intensity, pos, falloff } => {
+                let dir = pos - p;
+                let intensity = intensity * falloff.attenuation(dir.magnitude());
+                Some((dir, intensity, 1.0))
+            }
+            LightSource::Directional { intensity, dir, .. } => Some((dir * -1.0, *intensity, Float::INFINITY)),
+            LightSource::Tube { intensity, falloff, .. } => {
+                // Stepping round by the golden ratio of a turn, so neighbouring places face different ways
+                let around = (i as Float * 0.618_034).fract();
+                let dir = &self.tube_point((i as Float + 0.5) / TUBE_POINTS as Float, around) - p;
+                let intensity = intensity / TUBE_POINTS as Float * falloff.attenuation(dir.magnitude());
+                Some((dir, intensity, 1.0))
+            }
+        }
+    }
+
+    // towards for one place chosen at random, with all of the light's intensity, as the path tracer samples lights.
+    // None for ambient light
+    pub fn sample_towards(&self, p: &Vec3d, rng: &mut impl Rng) -> Option<(Vec3d, Float, Float)> {
+        match self {
+            LightSource::Tube { intensity, falloff, .. } => {
+                let dir = &self.tube_point(rng.random(), rng.random()) - p;
+                let intensity = intensity * falloff.attenuation(dir.magnitude());
+                Some((dir, intensity, 1.0))
+            }
+            _ => self.towards(p, 0)
+        }
+    }
+
+    // Why the light can't be used, if it can't: its intensity must be finite, a directional light needs a direction to
+    // shine in, a tube light needs an axis to lie along, and a length and radius that aren't negative, and a point or
+    // tube light's falloff must be valid
+    pub fn validate(&self) -> Result<(), LightError> {
+        if !self.intensity().is_finite() {
+            return Err(LightError::InvalidIntensity(self.intensity()));
+        }
+        match self {
+            LightSource::Directional { dir, .. } if !dir.magnitude().is_finite() || dir.magnitude() == 0.0 => Err(LightError::ZeroDirection),
+            LightSource::Tube { axis, .. } if !axis.magnitude().is_finite() || axis.magnitude() == 0.0 => Err(LightError::ZeroTubeAxis),
+            LightSource::Tube { length, .. } if !length.is_finite() || *length < 0.0 => Err(LightError::InvalidTubeLength(*length)),
+            LightSource::Tube { radius, .. } if !radius.is_finite() || *radius < 0.0 => Err(LightError::InvalidTubeRadius(*radius)),
+            LightSource::Point { falloff, .. } | LightSource::Tube { falloff, .. } => falloff.validate().map_err(LightError::InvalidFalloff),
+            _ => Ok(())
+        }
+    }
+
+    // The point of a tube light's surface a fraction along it from one end and round it from a side
+    fn tube_point(&self, along: Float, around: Float) -> Vec3d {
+        let LightSource::Tube { pos, axis, length, radius, .. } = self else {
+            unreachable!()
+        };
+        let axis = axis.normalize();
+        let side = if axis.x().abs() < 0.9 { Vec3d::new(1.0, 0.0, 0.0) } else { Vec3d::new(0.0, 1.0, 0.0) };
+        let (u, v) = (axis.cross(&side).normalize(), axis.cross(&axis.cross(&side)).normalize());
+        let angle = 2.0 * PI * around;
+        let rim = &(&u * (radius * angle.cos())) + &(&v * (radius * angle.sin()));
+        &(pos + &(&axis * ((along - 0.5) * length))) + &rim
+    }
+}
+
+/*
+
+Light Error
+
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LightError {
+    InvalidIntensity(Float),
+    ZeroDirection, // A directional light without a direction
+    ZeroTubeAxis,
+    InvalidTubeLength(Float),
+    InvalidTubeRadius(Float),
+    InvalidFalloff(FalloffError),
+    NoFalloff // A falloff given to an ambient or directional light, which have no distance to dim with
+}
+
+impl fmt::Display for LightError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LightError::InvalidIntensity(intensity) => write!(f, "light intensity must be finite, got {}", intensity),
+            LightError::ZeroDirection => write!(f, "directional light direction must be a non-zero direction"),
+            LightError::ZeroTubeAxis => write!(f, "tube light axis must be a non-zero direction"),
+            LightError::InvalidTubeLength(length) => write!(f, "tube light length must be non-negative, got {}", length),
+            LightError::InvalidTubeRadius(radius) => write!(f, "tube light radius must be non-negative, got {}", radius),
+            LightError::InvalidFalloff(e) => write!(f, "{}", e),
+            LightError::NoFalloff => write!(f, "only point and tube lights dim with distance")
+        }
+    }
+}
+
+impl Error for LightError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LightError::InvalidFalloff(e) => Some(e),
+            _ => None
+        }
+    }
+}
@@ -1,4 +1,27 @@
-use std::{f64::consts::PI, ops::{Add, Mul, Sub}};
+use std::ops::{Add, Mul, Sub};
+
+/*
+
+Float
+
+The precision rays are traced with. The f32 feature halves the size of every vector and color, for a viewer that runs
+faster where the extra precision of f64 can't be seen
+
+*/
+
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+
+#[cfg(feature = "f32")]
+pub type Float = f32;
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
+
+// How far rays leaving a surface start from it, so they don't hit it again through rounding, and how near a point must be
+// to a surface to be on it
+pub const RAY_EPSILON: Float = if cfg!(feature = "f32") { 1e-3 } else { Float::EPSILON * 1000000.0 };
 
 /*
 
@@ -6,31 +29,31 @@ use std::{f64::consts::PI, ops::{Add, Mul, Sub}};
 
 */
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Vec3d {
-    x: f64,
-    y: f64,
-    z: f64
+    x: Float,
+    y: Float,
+    z: Float
 }
 
 impl Vec3d {
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
+    pub fn new(x: Float, y: Float, z: Float) -> Self {
         Self { x, y, z }
     }
 
-    pub fn x(&self) -> f64 {
+    pub fn x(&self) -> Float {
         self.x
     }
 
-    pub fn y(&self) -> f64 {
+    pub fn y(&self) -> Float {
         self.y
     }
 
-    pub fn z(&self) -> f64 {
+    pub fn z(&self) -> Float {
         self.z
     }
 
-    pub fn magnitude(&self) -> f64 {
+    pub fn magnitude(&self) -> Float {
         (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
     }
 
@@ -82,10 +105,10 @@ impl Sub for &Vec3d {
 }
 
 // Scaling vector
-impl Mul<f64> for &Vec3d {
+impl Mul<Float> for &Vec3d {
     type Output = Vec3d;
 
-    fn mul(self, f: f64) -> Vec3d {
+    fn mul(self, f: Float) -> Vec3d {
         Vec3d {
             x: self.x * f,
             y: self.y * f,
@@ -96,9 +119,9 @@ impl Mul<f64> for &Vec3d {
 
 // Dot product
 impl Mul for &Vec3d {
-    type Output = f64;
+    type Output = Float;
     
-    fn mul(self, b: &Vec3d) -> f64 {
+    fn mul(self, b: &Vec3d) -> Float {
         self.x * b.x + self.y * b.y + self.z * b.z
     }
 }
@@ -107,21 +130,32 @@ impl Mul for &Vec3d {
 
 Ray
 
+A ray also carries where the camera that its path started from is (its view), which objects with levels of detail pick
+theirs by. A ray starts out seen from its own origin
+
 */
 
 pub struct Ray {
     origin: Vec3d,
-    dir: Vec3d
+    dir: Vec3d,
+    view: Vec3d
 }
 
 impl Ray {
     pub fn new(origin: Vec3d, dir: Vec3d) -> Self {
         Self {
+            view: origin.clone(),
             origin,
             dir
         }
     }
 
+    // The ray seen from elsewhere, e.g. a shadow or bounce ray leaving a surface seen from the camera
+    pub fn with_view(mut self, view: Vec3d) -> Self {
+        self.view = view;
+        self
+    }
+
     pub fn origin(&self) -> &Vec3d {
         &self.origin
     }
@@ -130,8 +164,12 @@ impl Ray {
         &self.dir
     }
 
-    pub fn at(&self, t: f64) -> Vec3d {
-        return &self.origin + &(&self.dir * t);
+    pub fn view(&self) -> &Vec3d {
+        &self.view
+    }
+
+    pub fn at(&self, t: Float) -> Vec3d {
+        &self.origin + &(&self.dir * t)
     }
 }
 
@@ -141,12 +179,13 @@ impl Ray {
 
 */
 
+#[derive(Clone)]
 pub struct Mat3 {
-    data: [[f64; 3]; 3]
+    data: [[Float; 3]; 3]
 }
 
 impl Mat3 { 
-    pub fn new(m: [[f64; 3]; 3]) -> Self {
+    pub fn new(m: [[Float; 3]; 3]) -> Self {
         Self { data: m }
     }
 
@@ -160,8 +199,8 @@ impl Mat3 {
         }
     }
 
-    pub fn rotation_x(deg: f64) -> Self {
-        let angle = deg * (PI / 180.0);
+    pub fn rotation_x(deg: Float) -> Self {
+        let angle = deg * (consts::PI / 180.0);
         Self {
             data: [
                 [1.0, 0.0, 0.0],
@@ -171,8 +210,8 @@ impl Mat3 {
         }
     }
 
-    pub fn rotation_y(deg: f64) -> Self {
-        let angle = deg * (PI / 180.0);
+    pub fn rotation_y(deg: Float) -> Self {
+        let angle = deg * (consts::PI / 180.0);
         Self {
             data: [
                 [angle.cos(), 0.0, angle.sin()],
@@ -182,8 +221,8 @@ impl Mat3 {
         }
     }
 
-    pub fn rotation_z(deg: f64) -> Self {
-        let angle = deg * (PI / 180.0);
+    pub fn rotation_z(deg: Float) -> Self {
+        let angle = deg * (consts::PI / 180.0);
         Self {
             data: [
                 [angle.cos(), -angle.sin(), 0.0],
@@ -193,8 +232,13 @@ impl Mat3 {
         }
     }
 
+    // Swap rows and columns. For a rotation this is the rotation back
+    pub fn transpose(&self) -> Self {
+        Self::new(std::array::from_fn(|i| std::array::from_fn(|j| self.data[j][i])))
+    }
+
     // Rotation about a specified axis
-    pub fn rotation_matrix(axis: &Vec3d, angle: f64) -> Self {
+    pub fn rotation_matrix(axis: &Vec3d, angle: Float) -> Self {
         let cos_angle = angle.to_radians().cos();
         let sin_angle = angle.to_radians().sin();
 
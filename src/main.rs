@@ -1,264 +1,366 @@
-use std::sync::Arc;
-use rand::Rng;
+use std::{error::Error, fs::{self, File}, io::{BufWriter, Write}, path::PathBuf, process, sync::Arc};
 
-use raytracer::{
-    color::Color, light::LightSource, linalg::Vec3d, object::{Material, Object, RectangularPrism, Sphere}, Renderer, Scene
-};
+use clap::Parser;
+
+use raytracer::{aov::{self, Aov}, bloom::Bloom, compare::Comparison, config::RendererConfig, controls::Controls, display::{self, Display, HeadlessDisplay, VideoDisplay}, distributed, export, filter::Filter, image, integrator::Integrator, linalg::Float, pipeline::Pipeline, post::{Effect, PostEffect}, sample_pattern::SamplePattern, progress::Progress, scene_file::{self, LoadOptions}, stereo::Stereo, upscale::Upscale, Renderer};
+
+/*
+
+Command Line
+
+Renders a scene file either to a window that can be navigated, or headlessly straight to a PNG, e.g.
+raytracer --scene scenes/mirror.ron --width 1280 --samples 16 --threads 8 --output out.png
+
+Every frame shown can be recorded to a video with --video out.mp4 (needs ffmpeg), either from the window or from a
+headless sequence of --frames
+
+Large renders can be split across machines by running raytracer --worker 0.0.0.0:7878 on each of them, then rendering
+with --workers host1:7878,host2:7878
+
+*/
+
+#[derive(Parser)]
+#[command(version, about = "A multithreaded ray tracer")]
+struct Args {
+    #[arg(long, help = "Scene file to render. The built in demo scene is used when none is given")]
+    scene: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 800)]
+    width: usize,
+
+    #[arg(long, default_value_t = 16.0 / 9.0)]
+    aspect_ratio: Float,
+
+    #[arg(long, default_value_t = 1.0, help = "Canvas units traced per pixel along each side, independently of the window size: below 1 traces fewer (e.g. 0.5 for half resolution, stretched over the window), above 1 supersamples (e.g. 2 traces four units per pixel and averages them)")]
+    render_scale: Float,
+
+    #[arg(long, default_value_t = Upscale::Nearest, help = "How a canvas smaller than the window is stretched over it: nearest (blocky) or bilinear (smooth)")]
+    upscale: Upscale,
+
+    #[arg(long, help = "Open the window fullscreen. Toggled with F11 in the window")]
+    fullscreen: bool,
+
+    #[arg(long, default_value_t = 1, help = "Anti-aliasing samples per canvas unit")]
+    samples: usize,
+
+    #[arg(long, default_value_t = Filter::Box, help = "How anti-aliasing samples are weighted: box, tent or gaussian")]
+    filter: Filter,
+
+    #[arg(long, default_value_t = SamplePattern::Random, help = "Where anti-aliasing samples are placed: random jitter, or a fixed rotated grid that keeps edges steady while moving")]
+    sample_pattern: SamplePattern,
+
+    #[arg(long, help = "Darken anti-aliasing samples brighter than this many times the median of their canvas unit, removing stray bright speckles")]
+    firefly_clamp: Option<Float>,
+
+    #[arg(long, help = "Render threads. Defaults to the number of available cores")]
+    threads: Option<usize>,
+
+    #[arg(long, default_value_t = 2, help = "Number of times a ray may be reflected")]
+    max_depth: u32,
+
+    #[arg(long, default_value_t = Integrator::Whitted, help = "How light is found: whitted (direct light and mirror reflections), path (path tracing, needs more samples) or ao (ambient occlusion preview)")]
+    integrator: Integrator,
+
+    #[arg(long, default_value_t = Pipeline::Immediate, help = "Order samples are traced in: immediate (one at a time) or sorted (a tile at a time, shaded grouped by material, whitted only)")]
+    pipeline: Pipeline,
+
+    #[arg(long, help = "Make the brightest parts of every frame glow: the luminance above which light blooms, then optionally how strongly and how far it spreads (as a fraction of the frame's height), e.g. 0.7,1.0,0.03")]
+    bloom: Option<Bloom>,
+
+    #[arg(long, help = "Smooth jagged edges after tracing (FXAA), which is much cheaper than more samples. Toggled with F in the window")]
+    fxaa: bool,
+
+    #[arg(long, help = "Sharpen every frame after tracing (and FXAA) with contrast-adaptive sharpening of this sharpness, from 0 (subtle) to 1 (strong), countering softness from FXAA or wide filters")]
+    cas: Option<Float>,
+
+    #[arg(long, value_delimiter = ',', help = "Effects applied to every frame in this order, after bloom, comma separated: vignette, chromatic-aberration, grain or sharpen, each optionally followed by its strength, e.g. vignette:0.4,grain:0.03")]
+    post: Vec<Effect>,
+
+    #[arg(long, help = "Render every frame a second time with some settings changed, shown right of a divider that can be dragged with the mouse, comma separated: integrator=, samples=, bounces=, exposure= or raw (without post-processing), e.g. integrator=path,samples=16")]
+    compare: Option<Comparison>,
+
+    #[arg(long, default_value_t = Stereo::Mono, help = "Render a view for each eye: mono (a single view), anaglyph (red/cyan, for 3D glasses), side-by-side (for VR viewers) or cross-eyed (halves swapped)")]
+    stereo: Stereo,
+
+    #[arg(long, default_value_t = 0.2, help = "Distance between the eyes when rendering in stereo")]
+    interocular: Float,
+
+    #[arg(long, default_value_t = 4, help = "While the camera moves, trace canvas units this many times larger, sharpening back to full size once it stops. 1 turns the preview off")]
+    preview_scale: usize,
+
+    #[arg(long, help = "Trace every change at 1/8, 1/4 and 1/2 resolution with fewer samples before full size, showing each level as it finishes")]
+    progressive: bool,
+
+    #[arg(long, default_value_t = 1.0, help = "Camera speed with a gamepad's sticks fully pushed, relative to the keys. Needs the gamepad feature")]
+    gamepad_sensitivity: Float,
+
+    #[arg(long, default_value_t = 60.0, help = "Seconds a whole day takes to pass while the time of day is animated (toggled with T)")]
+    day_length: Float,
+
+    #[arg(long, help = "Start with the camera moving round the point --orbit-radius in front of it, tracing every frame at full size, for showcase renders and repeatable benchmarks. Toggled with R in the window")]
+    turntable: bool,
+
+    #[arg(long, default_value_t = 5.0, help = "Distance from the camera to the point the turntable moves it round")]
+    orbit_radius: Float,
+
+    #[arg(long, default_value_t = 1.0, allow_negative_numbers = true, help = "Degrees the turntable moves the camera round every frame, anticlockwise seen from above, or clockwise when negative")]
+    orbit_speed: Float,
+
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    #[arg(long, help = "TOML file of key bindings and camera speeds. controls.toml in the working directory is used when there is one")]
+    controls: Option<PathBuf>,
+
+    #[arg(long, help = "Write the rendered frame to this PNG file")]
+    output: Option<PathBuf>,
+
+    #[arg(long, help = "Write the scene's objects as triangles with their materials to this OBJ (.obj, with a .mtl next to it), glTF (.gltf, with a .bin next to it) or GLB (.glb) file instead of rendering, e.g. to open it in Blender")]
+    export: Option<PathBuf>,
+
+    #[arg(long, value_delimiter = ',', help = "Also save these AOVs next to --output (e.g. out.normal.png), comma separated: normal, depth, albedo, direct, indirect, motion, object-id, material-id or coverage0, coverage1...")]
+    aovs: Vec<Aov>,
+
+    #[arg(long, help = "Also save the linear depth of every canvas unit to this file: 32-bit floats in an OpenEXR file (.exr), or a 16-bit PNG (.png) from 0 at the camera to 65535 at the furthest it sees. Requires --output")]
+    depth: Option<PathBuf>,
+
+    #[arg(long, help = "Also save soft, anti-aliased object mattes to this OpenEXR file: the ID and coverage of the objects covering the most of each canvas unit, in channels coverage0.id, coverage0.coverage, coverage1.id and so on. Requires --output")]
+    mattes: Option<PathBuf>,
+
+    #[arg(long, help = "Open a window even when rendering to a file. The last frame shown is saved once the window closes")]
+    interactive: bool,
+
+    #[arg(long, help = "Render this many frames without opening a window")]
+    frames: Option<usize>,
+
+    #[arg(long, help = "Record every frame shown to a video file (e.g. out.mp4 or out.webm) using ffmpeg")]
+    video: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 30, help = "Frame rate of the recorded video")]
+    fps: u32,
+
+    #[arg(long, value_delimiter = ',', help = "Render on these workers (host:port, comma separated) instead of locally. Requires --output")]
+    workers: Vec<String>,
+
+    #[arg(long, help = "Run as a worker, tracing frames for coordinators that connect to this address (e.g. 0.0.0.0:7878)")]
+    worker: Option<String>,
+
+    #[cfg(feature = "stream")]
+    #[arg(long, help = "Serve frames to browsers at this address (e.g. 0.0.0.0:8080) instead of opening a window")]
+    stream: Option<String>
+}
+
+const DEMO_SCENE: &str = include_str!("../scenes/spheres.ron");
+const CONTROLS_FILE: &str = "controls.toml";
+const MATTE_RANKS: usize = 4; // Objects covering each canvas unit that --mattes records, the most covering first
 
 fn main() {
-    let mut rng = rand::rng();
-
-    let mut scenes = vec![
-        Scene::new (
-            Vec3d::new(0.0, 2.0, -1.0),
-            Color::Black as usize,
-            vec![
-                LightSource::Ambient { intensity: 0.1 },
-                LightSource::Point { intensity: 0.9, pos: Vec3d::new(-3.0, 4.0, -6.0) },
-            ],
-            vec![
-                Box::new( 
-                    RectangularPrism::new (
-                        Vec3d::new(-40.0, 0.0, -40.0),
-                        80.0,
-                        -5.0,
-                        50.0,
-                        Color::Gray as usize,
-                        Material::Shiny { spclr_exp: 500.0, refl_rat: 0.3 }
-                    )
-                ),
-                Box::new(
-                    Sphere::new(
-                        Vec3d::new(3.0, 2.0, -8.0),
-                        2.0,
-                        Color::Red as usize,
-                        Material::Shiny { spclr_exp: 500.0, refl_rat: 0.1 }
-                    )
-                ),
-                Box::new(
-                    Sphere::new(
-                        Vec3d::new(3.0, 0.5, -5.0),
-                        0.5,
-                        Color::Green as usize,
-                        Material::Shiny { spclr_exp: 500.0, refl_rat: 0.1 }
-                    )
-                ),
-
-                Box::new(
-                    Sphere::new(
-                        Vec3d::new(1.5, 0.4, -5.0),
-                        0.4,
-                        Color::Purple as usize,
-                        Material::Shiny { spclr_exp: 500.0, refl_rat: 0.1 }
-                    )
-                ),
-                Box::new(
-                    Sphere::new(
-                        Vec3d::new(-0.3, 0.7, -6.0),
-                        0.7,
-                        Color::SlateGray as usize,
-                        Material::Shiny { spclr_exp: 500.0, refl_rat: 0.1 }
-                    )
-                ),
-                Box::new(
-                    Sphere::new(
-                        Vec3d::new(-2.5, 1.0, -5.0),
-                        1.0,
-                        Color::Blue as usize,
-                        Material::Shiny { spclr_exp: 500.0, refl_rat: 0.1 }
-                    )
-                ),
-                Box::new(
-                    Sphere::new(
-                        Vec3d::new(-2.5, 1.2, -9.0),
-                        1.2,
-                        Color::Pink as usize,
-                        Material::Shiny { spclr_exp: 500.0, refl_rat: 0.1 }
-                    )
-                ),
-                Box::new(
-                    RectangularPrism::new(
-                        Vec3d::new(1.0, 0.0, -7.5),
-                        0.5,
-                        0.5,
-                        0.5,
-                        Color::SeaGreen as usize,
-                        Material::Shiny { spclr_exp: 500.0, refl_rat: 0.1 }
-                    )
-                ),
-                Box::new(
-                    RectangularPrism::new(
-                        Vec3d::new(1.0, 0.0, -15.0),
-                        3.0,
-                        3.0,
-                        3.0,
-                        Color::DarkViolet as usize,
-                        Material::Shiny { spclr_exp: 500.0, refl_rat: 0.1 }
-                    )
-                ),
-            ]
-        ),
-        
-        Scene::new (
-            Vec3d::new(0.0, 3.0, 0.0),
-            Color::Black as usize,
-            vec![
-                LightSource::Ambient { intensity: 0.1 },
-                LightSource::Point { intensity: 0.9, pos: Vec3d::new(0.0, 4.0, 0.0) },
-            ],
-            vec![
-                Box::new( 
-                    RectangularPrism::new (
-                        Vec3d::new(-400.0, 0.0, -400.0),
-                        800.0,
-                        -5.0,
-                        800.0,
-                        Color::Gray as usize,
-                        Material::Shiny { spclr_exp: 500.0, refl_rat: 0.4 }
-                    )
-                ),
-                Box::new( 
-                    Sphere::new (
-                        Vec3d::new(0.0, 1.0, -6.0),
-                        1.0,
-                        Color::DarkRed as usize,
-                        Material::Shiny { spclr_exp: 500.0, refl_rat: 0.2 }
-                    )
-                ),
-                Box::new( 
-                    Sphere::new (
-                        Vec3d::new(2.0, 1.3, -9.0),
-                        1.3,
-                        Color::DarkOrange as usize,
-                        Material::Shiny { spclr_exp: 500.0, refl_rat: 0.2 }
-                    )
-                ),
-                Box::new( 
-                    Sphere::new (
-                        Vec3d::new(-3.0, 3.0, -11.0),
-                        3.0,
-                        Color::Black as usize,
-                        Material::Shiny { spclr_exp: 500.0, refl_rat: 0.7 }
-                    )
-                ),
-            ]
-        ),
-        
-        Scene::new (
-            Vec3d::new(0.0, 1.5, -1.5),
-            Color::Black as usize,
-            vec![
-                LightSource::Ambient { intensity: 0.1 },
-                LightSource::Point { intensity: 0.4, pos: Vec3d::new(0.0, 1.0, -10.0) },
-                LightSource::Directional { intensity: 0.4, dir: Vec3d::new(1.0, -1.0, -1.0) },
-            ],
-            vec![
-                Box::new( 
-                    RectangularPrism::new (
-                        Vec3d::new(-400.0, 0.0, -400.0),
-                        800.0,
-                        -5.0,
-                        800.0,
-                        Color::WhiteSmoke as usize,
-                        Material::Matte
-                    )
-                ),
-                Box::new( 
-                    RectangularPrism::new (
-                        Vec3d::new(-400.0, 0.0, -15.0),
-                        800.0,
-                        40.0,
-                        1.0,
-                        Color::WhiteSmoke as usize,
-                        Material::Matte
-                    )
-                ),
-                Box::new( 
-                    Sphere::new (
-                        Vec3d::new(-1.5, 2.0, -6.0),
-                        2.0,
-                        Color::DeepPink as usize,
-                        Material::Shiny { spclr_exp: 20.0, refl_rat: 0.2 }
-                    )
-                ),
-                Box::new( 
-                    Sphere::new (
-                        Vec3d::new(3.0, 2.0, -8.0),
-                        2.0,
-                        Color::Pink as usize,
-                        Material::Shiny { spclr_exp: 20.0, refl_rat: 0.8 }
-                    )
-                ),
-                Box::new( 
-                    Sphere::new (
-                        Vec3d::new(0.5, 0.6, -4.0),
-                        0.6,
-                        Color::Teal as usize,
-                        Material::Shiny { spclr_exp: 20.0, refl_rat: 0.0 }
-                    )
-                ),
-                Box::new( 
-                    Sphere::new (
-                        Vec3d::new(2.0, 0.6, -5.5),
-                        0.6,
-                        Color::Pink as usize,
-                        Material::Shiny { spclr_exp: 20.0, refl_rat: 0.0 }
-                    )
-                ),
-            ]
-        ),
-
-        Scene::new (
-            Vec3d::new(0.0, 1.0, 2.0),
-            Color::Black as usize,
-            vec![
-                LightSource::Point { intensity: 1.0, pos: Vec3d::new(0.0, 10.0, -20.0) },
-            ],
-            (0..30).map(|_| {
-                let radius = 1.0;
-                
-                let position = Vec3d::new(
-                    rng.random_range(-15.0..15.0),
-                    0.0 + radius,
-                    rng.random_range(-30.0..0.0),
-                );
-                
-                let material = if rng.random_bool(0.5) {
-                    Material::Shiny { spclr_exp: rng.random_range(0.0..10.0), refl_rat: rng.random_range(0.0..0.7) }
-                } else {
-                    Material::Matte
-                };
-                
-                Box::new(Sphere::new(position, radius, rng.random_range(0..0xFFFFFF), material)) as Box<dyn Object>
-            })
-            .chain(std::iter::once(
-                Box::new( 
-                    Sphere::new (
-                        Vec3d::new(0.0, -5000.0, 0.0),
-                        5000.0,
-                        Color::Brown as usize,
-                        Material::Matte
-                    ) 
-                ) as Box<dyn Object>
-            ))
-            .collect::<Vec<_>>()
-        )
-    ];
-
-    /*
-    
-    Renderer
-
-    */
-
-    let mut renderer = Renderer::new (
-        8,
-        800,
-        16.0 / 9.0, 
-        1,
-        Arc::new(scenes.swap_remove(0)),
-        1
-    );
-
-    renderer.run();
-}
\ No newline at end of file
+    if let Err(e) = run(Args::parse()) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let defaults = RendererConfig::default();
+
+    if let Some(addr) = &args.worker {
+        distributed::serve_worker(addr, args.threads.unwrap_or(defaults.num_threads))?;
+        return Ok(());
+    }
+
+    let scene_src = match &args.scene {
+        Some(path) => fs::read_to_string(path)?,
+        None => DEMO_SCENE.to_string()
+    };
+
+    let controls = match args.controls.clone().or_else(|| Some(PathBuf::from(CONTROLS_FILE)).filter(|path| path.exists())) {
+        Some(path) => Controls::load(path)?,
+        None => Controls::default()
+    };
+
+    let config = RendererConfig {
+        num_threads: args.threads.unwrap_or(defaults.num_threads),
+        screen_width: args.width,
+        aspect_ratio: args.aspect_ratio,
+        render_scale: args.render_scale,
+        upscale: args.upscale,
+        fullscreen: args.fullscreen,
+        num_samples: args.samples,
+        filter: args.filter,
+        sample_pattern: args.sample_pattern,
+        firefly_clamp: args.firefly_clamp,
+        max_depth: args.max_depth,
+        integrator: args.integrator,
+        pipeline: args.pipeline,
+        fxaa: args.fxaa,
+        cas: args.cas,
+        bloom: args.bloom,
+        post_effects: args.post.iter().map(|&effect| Arc::new(effect) as Arc<dyn PostEffect>).collect(),
+        comparison: args.compare,
+        stereo: args.stereo,
+        interocular: args.interocular,
+        preview_scale: args.preview_scale,
+        progressive: args.progressive,
+        controls,
+        gamepad_sensitivity: args.gamepad_sensitivity,
+        day_length: args.day_length,
+        turntable: args.turntable,
+        orbit_radius: args.orbit_radius,
+        orbit_speed: args.orbit_speed,
+        seed: args.seed,
+        aovs: args.aovs.iter().copied()
+            .chain(args.depth.as_ref().map(|_| Aov::Depth))
+            .chain(args.mattes.as_ref().into_iter().flat_map(|_| (0..MATTE_RANKS).map(Aov::Coverage)))
+            .collect(),
+        ..defaults
+    };
+
+    let (width, height) = (config.screen_width, config.screen_height());
+
+    if !args.aovs.is_empty() && args.output.is_none() {
+        return Err("--aovs requires --output".into());
+    }
+    if args.depth.is_some() && args.output.is_none() {
+        return Err("--depth requires --output".into());
+    }
+    if args.mattes.is_some() && args.output.is_none() {
+        return Err("--mattes requires --output".into());
+    }
+
+    if !args.workers.is_empty() {
+        let Some(path) = &args.output else {
+            return Err("--workers requires --output".into());
+        };
+        if config.stereo != Stereo::Mono {
+            return Err("--workers can't render in stereo".into());
+        }
+        if config.render_scale != 1.0 {
+            return Err("--workers can't change the render scale".into());
+        }
+        if config.comparison.is_some() {
+            return Err("--workers can't render comparisons".into());
+        }
+        if config.fxaa || config.cas.is_some() || config.bloom.is_some() || !config.post_effects.is_empty() {
+            return Err("--workers can't apply FXAA, sharpening, bloom or post effects".into());
+        }
+        if !config.aovs.is_empty() {
+            return Err("--workers can't write AOVs, depth or mattes".into());
+        }
+        if args.video.is_some() {
+            return Err("--workers can't record video".into());
+        }
+
+        let buffer = distributed::render(&scene_src, &config, &args.workers, progress_printer())?;
+        eprintln!();
+
+        image::write_png(BufWriter::new(File::create(path)?), &buffer, width, height)?;
+        eprintln!("Saved {}x{} render to {}", width, height, path.display());
+        return Ok(());
+    }
+
+    if let Some(path) = &args.export {
+        let left_out = export::export(&scene_file::parse(&scene_src)?, path)?;
+        eprintln!("Exported scene to {}", path.display());
+        if left_out > 0 {
+            eprintln!("Left out {} objects that can't be made of triangles", left_out);
+        }
+        return Ok(());
+    }
+
+    // A window shows large meshes straight away, drawn slowly until their hierarchies are built
+    let batch = args.frames.is_some() || (args.output.is_some() && !args.interactive);
+    let scene = scene_file::parse_with(&scene_src, &LoadOptions { background_bvh: !batch, ..Default::default() })?;
+
+    let display: Box<dyn Display> = if batch {
+        Box::new(HeadlessDisplay::new().with_frame_limit(args.frames.unwrap_or(1)))
+    } else {
+        interactive_display(&args, width, height)?
+    };
+
+    let display: Box<dyn Display> = match &args.video {
+        Some(path) => Box::new(VideoDisplay::new(display, path, width, height, args.fps)?),
+        None => display
+    };
+
+    let mut renderer = Renderer::with_display(config, Arc::new(scene), display)?;
+    if batch {
+        renderer.set_progress_callback(progress_printer());
+    }
+    renderer.run()?;
+    if batch {
+        eprintln!();
+    }
+
+    if let Some(path) = &args.output {
+        let (buffer, width, height) = renderer.frame_buffer();
+        image::write_png(BufWriter::new(File::create(path)?), buffer, width, height)?;
+        eprintln!("Saved {}x{} render to {}", width, height, path.display());
+
+        for &aov in &args.aovs {
+            let Some((values, width, height)) = renderer.aov_buffer(aov) else {
+                continue;
+            };
+            let aov_path = path.with_extension(format!("{}.png", aov));
+            image::write_png(BufWriter::new(File::create(&aov_path)?), &aov.encode(values), width, height)?;
+            eprintln!("Saved {} AOV to {}", aov, aov_path.display());
+        }
+    }
+
+    if let (Some(path), Some((values, width, height))) = (&args.depth, renderer.aov_buffer(Aov::Depth)) {
+        let writer = BufWriter::new(File::create(path)?);
+        if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("exr")) {
+            image::write_exr(writer, &[("Z", &aov::channel_floats(values, 0))], width, height)?;
+        } else {
+            image::write_png16(writer, &aov::depth_levels(values), width, height)?;
+        }
+        eprintln!("Saved {}x{} depth to {}", width, height, path.display());
+    }
+
+    if let Some(path) = &args.mattes {
+        let ranks: Vec<_> = (0..MATTE_RANKS).filter_map(|rank| renderer.aov_buffer(Aov::Coverage(rank)).map(|buffer| (rank, buffer))).collect();
+        let (names, values): (Vec<_>, Vec<_>) = ranks.iter().flat_map(|(rank, (values, ..))| [
+            (format!("coverage{}.id", rank), aov::channel_floats(values, 0)),
+            (format!("coverage{}.coverage", rank), aov::channel_floats(values, 1))
+        ]).unzip();
+        let channels: Vec<(&str, &[f32])> = names.iter().map(String::as_str).zip(values.iter().map(Vec::as_slice)).collect();
+        let (_, (_, width, height)) = ranks[0];
+        image::write_exr(BufWriter::new(File::create(path)?), &channels, width, height)?;
+        eprintln!("Saved {}x{} mattes to {}", width, height, path.display());
+    }
+
+    // Drop the renderer first so the video is finished before reporting it
+    drop(renderer);
+    if let Some(path) = &args.video {
+        eprintln!("Saved video to {}", path.display());
+    }
+
+    Ok(())
+}
+
+// A window, or a browser stream when one was asked for
+fn interactive_display(args: &Args, width: usize, height: usize) -> Result<Box<dyn Display>, Box<dyn Error>> {
+    #[cfg(feature = "stream")]
+    if let Some(addr) = &args.stream {
+        let display = display::StreamDisplay::bind(addr)?;
+        eprintln!("Streaming at http://{}", display.local_addr());
+        return Ok(Box::new(display));
+    }
+    #[cfg(not(feature = "stream"))]
+    let _ = args;
+
+    Ok(display::default_display(width, height)?)
+}
+
+// Prints the percentage of the frame rendered so far, whenever it changes
+fn progress_printer() -> impl FnMut(&Progress) {
+    let mut last_percent = None;
+    move |progress| {
+        let percent = (progress.fraction() * 100.0) as u32;
+        if last_percent != Some(percent) {
+            last_percent = Some(percent);
+            eprint!("\rRendering {:>3}%", percent);
+            let _ = std::io::stderr().flush();
+        }
+    }
+}
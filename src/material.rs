@@ -0,0 +1,3 @@
+pub use crate::object::Material;
+
+pub mod presets;
@@ -0,0 +1,72 @@
+use crate::linalg::Float;
+
+use super::Material;
+
+/*
+
+Material Presets
+
+Materials tuned to look like common real surfaces, so scenes get them without guessing specular exponents and
+reflection ratios. A preset only sets how a surface reflects, the object's color still sets its tint, e.g. gold is
+best paired with Color::Gold and rubber with a dark color
+
+Surfaces aren't transparent, so glass is its reflections and sharp highlights over the object's color
+
+*/
+
+// Names the presets are looked up by in scene files
+pub const NAMES: [&str; 6] = ["glass", "gold", "chrome", "plastic", "rubber", "mirror"];
+
+// Faint, perfectly sharp reflections (about 4% of light at normal incidence, more at grazing angles) and pinpoint
+// highlights
+pub fn glass() -> Material {
+    shiny(2000.0, 0.08)
+}
+
+// Warm, slightly blurred reflections under the object's color
+pub fn gold() -> Material {
+    shiny(300.0, 0.45)
+}
+
+// Bright, crisp reflections that mostly hide the object's color
+pub fn chrome() -> Material {
+    shiny(1000.0, 0.65)
+}
+
+// Mostly its own color with a small, tight highlight and a hint of reflection
+pub fn plastic() -> Material {
+    shiny(120.0, 0.05)
+}
+
+// Broad, dull sheen and no visible reflection
+pub fn rubber() -> Material {
+    shiny(8.0, 0.0)
+}
+
+// Reflects nearly all light, as a silvered mirror does
+pub fn mirror() -> Material {
+    shiny(10000.0, 0.95)
+}
+
+// The preset with a name, ignoring case, spaces, underscores and dashes
+pub fn from_name(name: &str) -> Option<Material> {
+    let name: String = name
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '_' | '-'))
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    match name.as_str() {
+        "glass" => Some(glass()),
+        "gold" => Some(gold()),
+        "chrome" => Some(chrome()),
+        "plastic" => Some(plastic()),
+        "rubber" => Some(rubber()),
+        "mirror" => Some(mirror()),
+        _ => None
+    }
+}
+
+fn shiny(spclr_exp: Float, refl_rat: Float) -> Material {
+    Material::Shiny { spclr_exp, refl_rat, double_sided: true }
+}
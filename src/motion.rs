@@ -0,0 +1,90 @@
+use crate::{linalg::{Float, Ray, Vec3d}, object::{Object, RayKind}, stereo::Eye, Camera, Scene};
+
+/*
+
+Motion Vectors
+
+How far the point seen through each canvas unit moved across the canvas since the frame before (the motion AOV), for
+temporal denoisers and motion blur applied after rendering. The point through the center of a unit is moved back by
+however far the object it's on moved, then seen through the camera as it was, so both the camera and objects moving
+show up. Objects are taken to move without turning, by how far the center of their bounds moved. The background is
+infinitely far away, so only turning the camera moves it
+
+Vectors are in canvas units, from where a point is now to where it was, so adding a unit's vector to its column and row
+finds it in the frame before. Points that were behind the camera, or on objects added since, are left where they are
+
+*/
+
+// The camera and where every object was when a frame was traced
+#[derive(Clone)]
+pub(crate) struct View {
+    camera: Camera,
+    centers: Vec<Option<Vec3d>> // Center of every object's bounds, in the order the scene was given its objects
+}
+
+impl View {
+    pub(crate) fn new(camera: &Camera, scene: &Scene) -> Self {
+        Self {
+            camera: camera.clone(),
+            centers: scene.objs.iter().map(|obj| obj.bounds().map(|bounds| &(&bounds.min + &bounds.max) * 0.5)).collect()
+        }
+    }
+}
+
+// The views of the frame before and the frame being traced, and the canvas the frame is traced on
+pub(crate) struct Motion {
+    pub(crate) before: View,
+    pub(crate) now: View,
+    pub(crate) canvas_width: usize,
+    pub(crate) canvas_height: usize,
+    pub(crate) views: usize, // Views side by side on the canvas, each as wide as the others
+    pub(crate) interocular: Float
+}
+
+impl Motion {
+    // The motion vector of the canvas unit at (col, row), seen along a ray through its center from an eye
+    pub(crate) fn at(&self, scene: &Scene, ray: &Ray, eye: Option<Eye>, (col, row): (usize, usize)) -> [Float; 2] {
+        let before = match scene.cast_ray(ray, &self.now.camera.view_range(), RayKind::Camera) {
+            Some(hit) => {
+                let moved = match (self.before.centers.get(hit.object), self.now.centers.get(hit.object)) {
+                    (Some(Some(before)), Some(Some(now))) => now - before,
+                    _ => Vec3d::new(0.0, 0.0, 0.0)
+                };
+                self.project_before(&(&hit.point - &moved), true, eye)
+            },
+            None => self.project_before(ray.dir(), false, eye)
+        };
+
+        match before {
+            Some((before_col, before_row)) => [before_col - (col % self.view_width()) as Float, before_row - row as Float],
+            None => [0.0; 2]
+        }
+    }
+
+    fn view_width(&self) -> usize {
+        self.canvas_width / self.views
+    }
+
+    // Where a point, or a direction to the background, landed on the canvas through the camera of the frame before, in
+    // canvas units from its view's left and the canvas's top. None when it was behind the camera
+    fn project_before(&self, to: &Vec3d, is_point: bool, eye: Option<Eye>) -> Option<(Float, Float)> {
+        let camera = &self.before.camera;
+        let to = if is_point { to - &camera.eye_origin(eye, self.interocular) } else { to.clone() };
+
+        // Undo the camera's turning, then follow the ray out to the viewport
+        let local = &camera.rot_m.transpose() * &to;
+        let depth = camera.vp_depth as Float;
+        if local.z() * depth <= 0.0 {
+            return None;
+        }
+        let scale = depth / local.z();
+
+        // The reverse of how the camera spreads its rays over the canvas
+        let (view_width, canvas_height) = (self.view_width(), self.canvas_height);
+        let vp_width = camera.vp_width / self.views as Float;
+        Some((
+            local.x() * scale * view_width as Float / vp_width + (view_width as isize / 2) as Float,
+            (canvas_height as isize / 2) as Float - local.y() * scale * canvas_height as Float / camera.vp_height
+        ))
+    }
+}
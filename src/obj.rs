@@ -0,0 +1,266 @@
+use std::{error::Error, fmt, fs, io, path::{Path, PathBuf}};
+
+use crate::{color::Color, linalg::{Float, Vec3d}, object::Material};
+
+/*
+
+OBJ Models
+
+Reads the triangles of a Wavefront OBJ file, the format most modelling tools export, along with the materials of the
+MTL files it names (mtllib), looked for next to it. Faces with more than three corners are split into triangles around
+their first corner, and corners may be counted back from the latest vertex (negative indices). Normals, groups and
+smoothing are skipped, faces are shaded flat
+
+A material maps onto the tracer's as closely as it can:
+    Kd          the color, 0 to 1 per channel
+    Ns, Ks      a shiny material when the specular color isn't black, its highlights sharpening with Ns
+    illum       3 and above ray trace reflections, reflecting the average of Ks (or Pm, if given) off the surface
+    Ke          an emissive material glowing as brightly as its brightest channel, when it isn't black
+    map_Kd      a PNG painted over the color by the faces' texture coordinates (vt), looked for next to the MTL file
+Everything else (transparency, bump maps, ...) is skipped. Faces before any usemtl, or naming a material the files
+don't have, keep whatever color and material the model is given
+
+*/
+
+pub struct Model {
+    pub vertices: Vec<Vec3d>,
+    pub faces: Vec<[usize; 3]>,                    // Indices of each triangle's corners
+    pub uvs: Vec<Option<[(Float, Float); 3]>>,     // Texture coordinates at each triangle's corners, if it has them
+    pub face_materials: Vec<Option<usize>>,        // Index of each triangle's material, if it has one
+    pub materials: Vec<MtlMaterial>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MtlMaterial {
+    pub name: String,
+    pub diffuse: [Float; 3],       // Kd
+    pub specular: [Float; 3],      // Ks
+    pub specular_exp: Float,       // Ns
+    pub emission: [Float; 3],      // Ke
+    pub illum: u32,                // Illumination model
+    pub metallic: Option<Float>,   // Pm
+    pub texture: Option<PathBuf>   // map_Kd, relative to where the model was read from
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            diffuse: [0.8; 3],
+            specular: [0.0; 3],
+            specular_exp: 0.0,
+            emission: [0.0; 3],
+            illum: 2,
+            metallic: None,
+            texture: None
+        }
+    }
+}
+
+impl MtlMaterial {
+    pub fn color(&self) -> usize {
+        let [r, g, b] = self.diffuse.map(|c| (c * 255.0).round());
+        Color::from_rgb(r, g, b)
+    }
+
+    pub fn material(&self) -> Material {
+        let brightest = |rgb: [Float; 3]| rgb.into_iter().fold(0.0, Float::max);
+        if brightest(self.emission) > 0.0 {
+            return Material::Emissive { intensity: brightest(self.emission), double_sided: true };
+        }
+        if brightest(self.specular) <= 0.0 || self.specular_exp <= 0.0 {
+            return Material::default();
+        }
+        let refl_rat = match self.illum {
+            3.. => self.metallic.unwrap_or(self.specular.iter().sum::<Float>() / 3.0).clamp(0.0, 1.0),
+            _ => 0.0
+        };
+        Material::Shiny { spclr_exp: self.specular_exp, refl_rat, double_sided: true }
+    }
+}
+
+// Read the OBJ model stored at path, with the materials of the MTL files it names
+pub fn load(path: impl AsRef<Path>) -> Result<Model, ObjError> {
+    let path = path.as_ref();
+    let dir = path.parent().unwrap_or(Path::new(""));
+    parse_with(&fs::read_to_string(path)?, |file| {
+        let mtl_path = dir.join(file);
+        let src = fs::read_to_string(&mtl_path).map_err(|e| ObjError::MaterialFile(file.to_string(), e))?;
+        let mtl_dir = mtl_path.parent().unwrap_or(Path::new(""));
+        let mut materials = parse_mtl(&src).map_err(|line| ObjError::Material(file.to_string(), line))?;
+        for material in &mut materials {
+            material.texture = material.texture.take().map(|texture| mtl_dir.join(texture));
+        }
+        Ok(materials)
+    })
+}
+
+// Read a model from the contents of an OBJ file, without materials
+pub fn parse(src: &str) -> Result<Model, ObjError> {
+    parse_with(src, |_| Ok(Vec::new()))
+}
+
+// Read a model from the contents of an OBJ file, reading the materials of each MTL file it names with mtllib
+fn parse_with(src: &str, mut mtllib: impl FnMut(&str) -> Result<Vec<MtlMaterial>, ObjError>) -> Result<Model, ObjError> {
+    let mut model = Model { vertices: Vec::new(), faces: Vec::new(), uvs: Vec::new(), face_materials: Vec::new(), materials: Vec::new() };
+    let mut tex_coords: Vec<(Float, Float)> = Vec::new();
+    let mut current: Option<usize> = None;
+
+    for (i, line) in src.lines().enumerate() {
+        let line_number = i + 1;
+        let mut words = line.split('#').next().unwrap_or("").split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let numbers = |words: std::str::SplitWhitespace| -> Result<Vec<Float>, ObjError> {
+            words.map(str::parse).collect::<Result<_, _>>().map_err(|_| ObjError::Line(line_number))
+        };
+
+        match keyword {
+            "v" => match numbers(words)?.as_slice() {
+                [x, y, z, ..] => model.vertices.push(Vec3d::new(*x, *y, *z)),
+                _ => return Err(ObjError::Line(line_number))
+            },
+            "vt" => match numbers(words)?.as_slice() {
+                [u, v, ..] => tex_coords.push((*u, *v)),
+                [u] => tex_coords.push((*u, 0.0)),
+                _ => return Err(ObjError::Line(line_number))
+            },
+            "f" => {
+                let corners = words.map(|corner| face_corner(corner, model.vertices.len(), tex_coords.len()))
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|corners| corners.len() >= 3)
+                    .ok_or(ObjError::Face(line_number))?;
+                for pair in corners[1..].windows(2) {
+                    let triangle = [corners[0], pair[0], pair[1]];
+                    model.faces.push(triangle.map(|(v, _)| v));
+                    model.uvs.push(match triangle.map(|(_, vt)| vt) {
+                        [Some(a), Some(b), Some(c)] => Some([tex_coords[a], tex_coords[b], tex_coords[c]]),
+                        _ => None
+                    });
+                    model.face_materials.push(current);
+                }
+            }
+            "mtllib" => {
+                for file in words {
+                    model.materials.extend(mtllib(file)?);
+                }
+            }
+            // The last of the materials read so far with the name, as later files override earlier ones
+            "usemtl" => {
+                let name = words.collect::<Vec<_>>().join(" ");
+                current = model.materials.iter().rposition(|material| material.name == name);
+            }
+            _ => {}
+        }
+    }
+
+    if model.faces.is_empty() {
+        return Err(ObjError::NoFaces);
+    }
+    Ok(model)
+}
+
+// The vertex and texture coordinate indices of a face's corner, e.g. "3", "3/1", "3//2" or "3/1/2", counting from 0
+fn face_corner(corner: &str, vertices: usize, tex_coords: usize) -> Option<(usize, Option<usize>)> {
+    let mut indices = corner.split('/');
+    let vertex = index(indices.next()?, vertices)?;
+    let tex_coord = match indices.next() {
+        Some(vt) if !vt.is_empty() => Some(index(vt, tex_coords)?),
+        _ => None
+    };
+    Some((vertex, tex_coord))
+}
+
+// An index counting from 1, or back from the latest of count things when negative
+fn index(word: &str, count: usize) -> Option<usize> {
+    let i: isize = word.parse().ok()?;
+    let i = if i < 0 { count as isize + i } else { i - 1 };
+    (0..count as isize).contains(&i).then_some(i as usize)
+}
+
+// Read the materials from the contents of an MTL file, or the number of the line that couldn't be read
+pub fn parse_mtl(src: &str) -> Result<Vec<MtlMaterial>, usize> {
+    let mut materials: Vec<MtlMaterial> = Vec::new();
+
+    for (i, line) in src.lines().enumerate() {
+        let line_number = i + 1;
+        let mut words = line.split('#').next().unwrap_or("").split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        if keyword == "newmtl" {
+            materials.push(MtlMaterial { name: words.collect::<Vec<_>>().join(" "), ..Default::default() });
+            continue;
+        }
+        let Some(material) = materials.last_mut() else {
+            continue;
+        };
+
+        let numbers: Vec<Float> = words.clone().map_while(|word| word.parse().ok()).collect();
+        let rgb = || match numbers.as_slice() {
+            [r, g, b, ..] => Ok([*r, *g, *b]),
+            [c] => Ok([*c; 3]),
+            _ => Err(line_number)
+        };
+        let number = || numbers.first().copied().ok_or(line_number);
+        match keyword {
+            "Kd" => material.diffuse = rgb()?,
+            "Ks" => material.specular = rgb()?,
+            "Ke" => material.emission = rgb()?,
+            "Ns" => material.specular_exp = number()?,
+            "Pm" => material.metallic = Some(number()?),
+            "illum" => material.illum = number()? as u32,
+            // Options like -s 1 1 1 come before the file
+            "map_Kd" => material.texture = Some(PathBuf::from(words.last().ok_or(line_number)?)),
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+/*
+
+OBJ Error
+
+*/
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    Line(usize),                      // The line with a missing or invalid value
+    Face(usize),                      // The line with a missing or invalid corner
+    MaterialFile(String, io::Error),  // The MTL file and why it couldn't be read
+    Material(String, usize),          // The MTL file and the line with a missing or invalid value
+    NoFaces
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(e) => write!(f, "couldn't read OBJ file: {}", e),
+            ObjError::Line(line) => write!(f, "invalid OBJ value on line {}", line),
+            ObjError::Face(line) => write!(f, "invalid OBJ face on line {}", line),
+            ObjError::MaterialFile(file, e) => write!(f, "couldn't read MTL file \"{}\": {}", file, e),
+            ObjError::Material(file, line) => write!(f, "invalid MTL value in \"{}\" on line {}", file, line),
+            ObjError::NoFaces => write!(f, "OBJ file has no faces")
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io(e) => Some(e),
+            ObjError::MaterialFile(_, e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for ObjError {
+    fn from(e: io::Error) -> Self {
+        ObjError::Io(e)
+    }
+}
@@ -0,0 +1,215 @@
+use crate::linalg::{Float, Ray, Vec3d};
+use crate::utils::Range;
+
+use super::{bounds::Bounds, grid, HitRecord, Material, Object, Triangles};
+
+/*
+
+Bezier Patch
+
+A smooth curved surface shaped by a 4x4 grid of control points, e.g. one of the 32 patches of the Utah teapot. The
+surface passes through the four corner points and is pulled towards the others, so a handful of patches describe
+curves that would otherwise take thousands of triangles
+
+Rays are intersected with the surface directly. The patch is split into a grid of cells, each bounded by a box around
+its own control points (the surface never leaves them), and Newton's method finds where a ray crosses the surface
+inside each cell the ray passes through
+
+*/
+
+const CELLS: usize = 8;            // Cells along each side of the patch
+const NEWTON_ITERATIONS: usize = 16;
+// How close, relative to the patch's size, a point must be to count as on the surface
+const TOLERANCE: Float = if cfg!(feature = "f32") { 1e-5 } else { 1e-12 };
+
+pub struct BezierPatch {
+    cps: [[Vec3d; 4]; 4], // Control points in rows along v, each row going along u
+    cells: Vec<Cell>,
+    bounds: Bounds,
+    scale: Float,         // Size of the patch, so tolerances don't depend on the scene's units
+    color: usize,
+    material: Material
+}
+
+struct Cell {
+    u: Range<Float>,
+    v: Range<Float>,
+    bounds: Bounds
+}
+
+impl BezierPatch {
+    // Control points are given row by row, with the first four along the v = 0 edge
+    pub fn new(points: [Vec3d; 16], color: usize, material: Material) -> Self {
+        let cps: [[Vec3d; 4]; 4] = std::array::from_fn(|row| std::array::from_fn(|col| points[row * 4 + col].clone()));
+        let bounds = Bounds::around(cps.iter().flatten());
+        let scale = (&bounds.max - &bounds.min).magnitude().max(Float::EPSILON);
+
+        let step = 1.0 / CELLS as Float;
+        let cells = (0..CELLS * CELLS).map(|i| {
+            let u = Range::new((i % CELLS) as Float * step, (i % CELLS + 1) as Float * step);
+            let v = Range::new((i / CELLS) as Float * step, (i / CELLS + 1) as Float * step);
+            let sub = sub_patch(&cps, &u, &v);
+            Cell { u, v, bounds: Bounds::around(sub.iter().flatten()) }
+        }).collect();
+
+        Self {
+            cps,
+            cells,
+            bounds,
+            scale,
+            color,
+            material
+        }
+    }
+
+    // Point on the surface at (u, v), and how it moves as u and v change
+    fn eval(&self, u: Float, v: Float) -> (Vec3d, Vec3d, Vec3d) {
+        let (bu, dbu) = bernstein(u);
+        let (bv, dbv) = bernstein(v);
+
+        let zero = || Vec3d::new(0.0, 0.0, 0.0);
+        let (mut p, mut pu, mut pv) = (zero(), zero(), zero());
+        for (row, cps) in self.cps.iter().enumerate() {
+            for (col, cp) in cps.iter().enumerate() {
+                p = &p + &(cp * (bu[col] * bv[row]));
+                pu = &pu + &(cp * (dbu[col] * bv[row]));
+                pv = &pv + &(cp * (bu[col] * dbv[row]));
+            }
+        }
+        (p, pu, pv)
+    }
+
+    // Where the ray crosses the surface within a cell, found with Newton's method starting from the cell's center
+    // Returns t and the surface coordinates (u, v) there
+    fn intersect_cell(&self, cell: &Cell, ray: &Ray) -> Option<(Float, Float, Float)> {
+        let (mut u, mut v) = ((cell.u.min + cell.u.max) / 2.0, (cell.v.min + cell.v.max) / 2.0);
+        let (p, _, _) = self.eval(u, v);
+        let mut t = &(&p - ray.origin()) * ray.dir() / (ray.dir() * ray.dir());
+
+        // Solve P(u, v) = origin + t * dir, i.e. [Pu Pv -dir] * (du, dv, dt) = -(P(u, v) - ray(t))
+        let neg_dir = ray.dir() * -1.0;
+        for _ in 0..NEWTON_ITERATIONS {
+            let (p, pu, pv) = self.eval(u, v);
+            let err = &p - &ray.at(t);
+            if err.magnitude() < TOLERANCE * self.scale {
+                // Allow a little overlap so rays through the seam between cells aren't lost
+                let slack = 1e-6;
+                let inside = cell.u.expand(slack).contains(u) && cell.v.expand(slack).contains(v);
+                return inside.then_some((t, u, v));
+            }
+
+            let det = &pu * &pv.cross(&neg_dir);
+            if det.abs() < Float::EPSILON {
+                return None;
+            }
+            let rhs = &err * -1.0;
+            u += &rhs * &pv.cross(&neg_dir) / det;
+            v += &pu * &rhs.cross(&neg_dir) / det;
+            t += &pu * &pv.cross(&rhs) / det;
+
+            if !u.is_finite() || !v.is_finite() {
+                return None;
+            }
+        }
+        None
+    }
+
+    // Unit normal at (u, v), along Pu x Pv
+    fn normal(&self, u: Float, v: Float, ray: &Ray) -> Vec3d {
+        // Where a side of the patch collapses to a point (e.g. the top of a dome) the surface has no tangent plane, so
+        // take the normal from just inside the patch instead
+        let (mut u, mut v) = (u.clamp(0.0, 1.0), v.clamp(0.0, 1.0));
+        for _ in 0..4 {
+            let (_, pu, pv) = self.eval(u, v);
+            let normal = pu.cross(&pv);
+            if normal.magnitude() > Float::EPSILON * self.scale * self.scale {
+                return normal.normalize();
+            }
+            u += (0.5 - u) * 1e-3;
+            v += (0.5 - v) * 1e-3;
+        }
+
+        // Failing that, face the ray
+        (ray.dir() * -1.0).normalize()
+    }
+}
+
+impl Object for BezierPatch {
+    fn name(&self) -> &'static str {
+        "bezier patch"
+    }
+
+    fn get_color(&self) -> &usize {
+        &self.color
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        Some(self.bounds.clone())
+    }
+
+    fn get_closest_intersection(&self, ray: &Ray, t_range: &Range<Float>) -> Option<HitRecord<'_>> {
+        if !self.bounds.hit(ray, t_range) {
+            return None;
+        }
+
+        let mut closest: Option<(Float, Float, Float)> = None;
+        for cell in self.cells.iter().filter(|cell| cell.bounds.hit(ray, t_range)) {
+            if let Some((t, u, v)) = self.intersect_cell(cell, ray).filter(|&(t, _, _)| t_range.contains(t)) {
+                if closest.is_none_or(|(closest_t, _, _)| t < closest_t) {
+                    closest = Some((t, u, v));
+                }
+            }
+        }
+
+        let (t, u, v) = closest?;
+        Some(HitRecord::new(ray, t, self.normal(u, v, ray), &self.material).with_uv((u, v)))
+    }
+
+    fn triangles(&self) -> Option<Triangles> {
+        Some(grid(16, 16, |u, v| self.eval(u, v).0))
+    }
+}
+
+// Cubic Bernstein polynomials at t, and their derivatives
+fn bernstein(t: Float) -> ([Float; 4], [Float; 4]) {
+    let s = 1.0 - t;
+    (
+        [s * s * s, 3.0 * t * s * s, 3.0 * t * t * s, t * t * t],
+        [-3.0 * s * s, 3.0 * s * s - 6.0 * t * s, 6.0 * t * s - 3.0 * t * t, 3.0 * t * t]
+    )
+}
+
+// Control points of the part of a cubic curve between t0 and t1, by splitting it twice with de Casteljau's algorithm
+fn sub_curve(ps: &[Vec3d; 4], t0: Float, t1: Float) -> [Vec3d; 4] {
+    let before_t1 = split(ps, t1).0;
+    if t1 <= 0.0 {
+        return before_t1;
+    }
+    split(&before_t1, t0 / t1).1
+}
+
+// The two halves of a cubic curve either side of t
+fn split(ps: &[Vec3d; 4], t: Float) -> ([Vec3d; 4], [Vec3d; 4]) {
+    let lerp = |a: &Vec3d, b: &Vec3d| a + &(&(b - a) * t);
+    let ab = lerp(&ps[0], &ps[1]);
+    let bc = lerp(&ps[1], &ps[2]);
+    let cd = lerp(&ps[2], &ps[3]);
+    let abc = lerp(&ab, &bc);
+    let bcd = lerp(&bc, &cd);
+    let mid = lerp(&abc, &bcd);
+
+    ([ps[0].clone(), ab, abc, mid.clone()], [mid, bcd, cd, ps[3].clone()])
+}
+
+// Control points of the part of a patch within a range of u and v
+fn sub_patch(cps: &[[Vec3d; 4]; 4], u: &Range<Float>, v: &Range<Float>) -> [[Vec3d; 4]; 4] {
+    let rows = cps.each_ref().map(|row| sub_curve(row, u.min, u.max));
+    let cols: [[Vec3d; 4]; 4] = std::array::from_fn(|col| {
+        sub_curve(&std::array::from_fn(|row| rows[row][col].clone()), v.min, v.max)
+    });
+    std::array::from_fn(|row| std::array::from_fn(|col| cols[col][row].clone()))
+}
@@ -0,0 +1,79 @@
+use crate::linalg::{Float, Ray, Vec3d};
+use crate::utils::Range;
+
+/*
+
+Bounds
+
+An axis aligned box around an object or part of one, used to skip the objects and parts a ray can't hit
+
+*/
+
+#[derive(Clone)]
+pub struct Bounds {
+    pub min: Vec3d,
+    pub max: Vec3d
+}
+
+impl Bounds {
+    pub fn around<'a>(ps: impl Iterator<Item = &'a Vec3d>) -> Self {
+        ps.fold(
+            Self { min: Vec3d::new(Float::INFINITY, Float::INFINITY, Float::INFINITY), max: Vec3d::new(Float::NEG_INFINITY, Float::NEG_INFINITY, Float::NEG_INFINITY) },
+            |b, p| Self {
+                min: Vec3d::new(b.min.x().min(p.x()), b.min.y().min(p.y()), b.min.z().min(p.z())),
+                max: Vec3d::new(b.max.x().max(p.x()), b.max.y().max(p.y()), b.max.z().max(p.z()))
+            }
+        )
+    }
+
+    // The box around this one and another
+    pub fn union(&self, other: &Bounds) -> Self {
+        Self::around([&self.min, &self.max, &other.min, &other.max].into_iter())
+    }
+
+    // Area of the box's faces, proportional to the chance a random ray passing near it goes through it
+    pub fn surface_area(&self) -> Float {
+        let size = &self.max - &self.min;
+        2.0 * (size.x() * size.y() + size.y() * size.z() + size.z() * size.x())
+    }
+
+    // The eight corners of the box
+    pub fn corners(&self) -> [Vec3d; 8] {
+        std::array::from_fn(|i| Vec3d::new(
+            if i & 1 == 0 { self.min.x() } else { self.max.x() },
+            if i & 2 == 0 { self.min.y() } else { self.max.y() },
+            if i & 4 == 0 { self.min.z() } else { self.max.z() }
+        ))
+    }
+
+    pub fn contains(&self, p: &Vec3d, pad: Float) -> bool {
+        Range::new(self.min.x(), self.max.x()).expand(pad).contains(p.x())
+            && Range::new(self.min.y(), self.max.y()).expand(pad).contains(p.y())
+            && Range::new(self.min.z(), self.max.z()).expand(pad).contains(p.z())
+    }
+
+    // Whether the ray passes through the box within t_range
+    pub fn hit(&self, ray: &Ray, t_range: &Range<Float>) -> bool {
+        self.interval(ray, t_range).is_some()
+    }
+
+    // The part of the ray (as a range of t) inside the box and within t_range, intersecting the slabs between each pair
+    // of opposite faces
+    pub fn interval(&self, ray: &Ray, t_range: &Range<Float>) -> Option<Range<Float>> {
+        let o = ray.origin();
+        let d = ray.dir();
+        let slabs = [(o.x(), d.x(), self.min.x(), self.max.x()), (o.y(), d.y(), self.min.y(), self.max.y()), (o.z(), d.z(), self.min.z(), self.max.z())];
+
+        // Flat boxes get a little thickness so rays grazing them aren't lost to rounding
+        let pad = if cfg!(feature = "f32") { 1e-4 } else { 1e-9 };
+        let inside = slabs.iter().fold(*t_range, |inside, &(o, d, lo, hi)| {
+            let (t0, t1) = ((lo - pad - o) / d, (hi + pad - o) / d);
+            inside.intersect(&Range::new(t0.min(t1), t0.max(t1)))
+        });
+        if inside.is_empty() || inside.size().is_nan() {
+            None
+        } else {
+            Some(inside)
+        }
+    }
+}
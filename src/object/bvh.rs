@@ -0,0 +1,417 @@
+use std::sync::{Arc, OnceLock};
+
+use log::debug;
+
+use crate::linalg::{Float, Ray, Vec3d};
+use crate::utils::{Instant, Range};
+
+use super::{bounds::Bounds, packet::{Packet, PACKET_SIZE}};
+
+/*
+
+Bounding Volume Hierarchy
+
+A tree of boxes over the parts of an object with many parts, like the triangles of a mesh or the points of a cloud, so
+a ray or a point only visits the parts in the boxes it touches instead of every one
+
+Boxes are split in one of two ways:
+- Median: each half of a box holds half of its parts, split along its longest side. Quick to build, but parts that are
+  spread unevenly end up in large, overlapping boxes
+- Surface area heuristic (SAH): the split is the one that makes a ray passing through the box expect to test the fewest
+  parts, since the chance a ray hits a box grows with its surface area. Parts are sorted into a few bins along each
+  axis by their center, and only the splits between bins are weighed, so building stays quick. Slower to build than a
+  median split, but traced faster, particularly for scenes with parts of very different sizes
+
+Objects don't build their tree until a ray first needs it, so an object passed through several steps (e.g. loaded,
+simplified, then moved into place) only builds it once. Objects can also have it built on another thread instead, e.g.
+for a huge scan just loaded into an interactive session: rays test every part whose box they pass through until the
+tree is ready, then switch over to it, so the scene shows up straight away and only draws slowly for a moment
+
+*/
+
+const LEAF_SIZE: usize = 4;  // Most parts in a box before it's split in two
+const MAX_DEPTH: usize = 64; // Splitting at the median halves the parts at every level, so trees are never this deep
+const SAH_BINS: usize = 16;
+const SAH_DEPTH: usize = 24; // SAH splits can be lopsided, so deeper boxes are split at the median to stay under MAX_DEPTH
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BvhBuild {
+    Median,
+    #[default]
+    Sah
+}
+
+pub(crate) struct Bvh {
+    nodes: Vec<Node>, // The root is the first node
+    order: Vec<usize> // Indices of the parts, ordered so every node's parts are next to each other
+}
+
+struct Node {
+    bounds: Bounds,
+    kind: NodeKind
+}
+
+enum NodeKind {
+    Leaf { start: usize, count: usize },
+    Split { left: usize, right: usize }
+}
+
+impl Bvh {
+    // Build the tree over parts with the given bounds
+    pub fn new(bounds: &[Bounds], build: BvhBuild) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::new(),
+            order: (0..bounds.len()).collect()
+        };
+        if !bounds.is_empty() {
+            bvh.build(bounds, build, 0, bounds.len(), 0);
+        }
+        bvh
+    }
+
+    // Add a node at depth for the parts from start to start + count in order, splitting it until every leaf is small,
+    // and return its index
+    fn build(&mut self, bounds: &[Bounds], build: BvhBuild, start: usize, count: usize, depth: usize) -> usize {
+        let node_bounds = Bounds::around(self.order[start..start + count].iter().flat_map(|&i| [&bounds[i].min, &bounds[i].max]));
+
+        let index = self.nodes.len();
+        self.nodes.push(Node { bounds: node_bounds, kind: NodeKind::Leaf { start, count } });
+        if count <= LEAF_SIZE {
+            return index;
+        }
+
+        let left_count = match build {
+            BvhBuild::Sah if depth < SAH_DEPTH => self.split_sah(bounds, start, count),
+            _ => None
+        }.unwrap_or_else(|| self.split_median(bounds, start, count));
+
+        let left = self.build(bounds, build, start, left_count, depth + 1);
+        let right = self.build(bounds, build, start + left_count, count - left_count, depth + 1);
+        self.nodes[index].kind = NodeKind::Split { left, right };
+        index
+    }
+
+    // Split the parts from start to start + count at the median center along their box's longest side, and return how
+    // many are in the first half
+    fn split_median(&mut self, bounds: &[Bounds], start: usize, count: usize) -> usize {
+        let size = &self.nodes.last().unwrap().bounds.max - &self.nodes.last().unwrap().bounds.min;
+        let axis = if size.x() >= size.y() && size.x() >= size.z() { 0 } else if size.y() >= size.z() { 1 } else { 2 };
+        self.order[start..start + count].select_nth_unstable_by(count / 2, |&a, &b| center(&bounds[a], axis).total_cmp(&center(&bounds[b], axis)));
+        count / 2
+    }
+
+    // Split the parts from start to start + count between the bins of centers, along any axis, with the lowest expected
+    // cost, and return how many are in the first half. None if the centers are too close together to bin
+    fn split_sah(&mut self, bounds: &[Bounds], start: usize, count: usize) -> Option<usize> {
+        let parts = &mut self.order[start..start + count];
+        let bin = |i: usize, axis: usize, (lo, hi): (Float, Float)| {
+            (((center(&bounds[i], axis) - lo) / (hi - lo) * SAH_BINS as Float) as usize).min(SAH_BINS - 1)
+        };
+
+        // The best (cost, axis, range of centers, bins on the left) over every axis
+        let mut best: Option<(Float, usize, (Float, Float), usize)> = None;
+        for axis in 0..3 {
+            let centers = parts.iter().fold((Float::INFINITY, Float::NEG_INFINITY), |(lo, hi), &i| {
+                let c = center(&bounds[i], axis);
+                (lo.min(c), hi.max(c))
+            });
+            if centers.1 - centers.0 <= Float::EPSILON * centers.1.abs().max(1.0) {
+                continue;
+            }
+
+            let mut bins: [(usize, Option<Bounds>); SAH_BINS] = std::array::from_fn(|_| (0, None));
+            for &i in parts.iter() {
+                let (n, b) = &mut bins[bin(i, axis, centers)];
+                *n += 1;
+                *b = Some(b.as_ref().map_or_else(|| bounds[i].clone(), |b| b.union(&bounds[i])));
+            }
+
+            // Sweep from the right to find the area and count of everything right of each split, then from the left
+            let mut right = vec![(0, 0.0); SAH_BINS];
+            let mut acc: (usize, Option<Bounds>) = (0, None);
+            for split in (1..SAH_BINS).rev() {
+                acc = merge(acc, &bins[split]);
+                right[split] = (acc.0, acc.1.as_ref().map_or(0.0, Bounds::surface_area));
+            }
+            let mut acc: (usize, Option<Bounds>) = (0, None);
+            for split in 1..SAH_BINS {
+                acc = merge(acc, &bins[split - 1]);
+                let (right_count, right_area) = right[split];
+                if acc.0 == 0 || right_count == 0 {
+                    continue;
+                }
+                let cost = acc.1.as_ref().map_or(0.0, Bounds::surface_area) * acc.0 as Float + right_area * right_count as Float;
+                if best.is_none_or(|(best_cost, ..)| cost < best_cost) {
+                    best = Some((cost, axis, centers, split));
+                }
+            }
+        }
+
+        // Sort the parts so those in bins left of the split come first
+        let (_, axis, centers, split) = best?;
+        let mut left_count = 0;
+        for j in 0..count {
+            if bin(parts[j], axis, centers) < split {
+                parts.swap(j, left_count);
+                left_count += 1;
+            }
+        }
+        Some(left_count)
+    }
+
+    // Fit every box to the parts' new bounds, keeping the tree as it is. Much quicker than building it again when parts
+    // have moved a little, e.g. between frames of an animation, though the tree gets slower to walk the further parts
+    // move from where they were when it was built
+    pub fn refit(&mut self, bounds: &[Bounds]) {
+        // Children are always added after their parent, so going backwards fits them first
+        for index in (0..self.nodes.len()).rev() {
+            self.nodes[index].bounds = match self.nodes[index].kind {
+                NodeKind::Leaf { start, count } => {
+                    Bounds::around(self.order[start..start + count].iter().flat_map(|&i| [&bounds[i].min, &bounds[i].max]))
+                }
+                NodeKind::Split { left, right } => self.nodes[left].bounds.union(&self.nodes[right].bounds)
+            };
+        }
+    }
+
+    // The closest hit along a ray, given how to intersect a single part within a range of t. Returns the part and t
+    pub fn closest_hit(&self, ray: &Ray, t_range: &Range<Float>, mut hit: impl FnMut(usize, &Range<Float>) -> Option<Float>) -> Option<(usize, Float)> {
+        let mut closest: Option<(usize, Float)> = None;
+        let mut stack = self.stack();
+
+        while let Some(index) = stack.pop() {
+            // Only boxes that could hold something closer than the closest hit so far are worth opening
+            let t_range = Range::new(t_range.min, closest.map_or(t_range.max, |(_, t)| t));
+            let node = &self.nodes[index];
+            if !node.bounds.hit(ray, &t_range) {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Split { left, right } => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+                NodeKind::Leaf { start, count } => {
+                    for &part in &self.order[start..start + count] {
+                        let t_range = Range::new(t_range.min, closest.map_or(t_range.max, |(_, t)| t));
+                        if let Some(t) = hit(part, &t_range) {
+                            closest = Some((part, t));
+                        }
+                    }
+                }
+            }
+        }
+
+        closest
+    }
+
+    // The closest hit along each ray of a packet of up to PACKET_SIZE rays, like closest_hit, given how to intersect a
+    // single part with the ray in a lane (its index in the packet) within a range of t. With any set, each ray stops at
+    // the first hit it finds instead, e.g. for shadow rays that only need to know if anything is in the way
+    pub fn packet_hits(&self, rays: &[Ray], t_ranges: &[Range<Float>], any: bool, mut hit: impl FnMut(usize, usize, &Range<Float>) -> Option<Float>) -> [Option<(usize, Float)>; PACKET_SIZE] {
+        let lanes = rays.len().min(PACKET_SIZE);
+        let packet = Packet::new(&rays[..lanes]);
+        let mut closest: [Option<(usize, Float)>; PACKET_SIZE] = [None; PACKET_SIZE];
+        let so_far = |closest: &[Option<(usize, Float)>], lane: usize| {
+            Range::new(t_ranges[lane].min, closest[lane].map_or(t_ranges[lane].max, |(_, t)| t))
+        };
+        let searching = |closest: &[Option<(usize, Float)>], lane: usize| !any || closest[lane].is_none();
+
+        // Every node is visited with the first lane that may still hit it, lanes before it having missed a box above
+        let mut stack: Stack<(usize, usize)> = Stack::new();
+        if !self.nodes.is_empty() {
+            stack.push((0, 0));
+        }
+
+        while let Some((index, first)) = stack.pop() {
+            let node = &self.nodes[index];
+            let reach = (first..lanes).filter(|&lane| searching(&closest, lane)).fold(Range::EMPTY, |reach, lane| reach.union(&so_far(&closest, lane)));
+            if reach.is_empty() || !packet.may_hit(&node.bounds, &reach) {
+                continue;
+            }
+            let hits_node = |closest: &[Option<(usize, Float)>], lane: usize| searching(closest, lane) && node.bounds.hit(&rays[lane], &so_far(closest, lane));
+            let Some(first) = (first..lanes).find(|&lane| hits_node(&closest, lane)) else {
+                continue;
+            };
+
+            match node.kind {
+                NodeKind::Split { left, right } => {
+                    stack.push((left, first));
+                    stack.push((right, first));
+                }
+                NodeKind::Leaf { start, count } => {
+                    for lane in first..lanes {
+                        if lane > first && !hits_node(&closest, lane) {
+                            continue;
+                        }
+                        for &part in &self.order[start..start + count] {
+                            if let Some(t) = hit(lane, part, &so_far(&closest, lane)) {
+                                closest[lane] = Some((part, t));
+                                if any {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        closest
+    }
+
+    // The parts that could be at a point, those in every leaf whose box, padded on every side, contains it
+    pub fn containing<'a>(&'a self, p: &'a Vec3d, pad: Float) -> impl Iterator<Item = usize> + 'a {
+        let mut stack = self.stack();
+        let mut leaf: &[usize] = &[];
+
+        std::iter::from_fn(move || loop {
+            if let Some((&part, rest)) = leaf.split_first() {
+                leaf = rest;
+                return Some(part);
+            }
+
+            let node = &self.nodes[stack.pop()?];
+            if !node.bounds.contains(p, pad) {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Split { left, right } => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+                NodeKind::Leaf { start, count } => leaf = &self.order[start..start + count]
+            }
+        })
+    }
+
+    // A stack of nodes to visit, starting from the root
+    fn stack(&self) -> Stack<usize> {
+        let mut stack = Stack::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        stack
+    }
+}
+
+// A tree built when it's first needed, or on another thread
+pub(crate) struct LazyBvh {
+    parts: Arc<Vec<Bounds>>, // The bounds of every part
+    bounds: Option<Bounds>,  // The box around every part, if there are any
+    build: BvhBuild,
+    bvh: Arc<OnceLock<Bvh>>,
+    background: bool         // Whether the tree is being built on another thread, parts being tested one by one meanwhile
+}
+
+impl LazyBvh {
+    pub fn new(parts: Vec<Bounds>, build: BvhBuild) -> Self {
+        Self {
+            bounds: (!parts.is_empty()).then(|| Bounds::around(parts.iter().flat_map(|b| [&b.min, &b.max]))),
+            parts: Arc::new(parts),
+            build,
+            bvh: Arc::new(OnceLock::new()),
+            background: false
+        }
+    }
+
+    // Start building the tree on another thread. Browsers don't give the renderer threads of its own, so there the
+    // tree is still built when it's first needed
+    pub fn in_background(mut self) -> Self {
+        if cfg!(target_arch = "wasm32") || self.background {
+            return self;
+        }
+
+        let (parts, build, bvh) = (self.parts.clone(), self.build, self.bvh.clone());
+        std::thread::spawn(move || {
+            let start = Instant::now();
+            bvh.get_or_init(|| Bvh::new(&parts, build));
+            debug!("Built a hierarchy over {} parts in the background in {:.2?}", parts.len(), start.elapsed());
+        });
+        self.background = true;
+        self
+    }
+
+    // The tree, built now if it hasn't been yet. None while it's still being built on another thread
+    fn get(&self) -> Option<&Bvh> {
+        if self.background {
+            self.bvh.get()
+        } else {
+            Some(self.bvh.get_or_init(|| Bvh::new(&self.parts, self.build)))
+        }
+    }
+
+    pub fn bounds(&self) -> Option<&Bounds> {
+        self.bounds.as_ref()
+    }
+
+    // Bvh::closest_hit, testing every part the ray passes the box of while the tree isn't ready
+    pub fn closest_hit(&self, ray: &Ray, t_range: &Range<Float>, mut hit: impl FnMut(usize, &Range<Float>) -> Option<Float>) -> Option<(usize, Float)> {
+        if let Some(bvh) = self.get() {
+            return bvh.closest_hit(ray, t_range, hit);
+        }
+
+        let mut closest: Option<(usize, Float)> = None;
+        for (part, bounds) in self.parts.iter().enumerate() {
+            let t_range = Range::new(t_range.min, closest.map_or(t_range.max, |(_, t)| t));
+            if bounds.hit(ray, &t_range) {
+                if let Some(t) = hit(part, &t_range) {
+                    closest = Some((part, t));
+                }
+            }
+        }
+        closest
+    }
+
+    // Bvh::containing, checking the box of every part while the tree isn't ready
+    pub fn containing<'a>(&'a self, p: &'a Vec3d, pad: Float) -> impl Iterator<Item = usize> + 'a {
+        let bvh = self.get();
+        let every = bvh.is_none().then(|| self.parts.iter().enumerate().filter(move |(_, b)| b.contains(p, pad)).map(|(part, _)| part));
+        bvh.into_iter().flat_map(move |bvh| bvh.containing(p, pad)).chain(every.into_iter().flatten())
+    }
+}
+
+// Twice the center of a box along an axis
+fn center(bounds: &Bounds, axis: usize) -> Float {
+    coord(&bounds.min, axis) + coord(&bounds.max, axis)
+}
+
+pub(super) fn coord(p: &Vec3d, axis: usize) -> Float {
+    match axis {
+        0 => p.x(),
+        1 => p.y(),
+        _ => p.z()
+    }
+}
+
+// Add a bin's parts to those counted so far
+fn merge(acc: (usize, Option<Bounds>), (n, b): &(usize, Option<Bounds>)) -> (usize, Option<Bounds>) {
+    let bounds = match (acc.1, b) {
+        (Some(acc), Some(b)) => Some(acc.union(b)),
+        (acc, b) => acc.or_else(|| b.clone())
+    };
+    (acc.0 + n, bounds)
+}
+
+// Nodes waiting to be visited while walking the tree. A visit pushes at most one more node than it pops, so the stack
+// never holds more nodes than the tree is deep, and can live on the call stack rather than being allocated for every ray
+struct Stack<T> {
+    nodes: [T; MAX_DEPTH],
+    len: usize
+}
+
+impl<T: Copy + Default> Stack<T> {
+    fn new() -> Self {
+        Self { nodes: [T::default(); MAX_DEPTH], len: 0 }
+    }
+
+    fn push(&mut self, node: T) {
+        self.nodes[self.len] = node;
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.len = self.len.checked_sub(1)?;
+        Some(self.nodes[self.len])
+    }
+}
@@ -0,0 +1,141 @@
+use crate::linalg::{Float, Ray, Vec3d};
+use crate::utils::Range;
+
+use super::{bounds::Bounds, HitRecord, Material, Object};
+
+/*
+
+Curve
+
+A thin tube swept along a quadratic Bezier curve, for strands of hair, blades of grass and wires. The curve starts at
+its first control point, ends at its last and bends towards the middle one, and its radius may narrow towards the tip
+
+The curve is split into straight pieces short enough that they stray from it by a small fraction of its radius, and
+rays are intersected with the cylinder around each piece. Only pieces whose boxes the ray passes through are tested
+
+*/
+
+const MAX_DEPTH: u32 = 6;       // Splits of the curve in half, so at most 64 pieces
+const FLATNESS: Float = 0.05;   // How far, as a fraction of the radius, a piece may stray from the curve
+
+pub struct Curve {
+    pieces: Vec<Piece>,
+    radius: Float,     // Radius at the start
+    tip_radius: Float, // Radius at the end
+    color: usize,
+    material: Material
+}
+
+// A straight piece of the curve, from u0 to u1 along it
+struct Piece {
+    start: Vec3d,
+    end: Vec3d,
+    u: Range<Float>,
+    bounds: Bounds
+}
+
+impl Curve {
+    pub fn new(points: [Vec3d; 3], radius: Float, tip_radius: Float, color: usize, material: Material) -> Self {
+        // The chords of n pieces of a quadratic curve stray from it by at most |p0 - 2p1 + p2| / (4n^2)
+        let bend = (&(&points[0] - &(&points[1] * 2.0)) + &points[2]).magnitude();
+        let tolerance = FLATNESS * radius.max(tip_radius).max(Float::EPSILON);
+        let n = (bend / (4.0 * tolerance)).sqrt();
+        let depth = if n > 1.0 { (n.log2().ceil() as u32).min(MAX_DEPTH) } else { 0 };
+        let count = 1 << depth;
+
+        let pad = radius.max(tip_radius);
+        let at = |u: Float| {
+            let s = 1.0 - u;
+            &(&(&points[0] * (s * s)) + &(&points[1] * (2.0 * s * u))) + &(&points[2] * (u * u))
+        };
+        let pieces = (0..count).map(|i| {
+            let u = Range::new(i as Float / count as Float, (i + 1) as Float / count as Float);
+            let (start, end) = (at(u.min), at(u.max));
+            let bounds = Bounds::around([&start, &end].into_iter());
+            let bounds = Bounds { min: &bounds.min - &Vec3d::new(pad, pad, pad), max: &bounds.max + &Vec3d::new(pad, pad, pad) };
+            Piece { start, end, u, bounds }
+        }).collect();
+
+        Self {
+            pieces,
+            radius,
+            tip_radius,
+            color,
+            material
+        }
+    }
+
+    fn radius_at(&self, u: Float) -> Float {
+        self.radius + (self.tip_radius - self.radius) * u
+    }
+
+    // Where a ray enters the cylinder around a piece of the curve
+    fn intersect_piece(&self, piece: &Piece, ray: &Ray) -> Option<Float> {
+        let axis = &piece.end - &piece.start;
+        let len = axis.magnitude();
+        let dir_len = ray.dir().magnitude();
+        if len < Float::EPSILON || dir_len < Float::EPSILON {
+            return None;
+        }
+        let (axis, dir) = (&axis * (1.0 / len), ray.dir() * (1.0 / dir_len));
+
+        // Closest approach between the ray and the line along the piece
+        let across = dir.cross(&axis);
+        let sin = across.magnitude();
+        if sin < 1e-9 {
+            return None; // Running along the piece
+        }
+        let to_start = &piece.start - ray.origin();
+        let dist = (&to_start * &across).abs() / sin;
+        let s = &to_start.cross(&axis) * &across / (sin * sin); // Distance along the ray
+        let w = &to_start.cross(&dir) * &across / (sin * sin);  // Distance along the piece
+
+        if !(0.0..=len).contains(&w) {
+            return None;
+        }
+        let radius = self.radius_at(piece.u.min + (piece.u.max - piece.u.min) * w / len);
+        if dist > radius {
+            return None;
+        }
+
+        // Step back from the closest approach to the cylinder's surface
+        Some((s - (radius * radius - dist * dist).sqrt() / sin) / dir_len)
+    }
+}
+
+impl Object for Curve {
+    fn name(&self) -> &'static str {
+        "curve"
+    }
+
+    fn get_color(&self) -> &usize {
+        &self.color
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        Some(Bounds::around(self.pieces.iter().flat_map(|piece| [&piece.bounds.min, &piece.bounds.max])))
+    }
+
+    // The normal points straight out from the middle of the piece the ray hit
+    fn get_closest_intersection(&self, ray: &Ray, t_range: &Range<Float>) -> Option<HitRecord<'_>> {
+        let mut closest: Option<(&Piece, Float)> = None;
+        for piece in self.pieces.iter().filter(|piece| piece.bounds.hit(ray, t_range)) {
+            if let Some(t) = self.intersect_piece(piece, ray).filter(|&t| t_range.contains(t)) {
+                if closest.is_none_or(|(_, closest_t)| t < closest_t) {
+                    closest = Some((piece, t));
+                }
+            }
+        }
+
+        let (piece, t) = closest?;
+        let p = ray.at(t);
+        let axis = &piece.end - &piece.start;
+        let w = (&(&p - &piece.start) * &axis / (&axis * &axis)).clamp(0.0, 1.0);
+        let out = &p - &(&piece.start + &(&axis * w));
+        Some(HitRecord::new(ray, t, out.normalize(), &self.material))
+    }
+}
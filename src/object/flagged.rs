@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use crate::linalg::{Float, Ray, Vec3d};
+use crate::utils::Range;
+
+use super::{bounds::Bounds, HitRecord, Material, Object, Triangles};
+
+/*
+
+Render Flags
+
+Which rays see an object, for staging a render: e.g. a light blocker the camera can't see but that still casts shadows,
+a floor that shows nothing but the shadows falling on it so objects can be composited over a photo, or an object kept
+out of its neighbours' reflections
+
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderFlags {
+    pub camera: bool,        // Seen by rays from the camera
+    pub reflections: bool,   // Seen in reflections and by light bouncing between surfaces
+    pub shadows: bool,       // Blocks light from reaching other surfaces
+    pub shadow_catcher: bool // Shows only the shadows falling on it, over whatever is behind it
+}
+
+impl Default for RenderFlags {
+    fn default() -> Self {
+        Self {
+            camera: true,
+            reflections: true,
+            shadows: true,
+            shadow_catcher: false
+        }
+    }
+}
+
+// What a ray is for, which decides the objects it can hit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RayKind {
+    Camera,     // From the camera into the scene
+    Reflection, // Reflected or bounced off a surface
+    Shadow,     // From a surface towards a light
+    Through     // Carrying on from the camera past a shadow catcher, to what's behind it
+}
+
+impl RenderFlags {
+    pub(crate) fn sees(&self, kind: RayKind) -> bool {
+        match kind {
+            RayKind::Camera => self.camera,
+            RayKind::Reflection => self.reflections,
+            RayKind::Shadow => self.shadows,
+            RayKind::Through => self.camera && !self.shadow_catcher
+        }
+    }
+}
+
+// An object with render flags other than the defaults
+pub struct Flagged {
+    object: Arc<dyn Object>,
+    flags: RenderFlags
+}
+
+impl Flagged {
+    pub fn new(object: Arc<dyn Object>, flags: RenderFlags) -> Self {
+        Self { object, flags }
+    }
+}
+
+impl Object for Flagged {
+    fn name(&self) -> &'static str {
+        self.object.name()
+    }
+
+    fn get_color(&self) -> &usize {
+        self.object.get_color()
+    }
+
+    fn get_material(&self) -> &Material {
+        self.object.get_material()
+    }
+
+    fn render_flags(&self) -> RenderFlags {
+        self.flags
+    }
+
+    fn color_at(&self, hit: &HitRecord) -> usize {
+        self.object.color_at(hit)
+    }
+
+    fn get_closest_intersection(&self, ray: &Ray, t_range: &Range<Float>) -> Option<HitRecord<'_>> {
+        self.object.get_closest_intersection(ray, t_range)
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        self.object.bounds()
+    }
+
+    fn sample_direction(&self, from: &Vec3d, view: &Vec3d, u: (Float, Float)) -> Option<(Vec3d, Float)> {
+        self.object.sample_direction(from, view, u)
+    }
+
+    fn direction_pdf(&self, from: &Vec3d, view: &Vec3d, dir: &Vec3d) -> Float {
+        self.object.direction_pdf(from, view, dir)
+    }
+    fn triangles(&self) -> Option<Triangles> {
+        self.object.triangles()
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use crate::linalg::{Float, Mat3, Ray, Vec3d};
+use crate::utils::Range;
+
+use super::{bounds::Bounds, HitRecord, Material, Object, RenderFlags, Triangles};
+
+/*
+
+Instance
+
+A shared object placed in the scene, rotated about its own origin and then moved, e.g. one of a forest of trees made
+from the same mesh. Rays are moved into the object's space rather than the object into the scene's, so an object's
+own hierarchy of parts is built once however many times it's placed, and an instance can be moved by replacing it
+without rebuilding anything inside the object
+
+*/
+
+pub struct Instance {
+    object: Arc<dyn Object>,
+    rotation: Mat3,
+    inverse: Mat3, // Rotates from the scene back into the object's space
+    offset: Vec3d
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Object>, rotation: Mat3, offset: Vec3d) -> Self {
+        Self {
+            inverse: rotation.transpose(),
+            object,
+            rotation,
+            offset
+        }
+    }
+
+    // A point in the scene, in the object's space
+    fn to_local(&self, p: &Vec3d) -> Vec3d {
+        &self.inverse * &(p - &self.offset)
+    }
+}
+
+impl Object for Instance {
+    fn name(&self) -> &'static str {
+        self.object.name()
+    }
+
+    fn get_color(&self) -> &usize {
+        self.object.get_color()
+    }
+
+    fn get_material(&self) -> &Material {
+        self.object.get_material()
+    }
+
+    fn render_flags(&self) -> RenderFlags {
+        self.object.render_flags()
+    }
+
+    fn color_at(&self, hit: &HitRecord) -> usize {
+        let local = HitRecord { point: self.to_local(&hit.point), normal: &self.inverse * &hit.normal, view: self.to_local(&hit.view), ..hit.clone() };
+        self.object.color_at(&local)
+    }
+
+    // Rotating and moving a ray doesn't change its length, so t is the same in both spaces
+    fn get_closest_intersection(&self, ray: &Ray, t_range: &Range<Float>) -> Option<HitRecord<'_>> {
+        let local = Ray::new(self.to_local(ray.origin()), &self.inverse * ray.dir()).with_view(self.to_local(ray.view()));
+        let hit = self.object.get_closest_intersection(&local, t_range)?;
+        Some(HitRecord { point: ray.at(hit.t), normal: &self.rotation * &hit.normal, view: ray.view().clone(), ..hit })
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        let corners = self.object.bounds()?.corners().map(|corner| &(&self.rotation * &corner) + &self.offset);
+        Some(Bounds::around(corners.iter()))
+    }
+
+    fn sample_direction(&self, from: &Vec3d, view: &Vec3d, u: (Float, Float)) -> Option<(Vec3d, Float)> {
+        let (dir, pdf) = self.object.sample_direction(&self.to_local(from), &self.to_local(view), u)?;
+        Some((&self.rotation * &dir, pdf))
+    }
+
+    fn direction_pdf(&self, from: &Vec3d, view: &Vec3d, dir: &Vec3d) -> Float {
+        self.object.direction_pdf(&self.to_local(from), &self.to_local(view), &(&self.inverse * dir))
+    }
+
+    fn triangles(&self) -> Option<Triangles> {
+        let (vertices, faces) = self.object.triangles()?;
+        Some((vertices.iter().map(|v| &(&self.rotation * v) + &self.offset).collect(), faces))
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use crate::linalg::{Float, Ray, Vec3d};
+use crate::utils::Range;
+
+use super::{bounds::Bounds, HitRecord, Material, Object, RenderFlags, Triangles};
+
+/*
+
+Level of Detail
+
+An object made in several versions, from the most detailed to the cheapest, e.g. a heavy mesh and simpler stand-ins for
+it, that shows whichever suits how far it is from the camera. Each level is used up to a distance between the camera and
+the middle of the object, and past the last of those the last level is used
+
+Every ray on a path picks its level by its view, where the camera the path started from is, rather than where the ray
+starts (see Ray), so shadow, reflection and bounce rays leaving a surface see the same version of the object the camera
+did, and it can't shadow itself
+
+*/
+
+pub struct Lod {
+    levels: Vec<(Float, Arc<dyn Object>)>, // Each level and the furthest distance it's used at, most detailed first
+    center: Vec3d,
+    bounds: Option<Bounds>
+}
+
+impl Lod {
+    // Levels are given with the furthest distance each is used at, and sorted by it
+    pub fn new(mut levels: Vec<(Float, Arc<dyn Object>)>) -> Self {
+        assert!(!levels.is_empty(), "a level of detail object needs at least one level");
+        levels.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        // Levels without a box (e.g. infinite planes) leave the whole object without one
+        let bounds = levels.iter()
+            .map(|(_, level)| level.bounds())
+            .reduce(|a, b| Some(a?.union(&b?)))
+            .flatten();
+        let center = bounds.as_ref().map_or(Vec3d::new(0.0, 0.0, 0.0), |b| &(&b.min + &b.max) * 0.5);
+
+        Self { levels, center, bounds }
+    }
+
+    // The level shown to a camera at a point
+    fn level(&self, view: &Vec3d) -> &dyn Object {
+        let dist = (view - &self.center).magnitude();
+        let (_, level) = self.levels.iter().find(|(max_dist, _)| dist <= *max_dist).unwrap_or(&self.levels[self.levels.len() - 1]);
+        level.as_ref()
+    }
+
+    // The most detailed level, which describes the object as a whole
+    fn finest(&self) -> &dyn Object {
+        self.levels[0].1.as_ref()
+    }
+}
+
+impl Object for Lod {
+    fn name(&self) -> &'static str {
+        self.finest().name()
+    }
+
+    fn get_color(&self) -> &usize {
+        self.finest().get_color()
+    }
+
+    fn get_material(&self) -> &Material {
+        self.finest().get_material()
+    }
+
+    fn render_flags(&self) -> RenderFlags {
+        self.finest().render_flags()
+    }
+
+    fn color_at(&self, hit: &HitRecord) -> usize {
+        self.level(&hit.view).color_at(hit)
+    }
+
+    fn get_closest_intersection(&self, ray: &Ray, t_range: &Range<Float>) -> Option<HitRecord<'_>> {
+        self.level(ray.view()).get_closest_intersection(ray, t_range)
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        self.bounds.clone()
+    }
+
+    fn sample_direction(&self, from: &Vec3d, view: &Vec3d, u: (Float, Float)) -> Option<(Vec3d, Float)> {
+        self.level(view).sample_direction(from, view, u)
+    }
+
+    fn direction_pdf(&self, from: &Vec3d, view: &Vec3d, dir: &Vec3d) -> Float {
+        self.level(view).direction_pdf(from, view, dir)
+    }
+
+    fn triangles(&self) -> Option<Triangles> {
+        self.finest().triangles()
+    }
+}
@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use crate::linalg::{Float, Mat3, Ray, Vec3d};
+use crate::texture::Texture;
+use crate::utils::Range;
+
+use super::{bounds::Bounds, bvh::{BvhBuild, LazyBvh}, simplify, HitRecord, Material, Object, Triangle, Triangles};
+
+/*
+
+Mesh
+
+A surface made of many triangles sharing one color and material, e.g. extruded text or an imported model. Triangles are
+kept in a bounding volume hierarchy, so a ray only tests the few near its path. The hierarchy of a large mesh can be
+built on another thread while the mesh is already being traced (see bvh.rs)
+
+Faces can be given surfaces of their own instead, each with a color, material and optionally a texture, e.g. the
+materials of an imported model, and texture coordinates at their corners. Faces without coordinates are textured flat,
+tiling once every unit of distance as lone triangles are. Simplifying a mesh drops both, since its faces change
+
+*/
+
+// How some of a mesh's faces look
+#[derive(Clone)]
+pub struct Surface {
+    pub color: usize,
+    pub material: Material,
+    pub texture: Option<Arc<Texture>> // Painted over the color by the faces' texture coordinates
+}
+
+pub struct Mesh {
+    vertices: Vec<Vec3d>,
+    faces: Vec<[usize; 3]>, // Indices of each triangle's corners
+    triangles: Vec<Triangle>,
+    bvh: LazyBvh,
+    build: BvhBuild,
+    background: bool, // Whether the hierarchy is built on another thread
+    color: usize,
+    material: Material,
+    surfaces: Vec<Surface>,
+    face_surfaces: Vec<usize>,           // Index of each face's surface, when faces have their own
+    uvs: Vec<Option<[(Float, Float); 3]>> // Texture coordinates at each face's corners, when any faces have them
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<Vec3d>, faces: Vec<[usize; 3]>, color: usize, material: Material) -> Self {
+        Self::built(vertices, faces, color, material, BvhBuild::default(), false)
+    }
+
+    // The same mesh with its hierarchy built another way
+    pub fn with_bvh(self, build: BvhBuild) -> Self {
+        if build == self.build {
+            return self;
+        }
+        let (surfaces, face_surfaces, uvs) = (self.surfaces, self.face_surfaces, self.uvs);
+        Self::built(self.vertices, self.faces, self.color, self.material, build, self.background).dressed(surfaces, face_surfaces, uvs)
+    }
+
+    // The same mesh with each face looking like one of surfaces, the one face_surfaces gives in the order of the faces
+    pub fn with_surfaces(mut self, surfaces: Vec<Surface>, face_surfaces: Vec<usize>) -> Self {
+        assert_eq!(face_surfaces.len(), self.faces.len(), "every face needs a surface");
+        assert!(face_surfaces.iter().all(|&i| i < surfaces.len()), "faces' surfaces must be among those given");
+        let uvs = std::mem::take(&mut self.uvs);
+        self.dressed(surfaces, face_surfaces, uvs)
+    }
+
+    // The same mesh with texture coordinates at the corners of faces, in the order of the faces
+    pub fn with_uvs(mut self, uvs: Vec<Option<[(Float, Float); 3]>>) -> Self {
+        assert_eq!(uvs.len(), self.faces.len(), "every face needs its texture coordinates, or None");
+        self.uvs = uvs;
+        self
+    }
+
+    // The same mesh with its hierarchy built on another thread, testing every triangle until it's ready
+    pub fn in_background(self) -> Self {
+        Self { bvh: self.bvh.in_background(), background: true, ..self }
+    }
+
+    fn built(vertices: Vec<Vec3d>, faces: Vec<[usize; 3]>, color: usize, material: Material, build: BvhBuild, background: bool) -> Self {
+        let triangles: Vec<Triangle> = faces.iter()
+            .map(|face| Triangle::new(face.map(|i| vertices[i].clone()), color, material.clone()))
+            .collect();
+        let bounds: Vec<Bounds> = triangles.iter().map(|tri| Bounds::around(tri.ps.iter())).collect();
+
+        let bvh = LazyBvh::new(bounds, build);
+        Self {
+            bvh: if background { bvh.in_background() } else { bvh },
+            build,
+            background,
+            vertices,
+            faces,
+            triangles,
+            color,
+            material,
+            surfaces: Vec::new(),
+            face_surfaces: Vec::new(),
+            uvs: Vec::new()
+        }
+    }
+
+    // The mesh with faces given surfaces and texture coordinates, its triangles taking on their surfaces' looks
+    fn dressed(mut self, surfaces: Vec<Surface>, face_surfaces: Vec<usize>, uvs: Vec<Option<[(Float, Float); 3]>>) -> Self {
+        for (triangle, &i) in self.triangles.iter_mut().zip(&face_surfaces) {
+            triangle.color = surfaces[i].color;
+            triangle.material = surfaces[i].material.clone();
+        }
+        Self { surfaces, face_surfaces, uvs, ..self }
+    }
+
+    // The face a point on the mesh is on: of the faces whose boxes hold it, the nearest to it
+    fn face_at(&self, p: &Vec3d) -> Option<usize> {
+        let pad = (p.magnitude() + 1.0) * if cfg!(feature = "f32") { 1e-5 } else { 1e-9 };
+        self.bvh.containing(p, pad)
+            .map(|i| {
+                let triangle = &self.triangles[i];
+                let outside: Float = triangle.barycentric(p).iter().map(|&b| (-b).max(0.0)).sum();
+                (i, (&(p - &triangle.ps[0]) * &triangle.normal()).abs() + outside)
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+    }
+
+    // The same mesh reduced to about target triangles by collapsing edges, e.g. so a huge scanned model can be explored
+    // interactively
+    pub fn simplify(self, target: usize) -> Self {
+        let (vertices, faces) = simplify::simplify(self.vertices, self.faces, target);
+        Self::built(vertices, faces, self.color, self.material, self.build, self.background)
+    }
+
+    // The same mesh rotated about the origin, then moved by offset
+    pub fn transform(self, rotation: &Mat3, offset: &Vec3d) -> Self {
+        let vertices = self.vertices.iter().map(|v| &(rotation * v) + offset).collect();
+        let (surfaces, face_surfaces, uvs) = (self.surfaces, self.face_surfaces, self.uvs);
+        Self::built(vertices, self.faces, self.color, self.material, self.build, self.background).dressed(surfaces, face_surfaces, uvs)
+    }
+}
+
+impl Object for Mesh {
+    fn name(&self) -> &'static str {
+        "mesh"
+    }
+
+    fn get_color(&self) -> &usize {
+        &self.color
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        self.bvh.bounds().cloned()
+    }
+
+    fn color_at(&self, hit: &HitRecord) -> usize {
+        if self.surfaces.is_empty() {
+            return self.color;
+        }
+        let Some(face) = self.face_at(&hit.point) else {
+            return self.color;
+        };
+        let surface = &self.surfaces[self.face_surfaces[face]];
+        match (&surface.texture, hit.uv) {
+            (Some(texture), Some(uv)) => texture.sample(uv),
+            _ => surface.color
+        }
+    }
+
+    fn get_closest_intersection(&self, ray: &Ray, t_range: &Range<Float>) -> Option<HitRecord<'_>> {
+        let (i, t) = self.bvh.closest_hit(ray, t_range, |i, t_range| self.triangles[i].closest_t(ray, t_range))?;
+        let triangle = &self.triangles[i];
+        let hit = HitRecord::new(ray, t, triangle.normal(), &triangle.material);
+        match self.uvs.get(i) {
+            Some(Some(uvs)) => {
+                let weights = triangle.barycentric(&hit.point);
+                let uv = uvs.iter().zip(weights).fold((0.0, 0.0), |(u, v), ((cu, cv), w)| (u + cu * w, v + cv * w));
+                Some(hit.with_uv(uv))
+            },
+            _ => Some(hit.with_planar_uv())
+        }
+    }
+
+    fn triangles(&self) -> Option<Triangles> {
+        Some((self.vertices.clone(), self.faces.clone()))
+    }
+}
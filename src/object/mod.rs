@@ -0,0 +1,578 @@
+mod bezier;
+mod bounds;
+mod bvh;
+mod curve;
+mod flagged;
+mod instance;
+mod lod;
+mod mesh;
+mod packet;
+mod point_cloud;
+mod polyhedron;
+mod primitive;
+mod simplify;
+mod textured;
+mod tlas;
+
+use std::fmt;
+
+use crate::linalg::{consts::PI, Float, Ray, Vec3d, RAY_EPSILON};
+use crate::utils::Range;
+
+pub use bezier::BezierPatch;
+pub use bounds::Bounds;
+pub use bvh::BvhBuild;
+pub use curve::Curve;
+pub use flagged::{Flagged, RayKind, RenderFlags};
+pub use instance::Instance;
+pub use lod::Lod;
+pub use mesh::{Mesh, Surface};
+pub(crate) use packet::PACKET_SIZE;
+pub use point_cloud::{PointCloud, Splat};
+pub use polyhedron::{ConvexPolyhedron, Plane};
+pub use primitive::Primitive;
+pub use simplify::simplify;
+pub use textured::{Mapping, Textured};
+pub(crate) use tlas::{Tlas, TraversalContext};
+
+#[derive(Clone, PartialEq)]
+pub enum Material {
+    // Every surface has a material type that describes its properties
+    // Surfaces are hit and shaded from whichever side a ray arrives on when double-sided, as thin quads and leaves need.
+    // Single-sided surfaces are seen through from behind instead (backface culling), which closed meshes can use
+
+    // Light reflects off a point on a matte object equally in every direction (diffuse reflection)
+    // This is possible because at the microscopic level, the material is composed up of irregular surfaces pointing in random directions
+    // A point will recieve less light the larger the angle between the ray and normal vector.
+    Matte { double_sided: bool },
+
+    // At the extreme (a perfect mirror), a ray of light intersecting a shiny object will be reflected in one direction (symmetrical to normal vector)
+    // This material exhibits specular reflection. A point receives less light the larger the angle between the vector from the point to the camera, and the reflected light ray vector
+    // Specular exponent: higher means more shiny, i.e there is less shine as camera moves away from reflected ray
+    // Reflection ratio: a ratio between 0 and 1 that describes how reflective the material is, e.g. 0 is not reflective, 1 is a perfect mirror
+    Shiny { spclr_exp: Float, refl_rat: Float, double_sided: bool },
+
+    // Glows with its color at some intensity, e.g. a lamp. The path tracer lights the scene with it, while the Whitted
+    // integrator only shows the glow itself
+    Emissive { intensity: Float, double_sided: bool }
+}
+
+impl Material {
+    pub fn double_sided(&self) -> bool {
+        match self {
+            Material::Matte { double_sided } | Material::Shiny { double_sided, .. } | Material::Emissive { double_sided, .. } => *double_sided
+        }
+    }
+
+    // The same material, seen through from behind
+    pub fn single_sided(mut self) -> Self {
+        match &mut self {
+            Material::Matte { double_sided } | Material::Shiny { double_sided, .. } | Material::Emissive { double_sided, .. } => *double_sided = false
+        }
+        self
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::Matte { double_sided: true }
+    }
+}
+
+impl fmt::Display for Material {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Material::Matte { .. } => write!(f, "matte")?,
+            Material::Shiny { spclr_exp, refl_rat, .. } => write!(f, "shiny (specular exponent {}, reflection ratio {})", spclr_exp, refl_rat)?,
+            Material::Emissive { intensity, .. } => write!(f, "emissive (intensity {})", intensity)?
+        }
+        if !self.double_sided() {
+            write!(f, ", single-sided")?;
+        }
+        Ok(())
+    }
+}
+
+// Where a ray hits an object, worked out along with the hit itself so nothing needs finding again from the point
+// Objects give the normal pointing out of the surface, and front_face records which side the ray arrived from. Shading
+// only ever uses facing_normal, so every branch lights, reflects and bounces off the side the ray is on
+#[derive(Clone)]
+pub struct HitRecord<'a> {
+    pub t: Float,
+    pub point: Vec3d,
+    pub normal: Vec3d,              // Unit normal pointing out of the surface, or to one side of a surface without an outside
+    pub front_face: bool,           // Whether the ray arrived from the side the normal points to
+    pub material: &'a Material,
+    pub uv: Option<(Float, Float)>, // Coordinates on the surface that textures are looked up by, for objects that have them
+    pub view: Vec3d                 // The view of the ray that hit the surface (see Ray)
+}
+
+impl<'a> HitRecord<'a> {
+    pub fn new(ray: &Ray, t: Float, normal: Vec3d, material: &'a Material) -> Self {
+        Self {
+            t,
+            point: ray.at(t),
+            front_face: &normal * ray.dir() < 0.0,
+            normal,
+            material,
+            uv: None,
+            view: ray.view().clone()
+        }
+    }
+
+    pub fn with_uv(mut self, uv: (Float, Float)) -> Self {
+        self.uv = Some(uv);
+        self
+    }
+
+    // Coordinates for a flat face, from the two axes it's most square on to, in units of distance. A texture then tiles
+    // evenly over the face and carries on seamlessly across faces in the same plane, e.g. the triangles of a quad or the
+    // sides of a box
+    pub fn with_planar_uv(self) -> Self {
+        let (p, n) = (&self.point, &self.normal);
+        let (x, y, z) = (n.x().abs(), n.y().abs(), n.z().abs());
+        let uv = if x >= y && x >= z {
+            (p.z(), p.y())
+        } else if y >= z {
+            (p.x(), p.z())
+        } else {
+            (p.x(), p.y())
+        };
+        self.with_uv(uv)
+    }
+
+    // The unit normal on the side of the surface the ray arrived from
+    pub fn facing_normal(&self) -> Vec3d {
+        if self.front_face { self.normal.clone() } else { &self.normal * -1.0 }
+    }
+}
+
+pub trait Object: Send + Sync {
+    fn get_color(&self) -> &usize;
+    fn get_material(&self) -> &Material;
+
+    // What kind of object this is, e.g. to describe it while debugging a scene
+    fn name(&self) -> &'static str {
+        "object"
+    }
+
+    // Which rays see the object, all but shadow catching by default
+    fn render_flags(&self) -> RenderFlags {
+        RenderFlags::default()
+    }
+
+    // Color of the surface where a ray hit it. Objects with a single color use get_color everywhere
+    fn color_at(&self, _hit: &HitRecord) -> usize {
+        *self.get_color()
+    }
+
+    // Find the closest intersection point of the obj along the ray. Check all points (ray at t) within the t range, and
+    // return the hit along with the surface's normal there
+    fn get_closest_intersection(&self, ray: &Ray, t_range: &Range<Float>) -> Option<HitRecord<'_>>;
+
+    // A box around the whole object, so rays that don't pass through it can skip the object
+    // Objects without one (e.g. those reaching off to infinity) are tested by every ray
+    fn bounds(&self) -> Option<Bounds> {
+        None
+    }
+
+    // Choose a unit direction from a point towards the object, given two uniform random numbers, so a glowing object
+    // can be sampled as a light. The point is on a path seen from view (see Ray). Returns the direction and its
+    // probability density (per unit solid angle)
+    // Objects that can't be sampled this way are only found by bounces that happen to hit them
+    fn sample_direction(&self, _from: &Vec3d, _view: &Vec3d, _u: (Float, Float)) -> Option<(Vec3d, Float)> {
+        None
+    }
+
+    // Probability density of sample_direction choosing a unit direction from a point seen from view
+    fn direction_pdf(&self, _from: &Vec3d, _view: &Vec3d, _dir: &Vec3d) -> Float {
+        0.0
+    }
+
+    // The surface as triangles, e.g. to export the scene to other programs: the corners, and the indices of each
+    // triangle's corners turning counterclockwise seen from outside. Curved surfaces are approximated
+    // None for objects that can't be made of triangles, which are left out
+    fn triangles(&self) -> Option<Triangles> {
+        None
+    }
+}
+
+// Corners, and the indices of the corners of each triangle between them
+pub type Triangles = (Vec<Vec3d>, Vec<[usize; 3]>);
+
+// A surface described by a point for every (u, v) from 0 to 1 as triangles between rows and columns of points, leaving
+// out those that collapse to a line, e.g. at the poles of a sphere. Corners turn counterclockwise when increasing u is
+// to the right and increasing v up
+pub(crate) fn grid(rows: usize, cols: usize, point: impl Fn(Float, Float) -> Vec3d) -> Triangles {
+    let vertices: Vec<Vec3d> = (0..=rows)
+        .flat_map(|row| (0..=cols).map(move |col| (col as Float / cols as Float, row as Float / rows as Float)))
+        .map(|(u, v)| point(u, v))
+        .collect();
+    let at = |row: usize, col: usize| row * (cols + 1) + col;
+    let faces = (0..rows)
+        .flat_map(|row| (0..cols).flat_map(move |col| {
+            let (a, b, c, d) = (at(row, col), at(row, col + 1), at(row + 1, col + 1), at(row + 1, col));
+            [[a, b, c], [a, c, d]]
+        }))
+        .filter(|&[a, b, c]| (&vertices[b] - &vertices[a]).cross(&(&vertices[c] - &vertices[a])).magnitude() > 0.0)
+        .collect();
+    (vertices, faces)
+}
+
+/*
+
+Sphere
+
+*/
+
+pub struct Sphere {
+    center: Vec3d,
+    radius: Float,
+    color: usize,
+    material: Material
+}
+
+impl Sphere {
+    pub fn new(center: Vec3d, radius: Float, color: usize, material: Material) -> Self {
+        Self {
+            center,
+            radius,
+            color,
+            material
+        }
+    }
+
+    // The closest t within the range where the ray crosses the sphere. Rays starting inside it, e.g. refracted into it or
+    // from a camera within it, have the near crossing behind them and leave through the far one
+    fn closest_t(&self, ray: &Ray, t_range: &Range<Float>) -> Option<Float> {
+        let (near, far) = sphere_roots(ray, &self.center, self.radius)?;
+        [near, far].into_iter().find(|&t| t_range.contains(t))
+    }
+}
+
+impl Object for Sphere {
+    fn name(&self) -> &'static str {
+        "sphere"
+    }
+
+    fn get_color(&self) -> &usize {
+        &self.color
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        let r = Vec3d::new(self.radius, self.radius, self.radius);
+        Some(Bounds { min: &self.center - &r, max: &self.center + &r })
+    }
+
+    fn get_closest_intersection(&self, ray: &Ray, t_range: &Range<Float>) -> Option<HitRecord<'_>> {
+        let t = self.closest_t(ray, t_range)?;
+        let normal = (&ray.at(t) - &self.center).normalize();
+
+        // Longitude around the vertical and latitude from the bottom pole, so an equirectangular image wraps the sphere
+        let u = 0.5 - normal.z().atan2(normal.x()) / (2.0 * PI);
+        let v = 0.5 + normal.y().clamp(-1.0, 1.0).asin() / PI;
+        Some(HitRecord::new(ray, t, normal, &self.material).with_uv((u, v)))
+    }
+
+    // Directions are chosen evenly within the cone the sphere covers as seen from the point
+    fn sample_direction(&self, from: &Vec3d, _view: &Vec3d, (u1, u2): (Float, Float)) -> Option<(Vec3d, Float)> {
+        let (axis, cos_max) = self.cone(from)?;
+
+        let cos_theta = 1.0 - u1 * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = 2.0 * PI * u2;
+
+        let up = if axis.x().abs() > 0.9 { Vec3d::new(0.0, 1.0, 0.0) } else { Vec3d::new(1.0, 0.0, 0.0) };
+        let tangent = up.cross(&axis).normalize();
+        let bitangent = axis.cross(&tangent);
+        let dir = &(&(&tangent * (sin_theta * phi.cos())) + &(&bitangent * (sin_theta * phi.sin()))) + &(&axis * cos_theta);
+
+        Some((dir, 1.0 / (2.0 * PI * (1.0 - cos_max))))
+    }
+
+    fn direction_pdf(&self, from: &Vec3d, _view: &Vec3d, dir: &Vec3d) -> Float {
+        match self.cone(from) {
+            Some((axis, cos_max)) if &axis * dir >= cos_max => 1.0 / (2.0 * PI * (1.0 - cos_max)),
+            _ => 0.0
+        }
+    }
+
+    // Rings of latitude from the bottom pole to the top
+    fn triangles(&self) -> Option<Triangles> {
+        Some(grid(16, 32, |u, v| {
+            let (longitude, latitude) = (2.0 * PI * u, PI * (v - 0.5));
+            let dir = Vec3d::new(latitude.cos() * longitude.cos(), latitude.sin(), -latitude.cos() * longitude.sin());
+            &self.center + &(&dir * self.radius)
+        }))
+    }
+}
+
+impl Sphere {
+    // Direction to the center and cosine of the half angle of the cone the sphere covers as seen from outside it
+    fn cone(&self, from: &Vec3d) -> Option<(Vec3d, Float)> {
+        let to_center = &self.center - from;
+        let dist = to_center.magnitude();
+        if dist <= self.radius {
+            return None;
+        }
+
+        let sin_max = self.radius / dist;
+        Some((&to_center * (1.0 / dist), (1.0 - sin_max * sin_max).sqrt()))
+    }
+}
+
+// The t where a ray crosses a sphere, nearest first, or None if it misses
+// The root further from the ray's origin is found first and the other from it, rather than both with the textbook
+// formula, which subtracts nearly equal numbers for the root nearer the origin. That way a ray leaving the surface gets
+// a root of almost exactly 0 there, instead of one thrown off by rounding that could land inside the range searched
+pub(crate) fn sphere_roots(ray: &Ray, center: &Vec3d, radius: Float) -> Option<(Float, Float)> {
+    let c_o = ray.origin() - center;
+
+    let a = ray.dir() * ray.dir();
+    let half_b = &c_o * ray.dir();
+    let c = &c_o * &c_o - radius * radius;
+
+    let discnm = half_b * half_b - a * c;
+    if discnm < 0.0 {
+        return None;
+    }
+
+    let q = -(half_b + half_b.signum() * discnm.sqrt());
+    if q == 0.0 {
+        // Grazing the sphere where the ray starts
+        return Some((0.0, 0.0));
+    }
+    let (t1, t2) = (q / a, c / q);
+    Some((t1.min(t2), t1.max(t2)))
+}
+
+/*
+
+Triangle
+
+*/
+
+pub struct Triangle {
+    ps: [Vec3d; 3],
+    color: usize,
+    material: Material
+}
+
+impl Triangle {
+    pub fn new(ps: [Vec3d; 3], color: usize, material: Material) -> Self {
+        Self {
+            ps,
+            color,
+            material
+        }
+    }
+
+    // The unit normal on the side the corners turn counterclockwise when seen from
+    pub(crate) fn normal(&self) -> Vec3d {
+        let e1 = &self.ps[1] - &self.ps[0];
+        let e2 = &self.ps[2] - &self.ps[0];
+        e1.cross(&e2).normalize()
+    }
+
+    // Where the ray crosses the triangle within the t range
+    pub(crate) fn closest_t(&self, ray: &Ray, t_range: &Range<Float>) -> Option<Float> {
+        // Möller–Trumbore ray-triangle intersection algorithm
+
+        let e1 = &self.ps[1] - &self.ps[0];
+        let e2 = &self.ps[2] - &self.ps[0];
+    
+        let v_cross_e2 = ray.dir().cross(&e2);
+        let det = &e1 * &v_cross_e2;
+    
+        if det > -Float::EPSILON && det < Float::EPSILON {
+            return None;
+        }
+    
+        let inv_det = 1.0 / det;
+        let s = ray.origin() - &self.ps[0];
+        let u = inv_det * (&s * &v_cross_e2);
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+    
+    	let s_cross_e1 = s.cross(&e1);
+        let a = inv_det * (ray.dir() * &s_cross_e1);
+        if a < 0.0 || u + a > 1.0 {
+            return None;
+        }
+
+        let t = inv_det * (&e2 * &s_cross_e1);
+    
+        if t_range.contains(t) {
+            Some(t)
+        }
+        else {
+            None
+        }
+    }
+}
+
+impl Object for Triangle {
+    fn name(&self) -> &'static str {
+        "triangle"
+    }
+
+    fn get_color(&self) -> &usize {
+        &self.color
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        Some(Bounds::around(self.ps.iter()))
+    }
+
+    fn get_closest_intersection(&self, ray: &Ray, t_range: &Range<Float>) -> Option<HitRecord<'_>> {
+        let t = self.closest_t(ray, t_range)?;
+        Some(HitRecord::new(ray, t, self.normal(), &self.material).with_planar_uv())
+    }
+
+    // Directions are chosen towards points spread evenly over the triangle's area
+    fn sample_direction(&self, from: &Vec3d, _view: &Vec3d, (u1, u2): (Float, Float)) -> Option<(Vec3d, Float)> {
+        let su = u1.sqrt();
+        let (b0, b1) = (1.0 - su, u2 * su);
+        let p = &(&(&self.ps[0] * b0) + &(&self.ps[1] * b1)) + &(&self.ps[2] * (1.0 - b0 - b1));
+
+        let to_p = &p - from;
+        let dist = to_p.magnitude();
+        let dir = &to_p * (1.0 / dist);
+
+        let pdf = self.area_to_solid_angle(&dir, dist);
+        (pdf > 0.0).then_some((dir, pdf))
+    }
+
+    fn direction_pdf(&self, from: &Vec3d, _view: &Vec3d, dir: &Vec3d) -> Float {
+        match self.closest_t(&Ray::new(from.clone(), dir.clone()), &Range::new(RAY_EPSILON, Float::INFINITY)) {
+            Some(dist) => self.area_to_solid_angle(dir, dist),
+            None => 0.0
+        }
+    }
+
+    fn triangles(&self) -> Option<Triangles> {
+        Some((self.ps.to_vec(), vec![[0, 1, 2]]))
+    }
+}
+
+impl Triangle {
+    // Weights of the corners that add up to a point in the triangle's plane, all between 0 and 1 for points inside it
+    pub(crate) fn barycentric(&self, p: &Vec3d) -> [Float; 3] {
+        let (e1, e2, to_p) = (&self.ps[1] - &self.ps[0], &self.ps[2] - &self.ps[0], p - &self.ps[0]);
+        let (d11, d12, d22) = (&e1 * &e1, &e1 * &e2, &e2 * &e2);
+        let (d1p, d2p) = (&e1 * &to_p, &e2 * &to_p);
+        let denom = d11 * d22 - d12 * d12;
+        if denom == 0.0 {
+            return [1.0, 0.0, 0.0];
+        }
+        let b1 = (d22 * d1p - d12 * d2p) / denom;
+        let b2 = (d11 * d2p - d12 * d1p) / denom;
+        [1.0 - b1 - b2, b1, b2]
+    }
+
+    // Density per unit solid angle of a point spread evenly over the area, seen dist away in a unit direction
+    fn area_to_solid_angle(&self, dir: &Vec3d, dist: Float) -> Float {
+        let e1 = &self.ps[1] - &self.ps[0];
+        let e2 = &self.ps[2] - &self.ps[0];
+        let cross = e1.cross(&e2);
+        let area = cross.magnitude() / 2.0;
+
+        // Seen edge on, the triangle covers no solid angle
+        let cos = (&cross * dir).abs() / cross.magnitude();
+        if area <= 0.0 || cos < 1e-9 {
+            return 0.0;
+        }
+
+        dist * dist / (area * cos)
+    }
+}
+
+pub struct RectangularPrism {
+    ts: Vec<Triangle>,
+    color: usize,
+    material: Material,
+}
+
+impl RectangularPrism {
+    pub fn new(origin: Vec3d, width: Float, height: Float, depth: Float, color: usize, material: Material) -> Self {
+        // Negative sizes reach back from the origin, which would turn the box inside out, so build it from its lowest corner
+        let origin = Vec3d::new(origin.x() + width.min(0.0), origin.y() + height.min(0.0), origin.z() + depth.min(0.0));
+        let (width, height, depth) = (width.abs(), height.abs(), depth.abs());
+
+        let mut ts = Vec::new();
+        let p0 = origin.clone();
+        let p1 = &origin + &Vec3d::new(width, 0.0, 0.0);
+        let p2 = &origin + &Vec3d::new(width, height, 0.0);
+        let p3 = &origin + &Vec3d::new(0.0, height, 0.0);
+        let p4 = &origin + &Vec3d::new(0.0, 0.0, depth);
+        let p5 = &origin + &Vec3d::new(width, 0.0, depth);
+        let p6 = &origin + &Vec3d::new(width, height, depth);
+        let p7 = &origin + &Vec3d::new(0.0, height, depth);
+
+        // Each face's corners turn counterclockwise seen from outside, so every normal points out of the box
+        let faces = vec![
+            (&p0, &p3, &p2, &p1), // Front
+            (&p4, &p5, &p6, &p7), // Back
+            (&p0, &p1, &p5, &p4), // Bottom
+            (&p3, &p7, &p6, &p2), // Top
+            (&p0, &p4, &p7, &p3), // Left
+            (&p1, &p2, &p6, &p5), // Right
+        ];
+
+        for (a, b, c, d) in faces {
+            ts.push(Triangle::new([a.clone(), b.clone(), c.clone()], color, material.clone()));
+            ts.push(Triangle::new([a.clone(), c.clone(), d.clone()], color, material.clone()));
+        }
+
+        Self { 
+            color, 
+            material, 
+            ts 
+        }
+    }
+}
+
+impl Object for RectangularPrism {
+    fn name(&self) -> &'static str {
+        "prism"
+    }
+
+    fn get_color(&self) -> &usize {
+        &self.color
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        Some(Bounds::around(self.ts.iter().flat_map(|tri| tri.ps.iter())))
+    }
+
+    // The normal is the face the ray hit, not whichever face is found first at the point, so edges shade correctly
+    fn get_closest_intersection(&self, ray: &Ray, t_range: &Range<Float>) -> Option<HitRecord<'_>> {
+        let mut closest: Option<(&Triangle, Float)> = None;
+        for tri in &self.ts {
+            if let Some(t) = tri.closest_t(ray, t_range) {
+                if closest.is_none_or(|(_, closest_t)| t < closest_t) {
+                    closest = Some((tri, t));
+                }
+            }
+        }
+        closest.map(|(tri, t)| HitRecord::new(ray, t, tri.normal(), &self.material).with_planar_uv())
+    }
+
+    fn triangles(&self) -> Option<Triangles> {
+        let vertices = self.ts.iter().flat_map(|tri| tri.ps.iter().cloned()).collect();
+        Some((vertices, (0..self.ts.len()).map(|i| [3 * i, 3 * i + 1, 3 * i + 2]).collect()))
+    }
+}
\ No newline at end of file
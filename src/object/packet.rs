@@ -0,0 +1,69 @@
+use crate::linalg::{Float, Ray};
+use crate::utils::Range;
+
+use super::{bounds::Bounds, bvh::coord};
+
+/*
+
+Ray Packets
+
+A few rays walked through a hierarchy together, like the camera rays of neighbouring canvas units or the shadow rays
+from points along a ray to the same light. Rays that start close together and head the same way open mostly the same
+boxes, so the packet visits each box once for all of its rays, and loads it once
+
+Before any of its rays are tested against a box, the whole packet is (interval culling): the rays' origins are bounded
+by a box of their own and their directions by a range along each axis, and following that from the origins through the
+slabs of the box bounds where every ray could enter and leave it. When even the earliest entry is past the latest exit
+no ray in the packet hits the box, and it's skipped with a single test. The bounds only hold while every ray heads the
+same way along each axis, so packets whose rays don't are never culled as a whole, and their rays are tested one by one
+
+Division rounds the same way whichever numbers it's given, so the packet's bounds are worked out exactly as a single
+ray's slabs are, and never cull a box one of its rays would have hit
+
+*/
+
+pub(crate) const PACKET_SIZE: usize = 8; // Most rays walked through a hierarchy together
+
+pub(crate) struct Packet {
+    origins: Bounds,
+    dirs: Option<[Range<Float>; 3]> // Range of the rays' directions along each axis, when they all head the same way
+}
+
+impl Packet {
+    pub fn new(rays: &[Ray]) -> Self {
+        let axis = |axis: usize| {
+            let coords = rays.iter().map(|ray| coord(ray.dir(), axis));
+            let range = coords.fold(Range::EMPTY, |range, d| range.union(&Range::new(d, d)));
+            (range.min > 0.0 || range.max < 0.0).then_some(range)
+        };
+
+        Self {
+            origins: Bounds::around(rays.iter().map(Ray::origin)),
+            dirs: axis(0).zip(axis(1)).zip(axis(2)).map(|((x, y), z)| [x, y, z])
+        }
+    }
+
+    // Whether any of the rays could pass through a box within t_range, the range covering every ray's own
+    pub fn may_hit(&self, bounds: &Bounds, t_range: &Range<Float>) -> bool {
+        let Some(dirs) = &self.dirs else {
+            return true;
+        };
+
+        // The same padding as a single ray's slabs get (see Bounds::interval)
+        let pad = if cfg!(feature = "f32") { 1e-4 } else { 1e-9 };
+        let inside = (0..3).fold(*t_range, |inside, axis| {
+            let (lo, hi) = (coord(&bounds.min, axis) - pad, coord(&bounds.max, axis) + pad);
+            let (o, d) = (Range::new(coord(&self.origins.min, axis), coord(&self.origins.max, axis)), dirs[axis]);
+            let (near, far) = if d.min > 0.0 { (lo, hi) } else { (hi, lo) };
+            inside.intersect(&Range::new(extreme(near, &o, &d, Float::min), extreme(far, &o, &d, Float::max)))
+        });
+
+        // NaNs from rays grazing the box aren't taken to miss it
+        !inside.is_empty()
+    }
+}
+
+// The smallest or largest t a ray from within a range of origins and directions meets a plane along an axis at
+fn extreme(plane: Float, o: &Range<Float>, d: &Range<Float>, pick: fn(Float, Float) -> Float) -> Float {
+    [(plane - o.min) / d.min, (plane - o.min) / d.max, (plane - o.max) / d.min, (plane - o.max) / d.max].into_iter().reduce(pick).unwrap()
+}
@@ -0,0 +1,153 @@
+use crate::linalg::{Float, Ray, Vec3d};
+use crate::utils::Range;
+
+use super::{bounds::Bounds, bvh::{BvhBuild, LazyBvh}, HitRecord, Material, Object};
+
+/*
+
+Point Cloud
+
+Scanned points, e.g. from LIDAR or photogrammetry, drawn as small splats. Points that come with a normal are drawn as
+disks facing along it, which join up into a surface, and points without one as spheres. Each point may have its own
+color, and takes the cloud's color otherwise
+
+Clouds can hold millions of points, so they're kept in a bounding volume hierarchy and a ray only tests the points near
+its path
+
+*/
+
+pub struct Splat {
+    center: Vec3d,
+    normal: Option<Vec3d>,
+    color: Option<usize>
+}
+
+impl Splat {
+    pub fn new(center: Vec3d, normal: Option<Vec3d>, color: Option<usize>) -> Self {
+        Self {
+            center,
+            normal: normal.map(|normal| normal.normalize()),
+            color
+        }
+    }
+}
+
+pub struct PointCloud {
+    splats: Vec<Splat>,
+    bvh: LazyBvh,
+    build: BvhBuild,
+    background: bool, // Whether the hierarchy is built on another thread
+    radius: Float, // Radius of every splat
+    color: usize,
+    material: Material
+}
+
+impl PointCloud {
+    pub fn new(splats: Vec<Splat>, radius: Float, color: usize, material: Material) -> Self {
+        Self::built(splats, radius, color, material, BvhBuild::default(), false)
+    }
+
+    // The same cloud with its hierarchy built another way
+    pub fn with_bvh(self, build: BvhBuild) -> Self {
+        if build == self.build {
+            return self;
+        }
+        Self::built(self.splats, self.radius, self.color, self.material, build, self.background)
+    }
+
+    // The same cloud with its hierarchy built on another thread, testing every splat until it's ready
+    pub fn in_background(self) -> Self {
+        Self { bvh: self.bvh.in_background(), background: true, ..self }
+    }
+
+    fn built(splats: Vec<Splat>, radius: Float, color: usize, material: Material, build: BvhBuild, background: bool) -> Self {
+        let pad = Vec3d::new(radius, radius, radius);
+        let bounds: Vec<Bounds> = splats.iter().map(|splat| Bounds { min: &splat.center - &pad, max: &splat.center + &pad }).collect();
+
+        let bvh = LazyBvh::new(bounds, build);
+        Self {
+            bvh: if background { bvh.in_background() } else { bvh },
+            build,
+            background,
+            splats,
+            radius,
+            color,
+            material
+        }
+    }
+
+    // Where a ray hits a single splat, if it does
+    fn intersect_splat(&self, splat: &Splat, ray: &Ray, t_range: &Range<Float>) -> Option<Float> {
+        let to_center = &splat.center - ray.origin();
+        match &splat.normal {
+            Some(normal) => {
+                let approach = normal * ray.dir();
+                if approach.abs() < Float::EPSILON {
+                    return None;
+                }
+                let t = (&to_center * normal) / approach;
+                let within = (&ray.at(t) - &splat.center).magnitude() <= self.radius;
+                (within && t_range.contains(t)).then_some(t)
+            }
+            None => {
+                let a = ray.dir() * ray.dir();
+                let b = &to_center * ray.dir();
+                let c = &to_center * &to_center - self.radius * self.radius;
+                let discnm = b * b - a * c;
+                if discnm < 0.0 {
+                    return None;
+                }
+                let discnm_sqrt = discnm.sqrt();
+                [(b - discnm_sqrt) / a, (b + discnm_sqrt) / a].into_iter().find(|&t| t_range.contains(t))
+            }
+        }
+    }
+
+    // The splat whose surface passes closest to a point on the cloud
+    fn nearest(&self, p: &Vec3d) -> Option<&Splat> {
+        self.bvh.containing(p, self.radius * 1e-6)
+            .map(|i| {
+                let splat = &self.splats[i];
+                let to_p = p - &splat.center;
+                let dist = match &splat.normal {
+                    Some(normal) => (&to_p * normal).abs() + (to_p.magnitude() - self.radius).max(0.0),
+                    None => (to_p.magnitude() - self.radius).abs()
+                };
+                (splat, dist)
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(splat, _)| splat)
+    }
+}
+
+impl Object for PointCloud {
+    fn name(&self) -> &'static str {
+        "point cloud"
+    }
+
+    fn get_color(&self) -> &usize {
+        &self.color
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        self.bvh.bounds().cloned()
+    }
+
+    fn color_at(&self, hit: &HitRecord) -> usize {
+        self.nearest(&hit.point).and_then(|splat| splat.color).unwrap_or(self.color)
+    }
+
+    fn get_closest_intersection(&self, ray: &Ray, t_range: &Range<Float>) -> Option<HitRecord<'_>> {
+        let (i, t) = self.bvh.closest_hit(ray, t_range, |i, t_range| self.intersect_splat(&self.splats[i], ray, t_range))?;
+        let splat = &self.splats[i];
+        let normal = match &splat.normal {
+            Some(normal) => normal.clone(),
+            None => (&ray.at(t) - &splat.center).normalize()
+        };
+        Some(HitRecord::new(ray, t, normal, &self.material))
+    }
+}
@@ -0,0 +1,142 @@
+use crate::linalg::{Float, Ray, Vec3d};
+use crate::utils::Range;
+
+use super::{HitRecord, Material, Object, Triangles};
+
+/*
+
+Convex Polyhedron
+
+A solid bounded by a set of planes, e.g. a box, a frustum or a room, without splitting its faces into triangles. Each
+plane keeps the half of space behind it, and the solid is what all of them keep
+
+A ray is clipped against every plane in turn. It enters the solid once it has crossed all the planes it approaches
+from the outside and leaves at the first plane it crosses from the inside, so it hits the solid if it enters before it
+leaves
+
+*/
+
+pub struct Plane {
+    normal: Vec3d, // Unit normal, pointing out of the solid
+    offset: Float  // Points on the plane satisfy normal * p = offset
+}
+
+impl Plane {
+    // A plane through a point, with its normal pointing out of the solid
+    pub fn new(point: Vec3d, normal: Vec3d) -> Self {
+        let normal = normal.normalize();
+        Self {
+            offset: &normal * &point,
+            normal
+        }
+    }
+
+    // Signed distance from the plane, positive outside the solid
+    fn distance(&self, p: &Vec3d) -> Float {
+        &self.normal * p - self.offset
+    }
+}
+
+pub struct ConvexPolyhedron {
+    planes: Vec<Plane>,
+    color: usize,
+    material: Material
+}
+
+impl ConvexPolyhedron {
+    pub fn new(planes: Vec<Plane>, color: usize, material: Material) -> Self {
+        Self {
+            planes,
+            color,
+            material
+        }
+    }
+}
+
+impl Object for ConvexPolyhedron {
+    fn name(&self) -> &'static str {
+        "polyhedron"
+    }
+
+    fn get_color(&self) -> &usize {
+        &self.color
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    // The normal is that of the face the ray enters or leaves through
+    fn get_closest_intersection(&self, ray: &Ray, t_range: &Range<Float>) -> Option<HitRecord<'_>> {
+        let mut inside = Range::<Float>::UNIVERSE;
+        let (mut entry, mut exit): (Option<&Plane>, Option<&Plane>) = (None, None);
+
+        for plane in &self.planes {
+            let approach = &plane.normal * ray.dir();
+            let dist = -plane.distance(ray.origin());
+
+            if approach == 0.0 {
+                // Parallel to the plane, so the ray is either always in front of it or always behind it
+                if dist < 0.0 {
+                    return None;
+                }
+                continue;
+            }
+
+            let t = dist / approach;
+            if approach < 0.0 && t > inside.min {
+                (inside.min, entry) = (t, Some(plane)); // Entering through the plane
+            } else if approach > 0.0 && t < inside.max {
+                (inside.max, exit) = (t, Some(plane)); // Leaving through the plane
+            }
+            if inside.is_empty() {
+                return None;
+            }
+        }
+
+        // A ray starting inside hits the solid where it leaves. Sides without a plane are open, and never hit
+        [(inside.min, entry), (inside.max, exit)].into_iter()
+            .find(|&(t, _)| t.is_finite() && t_range.contains(t))
+            .and_then(|(t, plane)| Some(HitRecord::new(ray, t, plane?.normal.clone(), &self.material).with_planar_uv()))
+    }
+
+    // The corners are where three planes meet inside the solid, and each face the corners on its plane, in order around
+    // their middle. Solids open on a side come out without it, and with the faces reaching off towards it cut short
+    fn triangles(&self) -> Option<Triangles> {
+        let eps = if cfg!(feature = "f32") { 1e-3 } else { 1e-7 };
+        let mut vertices: Vec<Vec3d> = Vec::new();
+        for (i, a) in self.planes.iter().enumerate() {
+            for (j, b) in self.planes.iter().enumerate().skip(i + 1) {
+                for c in &self.planes[j + 1..] {
+                    let det = &a.normal * &b.normal.cross(&c.normal);
+                    if det.abs() < eps {
+                        continue;
+                    }
+                    let p = &(&(&b.normal.cross(&c.normal) * a.offset) + &(&c.normal.cross(&a.normal) * b.offset)) + &(&a.normal.cross(&b.normal) * c.offset);
+                    let p = &p * (1.0 / det);
+                    if self.planes.iter().all(|plane| plane.distance(&p) <= eps) && vertices.iter().all(|v| (v - &p).magnitude() > eps) {
+                        vertices.push(p);
+                    }
+                }
+            }
+        }
+
+        let mut faces = Vec::new();
+        for plane in &self.planes {
+            let mut corners: Vec<usize> = (0..vertices.len()).filter(|&i| plane.distance(&vertices[i]).abs() <= eps).collect();
+            if corners.len() < 3 {
+                continue;
+            }
+            let middle = &corners.iter().fold(Vec3d::new(0.0, 0.0, 0.0), |sum, &i| &sum + &vertices[i]) * (1.0 / corners.len() as Float);
+            let across = (&vertices[corners[0]] - &middle).normalize();
+            let up = plane.normal.cross(&across);
+            let angle = |i: usize| {
+                let to = &vertices[i] - &middle;
+                (&to * &up).atan2(&to * &across)
+            };
+            corners.sort_by(|&a, &b| angle(a).total_cmp(&angle(b)));
+            faces.extend(corners[1..].windows(2).map(|pair| [corners[0], pair[0], pair[1]]));
+        }
+        Some((vertices, faces))
+    }
+}
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use crate::linalg::{Float, Ray, Vec3d};
+use crate::utils::Range;
+
+use super::{bounds::Bounds, ConvexPolyhedron, HitRecord, Material, Object, RectangularPrism, RenderFlags, Sphere, Triangle, Triangles};
+
+/*
+
+Primitive
+
+How a scene stores its objects. The simple shapes are held in the enum itself, so a scene's objects sit side by side
+in one array and are called directly rather than through a vtable. Anything else, i.e. larger objects like meshes and
+objects defined outside the crate, is kept behind a pointer as Other, so it doesn't make every primitive as large as it
+is. The pointer is shared, so geometry loaded once, e.g. a large mesh, can be placed in several scenes, and in
+instances, without copying it
+
+*/
+
+pub enum Primitive {
+    Sphere(Sphere),
+    Triangle(Triangle),
+    Prism(RectangularPrism),
+    Polyhedron(ConvexPolyhedron),
+    Other(Arc<dyn Object>)
+}
+
+// Call the same method on whichever object the primitive holds
+macro_rules! dispatch {
+    ($primitive:expr, $obj:ident => $call:expr) => {
+        match $primitive {
+            Primitive::Sphere($obj) => $call,
+            Primitive::Triangle($obj) => $call,
+            Primitive::Prism($obj) => $call,
+            Primitive::Polyhedron($obj) => $call,
+            Primitive::Other($obj) => $call
+        }
+    };
+}
+
+impl Object for Primitive {
+    fn name(&self) -> &'static str {
+        dispatch!(self, obj => obj.name())
+    }
+
+    fn get_color(&self) -> &usize {
+        dispatch!(self, obj => obj.get_color())
+    }
+
+    fn get_material(&self) -> &Material {
+        dispatch!(self, obj => obj.get_material())
+    }
+
+    fn render_flags(&self) -> RenderFlags {
+        dispatch!(self, obj => obj.render_flags())
+    }
+
+    fn color_at(&self, hit: &HitRecord) -> usize {
+        dispatch!(self, obj => obj.color_at(hit))
+    }
+
+    fn get_closest_intersection(&self, ray: &Ray, t_range: &Range<Float>) -> Option<HitRecord<'_>> {
+        dispatch!(self, obj => obj.get_closest_intersection(ray, t_range))
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        dispatch!(self, obj => obj.bounds())
+    }
+
+    fn sample_direction(&self, from: &Vec3d, view: &Vec3d, u: (Float, Float)) -> Option<(Vec3d, Float)> {
+        dispatch!(self, obj => obj.sample_direction(from, view, u))
+    }
+
+    fn direction_pdf(&self, from: &Vec3d, view: &Vec3d, dir: &Vec3d) -> Float {
+        dispatch!(self, obj => obj.direction_pdf(from, view, dir))
+    }
+
+    fn triangles(&self) -> Option<Triangles> {
+        dispatch!(self, obj => obj.triangles())
+    }
+}
+
+impl From<Sphere> for Primitive {
+    fn from(sphere: Sphere) -> Self {
+        Primitive::Sphere(sphere)
+    }
+}
+
+impl From<Triangle> for Primitive {
+    fn from(triangle: Triangle) -> Self {
+        Primitive::Triangle(triangle)
+    }
+}
+
+impl From<RectangularPrism> for Primitive {
+    fn from(prism: RectangularPrism) -> Self {
+        Primitive::Prism(prism)
+    }
+}
+
+impl From<ConvexPolyhedron> for Primitive {
+    fn from(polyhedron: ConvexPolyhedron) -> Self {
+        Primitive::Polyhedron(polyhedron)
+    }
+}
+
+impl From<Arc<dyn Object>> for Primitive {
+    fn from(obj: Arc<dyn Object>) -> Self {
+        Primitive::Other(obj)
+    }
+}
+
+impl From<Box<dyn Object>> for Primitive {
+    fn from(obj: Box<dyn Object>) -> Self {
+        Primitive::Other(obj.into())
+    }
+}
@@ -0,0 +1,265 @@
+use std::{cmp::Ordering, collections::{BinaryHeap, HashMap}};
+
+use crate::linalg::{Float, Vec3d};
+
+/*
+
+Mesh Simplification
+
+Reduces a mesh to fewer triangles by collapsing edges, merging their two corners into one, cheapest first (quadric
+error metrics, Garland and Heckbert). Every corner keeps a quadric, the sum of the squared distances to the planes of
+the triangles around it, and an edge costs the quadric of its corners measured at the point they'd merge to: a corner
+moved within a flat area costs nothing, and one moved off a sharp edge costs a lot, so flat areas lose their triangles
+first and the shape's outline survives longest. Merged corners go to whichever of the two corners or their midpoint
+costs least
+
+Open edges (used by one triangle, e.g. around the holes of a scan) add a steep plane standing along them, so the holes
+keep their shape. Collapses that would turn a triangle over are skipped
+
+*/
+
+const BOUNDARY_WEIGHT: Float = 1000.0;
+
+// The symmetric 4x4 matrix of a sum of squared distances to planes, upper triangle row by row
+#[derive(Clone, Copy, Default)]
+struct Quadric([Float; 10]);
+
+impl Quadric {
+    // Squared distance to the plane ax + by + cz + d = 0, for a unit normal (a, b, c), times weight
+    fn plane([a, b, c, d]: [Float; 4], weight: Float) -> Self {
+        Self([a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d].map(|q| q * weight))
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        let mut sum = self.0;
+        for (s, o) in sum.iter_mut().zip(other.0) {
+            *s += o;
+        }
+        Self(sum)
+    }
+
+    // The quadric at a point
+    fn error(&self, p: &Vec3d) -> Float {
+        let [a, b, c, d, e, f, g, h, i, j] = self.0;
+        let (x, y, z) = (p.x(), p.y(), p.z());
+        a * x * x + 2.0 * b * x * y + 2.0 * c * x * z + 2.0 * d * x
+            + e * y * y + 2.0 * f * y * z + 2.0 * g * y
+            + h * z * z + 2.0 * i * z
+            + j
+    }
+}
+
+// A collapse waiting in the queue, valid while neither corner has changed since it was costed
+struct Collapse {
+    cost: Float,
+    corners: [usize; 2],
+    versions: [u32; 2],
+    to: Vec3d
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Reversed, so the queue pops the cheapest collapse first
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost).then_with(|| other.corners.cmp(&self.corners))
+    }
+}
+
+struct Simplifier {
+    vertices: Vec<Vec3d>,
+    faces: Vec<[usize; 3]>,
+    alive: Vec<bool>,              // Whether each face is still there
+    quadrics: Vec<Quadric>,
+    versions: Vec<u32>,            // How many times each corner has changed, to spot outdated collapses
+    merged_into: Vec<usize>,       // Each corner, or the corner it was merged into
+    corner_faces: Vec<Vec<usize>>, // The faces around each corner, including faces since removed
+    queue: BinaryHeap<Collapse>
+}
+
+// A mesh's corners and triangles reduced to about target triangles, its corners renumbered to those still used
+pub fn simplify(vertices: Vec<Vec3d>, faces: Vec<[usize; 3]>, target: usize) -> (Vec<Vec3d>, Vec<[usize; 3]>) {
+    if faces.len() <= target {
+        return (vertices, faces);
+    }
+
+    let mut simplifier = Simplifier::new(vertices, faces);
+    let mut remaining = simplifier.alive.iter().filter(|&&alive| alive).count();
+    while remaining > target {
+        let Some(collapse) = simplifier.queue.pop() else {
+            break;
+        };
+        remaining -= simplifier.collapse(collapse);
+    }
+    simplifier.finish()
+}
+
+impl Simplifier {
+    fn new(vertices: Vec<Vec3d>, faces: Vec<[usize; 3]>) -> Self {
+        let n = vertices.len();
+        let mut quadrics = vec![Quadric::default(); n];
+        let mut corner_faces = vec![Vec::new(); n];
+        let mut edge_faces: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut alive = vec![true; faces.len()];
+
+        for (f, face) in faces.iter().enumerate() {
+            let Some((normal, area)) = Self::normal_and_area(&vertices, face) else {
+                alive[f] = false; // Already degenerate
+                continue;
+            };
+            let plane = Quadric::plane(Self::plane(&normal, &vertices[face[0]]), area);
+            for (k, &corner) in face.iter().enumerate() {
+                quadrics[corner] = quadrics[corner].add(&plane);
+                corner_faces[corner].push(f);
+                let next = face[(k + 1) % 3];
+                *edge_faces.entry((corner.min(next), corner.max(next))).or_insert(0) += 1;
+            }
+        }
+
+        // Open edges are held in place by a plane through them, at right angles to their face
+        for face in faces.iter().zip(&alive).filter(|(_, &alive)| alive).map(|(face, _)| face) {
+            let Some((normal, _)) = Self::normal_and_area(&vertices, face) else {
+                continue;
+            };
+            for k in 0..3 {
+                let (a, b) = (face[k], face[(k + 1) % 3]);
+                if edge_faces[&(a.min(b), a.max(b))] != 1 {
+                    continue;
+                }
+                let edge = &vertices[b] - &vertices[a];
+                let side = edge.cross(&normal);
+                if side.magnitude() <= 0.0 {
+                    continue;
+                }
+                let plane = Quadric::plane(Self::plane(&side.normalize(), &vertices[a]), BOUNDARY_WEIGHT * edge.magnitude() * edge.magnitude());
+                quadrics[a] = quadrics[a].add(&plane);
+                quadrics[b] = quadrics[b].add(&plane);
+            }
+        }
+
+        let mut simplifier = Self {
+            vertices,
+            faces,
+            alive,
+            quadrics,
+            versions: vec![0; n],
+            merged_into: (0..n).collect(),
+            corner_faces,
+            queue: BinaryHeap::new()
+        };
+        for (a, b) in edge_faces.into_keys() {
+            simplifier.push(a, b);
+        }
+        simplifier
+    }
+
+    // Unit normal and area of a face, if it has any area
+    fn normal_and_area(vertices: &[Vec3d], [a, b, c]: &[usize; 3]) -> Option<(Vec3d, Float)> {
+        let cross = (&vertices[*b] - &vertices[*a]).cross(&(&vertices[*c] - &vertices[*a]));
+        let twice_area = cross.magnitude();
+        (twice_area > 0.0).then(|| (&cross * (1.0 / twice_area), twice_area / 2.0))
+    }
+
+    fn plane(normal: &Vec3d, p: &Vec3d) -> [Float; 4] {
+        [normal.x(), normal.y(), normal.z(), -(normal * p)]
+    }
+
+    // Queue the collapse of the edge between two corners, at its cheapest point
+    fn push(&mut self, a: usize, b: usize) {
+        let quadric = self.quadrics[a].add(&self.quadrics[b]);
+        let (pa, pb) = (&self.vertices[a], &self.vertices[b]);
+        let candidates = [pa.clone(), pb.clone(), &(pa + pb) * 0.5];
+        let (cost, to) = candidates.into_iter()
+            .map(|p| (quadric.error(&p), p))
+            .min_by(|(x, _), (y, _)| x.total_cmp(y))
+            .unwrap();
+        self.queue.push(Collapse { cost, corners: [a, b], versions: [self.versions[a], self.versions[b]], to });
+    }
+
+    // Merge the corners of an edge if it's still there, returning how many faces that removed
+    fn collapse(&mut self, Collapse { corners: [a, b], versions, to, .. }: Collapse) -> usize {
+        let current = |c: usize| self.merged_into[c] == c;
+        if !current(a) || !current(b) || versions != [self.versions[a], self.versions[b]] {
+            return 0;
+        }
+        if self.flips(a, b, &to) || self.flips(b, a, &to) {
+            return 0;
+        }
+
+        // b is merged into a, and the faces they shared disappear
+        self.vertices[a] = to;
+        self.quadrics[a] = self.quadrics[a].add(&self.quadrics[b]);
+        self.merged_into[b] = a;
+        self.versions[a] += 1;
+        self.versions[b] += 1;
+
+        let mut removed = 0;
+        for f in std::mem::take(&mut self.corner_faces[b]) {
+            if !self.alive[f] {
+                continue;
+            }
+            let face = &mut self.faces[f];
+            if face.contains(&a) {
+                self.alive[f] = false;
+                removed += 1;
+            } else {
+                face.iter_mut().filter(|c| **c == b).for_each(|c| *c = a);
+                self.corner_faces[a].push(f);
+            }
+        }
+        self.corner_faces[a].retain(|&f| self.alive[f]);
+
+        // Every edge around the merged corner now costs something else
+        let mut neighbours: Vec<usize> = self.corner_faces[a].iter().flat_map(|&f| self.faces[f]).filter(|&c| c != a).collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        for c in neighbours {
+            self.push(a, c);
+        }
+        removed
+    }
+
+    // Whether moving corner from to a point turns over any of its faces that don't also use other
+    fn flips(&self, from: usize, other: usize, to: &Vec3d) -> bool {
+        self.corner_faces[from].iter()
+            .filter(|&&f| self.alive[f] && !self.faces[f].contains(&other))
+            .any(|&f| {
+                let face = self.faces[f];
+                let Some((before, _)) = Self::normal_and_area(&self.vertices, &face) else {
+                    return false;
+                };
+                let moved = face.map(|c| if c == from { to.clone() } else { self.vertices[c].clone() });
+                match Self::normal_and_area(&moved, &[0, 1, 2]) {
+                    Some((after, _)) => &before * &after <= 0.0,
+                    None => true
+                }
+            })
+    }
+
+    fn finish(self) -> (Vec<Vec3d>, Vec<[usize; 3]>) {
+        let mut renumbered = vec![usize::MAX; self.vertices.len()];
+        let mut vertices = Vec::new();
+        let faces = self.faces.iter().zip(&self.alive).filter(|(_, &alive)| alive).map(|(face, _)| {
+            face.map(|c| {
+                if renumbered[c] == usize::MAX {
+                    renumbered[c] = vertices.len();
+                    vertices.push(self.vertices[c].clone());
+                }
+                renumbered[c]
+            })
+        }).collect();
+        (vertices, faces)
+    }
+}
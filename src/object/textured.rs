@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::linalg::{Float, Ray, Vec3d};
+use crate::texture::Texture;
+use crate::utils::Range;
+
+use super::{bounds::Bounds, HitRecord, Material, Object, RenderFlags, Triangles};
+
+/*
+
+Textured
+
+An object painted with an image, looked up by the uv coordinates of wherever a ray hits it. Spheres wrap the image
+around by longitude and latitude once, and flat faces (triangles, boxes, polyhedra and meshes) tile it once every unit
+of distance, so repeat sets how many times it tiles within that. Meshes given texture coordinates use those instead.
+Objects without uv coordinates keep their own color
+
+Triplanar mapping needs no uv coordinates: the image is projected along each axis, tiling once every unit of distance,
+and the three are blended by how squarely the surface faces each axis, so terrain, CSG results and anything else
+without uvs can be textured without visible stretching
+
+*/
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Mapping {
+    #[default]
+    Uv,
+    Triplanar { sharpness: Float } // Higher narrows the blend where projections meet, 1 blends by the normal itself
+}
+
+pub struct Textured {
+    object: Arc<dyn Object>,
+    texture: Arc<Texture>,
+    repeat: Float, // Times the texture repeats over a unit of the object's uv coordinates
+    mapping: Mapping
+}
+
+impl Textured {
+    pub fn new(object: Arc<dyn Object>, texture: Arc<Texture>, repeat: Float) -> Self {
+        Self {
+            object,
+            texture,
+            repeat,
+            mapping: Mapping::Uv
+        }
+    }
+
+    pub fn with_mapping(mut self, mapping: Mapping) -> Self {
+        self.mapping = mapping;
+        self
+    }
+
+    // Blend of the texture projected along x, y and z onto the hit point, weighted by the normal
+    fn triplanar(&self, hit: &HitRecord, sharpness: Float) -> usize {
+        let (p, n) = (&hit.point * self.repeat, &hit.normal);
+        let weights = [n.x().abs(), n.y().abs(), n.z().abs()].map(|w| w.powf(sharpness));
+        let total: Float = weights.iter().sum();
+        if total <= 0.0 {
+            return self.object.color_at(hit);
+        }
+
+        // Each projection is mirrored on the negative side so images read the right way round from either side
+        let uvs = [
+            (p.z() * -n.x().signum(), p.y()),
+            (p.x(), p.z() * -n.y().signum()),
+            (p.x() * n.z().signum(), p.y())
+        ];
+        let mut rgb = [0.0; 3];
+        for (uv, weight) in uvs.into_iter().zip(weights) {
+            if weight > 0.0 {
+                let texel = Color::to_linear(self.texture.sample(uv));
+                for (c, t) in rgb.iter_mut().zip(texel) {
+                    *c += t * weight / total;
+                }
+            }
+        }
+        Color::from_linear(rgb)
+    }
+}
+
+impl Object for Textured {
+    fn name(&self) -> &'static str {
+        self.object.name()
+    }
+
+    fn get_color(&self) -> &usize {
+        self.object.get_color()
+    }
+
+    fn get_material(&self) -> &Material {
+        self.object.get_material()
+    }
+
+    fn render_flags(&self) -> RenderFlags {
+        self.object.render_flags()
+    }
+
+    fn color_at(&self, hit: &HitRecord) -> usize {
+        match (self.mapping, hit.uv) {
+            (Mapping::Triplanar { sharpness }, _) => self.triplanar(hit, sharpness),
+            (Mapping::Uv, Some((u, v))) => self.texture.sample((u * self.repeat, v * self.repeat)),
+            (Mapping::Uv, None) => self.object.color_at(hit)
+        }
+    }
+
+    fn get_closest_intersection(&self, ray: &Ray, t_range: &Range<Float>) -> Option<HitRecord<'_>> {
+        self.object.get_closest_intersection(ray, t_range)
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        self.object.bounds()
+    }
+
+    fn sample_direction(&self, from: &Vec3d, view: &Vec3d, u: (Float, Float)) -> Option<(Vec3d, Float)> {
+        self.object.sample_direction(from, view, u)
+    }
+
+    fn direction_pdf(&self, from: &Vec3d, view: &Vec3d, dir: &Vec3d) -> Float {
+        self.object.direction_pdf(from, view, dir)
+    }
+    fn triangles(&self) -> Option<Triangles> {
+        self.object.triangles()
+    }
+}
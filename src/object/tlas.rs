@@ -0,0 +1,175 @@
+use std::cell::Cell;
+
+use crate::linalg::{Float, Ray};
+use crate::utils::Range;
+
+use super::{bounds::Bounds, bvh::{Bvh, BvhBuild}, packet::PACKET_SIZE, HitRecord, Object, RayKind};
+
+/*
+
+Top Level Acceleration Structure
+
+A bounding volume hierarchy over the objects of a scene, so a ray only tests the objects near its path. Objects made of
+many parts, like meshes, keep a hierarchy of their own (the bottom level) that is built once with the object. The top
+level only covers each object's box, so it's quick to rebuild whenever objects are replaced, and quicker still to refit
+when they've only moved
+
+Objects without a box are tested by every ray, and objects whose render flags hide them from a kind of ray are skipped
+
+Neighbouring primary rays usually hit the same object, so rays traced with a traversal context for primary rays (one
+for each render thread) test the object the last of them hit first. The hit bounds the rest of the search from the
+start, and boxes behind it are never opened
+
+Coherent rays found together, like the camera rays of a tile or shadow rays towards the same light, can walk the
+hierarchy as a packet instead (see packet.rs), finding the same hits as they would one at a time
+
+*/
+
+// What the rays traced one after another with it pass on to each other
+#[derive(Clone, Default)]
+pub(crate) struct TraversalContext {
+    primary: bool,          // Whether its rays are primary rays, a coherent bundle from the camera
+    last_hit: Option<usize> // The object the last of its primary rays hit
+}
+
+impl TraversalContext {
+    // A context for primary rays, e.g. the camera rays a render thread traces
+    pub fn primary() -> Self {
+        Self { primary: true, last_hit: None }
+    }
+}
+
+pub(crate) struct Tlas {
+    bvh: Bvh,
+    bounded: Vec<usize>,  // The objects in the hierarchy, in the order their boxes were given to it
+    unbounded: Vec<usize> // The objects without a box
+}
+
+impl Tlas {
+    pub fn new(objs: &[impl Object]) -> Self {
+        let mut bounded = Vec::new();
+        let mut bounds: Vec<Bounds> = Vec::new();
+        let mut unbounded = Vec::new();
+        for (i, obj) in objs.iter().enumerate() {
+            match obj.bounds() {
+                Some(b) => {
+                    bounded.push(i);
+                    bounds.push(b);
+                }
+                None => unbounded.push(i)
+            }
+        }
+
+        Self {
+            bvh: Bvh::new(&bounds, BvhBuild::Sah),
+            bounded,
+            unbounded
+        }
+    }
+
+    // Fit the hierarchy to where the objects are now, rebuilding it if objects were added or removed, or gained or lost
+    // a box
+    pub fn refit(&mut self, objs: &[impl Object]) {
+        let bounds: Option<Vec<Bounds>> = self.bounded.iter().map(|&i| objs.get(i)?.bounds()).collect();
+        let same = objs.len() == self.bounded.len() + self.unbounded.len() && self.unbounded.iter().all(|&i| objs[i].bounds().is_none());
+        match bounds {
+            Some(bounds) if same => self.bvh.refit(&bounds),
+            _ => *self = Self::new(objs)
+        }
+    }
+
+    // The index of the closest object a kind of ray sees within t_range (excluding its far end), and where the ray hits it
+    // Ties go to the object listed first, so touching objects look the same however the hierarchy was built
+    pub fn closest_intersection<'a>(&self, objs: &'a [impl Object], ray: &Ray, t_range: &Range<Float>, kind: RayKind, context: &mut TraversalContext) -> Option<(usize, HitRecord<'a>)> {
+        let primary = context.primary;
+        let closest: Cell<Option<(usize, Float)>> = Cell::new(None);
+        let record: Cell<Option<HitRecord>> = Cell::new(None);
+        let hit = |i: usize, range: &Range<Float>| {
+            let hit = visible_hit(&objs[i], ray, range, t_range.max, kind)?;
+            let t = hit.t;
+            let closer = closest.get().is_none_or(|(closest_i, closest_t)| t < closest_t || (t == closest_t && i < closest_i));
+            closer.then(|| {
+                closest.set(Some((i, t)));
+                record.set(Some(hit));
+                t
+            })
+        };
+
+        let so_far = || Range::new(t_range.min, closest.get().map_or(t_range.max, |(_, t)| t));
+
+        // The object tested first isn't tested again
+        let first = context.last_hit.filter(|_| primary).filter(|&i| i < objs.len());
+        if let Some(i) = first {
+            hit(i, t_range);
+        }
+        self.bvh.closest_hit(ray, &so_far(), |part, range| Some(self.bounded[part]).filter(|&i| Some(i) != first).and_then(|i| hit(i, range)));
+        for &i in self.unbounded.iter().filter(|&&i| Some(i) != first) {
+            hit(i, &so_far());
+        }
+
+        if primary {
+            context.last_hit = closest.get().map(|(i, _)| i);
+        }
+        Some((closest.get()?.0, record.into_inner()?))
+    }
+
+    // closest_intersection for each ray of a packet of up to PACKET_SIZE rays, each within its own t_range
+    pub fn packet_intersections<'a>(&self, objs: &'a [impl Object], rays: &[Ray], t_ranges: &[Range<Float>], kind: RayKind) -> [Option<(usize, HitRecord<'a>)>; PACKET_SIZE] {
+        let mut records: [Option<HitRecord>; PACKET_SIZE] = std::array::from_fn(|_| None);
+        let closest = self.packet_hits(objs, rays, t_ranges, kind, false, |lane, hit| records[lane] = Some(hit));
+        std::array::from_fn(|lane| Some((closest[lane]?.0, records[lane].take()?)))
+    }
+
+    // Whether each ray of a packet of up to PACKET_SIZE rays sees anything within its own t_range, e.g. for shadow rays
+    pub fn packet_occluded(&self, objs: &[impl Object], rays: &[Ray], t_ranges: &[Range<Float>], kind: RayKind) -> [bool; PACKET_SIZE] {
+        self.packet_hits(objs, rays, t_ranges, kind, true, |_, _| ()).map(|hit| hit.is_some())
+    }
+
+    // The closest object each ray of a packet sees (or with any set, the first found), and its t, passing where each ray
+    // hits it to found as the search goes
+    fn packet_hits<'a>(&self, objs: &'a [impl Object], rays: &[Ray], t_ranges: &[Range<Float>], kind: RayKind, any: bool, mut found: impl FnMut(usize, HitRecord<'a>)) -> [Option<(usize, Float)>; PACKET_SIZE] {
+        let lanes = rays.len().min(PACKET_SIZE);
+        let mut closest: [Option<(usize, Float)>; PACKET_SIZE] = [None; PACKET_SIZE];
+        let mut hit = |lane: usize, i: usize, range: &Range<Float>, closest: &mut [Option<(usize, Float)>]| {
+            let hit = visible_hit(&objs[i], &rays[lane], range, t_ranges[lane].max, kind)?;
+            let t = hit.t;
+            let closer = closest[lane].is_none_or(|(closest_i, closest_t)| t < closest_t || (t == closest_t && i < closest_i));
+            closer.then(|| {
+                closest[lane] = Some((i, t));
+                found(lane, hit);
+                t
+            })
+        };
+
+        let bvh_hits = self.bvh.packet_hits(&rays[..lanes], t_ranges, any, |lane, part, range| hit(lane, self.bounded[part], range, &mut closest));
+        for lane in 0..lanes {
+            if any && bvh_hits[lane].is_some() {
+                continue;
+            }
+            for &i in &self.unbounded {
+                let so_far = Range::new(t_ranges[lane].min, closest[lane].map_or(t_ranges[lane].max, |(_, t)| t));
+                if hit(lane, i, &so_far, &mut closest).is_some() && any {
+                    break;
+                }
+            }
+        }
+        closest
+    }
+}
+
+// Where a kind of ray first hits an object within range, if the object's render flags let the ray see it, and the hit is
+// before max (the far end of the whole search, excluded). Single-sided materials are seen through from behind, to
+// whatever part of the object beyond faces the ray
+fn visible_hit<'a>(obj: &'a impl Object, ray: &Ray, range: &Range<Float>, max: Float, kind: RayKind) -> Option<HitRecord<'a>> {
+    if !obj.render_flags().sees(kind) {
+        return None;
+    }
+    let mut hit = obj.get_closest_intersection(ray, range)?;
+    while !hit.front_face && !hit.material.double_sided() {
+        hit = obj.get_closest_intersection(ray, &Range::new(hit.t.next_up(), range.max))?;
+    }
+    if hit.t >= max {
+        return None;
+    }
+    Some(hit)
+}
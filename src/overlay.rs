@@ -0,0 +1,231 @@
+use std::{collections::HashMap, time::Instant};
+
+use crate::{config::MAX_DEPTH, display::{Display, Key}, linalg::Float};
+
+use egui::{epaint::{ClippedPrimitive, Primitive, Vertex}, Color32, ImageData, Pos2, Rect, TextureId};
+
+/*
+
+Settings Overlay
+
+An egui panel drawn directly into the screen's pixel buffer. egui produces triangle meshes, which are rasterized here
+in software, so no GPU backend is needed. Press F1 to show or hide the panel.
+
+*/
+
+// The values the panel can edit. The renderer fills these in before every frame and applies them afterwards
+#[derive(Clone, PartialEq)]
+pub struct OverlaySettings {
+    pub num_samples: usize,
+    pub fixed_sample_pattern: bool, // Rotated grid instead of random jitter
+    pub max_depth: u32,
+    pub fov: Float,
+    pub exposure: Float,
+    pub fxaa: bool,
+    pub sharpen: bool,    // Contrast-adaptive sharpening
+    pub sharpness: Float,
+    pub lights: Vec<(&'static str, Float)>, // Name and intensity of every light source
+    pub hovered: Option<String> // The object under the crosshair while it's shown. Only read by the panel
+}
+
+// A texture uploaded by egui (e.g. the font atlas)
+struct Texture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color32>
+}
+
+pub struct Overlay {
+    ctx: egui::Context,
+    textures: HashMap<TextureId, Texture>,
+    primitives: Vec<ClippedPrimitive>,
+    visible: bool,
+    toggle_was_down: bool,
+    mouse_was_down: bool,
+    start: Instant
+}
+
+impl Default for Overlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Overlay {
+    pub fn new() -> Self {
+        Self {
+            ctx: egui::Context::default(),
+            textures: HashMap::new(),
+            primitives: Vec::new(),
+            visible: true,
+            toggle_was_down: false,
+            mouse_was_down: false,
+            start: Instant::now()
+        }
+    }
+
+    // Whether the pointer is over the panel, or dragging one of its controls
+    pub fn wants_pointer(&self) -> bool {
+        self.visible && (self.ctx.is_pointer_over_area() || self.ctx.is_using_pointer())
+    }
+
+    // Feed window input to egui, lay out the panel and record the meshes to paint
+    pub fn update(&mut self, display: &dyn Display, width: usize, height: usize, settings: &mut OverlaySettings) {
+        let toggle_down = display.keys_down().contains(&Key::F1);
+        if toggle_down && !self.toggle_was_down {
+            self.visible = !self.visible;
+        }
+        self.toggle_was_down = toggle_down;
+
+        if !self.visible {
+            self.primitives.clear();
+            return;
+        }
+
+        let mut input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, egui::vec2(width as f32, height as f32))),
+            time: Some(self.start.elapsed().as_secs_f64()),
+            ..Default::default()
+        };
+
+        if let Some((x, y)) = display.mouse_pos() {
+            let pos = Pos2::new(x, y);
+            input.events.push(egui::Event::PointerMoved(pos));
+
+            let mouse_down = display.mouse_down();
+            if mouse_down != self.mouse_was_down {
+                input.events.push(egui::Event::PointerButton {
+                    pos,
+                    button: egui::PointerButton::Primary,
+                    pressed: mouse_down,
+                    modifiers: egui::Modifiers::default()
+                });
+            }
+            self.mouse_was_down = mouse_down;
+        } else {
+            input.events.push(egui::Event::PointerGone);
+        }
+
+        let output = self.ctx.run(input, |ctx| {
+            egui::Window::new("Settings").default_pos(Pos2::new(10.0, 10.0)).show(ctx, |ui| {
+                ui.add(egui::Slider::new(&mut settings.num_samples, 1..=64).text("Samples"));
+                ui.checkbox(&mut settings.fixed_sample_pattern, "Fixed sample pattern");
+                ui.add(egui::Slider::new(&mut settings.max_depth, 0..=MAX_DEPTH).text("Bounce depth"));
+                ui.add(egui::Slider::new(&mut settings.fov, 20.0..=120.0).text("FOV"));
+                ui.add(egui::Slider::new(&mut settings.exposure, 0.0..=4.0).text("Exposure"));
+                ui.checkbox(&mut settings.fxaa, "FXAA");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut settings.sharpen, "Sharpen");
+                    ui.add_enabled(settings.sharpen, egui::Slider::new(&mut settings.sharpness, 0.0..=1.0));
+                });
+
+                ui.separator();
+
+                for (i, (name, intensity)) in settings.lights.iter_mut().enumerate() {
+                    ui.push_id(i, |ui| {
+                        ui.add(egui::Slider::new(intensity, 0.0..=2.0).text(*name));
+                    });
+                }
+
+                if let Some(hovered) = &settings.hovered {
+                    ui.separator();
+                    ui.label(format!("Under crosshair: {}", hovered));
+                }
+            });
+        });
+
+        for (id, delta) in output.textures_delta.set {
+            let ImageData::Color(image) = delta.image;
+            let [patch_width, patch_height] = image.size;
+
+            match delta.pos {
+                // Update a region of an existing texture
+                Some([x, y]) => {
+                    if let Some(texture) = self.textures.get_mut(&id) {
+                        for row in 0..patch_height {
+                            for col in 0..patch_width {
+                                texture.pixels[(y + row) * texture.width + x + col] = image.pixels[row * patch_width + col];
+                            }
+                        }
+                    }
+                }
+                None => {
+                    self.textures.insert(id, Texture { width: patch_width, height: patch_height, pixels: image.pixels.clone() });
+                }
+            }
+        }
+        for id in output.textures_delta.free {
+            self.textures.remove(&id);
+        }
+
+        self.primitives = self.ctx.tessellate(output.shapes, output.pixels_per_point);
+    }
+
+    // Blend the panel over the pixel buffer
+    pub fn paint(&self, buffer: &mut [u32], width: usize, height: usize) {
+        for clipped in &self.primitives {
+            let Primitive::Mesh(mesh) = &clipped.primitive else {
+                continue;
+            };
+            let Some(texture) = self.textures.get(&mesh.texture_id) else {
+                continue;
+            };
+
+            let clip = clipped.clip_rect.intersect(Rect::from_min_size(Pos2::ZERO, egui::vec2(width as f32, height as f32)));
+
+            for tri in mesh.indices.chunks_exact(3) {
+                let vs = [&mesh.vertices[tri[0] as usize], &mesh.vertices[tri[1] as usize], &mesh.vertices[tri[2] as usize]];
+                rasterize_triangle(vs, texture, clip, buffer, width);
+            }
+        }
+    }
+}
+
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+fn rasterize_triangle(vs: [&Vertex; 3], texture: &Texture, clip: Rect, buffer: &mut [u32], width: usize) {
+    let area = edge(vs[0].pos, vs[1].pos, vs[2].pos);
+    if area == 0.0 {
+        return;
+    }
+
+    let min_x = vs.iter().map(|v| v.pos.x).fold(clip.max.x, f32::min).max(clip.min.x).floor() as usize;
+    let max_x = vs.iter().map(|v| v.pos.x).fold(clip.min.x, f32::max).min(clip.max.x).ceil() as usize;
+    let min_y = vs.iter().map(|v| v.pos.y).fold(clip.max.y, f32::min).max(clip.min.y).floor() as usize;
+    let max_y = vs.iter().map(|v| v.pos.y).fold(clip.min.y, f32::max).min(clip.max.y).ceil() as usize;
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            // Sample at the pixel center, weights are the barycentric coordinates (work for either winding order)
+            let p = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let w0 = edge(vs[1].pos, vs[2].pos, p) / area;
+            let w1 = edge(vs[2].pos, vs[0].pos, p) / area;
+            let w2 = 1.0 - w0 - w1;
+            if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                continue;
+            }
+
+            let u = w0 * vs[0].uv.x + w1 * vs[1].uv.x + w2 * vs[2].uv.x;
+            let v = w0 * vs[0].uv.y + w1 * vs[1].uv.y + w2 * vs[2].uv.y;
+            let tx = ((u * texture.width as f32) as usize).min(texture.width - 1);
+            let ty = ((v * texture.height as f32) as usize).min(texture.height - 1);
+            let texel = texture.pixels[ty * texture.width + tx];
+
+            // Vertex colors and texels are both premultiplied sRGBA
+            let channel = |i: usize| {
+                let vertex = w0 * vs[0].color[i] as f32 + w1 * vs[1].color[i] as f32 + w2 * vs[2].color[i] as f32;
+                vertex * texel[i] as f32 / 255.0
+            };
+            let (r, g, b, a) = (channel(0), channel(1), channel(2), channel(3) / 255.0);
+
+            let dst = &mut buffer[y * width + x];
+            let blend = |src: f32, shift: u32| {
+                let d = ((*dst >> shift) & 0xFF) as f32;
+                ((src + d * (1.0 - a)).clamp(0.0, 255.0) as u32) << shift
+            };
+            *dst = blend(r, 16) | blend(g, 8) | blend(b, 0);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::{fmt, str::FromStr};
+
+use crate::{linalg::Vec3d, object::Material};
+
+/*
+
+Pipeline
+
+The order the samples of a tile are traced in. Immediately traces every sample from its camera ray to its color before
+moving on to the next, a canvas unit at a time. Sorted works on a whole tile a stage at a time instead: every sample's
+camera ray is found first, then what each ray hits (neighbouring rays together, as packets), and the hits are then
+shaded grouped by material, then by object and then by the direction they were seen from (misses last, together).
+Consecutive shading reads the same object's data and takes the same branches, and the reflection and shadow rays it
+sends out start close together heading the same way, so the caches stay warm
+
+Sorting only changes the order samples are shaded in, not their colors. Samples traced with random numbers (the path
+and ambient occlusion integrators) draw them in the order they're traced, so those integrators always trace immediately
+
+*/
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Pipeline {
+    #[default]
+    Immediate,
+    Sorted
+}
+
+// Where a hit is shaded among the rest of its tile's, given the material and index of the object hit (if any) and the
+// direction of the ray that hit it
+pub(crate) fn shading_key(hit: Option<(&Material, usize)>, dir: &Vec3d) -> (u8, usize, u8) {
+    let octant = (dir.x() < 0.0) as u8 | ((dir.y() < 0.0) as u8) << 1 | ((dir.z() < 0.0) as u8) << 2;
+    match hit {
+        Some((material, object)) => {
+            let material = match material {
+                Material::Matte { .. } => 0,
+                Material::Shiny { .. } => 1,
+                Material::Emissive { .. } => 2
+            };
+            (material, object, octant)
+        },
+        None => (u8::MAX, 0, octant)
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pipeline::Immediate => write!(f, "immediate"),
+            Pipeline::Sorted => write!(f, "sorted")
+        }
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "immediate" => Ok(Pipeline::Immediate),
+            "sorted" => Ok(Pipeline::Sorted),
+            _ => Err(format!("unknown pipeline \"{}\", expected immediate or sorted", s))
+        }
+    }
+}
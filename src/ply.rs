@@ -0,0 +1,205 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use crate::{color::Color, linalg::{Float, Vec3d}, object::Splat};
+
+/*
+
+PLY Point Clouds and Meshes
+
+Reads the points of a PLY file, the format most scanning and photogrammetry tools export point clouds in. Only the
+text (ascii) form is read. Each vertex needs x, y and z, and may have a normal (nx, ny, nz) and a color (red, green,
+blue from 0 to 255). Other elements, like faces, are skipped
+
+Scanned meshes are read the same way along with their faces. A face's first property is the list of its corners
+(vertex_indices), and faces with more than three corners are split into triangles around their first corner
+
+*/
+
+// An element in the order its rows appear, with its row count and property names
+type Element = (String, usize, Vec<String>);
+
+// A row and the number of the line it's on
+type Row<'a> = (usize, &'a str);
+
+// An element's property names and rows
+type Rows<'a, 'b> = (&'a [String], &'b [Row<'b>]);
+
+// Read the points of the PLY file stored at path
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<Splat>, PlyError> {
+    parse(&fs::read_to_string(path)?)
+}
+
+// Read the points from the contents of a PLY file
+pub fn parse(src: &str) -> Result<Vec<Splat>, PlyError> {
+    let (elements, rows) = header(src)?;
+    let (properties, rows) = vertex_rows(&elements, &rows)?;
+    let column = |name: &str| properties.iter().position(|property| property == name);
+    let columns = |names: [&str; 3]| -> Option<[usize; 3]> {
+        let [a, b, c] = names.map(column);
+        Some([a?, b?, c?])
+    };
+
+    let Some(position) = columns(["x", "y", "z"]) else {
+        return Err(PlyError::Header("vertices need x, y and z".to_string()));
+    };
+    let normal = columns(["nx", "ny", "nz"]);
+    let color = columns(["red", "green", "blue"]);
+
+    rows.iter().map(|&(line_number, line)| -> Result<Splat, PlyError> {
+        let values: Vec<Float> = line.split_whitespace().map(str::parse).collect::<Result<_, _>>()
+            .map_err(|_| PlyError::Vertex(line_number))?;
+        let get = |[a, b, c]: [usize; 3]| -> Result<[Float; 3], PlyError> {
+            match (values.get(a), values.get(b), values.get(c)) {
+                (Some(&a), Some(&b), Some(&c)) => Ok([a, b, c]),
+                _ => Err(PlyError::Vertex(line_number))
+            }
+        };
+
+        let [x, y, z] = get(position)?;
+        let normal = normal.map(get).transpose()?.map(|[x, y, z]| Vec3d::new(x, y, z));
+        let color = color.map(get).transpose()?.map(|[r, g, b]| Color::from_rgb(r, g, b));
+        Ok(Splat::new(Vec3d::new(x, y, z), normal, color))
+    }).collect()
+}
+
+// Read the corners and triangles of the PLY mesh stored at path
+pub fn load_mesh(path: impl AsRef<Path>) -> Result<(Vec<Vec3d>, Vec<[usize; 3]>), PlyError> {
+    parse_mesh(&fs::read_to_string(path)?)
+}
+
+// Read the corners and triangles of a mesh from the contents of a PLY file
+pub fn parse_mesh(src: &str) -> Result<(Vec<Vec3d>, Vec<[usize; 3]>), PlyError> {
+    let (elements, rows) = header(src)?;
+    let (properties, vertex_rows) = vertex_rows(&elements, &rows)?;
+    let column = |name: &str| properties.iter().position(|property| property == name);
+    let (Some(x), Some(y), Some(z)) = (column("x"), column("y"), column("z")) else {
+        return Err(PlyError::Header("vertices need x, y and z".to_string()));
+    };
+
+    let vertices = vertex_rows.iter().map(|&(line_number, line)| -> Result<Vec3d, PlyError> {
+        let values: Vec<Float> = line.split_whitespace().map(str::parse).collect::<Result<_, _>>()
+            .map_err(|_| PlyError::Vertex(line_number))?;
+        match (values.get(x), values.get(y), values.get(z)) {
+            (Some(&x), Some(&y), Some(&z)) => Ok(Vec3d::new(x, y, z)),
+            _ => Err(PlyError::Vertex(line_number))
+        }
+    }).collect::<Result<Vec<_>, _>>()?;
+
+    let Some((_, face_rows)) = element_rows(&elements, &rows, "face")? else {
+        return Err(PlyError::Header("no face element".to_string()));
+    };
+    let mut faces = Vec::new();
+    for &(line_number, line) in face_rows {
+        let values: Vec<usize> = line.split_whitespace().map(str::parse).collect::<Result<_, _>>()
+            .map_err(|_| PlyError::Face(line_number))?;
+        let corners = match values.split_first() {
+            Some((&n, rest)) if n >= 3 && rest.len() >= n && rest[..n].iter().all(|&i| i < vertices.len()) => &rest[..n],
+            _ => return Err(PlyError::Face(line_number))
+        };
+        faces.extend(corners[1..].windows(2).map(|pair| [corners[0], pair[0], pair[1]]));
+    }
+
+    Ok((vertices, faces))
+}
+
+// The elements a PLY file declares, and the numbered lines of the rows after its header
+fn header(src: &str) -> Result<(Vec<Element>, Vec<Row<'_>>), PlyError> {
+    let mut lines = src.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+
+    if lines.next().map(|(_, line)| line) != Some("ply") {
+        return Err(PlyError::Header("not a PLY file".to_string()));
+    }
+
+    let mut elements: Vec<Element> = Vec::new();
+    loop {
+        let Some((_, line)) = lines.next() else {
+            return Err(PlyError::Header("missing end_header".to_string()));
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", "ascii", ..] => {}
+            ["format", format, ..] => return Err(PlyError::Header(format!("unsupported format \"{}\", only ascii is read", format))),
+            ["element", name, count] => {
+                let count = count.parse().map_err(|_| PlyError::Header(format!("invalid element count \"{}\"", count)))?;
+                elements.push((name.to_string(), count, Vec::new()));
+            }
+            ["property", .., name] => match elements.last_mut() {
+                Some((_, _, properties)) => properties.push(name.to_string()),
+                None => return Err(PlyError::Header("property before any element".to_string()))
+            },
+            ["end_header"] => break,
+            _ => {}
+        }
+    }
+
+    Ok((elements, lines.collect()))
+}
+
+// The property names and rows of the vertex element
+fn vertex_rows<'a, 'b>(elements: &'a [Element], rows: &'b [Row<'b>]) -> Result<Rows<'a, 'b>, PlyError> {
+    element_rows(elements, rows, "vertex")?.ok_or_else(|| PlyError::Header("no vertex element".to_string()))
+}
+
+// The property names and rows of an element, if the file has it
+fn element_rows<'a, 'b>(elements: &'a [Element], rows: &'b [Row<'b>], name: &str) -> Result<Option<Rows<'a, 'b>>, PlyError> {
+    let Some(i) = elements.iter().position(|(element, _, _)| element == name) else {
+        return Ok(None);
+    };
+    let (_, count, properties) = &elements[i];
+
+    // Rows of earlier elements come first
+    let start: usize = elements[..i].iter().map(|(_, count, _)| count).sum();
+    let end = (start + count).min(rows.len());
+    let found = end.saturating_sub(start);
+    if found < *count {
+        return Err(match name {
+            "vertex" => PlyError::MissingVertices { expected: *count, found },
+            _ => PlyError::MissingFaces { expected: *count, found }
+        });
+    }
+    Ok(Some((properties, &rows[start..end])))
+}
+
+/*
+
+PLY Error
+
+*/
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io(io::Error),
+    Header(String),
+    Vertex(usize), // The line with a missing or invalid value
+    Face(usize),   // The line with a missing or invalid corner
+    MissingVertices { expected: usize, found: usize },
+    MissingFaces { expected: usize, found: usize }
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io(e) => write!(f, "couldn't read PLY file: {}", e),
+            PlyError::Header(e) => write!(f, "invalid PLY header: {}", e),
+            PlyError::Vertex(line) => write!(f, "invalid PLY vertex on line {}", line),
+            PlyError::Face(line) => write!(f, "invalid PLY face on line {}", line),
+            PlyError::MissingVertices { expected, found } => write!(f, "PLY file ends after {} of its {} vertices", found, expected),
+            PlyError::MissingFaces { expected, found } => write!(f, "PLY file ends after {} of its {} faces", found, expected)
+        }
+    }
+}
+
+impl Error for PlyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PlyError::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for PlyError {
+    fn from(e: io::Error) -> Self {
+        PlyError::Io(e)
+    }
+}
@@ -0,0 +1,74 @@
+use crate::linalg::{Float, Vec3d};
+
+/*
+
+Portals
+
+Rectangles marking the windows and openings that light from outside (the background and ambient light) comes into an
+interior through. Path traced rooms lit only through a window are otherwise very noisy, since few of the random
+bounces leaving a surface happen to find the way out. With portals, every surface also sends a ray through a random
+point of one of them, and only counts the outside light that reaches it, weighted against finding the same light by
+bouncing (multiple importance sampling). Bounces that escape without passing through a portal count fully, so a scene
+lit from outside in other ways too stays as bright
+
+A portal is a corner and two edges from it at right angles, e.g. the inside of a window frame. It isn't an object, so
+it's never seen or hit, and it should cover the opening without anything in the way. Only the path tracer samples
+portals
+
+*/
+
+#[derive(Clone, Debug)]
+pub struct Portal {
+    corner: Vec3d,
+    edge_u: Vec3d,
+    edge_v: Vec3d,
+    normal: Vec3d, // Unit normal, to either side
+    area: Float
+}
+
+impl Portal {
+    pub fn new(corner: Vec3d, edge_u: Vec3d, edge_v: Vec3d) -> Self {
+        let cross = edge_u.cross(&edge_v);
+        Self {
+            area: cross.magnitude(),
+            normal: cross.normalize(),
+            corner,
+            edge_u,
+            edge_v
+        }
+    }
+
+    // A unit direction from p through a random point of the portal, chosen by the pair of uniform random numbers, and
+    // the probability density of choosing it (per steradian). None when p is in the portal's plane
+    pub(crate) fn sample_direction(&self, p: &Vec3d, (u1, u2): (Float, Float)) -> Option<(Vec3d, Float)> {
+        let point = &(&self.corner + &(&self.edge_u * u1)) + &(&self.edge_v * u2);
+        let to = &point - p;
+        let dist_sq = &to * &to;
+        let dir = to.normalize();
+        let cos = (&self.normal * &dir).abs();
+        if cos < Float::EPSILON || self.area <= 0.0 {
+            return None;
+        }
+        Some((dir, dist_sq / (self.area * cos)))
+    }
+
+    // The density sample_direction chooses a unit direction from p with, 0 when it doesn't pass through the portal
+    pub(crate) fn direction_pdf(&self, p: &Vec3d, dir: &Vec3d) -> Float {
+        let denom = &self.normal * dir;
+        if denom.abs() < Float::EPSILON || self.area <= 0.0 {
+            return 0.0;
+        }
+        let t = &self.normal * &(&self.corner - p) / denom;
+        if t <= 0.0 {
+            return 0.0;
+        }
+
+        // Where it passes through the portal's plane, along each edge from the corner as a fraction of its length
+        let offset = &(p + &(dir * t)) - &self.corner;
+        let (u, v) = (&offset * &self.edge_u / (&self.edge_u * &self.edge_u), &offset * &self.edge_v / (&self.edge_v * &self.edge_v));
+        if !((0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v)) {
+            return 0.0;
+        }
+        t * t / (self.area * denom.abs())
+    }
+}
@@ -0,0 +1,202 @@
+use std::{fmt, str::FromStr};
+
+use rand::Rng;
+use rand_pcg::Pcg32;
+
+use crate::{color::{linear_to_srgb, srgb_to_linear}, linalg::Float};
+
+/*
+
+Post-Processing
+
+Effects applied to every finished frame, one after another in the order they're given, before it's presented or saved.
+They work on the frame's linear light, before it's encoded for display, so they can brighten past what the display
+shows and blend light as it adds up. Each view of a stereo frame is processed as a frame of its own
+
+Programs can write their own effects by implementing PostEffect. The built in ones are
+    vignette               darkens the frame towards its corners, by strength at the corners
+    chromatic-aberration   splits red and blue apart towards the edges as a cheap lens does, by a fraction of the frame
+    grain                  monochrome film grain, strength being how far it brightens or darkens a unit at most
+    sharpen                crisps edges by pushing each unit away from the average of its neighbours, by amount
+and are chosen by name with an optional value, e.g. "vignette:0.4"
+
+The accumulation buffer and AOVs are left as traced
+
+*/
+
+pub trait PostEffect: Send + Sync {
+    fn apply(&self, frame: &mut Frame);
+}
+
+// A finished frame (or one view of it) being post-processed
+pub struct Frame {
+    pub pixels: Vec<[Float; 3]>, // Linear RGB of every canvas unit, row by row from the top left
+    pub width: usize,
+    pub height: usize,
+    pub seed: u64                // Differs from frame to frame, for effects that vary randomly like grain
+}
+
+impl Frame {
+    // The unit at (x, y), or the closest one on the edge when outside the frame
+    pub fn at(&self, x: isize, y: isize) -> [Float; 3] {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    // The light at a point in units from the top left, blended from the four units around it
+    pub fn sample(&self, x: Float, y: Float) -> [Float; 3] {
+        let (x, y) = (x - 0.5, y - 0.5); // From the units' centers
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as isize, y0 as isize);
+        let [a, b, c, d] = [self.at(x0, y0), self.at(x0 + 1, y0), self.at(x0, y0 + 1), self.at(x0 + 1, y0 + 1)];
+        [0, 1, 2].map(|i| (a[i] * (1.0 - fx) + b[i] * fx) * (1.0 - fy) + (c[i] * (1.0 - fx) + d[i] * fx) * fy)
+    }
+
+    // Where the unit at index i is, relative to the frame's center, as fractions of half its height
+    fn offset_from_center(&self, i: usize) -> (Float, Float) {
+        let half = self.height as Float / 2.0;
+        let x = (i % self.width) as Float + 0.5 - self.width as Float / 2.0;
+        let y = (i / self.width) as Float + 0.5 - half;
+        (x / half, y / half)
+    }
+}
+
+/*
+
+Built In Effects
+
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vignette {
+    pub strength: Float // How much darker the corners are, from 0 (not at all) to 1 (black)
+}
+
+impl PostEffect for Vignette {
+    fn apply(&self, frame: &mut Frame) {
+        let corner = (frame.width as Float / frame.height as Float).hypot(1.0);
+        for i in 0..frame.pixels.len() {
+            let (x, y) = frame.offset_from_center(i);
+            let dist = x.hypot(y) / corner;
+            // Smooth over the middle of the frame, falling off faster towards the corners
+            let factor = (1.0 - self.strength * dist * dist).max(0.0);
+            frame.pixels[i] = frame.pixels[i].map(|c| c * factor);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChromaticAberration {
+    pub amount: Float // How far red and blue split at the edges, as a fraction of the distance to the center
+}
+
+impl PostEffect for ChromaticAberration {
+    fn apply(&self, frame: &mut Frame) {
+        let (center_x, center_y) = (frame.width as Float / 2.0, frame.height as Float / 2.0);
+        let pixels: Vec<[Float; 3]> = (0..frame.pixels.len()).map(|i| {
+            let (x, y) = ((i % frame.width) as Float + 0.5 - center_x, (i / frame.width) as Float + 0.5 - center_y);
+            // Red is magnified a little and blue shrunk, so they fringe opposite sides of edges
+            let red = frame.sample(center_x + x * (1.0 - self.amount), center_y + y * (1.0 - self.amount))[0];
+            let blue = frame.sample(center_x + x * (1.0 + self.amount), center_y + y * (1.0 + self.amount))[2];
+            [red, frame.pixels[i][1], blue]
+        }).collect();
+        frame.pixels = pixels;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FilmGrain {
+    pub strength: Float // The most a unit is brightened or darkened, as a fraction of full brightness as displayed
+}
+
+impl PostEffect for FilmGrain {
+    fn apply(&self, frame: &mut Frame) {
+        let mut rng = Pcg32::new(frame.seed, GRAIN_STREAM);
+        for rgb in frame.pixels.iter_mut() {
+            // Two uniform draws summed lean towards small changes, as grain does. Added to the displayed brightness, so
+            // it shows as much in the shadows as in the highlights
+            let noise = (rng.random::<Float>() + rng.random::<Float>() - 1.0) * self.strength;
+            *rgb = rgb.map(|c| srgb_to_linear((linear_to_srgb(c.max(0.0)) + noise).max(0.0)));
+        }
+    }
+}
+
+const GRAIN_STREAM: u64 = u64::MAX - 1; // Stream of the grain's generator, apart from those of the pixels and spawning
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sharpen {
+    pub amount: Float // How far each unit is pushed away from the average of its neighbours
+}
+
+impl PostEffect for Sharpen {
+    fn apply(&self, frame: &mut Frame) {
+        let pixels: Vec<[Float; 3]> = (0..frame.pixels.len()).map(|i| {
+            let (x, y) = ((i % frame.width) as isize, (i / frame.width) as isize);
+            let neighbours = [frame.at(x - 1, y), frame.at(x + 1, y), frame.at(x, y - 1), frame.at(x, y + 1)];
+            let rgb = frame.pixels[i];
+            [0, 1, 2].map(|c| {
+                let average = neighbours.iter().map(|n| n[c]).sum::<Float>() / 4.0;
+                (rgb[c] + (rgb[c] - average) * self.amount).max(0.0)
+            })
+        }).collect();
+        frame.pixels = pixels;
+    }
+}
+
+// One of the built in effects, as chosen on the command line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Effect {
+    Vignette(Vignette),
+    ChromaticAberration(ChromaticAberration),
+    FilmGrain(FilmGrain),
+    Sharpen(Sharpen)
+}
+
+impl PostEffect for Effect {
+    fn apply(&self, frame: &mut Frame) {
+        match self {
+            Effect::Vignette(effect) => effect.apply(frame),
+            Effect::ChromaticAberration(effect) => effect.apply(frame),
+            Effect::FilmGrain(effect) => effect.apply(frame),
+            Effect::Sharpen(effect) => effect.apply(frame)
+        }
+    }
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Effect::Vignette(Vignette { strength }) => write!(f, "vignette:{}", strength),
+            Effect::ChromaticAberration(ChromaticAberration { amount }) => write!(f, "chromatic-aberration:{}", amount),
+            Effect::FilmGrain(FilmGrain { strength }) => write!(f, "grain:{}", strength),
+            Effect::Sharpen(Sharpen { amount }) => write!(f, "sharpen:{}", amount)
+        }
+    }
+}
+
+// A name, optionally followed by a colon and its value, e.g. "vignette" or "grain:0.05"
+impl FromStr for Effect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = match s.split_once(':') {
+            Some((name, value)) => {
+                let value: Float = value.parse().map_err(|_| format!("invalid value \"{}\" for {}", value, name))?;
+                if !(value.is_finite() && value >= 0.0) {
+                    return Err(format!("{} must be non-negative, got {}", name, value));
+                }
+                (name, Some(value))
+            }
+            None => (s, None)
+        };
+        match name {
+            "vignette" => Ok(Effect::Vignette(Vignette { strength: value.unwrap_or(0.4) })),
+            "chromatic-aberration" => Ok(Effect::ChromaticAberration(ChromaticAberration { amount: value.unwrap_or(0.005) })),
+            "grain" => Ok(Effect::FilmGrain(FilmGrain { strength: value.unwrap_or(0.03) })),
+            "sharpen" => Ok(Effect::Sharpen(Sharpen { amount: value.unwrap_or(0.5) })),
+            _ => Err(format!("unknown effect \"{}\", expected vignette, chromatic-aberration, grain or sharpen", name))
+        }
+    }
+}
@@ -0,0 +1,22 @@
+/*
+
+Prelude
+
+The types most programs using the crate need, to be brought in at once with use raytracer::prelude::*
+
+*/
+
+pub use crate::{
+    color::Color,
+    config::{RendererBuilder, RendererConfig},
+    error::RendererError,
+    falloff::Falloff,
+    image::Image,
+    integrator::Integrator,
+    light::LightSource,
+    linalg::{Float, Mat3, Ray, Vec3d},
+    material::{presets, Material},
+    object::{BezierPatch, ConvexPolyhedron, Curve, Instance, Mesh, Object, Plane, PointCloud, Primitive, RectangularPrism, Sphere, Splat, Triangle},
+    scene_builder::SceneBuilder,
+    Fog, Hit, Renderer, Scene
+};
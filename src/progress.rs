@@ -0,0 +1,62 @@
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, time::Duration};
+
+/*
+
+Progress
+
+A snapshot of how far along the current frame is, passed to progress callbacks while tracing
+
+*/
+
+pub struct Progress {
+    pub tiles_done: usize,
+    pub tiles_total: usize,
+    pub elapsed: Duration  // Time spent on the frame so far
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f64 {
+        if self.tiles_total == 0 {
+            1.0
+        } else {
+            self.tiles_done as f64 / self.tiles_total as f64
+        }
+    }
+
+    // Estimated time until the frame is complete, assuming the remaining tiles take as long as the finished ones
+    pub fn eta(&self) -> Option<Duration> {
+        if self.tiles_done == 0 {
+            return None;
+        }
+        let per_tile = self.elapsed.as_secs_f64() / self.tiles_done as f64;
+        Some(Duration::from_secs_f64(per_tile * (self.tiles_total - self.tiles_done) as f64))
+    }
+}
+
+/*
+
+Cancellation Token
+
+A shared flag that aborts rendering when set. Clones refer to the same flag, so a token can be handed to another
+thread (e.g. a UI or signal handler) and cancelled from there
+
+*/
+
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
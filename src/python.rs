@@ -0,0 +1,329 @@
+use std::{fs::File, io::BufWriter, sync::Arc};
+
+use pyo3::{exceptions::{PyIOError, PyValueError}, prelude::*, types::PyBytes};
+
+use crate::{
+    color::Color,
+    falloff::Falloff,
+    image,
+    integrator::Integrator,
+    light::LightSource,
+    linalg::{Float, Vec3d},
+    material::presets,
+    object::{self, Primitive, RectangularPrism},
+    scene_file,
+    Renderer
+};
+
+/*
+
+Python Bindings
+
+Exposes scene construction and headless rendering to Python (with the python feature), so the tracer can be scripted
+from notebooks for teaching and experiments, e.g.
+
+    import raytracer
+    scene = raytracer.Scene(camera=(0.0, 1.0, 0.0), background="black")
+    scene.add(raytracer.Sphere((0.0, 1.0, -5.0), 1.0, "red", raytracer.Material.shiny(500.0, 0.2)))
+    scene.add_light(raytracer.Light.ambient(0.2))
+    scene.add_light(raytracer.Light.point(0.8, (2.0, 4.0, 0.0)))
+    image = scene.render(width=320, samples=4)
+    image.save("spheres.png")
+
+Images show themselves in notebooks. Colors are names ("slate gray") or 0xRRGGBB integers, as in scene files
+Build the module with maturin (maturin develop --features python), or cargo build --release --features python and copy
+the library to raytracer.so (raytracer.pyd on Windows) next to the script
+
+*/
+
+// A color given by name or as a 0xRRGGBB integer
+#[derive(FromPyObject)]
+enum ColorArg {
+    Name(String),
+    Hex(u32)
+}
+
+impl ColorArg {
+    fn resolve(self) -> PyResult<usize> {
+        match self {
+            ColorArg::Name(name) => Color::from_name(&name).map(|c| c as usize).ok_or_else(|| PyValueError::new_err(format!("unknown color \"{}\"", name))),
+            ColorArg::Hex(hex) => Ok(hex as usize & 0xFFFFFF)
+        }
+    }
+}
+
+fn vec3d((x, y, z): (Float, Float, Float)) -> Vec3d {
+    Vec3d::new(x, y, z)
+}
+
+#[pyclass(name = "Material", from_py_object)]
+#[derive(Clone)]
+struct PyMaterial(object::Material);
+
+#[pymethods]
+impl PyMaterial {
+    #[staticmethod]
+    fn matte() -> Self {
+        Self(object::Material::default())
+    }
+
+    // Specular highlights sharpening with spclr_exp, reflecting refl_rat of the light off the surface
+    #[staticmethod]
+    fn shiny(spclr_exp: Float, refl_rat: Float) -> Self {
+        Self(object::Material::Shiny { spclr_exp, refl_rat, double_sided: true })
+    }
+
+    #[staticmethod]
+    fn emissive(intensity: Float) -> Self {
+        Self(object::Material::Emissive { intensity, double_sided: true })
+    }
+
+    // One of the named presets, e.g. "gold"
+    #[staticmethod]
+    fn preset(name: &str) -> PyResult<Self> {
+        presets::from_name(name).map(Self).ok_or_else(|| PyValueError::new_err(format!("unknown material \"{}\"", name)))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Material({})", self.0)
+    }
+}
+
+#[pyclass(name = "Light", from_py_object)]
+#[derive(Clone)]
+struct PyLight(LightSource);
+
+#[pymethods]
+impl PyLight {
+    #[staticmethod]
+    fn ambient(intensity: Float) -> Self {
+        Self(LightSource::Ambient { intensity })
+    }
+
+    #[staticmethod]
+    fn point(intensity: Float, pos: (Float, Float, Float)) -> Self {
+        Self(LightSource::Point { intensity, pos: vec3d(pos), falloff: Falloff::None })
+    }
+
+    #[staticmethod]
+    fn directional(intensity: Float, dir: (Float, Float, Float)) -> Self {
+        Self(LightSource::Directional { intensity, dir: vec3d(dir), color: Color::White as usize })
+    }
+
+    #[staticmethod]
+    fn tube(intensity: Float, pos: (Float, Float, Float), axis: (Float, Float, Float), length: Float, radius: Float) -> PyResult<Self> {
+        let tube = LightSource::Tube { intensity, pos: vec3d(pos), axis: vec3d(axis), length, radius, falloff: Falloff::None };
+        tube.validate().map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(Self(tube))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Light({})", self.0.describe())
+    }
+}
+
+#[pyclass(name = "Sphere", from_py_object)]
+#[derive(Clone)]
+struct PySphere {
+    center: (Float, Float, Float),
+    radius: Float,
+    color: usize,
+    material: object::Material
+}
+
+#[pymethods]
+impl PySphere {
+    #[new]
+    #[pyo3(signature = (center, radius, color, material = None))]
+    fn new(center: (Float, Float, Float), radius: Float, color: ColorArg, material: Option<PyMaterial>) -> PyResult<Self> {
+        Ok(Self { center, radius, color: color.resolve()?, material: material.map_or(object::Material::default(), |m| m.0) })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Sphere(center={:?}, radius={}, color=0x{:06X}, material={})", self.center, self.radius, self.color, self.material)
+    }
+}
+
+// A box from its minimum corner (origin), its sides along the axes
+#[pyclass(name = "Prism", from_py_object)]
+#[derive(Clone)]
+struct PyPrism {
+    origin: (Float, Float, Float),
+    size: (Float, Float, Float),
+    color: usize,
+    material: object::Material
+}
+
+#[pymethods]
+impl PyPrism {
+    #[new]
+    #[pyo3(signature = (origin, width, height, depth, color, material = None))]
+    fn new(origin: (Float, Float, Float), width: Float, height: Float, depth: Float, color: ColorArg, material: Option<PyMaterial>) -> PyResult<Self> {
+        Ok(Self { origin, size: (width, height, depth), color: color.resolve()?, material: material.map_or(object::Material::default(), |m| m.0) })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Prism(origin={:?}, size={:?}, color=0x{:06X}, material={})", self.origin, self.size, self.color, self.material)
+    }
+}
+
+#[pyclass(name = "Triangle", from_py_object)]
+#[derive(Clone)]
+struct PyTriangle {
+    points: [(Float, Float, Float); 3],
+    color: usize,
+    material: object::Material
+}
+
+#[pymethods]
+impl PyTriangle {
+    #[new]
+    #[pyo3(signature = (points, color, material = None))]
+    fn new(points: [(Float, Float, Float); 3], color: ColorArg, material: Option<PyMaterial>) -> PyResult<Self> {
+        Ok(Self { points, color: color.resolve()?, material: material.map_or(object::Material::default(), |m| m.0) })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Triangle(points={:?}, color=0x{:06X}, material={})", self.points, self.color, self.material)
+    }
+}
+
+// Any of the objects a scene can be built from
+#[derive(Clone, FromPyObject)]
+enum Shape {
+    Sphere(PySphere),
+    Prism(PyPrism),
+    Triangle(PyTriangle)
+}
+
+impl Shape {
+    fn primitive(&self) -> Primitive {
+        match self.clone() {
+            Shape::Sphere(PySphere { center, radius, color, material }) => object::Sphere::new(vec3d(center), radius, color, material).into(),
+            Shape::Prism(PyPrism { origin, size: (width, height, depth), color, material }) => {
+                RectangularPrism::new(vec3d(origin), width, height, depth, color, material).into()
+            }
+            Shape::Triangle(PyTriangle { points, color, material }) => object::Triangle::new(points.map(vec3d), color, material).into()
+        }
+    }
+}
+
+// The objects and lights of a scene being put together, built into a scene for the renderer every time it's rendered
+#[pyclass(name = "Scene")]
+struct PyScene {
+    camera: (Float, Float, Float),
+    background: usize,
+    lights: Vec<LightSource>,
+    shapes: Vec<Shape>
+}
+
+#[pymethods]
+impl PyScene {
+    #[new]
+    #[pyo3(signature = (camera = (0.0, 0.0, 0.0), background = ColorArg::Name("black".to_string())))]
+    fn new(camera: (Float, Float, Float), background: ColorArg) -> PyResult<Self> {
+        Ok(Self { camera, background: background.resolve()?, lights: Vec::new(), shapes: Vec::new() })
+    }
+
+    fn add(&mut self, shape: Shape) {
+        self.shapes.push(shape);
+    }
+
+    fn add_light(&mut self, light: PyLight) {
+        self.lights.push(light.0);
+    }
+
+    // Trace the scene headlessly. Other threads carry on running Python meanwhile
+    #[pyo3(signature = (width = 400, aspect_ratio = 16.0 / 9.0, samples = 1, max_depth = 2, fov = None, integrator = "whitted", threads = None, seed = 0))]
+    #[allow(clippy::too_many_arguments)]
+    fn render(&self, py: Python<'_>, width: usize, aspect_ratio: Float, samples: usize, max_depth: u32, fov: Option<Float>, integrator: &str, threads: Option<usize>, seed: u64) -> PyResult<PyImage> {
+        let integrator: Integrator = integrator.parse().map_err(PyValueError::new_err)?;
+        let lights = self.lights.clone();
+        let objs: Vec<Primitive> = self.shapes.iter().map(Shape::primitive).collect();
+        let scene = crate::Scene::new(vec3d(self.camera), self.background, lights, objs).map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+        py.detach(|| {
+            let mut builder = Renderer::builder().screen_width(width).aspect_ratio(aspect_ratio).samples(samples).max_depth(max_depth).integrator(integrator).seed(seed);
+            if let Some(fov) = fov {
+                builder = builder.fov(fov);
+            }
+            if let Some(threads) = threads {
+                builder = builder.threads(threads);
+            }
+            builder.render_to_image(Arc::new(scene))
+        }).map(PyImage).map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Scene({} objects, {} lights)", self.shapes.len(), self.lights.len())
+    }
+}
+
+// A rendered frame
+#[pyclass(name = "Image")]
+struct PyImage(image::Image);
+
+#[pymethods]
+impl PyImage {
+    #[getter]
+    fn width(&self) -> usize {
+        self.0.width
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.0.height
+    }
+
+    // The pixel at (x, y) from the top left, as a 0xRRGGBB integer
+    fn pixel(&self, x: usize, y: usize) -> PyResult<u32> {
+        if x >= self.0.width || y >= self.0.height {
+            return Err(PyValueError::new_err(format!("({}, {}) is outside the {}x{} image", x, y, self.0.width, self.0.height)));
+        }
+        Ok(self.0.pixel(x, y))
+    }
+
+    // Every pixel as red, green and blue bytes, row by row, e.g. for numpy.frombuffer(...).reshape(height, width, 3)
+    fn to_rgb<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.to_rgb())
+    }
+
+    fn save(&self, path: &str) -> PyResult<()> {
+        let file = File::create(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        self.0.write_png(BufWriter::new(file)).map_err(|e| PyIOError::new_err(e.to_string()))
+    }
+
+    // Shown inline by notebooks
+    fn _repr_png_<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let mut png = Vec::new();
+        self.0.write_png(&mut png).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        Ok(PyBytes::new(py, &png))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Image({}x{})", self.0.width, self.0.height)
+    }
+}
+
+// Render a scene file headlessly, e.g. one of the scenes directory's examples
+#[pyfunction]
+#[pyo3(signature = (path, width = 400, samples = 1))]
+fn render_file(py: Python<'_>, path: &str, width: usize, samples: usize) -> PyResult<PyImage> {
+    let scene = scene_file::load(path).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    py.detach(|| Renderer::builder().screen_width(width).samples(samples).render_to_image(Arc::new(scene)))
+        .map(PyImage)
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+#[pymodule]
+fn raytracer(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyMaterial>()?;
+    m.add_class::<PyLight>()?;
+    m.add_class::<PySphere>()?;
+    m.add_class::<PyPrism>()?;
+    m.add_class::<PyTriangle>()?;
+    m.add_class::<PyScene>()?;
+    m.add_class::<PyImage>()?;
+    m.add_function(wrap_pyfunction!(render_file, m)?)?;
+    Ok(())
+}
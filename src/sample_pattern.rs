@@ -0,0 +1,83 @@
+use std::{fmt, str::FromStr};
+
+use rand::Rng;
+
+use crate::linalg::Float;
+
+/*
+
+Sample Pattern
+
+Where a canvas unit's anti-aliasing samples are placed. Random jitter changes every frame, which averages away over
+many frames but makes edges shimmer while moving around. A fixed rotated grid puts samples at the same offsets every
+frame, so edges are stable, and still covers both the horizontal and vertical position of near axis aligned edges
+
+Offsets are in the range -1 to 1 on each axis and are scaled to the reconstruction filter's footprint
+
+*/
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SamplePattern {
+    #[default]
+    Random,
+    RotatedGrid
+}
+
+// The standard 2x, 4x and 8x multisampling patterns, in sixteenths of a pixel from its center
+const GRID_2X: [(i8, i8); 2] = [(4, 4), (-4, -4)];
+const GRID_4X: [(i8, i8); 4] = [(-2, -6), (6, -2), (-6, 2), (2, 6)];
+const GRID_8X: [(i8, i8); 8] = [(1, -3), (-1, 3), (5, 1), (-3, -5), (-5, 5), (-7, -1), (3, 7), (7, -7)];
+
+impl SamplePattern {
+    // Offset of sample i out of num_samples from the center of a canvas unit
+    pub fn offset(&self, i: usize, num_samples: usize, rng: &mut impl Rng) -> (Float, Float) {
+        match self {
+            SamplePattern::Random => (rng.random::<Float>() * 2.0 - 1.0, rng.random::<Float>() * 2.0 - 1.0),
+            SamplePattern::RotatedGrid => {
+                let table: &[(i8, i8)] = match num_samples {
+                    2 => &GRID_2X,
+                    4 => &GRID_4X,
+                    8 => &GRID_8X,
+                    _ => return rotated_grid(i, num_samples)
+                };
+                let (x, y) = table[i];
+                (x as Float / 8.0, y as Float / 8.0)
+            }
+        }
+    }
+}
+
+// Any other sample count is laid out on the smallest square grid that fits it, rotated so no two samples share a row
+// or column, and wrapped back into the unit
+fn rotated_grid(i: usize, num_samples: usize) -> (Float, Float) {
+    let k = (num_samples as Float).sqrt().ceil() as usize;
+    let u = ((i % k) as Float + 0.5) / k as Float * 2.0 - 1.0;
+    let v = ((i / k) as Float + 0.5) / k as Float * 2.0 - 1.0;
+
+    let angle = Float::atan(0.5);
+    let (sin, cos) = angle.sin_cos();
+    let wrap = |d: Float| (d + 1.0).rem_euclid(2.0) - 1.0;
+
+    (wrap(u * cos - v * sin), wrap(u * sin + v * cos))
+}
+
+impl fmt::Display for SamplePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SamplePattern::Random => write!(f, "random"),
+            SamplePattern::RotatedGrid => write!(f, "grid")
+        }
+    }
+}
+
+impl FromStr for SamplePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(SamplePattern::Random),
+            "grid" => Ok(SamplePattern::RotatedGrid),
+            _ => Err(format!("unknown sample pattern \"{}\", expected random or grid", s))
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::sync::Arc;
+
+use crate::{
+    atmosphere::Atmosphere,
+    color::Color,
+    decal::Decal,
+    error::SceneError,
+    falloff::Falloff,
+    light::LightSource,
+    linalg::{Float, Vec3d},
+    object::{Flagged, Material, Object, Primitive, RectangularPrism, RenderFlags, Sphere, Triangle},
+    portal::Portal,
+    volume::Volume,
+    Fog, Scene
+};
+
+/*
+
+Scene Builder
+
+Builds a scene in code one call at a time, e.g.
+
+    Scene::builder()
+        .camera(Vec3d::new(0.0, 2.0, -1.0))
+        .ambient(0.1)
+        .point_light(0.9, Vec3d::new(-3.0, 4.0, -6.0))
+        .sphere(Vec3d::new(3.0, 2.0, -8.0), 2.0).color(Color::Red).shiny(500.0, 0.1)
+        .build()?
+
+Shapes are white, matte and seen by every ray until given a color, material or render flags, which apply to the shape
+added last. A light that can't be used is reported by build, rather than by the call that added it
+
+*/
+
+pub struct SceneBuilder {
+    camera_origin: Vec3d,
+    bg_col: usize,
+    lights: Vec<LightSource>,
+    objs: Vec<Primitive>,
+    shape: Option<Shape>, // The shape added last, still open to a color and material
+    volumes: Vec<Volume>,
+    decals: Vec<Decal>,
+    portals: Vec<Portal>,
+    fog: Option<Fog>,
+    atmosphere: Option<Atmosphere>,
+    error: Option<SceneError> // The first thing added that can't be used, returned from build
+}
+
+struct Shape {
+    kind: ShapeKind,
+    color: usize,
+    material: Material,
+    flags: RenderFlags
+}
+
+enum ShapeKind {
+    Sphere { center: Vec3d, radius: Float },
+    Prism { origin: Vec3d, width: Float, height: Float, depth: Float },
+    Triangle { ps: [Vec3d; 3] }
+}
+
+impl Default for SceneBuilder {
+    fn default() -> Self {
+        Self {
+            camera_origin: Vec3d::new(0.0, 0.0, 0.0),
+            bg_col: Color::Black as usize,
+            lights: Vec::new(),
+            objs: Vec::new(),
+            shape: None,
+            volumes: Vec::new(),
+            decals: Vec::new(),
+            portals: Vec::new(),
+            fog: None,
+            atmosphere: None,
+            error: None
+        }
+    }
+}
+
+impl SceneBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn camera(mut self, origin: Vec3d) -> Self {
+        self.camera_origin = origin;
+        self
+    }
+
+    pub fn background(mut self, color: impl Into<usize>) -> Self {
+        self.bg_col = color.into();
+        self
+    }
+
+    pub fn ambient(mut self, intensity: Float) -> Self {
+        self.lights.push(LightSource::Ambient { intensity });
+        self
+    }
+
+    pub fn point_light(mut self, intensity: Float, pos: Vec3d) -> Self {
+        self.lights.push(LightSource::Point { intensity, pos, falloff: Falloff::None });
+        self
+    }
+
+    pub fn directional_light(mut self, intensity: Float, dir: Vec3d) -> Self {
+        self.lights.push(LightSource::Directional { intensity, dir, color: Color::White as usize });
+        self
+    }
+
+    // build fails when the axis is zero or the length or radius negative
+    pub fn tube_light(mut self, intensity: Float, pos: Vec3d, axis: Vec3d, length: Float, radius: Float) -> Self {
+        self.lights.push(LightSource::Tube { intensity, pos, axis, length, radius, falloff: Falloff::None });
+        self
+    }
+
+    // How the point or tube light added last dims with distance. build fails when the falloff isn't valid, or the last
+    // light added isn't a point or tube light
+    pub fn falloff(mut self, falloff: Falloff) -> Self {
+        let Some(i) = self.lights.len().checked_sub(1) else {
+            self.fail(SceneError::FalloffWithoutLight);
+            return self;
+        };
+        if let Err(e) = self.lights[i].set_falloff(falloff) {
+            self.fail(SceneError::InvalidLight(i, e));
+        }
+        self
+    }
+
+    pub fn sphere(self, center: Vec3d, radius: Float) -> Self {
+        self.shape(ShapeKind::Sphere { center, radius })
+    }
+
+    pub fn prism(self, origin: Vec3d, width: Float, height: Float, depth: Float) -> Self {
+        self.shape(ShapeKind::Prism { origin, width, height, depth })
+    }
+
+    pub fn triangle(self, ps: [Vec3d; 3]) -> Self {
+        self.shape(ShapeKind::Triangle { ps })
+    }
+
+    // Add an object built elsewhere, e.g. a mesh, as it is
+    pub fn object(self, obj: impl Object + 'static) -> Self {
+        self.primitive(Primitive::Other(Arc::new(obj)))
+    }
+
+    // Add an object shared with other scenes or instances, without copying it
+    pub fn shared(self, obj: &Arc<dyn Object>) -> Self {
+        self.primitive(Arc::clone(obj))
+    }
+
+    // Add a shape built elsewhere with its own color and material, or an object already wrapped as a primitive
+    pub fn primitive(mut self, primitive: impl Into<Primitive>) -> Self {
+        self.finish_shape();
+        self.objs.push(primitive.into());
+        self
+    }
+
+    // Color of the shape added last
+    pub fn color(mut self, color: impl Into<usize>) -> Self {
+        if let Some(shape) = self.shape.as_mut() {
+            shape.color = color.into();
+        }
+        self
+    }
+
+    // Material of the shape added last
+    pub fn material(mut self, material: Material) -> Self {
+        if let Some(shape) = self.shape.as_mut() {
+            shape.material = material;
+        }
+        self
+    }
+
+    pub fn matte(self) -> Self {
+        self.material(Material::default())
+    }
+
+    pub fn shiny(self, spclr_exp: Float, refl_rat: Float) -> Self {
+        self.material(Material::Shiny { spclr_exp, refl_rat, double_sided: true })
+    }
+
+    pub fn emissive(self, intensity: Float) -> Self {
+        self.material(Material::Emissive { intensity, double_sided: true })
+    }
+
+    // Make the material of the shape added last single-sided, so it's seen through from behind
+    pub fn single_sided(mut self) -> Self {
+        if let Some(shape) = self.shape.as_mut() {
+            shape.material = shape.material.clone().single_sided();
+        }
+        self
+    }
+
+    // Which rays see the shape added last
+    pub fn flags(mut self, flags: RenderFlags) -> Self {
+        if let Some(shape) = self.shape.as_mut() {
+            shape.flags = flags;
+        }
+        self
+    }
+
+    pub fn volume(mut self, volume: Volume) -> Self {
+        self.volumes.push(volume);
+        self
+    }
+
+    pub fn decal(mut self, decal: Decal) -> Self {
+        self.decals.push(decal);
+        self
+    }
+
+    pub fn portal(mut self, portal: Portal) -> Self {
+        self.portals.push(portal);
+        self
+    }
+
+    pub fn fog(mut self, fog: Fog) -> Self {
+        self.fog = Some(fog);
+        self
+    }
+
+    pub fn atmosphere(mut self, atmosphere: Atmosphere) -> Self {
+        self.atmosphere = Some(atmosphere);
+        self
+    }
+
+    pub fn build(mut self) -> Result<Scene, SceneError> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        self.finish_shape();
+        let mut scene = Scene::new(self.camera_origin, self.bg_col, self.lights, self.objs)?;
+        for volume in self.volumes {
+            scene = scene.with_volume(volume);
+        }
+        for decal in self.decals {
+            scene = scene.with_decal(decal);
+        }
+        for portal in self.portals {
+            scene = scene.with_portal(portal);
+        }
+        if let Some(fog) = self.fog {
+            scene = scene.with_fog(fog);
+        }
+        if let Some(atmosphere) = self.atmosphere {
+            scene = scene.with_atmosphere(atmosphere);
+        }
+        Ok(scene)
+    }
+
+    // Remember why the scene can't be built, unless an earlier call already failed
+    fn fail(&mut self, e: SceneError) {
+        self.error.get_or_insert(e);
+    }
+
+    fn shape(mut self, kind: ShapeKind) -> Self {
+        self.finish_shape();
+        self.shape = Some(Shape { kind, color: Color::White as usize, material: Material::default(), flags: RenderFlags::default() });
+        self
+    }
+
+    // Build the shape added last, now that nothing more can be set on it
+    fn finish_shape(&mut self) {
+        let Some(Shape { kind, color, material, flags }) = self.shape.take() else {
+            return;
+        };
+        let primitive: Primitive = match kind {
+            ShapeKind::Sphere { center, radius } => Sphere::new(center, radius, color, material).into(),
+            ShapeKind::Prism { origin, width, height, depth } => RectangularPrism::new(origin, width, height, depth, color, material).into(),
+            ShapeKind::Triangle { ps } => Triangle::new(ps, color, material).into()
+        };
+        // Only shapes with flags set are wrapped, so the rest stay in the scene's array rather than behind a pointer
+        self.objs.push(if flags == RenderFlags::default() {
+            primitive
+        } else {
+            Primitive::Other(Arc::new(Flagged::new(Arc::new(primitive), flags)))
+        });
+    }
+}
@@ -0,0 +1,738 @@
+use std::{collections::HashMap, error::Error, fmt, fs, io, path::Path, sync::Arc};
+
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    atmosphere::Atmosphere,
+    color::Color,
+    decal::Decal,
+    error::SceneError,
+    falloff::Falloff,
+    light::LightSource,
+    linalg::{Float, Mat3, Vec3d},
+    material::presets,
+    obj::{self, ObjError},
+    object::{simplify, BezierPatch, BvhBuild, ConvexPolyhedron, Curve, Flagged, Lod, Mapping, Material, Mesh, Object, Plane, PointCloud, Primitive, RectangularPrism, RenderFlags, Sphere, Splat, Surface, Textured, Triangle},
+    ply::{self, PlyError},
+    portal::Portal,
+    text::{self, TextError},
+    texture::{Texture, TextureError},
+    volume::{Volume, VolumeShape},
+    Fog, Scene
+};
+
+/*
+
+Scene Files
+
+Scenes described in RON, so they can be rendered without recompiling. Colors are either a name ("slate gray") or a
+hex value (0x708090), and materials default to matte or are one of the presets by name (Preset("gold")). Any
+material can be made single-sided (SingleSided(Preset("gold"))), so its surfaces are seen through from behind, e.g.
+
+(
+    camera: (0.0, 2.0, -1.0),
+    background: "black",
+    lights: [
+        Ambient(intensity: 0.1),
+        Point(intensity: 0.6, pos: (-3.0, 4.0, -6.0)),
+        Tube(intensity: 0.3, pos: (0.0, 5.0, -7.0), axis: (1.0, 0.0, 0.0), length: 2.0, radius: 0.05), // A line of light
+        // Point and tube lights can dim with distance, by falloff: None (default), Linear(range), InverseSquare, or
+        // Lookup([(distance, brightness), ...]) blended between increasing distances, e.g.
+        // Point(intensity: 4.0, pos: (0.0, 3.0, -4.0), falloff: InverseSquare),
+        // Directional lights are white unless given a color, e.g.
+        // Directional(intensity: 0.5, dir: (1.0, -1.0, -1.0), color: "light yellow"),
+    ],
+    objects: [
+        Sphere(center: (3.0, 2.0, -8.0), radius: 2.0, color: "red", material: Shiny(spclr_exp: 500.0, refl_rat: 0.1)),
+        Prism(origin: (1.0, 0.0, -7.5), width: 0.5, height: 0.5, depth: 0.5, color: 0x2E8B57),
+        Sphere(center: (0.0, 3.0, -6.0), radius: 0.2, color: "white", material: Emissive(intensity: 5.0)),
+        Sphere(center: (-3.0, 1.0, -8.0), radius: 1.0, color: "gold", material: Preset("gold")),
+        BezierPatch(color: "gold", points: ( // 4x4 control points, row by row
+            (-1.0, 0.0, -9.0), (-0.3, 0.0, -9.0), (0.3, 0.0, -9.0), (1.0, 0.0, -9.0),
+            (-1.0, 0.0, -8.3), (-0.3, 1.0, -8.3), (0.3, 1.0, -8.3), (1.0, 0.0, -8.3),
+            (-1.0, 0.0, -7.7), (-0.3, 1.0, -7.7), (0.3, 1.0, -7.7), (1.0, 0.0, -7.7),
+            (-1.0, 0.0, -7.0), (-0.3, 0.0, -7.0), (0.3, 0.0, -7.0), (1.0, 0.0, -7.0),
+        )),
+        ConvexPolyhedron(color: "goldenrod", planes: [ // Each plane through a point, its normal pointing out of the solid
+            (point: (-3.0, 0.0, -6.0), normal: (0.0, -1.0, 0.0)),
+            (point: (-3.0, 1.0, -6.0), normal: (0.0, 1.0, 0.0)),
+            (point: (-3.5, 0.0, -6.0), normal: (-1.0, 0.0, 0.0)),
+            (point: (-2.5, 0.0, -6.0), normal: (1.0, 0.0, 0.0)),
+            (point: (-3.0, 0.0, -5.5), normal: (0.0, 0.0, 1.0)),
+            (point: (-3.0, 0.0, -6.5), normal: (0.0, 0.0, -1.0)),
+        ]),
+        Curve(points: ((2.0, 0.0, -5.0), (2.1, 0.5, -5.0), (2.4, 0.8, -5.1)), radius: 0.02, tip_radius: Some(0.0), color: "green"),
+        // Solid letters facing +z from the baseline at origin, depth defaults to a fifth of the size, angle turns them about y
+        Text(text: "Hello", font: "scenes/fonts/Hack-Regular.ttf", origin: (-1.0, 0.0, -8.0), size: 0.8, depth: Some(0.1), angle: 0.0, color: "white"),
+        // A PLY mesh turned angle degrees about y and moved to origin, simplified to about a number of triangles if given
+        Mesh(file: "statue.ply", origin: (0.0, 0.0, -6.0), angle: 0.0, triangles: Some(20000), color: "white"),
+        // OBJ models keep the materials of their MTL files (see obj.rs), color and material are for faces without one.
+        // Simplifying drops the materials
+        Mesh(file: "scenes/meshes/crate.obj", origin: (2.0, 0.0, -6.0), color: "white"),
+        PointCloud(radius: 0.05, color: "white", file: Some("scan.ply"), points: [ // PLY file relative to the working directory
+            (pos: (0.0, 0.5, -5.0), normal: Some((0.0, 0.0, 1.0)), color: Some("red")), // Disk facing the normal
+            (pos: (0.2, 0.5, -5.0)), // Sphere in the cloud's color
+        ]),
+        // Any object painted with a PNG relative to the working directory, tiled repeat times (default 1) over its uv
+        Textured(image: "scenes/textures/checker.png", repeat: Some(2.0), object: Sphere(center: (0.0, 1.0, -4.0), radius: 0.5, color: "white")),
+        Textured(image: "scenes/textures/checker.png", mapping: Triplanar(sharpness: Some(4.0)), object: Prism(origin: (-2.0, 0.0, -5.0), width: 1.0, height: 1.0, depth: 1.0, color: "white")), // Mapping is Uv by default
+        // Any object with the rays that see it changed: camera, reflections and shadows default to true, shadow_catcher to
+        // false. A shadow catcher shows only the shadows falling on it, over whatever is behind it
+        Flagged(camera: false, object: Sphere(center: (-2.0, 4.0, -6.0), radius: 1.0, color: "black")),
+        // Versions of an object, each shown while the camera is within its distance of the object, and the last beyond
+        Lod(levels: [
+            (distance: 10.0, object: Sphere(center: (4.0, 1.0, -20.0), radius: 1.0, color: "white")),
+            (distance: 30.0, object: Prism(origin: (3.0, 0.0, -21.0), width: 2.0, height: 2.0, depth: 2.0, color: "white")),
+        ]),
+        // An object registered under a name by the program loading the scene (see Registry), built from args
+        Custom(name: "torus", args: (center: (0.0, 1.0, -6.0), radii: (1.0, 0.25)), color: "gold", material: Preset("gold")),
+    ],
+    volumes: [ // Optional
+        Sphere(center: (0.0, 1.0, -6.0), radius: 1.0, color: "white", absorption: 0.2, scattering: 0.8),
+        Box(min: (-2.0, 0.0, -9.0), max: (2.0, 1.0, -7.0), color: "teal", absorption: 0.5, scattering: 0.3),
+    ],
+    decals: [ // Optional, each image projected along dir through a box centred on pos, size across and depth along dir
+        (image: "scenes/textures/target.png", pos: (0.0, 1.0, -7.0), dir: (0.0, 0.0, -1.0), size: (1.0, 1.0), depth: Some(0.5), opacity: Some(0.8)),
+    ],
+    portals: [ // Optional, windows light from outside comes in through, as a corner and two edges at right angles from it
+        (corner: (-5.0, 1.0, -8.0), u: (0.0, 0.0, 2.0), v: (0.0, 1.5, 0.0)),
+    ],
+    fog: Some((color: "gray", density: 0.05, scattering: 0.5)), // Optional, scattering defaults to 0
+    atmosphere: Some((sun_dir: (1.0, 0.3, -1.0), sun_intensity: 20.0, haze: 50.0)), // Optional, replaces the background
+    bvh: Median, // Optional, how text and point clouds split their parts, Sah (default) or the quicker to build Median
+    ray_bias: Some(1e-6), // Optional, how far rays leaving a surface start from it per unit of distance, raise for huge scenes
+)
+
+*/
+
+type Point = (Float, Float, Float);
+
+#[derive(Deserialize)]
+struct SceneDesc {
+    camera: Point,
+    #[serde(default = "default_background")]
+    background: ColorDesc,
+    lights: Vec<LightDesc>,
+    objects: Vec<ObjectDesc>,
+    #[serde(default)]
+    volumes: Vec<VolumeDesc>,
+    #[serde(default)]
+    decals: Vec<DecalDesc>,
+    #[serde(default)]
+    portals: Vec<PortalDesc>,
+    #[serde(default)]
+    fog: Option<FogDesc>,
+    #[serde(default)]
+    atmosphere: Option<AtmosphereDesc>,
+    #[serde(default)]
+    bvh: BvhDesc,
+    #[serde(default)]
+    ray_bias: Option<Float>
+}
+
+#[derive(Deserialize, Default)]
+enum BvhDesc {
+    Median,
+    #[default]
+    Sah
+}
+
+#[derive(Deserialize)]
+enum VolumeDesc {
+    Sphere { center: Point, radius: Float, color: ColorDesc, absorption: Float, scattering: Float },
+    Box { min: Point, max: Point, color: ColorDesc, absorption: Float, scattering: Float }
+}
+
+#[derive(Deserialize)]
+struct DecalDesc {
+    image: String,
+    pos: Point,
+    dir: Point,
+    size: (Float, Float),
+    #[serde(default)]
+    depth: Option<Float>,
+    #[serde(default)]
+    opacity: Option<Float>
+}
+
+#[derive(Deserialize)]
+struct PortalDesc {
+    corner: Point,
+    u: Point,
+    v: Point
+}
+
+#[derive(Deserialize)]
+struct FogDesc {
+    color: ColorDesc,
+    density: Float,
+    #[serde(default)]
+    scattering: Float
+}
+
+#[derive(Deserialize)]
+struct AtmosphereDesc {
+    sun_dir: Point,
+    #[serde(default = "default_sun_intensity")]
+    sun_intensity: Float,
+    #[serde(default)]
+    haze: Float
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorDesc {
+    Name(String),
+    Hex(u32)
+}
+
+#[derive(Deserialize)]
+enum LightDesc {
+    Ambient { intensity: Float },
+    Point { intensity: Float, pos: Point, #[serde(default)] falloff: FalloffDesc },
+    Directional { intensity: Float, dir: Point, #[serde(default)] color: Option<ColorDesc> },
+    Tube { intensity: Float, pos: Point, axis: Point, length: Float, radius: Float, #[serde(default)] falloff: FalloffDesc }
+}
+
+#[derive(Deserialize, Default)]
+enum FalloffDesc {
+    #[default]
+    None,
+    Linear(Float),
+    InverseSquare,
+    Lookup(Vec<(Float, Float)>)
+}
+
+#[derive(Deserialize, Default)]
+enum MaterialDesc {
+    #[default]
+    Matte,
+    Shiny { spclr_exp: Float, refl_rat: Float },
+    Emissive { intensity: Float },
+    Preset(String),
+    SingleSided(Box<MaterialDesc>)
+}
+
+#[derive(Deserialize)]
+enum ObjectDesc {
+    Sphere { center: Point, radius: Float, color: ColorDesc, #[serde(default)] material: MaterialDesc },
+    Triangle { points: [Point; 3], color: ColorDesc, #[serde(default)] material: MaterialDesc },
+    Prism { origin: Point, width: Float, height: Float, depth: Float, color: ColorDesc, #[serde(default)] material: MaterialDesc },
+    BezierPatch { points: Box<[Point; 16]>, color: ColorDesc, #[serde(default)] material: MaterialDesc },
+    ConvexPolyhedron { planes: Vec<PlaneDesc>, color: ColorDesc, #[serde(default)] material: MaterialDesc },
+    Curve {
+        points: [Point; 3],
+        radius: Float,
+        #[serde(default)]
+        tip_radius: Option<Float>,
+        color: ColorDesc,
+        #[serde(default)]
+        material: MaterialDesc
+    },
+    Text {
+        text: String,
+        font: String,
+        origin: Point,
+        size: Float,
+        #[serde(default)]
+        depth: Option<Float>,
+        #[serde(default)]
+        angle: Float,
+        color: ColorDesc,
+        #[serde(default)]
+        material: MaterialDesc
+    },
+    Mesh {
+        file: String,
+        #[serde(default)]
+        origin: Point,
+        #[serde(default)]
+        angle: Float,
+        #[serde(default)]
+        triangles: Option<usize>,
+        color: ColorDesc,
+        #[serde(default)]
+        material: MaterialDesc
+    },
+    PointCloud {
+        #[serde(default)]
+        points: Vec<SplatDesc>,
+        #[serde(default)]
+        file: Option<String>,
+        radius: Float,
+        color: ColorDesc,
+        #[serde(default)]
+        material: MaterialDesc
+    },
+    Textured {
+        image: String,
+        #[serde(default)]
+        repeat: Option<Float>,
+        #[serde(default)]
+        mapping: MappingDesc,
+        object: Box<ObjectDesc>
+    },
+    Flagged {
+        #[serde(default = "default_true")]
+        camera: bool,
+        #[serde(default = "default_true")]
+        reflections: bool,
+        #[serde(default = "default_true")]
+        shadows: bool,
+        #[serde(default)]
+        shadow_catcher: bool,
+        object: Box<ObjectDesc>
+    },
+    Lod {
+        levels: Vec<LevelDesc>
+    },
+    Custom {
+        name: String,
+        #[serde(default = "default_args")]
+        args: ron::Value,
+        color: ColorDesc,
+        #[serde(default)]
+        material: MaterialDesc
+    }
+}
+
+#[derive(Deserialize)]
+struct LevelDesc {
+    distance: Float,
+    object: ObjectDesc
+}
+
+#[derive(Deserialize, Default)]
+enum MappingDesc {
+    #[default]
+    Uv,
+    Triplanar { #[serde(default)] sharpness: Option<Float> }
+}
+
+#[derive(Deserialize)]
+struct SplatDesc {
+    pos: Point,
+    #[serde(default)]
+    normal: Option<Point>,
+    #[serde(default)]
+    color: Option<ColorDesc>
+}
+
+#[derive(Deserialize)]
+struct PlaneDesc {
+    point: Point,
+    normal: Point
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_args() -> ron::Value {
+    ron::Value::Unit
+}
+
+fn default_background() -> ColorDesc {
+    ColorDesc::Hex(Color::Black as u32)
+}
+
+fn default_sun_intensity() -> Float {
+    20.0
+}
+
+// Read and build the scene stored at path
+pub fn load(path: impl AsRef<Path>) -> Result<Scene, SceneFileError> {
+    parse(&fs::read_to_string(path)?)
+}
+
+// load, with the objects built as options ask
+pub fn load_with(path: impl AsRef<Path>, options: &LoadOptions) -> Result<Scene, SceneFileError> {
+    parse_with(&fs::read_to_string(path)?, options)
+}
+
+// How a scene file's objects are built, beyond what the file describes
+#[derive(Clone, Default)]
+pub struct LoadOptions {
+    pub background_bvh: bool, // Build mesh and point cloud hierarchies on other threads, tracing them slowly until they're ready
+    pub registry: Registry,   // The objects Custom(...) can name
+    pub no_files: bool        // Refuse scenes that read meshes, fonts, point clouds or images, e.g. sent from another machine
+}
+
+impl LoadOptions {
+    fn allow_file(&self, file: &str) -> Result<(), SceneFileError> {
+        match self.no_files {
+            true => Err(SceneFileError::FileNotAllowed(file.to_string())),
+            false => Ok(())
+        }
+    }
+}
+
+// Build a scene from the contents of a scene file
+pub fn parse(src: &str) -> Result<Scene, SceneFileError> {
+    parse_with(src, &LoadOptions::default())
+}
+
+// parse, with the objects built as options ask
+pub fn parse_with(src: &str, options: &LoadOptions) -> Result<Scene, SceneFileError> {
+    let desc: SceneDesc = ron::from_str(src).map_err(|e| SceneFileError::Parse(e.to_string()))?;
+
+    // Lights are checked as the scene is built
+    let lights = desc.lights.into_iter().map(|light| Ok(match light {
+        LightDesc::Ambient { intensity } => LightSource::Ambient { intensity },
+        LightDesc::Point { intensity, pos, falloff } => LightSource::Point { intensity, pos: vec3d(pos), falloff: falloff.into() },
+        LightDesc::Directional { intensity, dir, color } => {
+            let color = color.map_or(Ok(Color::White as usize), ColorDesc::resolve)?;
+            LightSource::Directional { intensity, dir: vec3d(dir), color }
+        }
+        LightDesc::Tube { intensity, pos, axis, length, radius, falloff } => {
+            LightSource::Tube { intensity, pos: vec3d(pos), axis: vec3d(axis), length, radius, falloff: falloff.into() }
+        }
+    })).collect::<Result<Vec<_>, SceneFileError>>()?;
+
+    let build = desc.bvh.into();
+    let objs = desc.objects.into_iter().map(|obj| object(obj, build, options)).collect::<Result<Vec<_>, _>>()?;
+
+    let mut scene = Scene::new(vec3d(desc.camera), desc.background.resolve()?, lights, objs)?;
+    for volume in desc.volumes {
+        scene = scene.with_volume(match volume {
+            VolumeDesc::Sphere { center, radius, color, absorption, scattering } => {
+                Volume::new(VolumeShape::Sphere { center: vec3d(center), radius }, color.resolve()?, absorption, scattering)
+            }
+            VolumeDesc::Box { min, max, color, absorption, scattering } => {
+                Volume::new(VolumeShape::Box { min: vec3d(min), max: vec3d(max) }, color.resolve()?, absorption, scattering)
+            }
+        });
+    }
+    for DecalDesc { image, pos, dir, size, depth, opacity } in desc.decals {
+        options.allow_file(&image)?;
+        let texture = Texture::load(&image).map_err(|e| SceneFileError::Texture(image, e))?;
+        let mut decal = Decal::new(vec3d(pos), vec3d(dir), size, Arc::new(texture));
+        if let Some(depth) = depth {
+            decal = decal.with_depth(depth);
+        }
+        if let Some(opacity) = opacity {
+            decal = decal.with_opacity(opacity);
+        }
+        scene = scene.with_decal(decal);
+    }
+    for PortalDesc { corner, u, v } in desc.portals {
+        scene = scene.with_portal(Portal::new(vec3d(corner), vec3d(u), vec3d(v)));
+    }
+    if let Some(fog) = desc.fog {
+        scene = scene.with_fog(Fog { color: fog.color.resolve()?, density: fog.density, scattering: fog.scattering });
+    }
+    if let Some(atmosphere) = desc.atmosphere {
+        scene = scene.with_atmosphere(Atmosphere::new(vec3d(atmosphere.sun_dir), atmosphere.sun_intensity, atmosphere.haze));
+    }
+    if let Some(ray_bias) = desc.ray_bias {
+        if !(ray_bias.is_finite() && ray_bias >= 0.0) {
+            return Err(SceneFileError::InvalidRayBias(ray_bias));
+        }
+        scene = scene.with_ray_bias(ray_bias);
+    }
+
+    Ok(scene)
+}
+
+// Build an object of the scene, or the object inside a textured one
+fn object(obj: ObjectDesc, build: BvhBuild, options: &LoadOptions) -> Result<Primitive, SceneFileError> {
+    Ok(match obj {
+        ObjectDesc::Sphere { center, radius, color, material } => {
+            Sphere::new(vec3d(center), radius, color.resolve()?, material.resolve()?).into()
+        }
+        ObjectDesc::Triangle { points, color, material } => {
+            Triangle::new(points.map(vec3d), color.resolve()?, material.resolve()?).into()
+        }
+        ObjectDesc::Prism { origin, width, height, depth, color, material } => {
+            RectangularPrism::new(vec3d(origin), width, height, depth, color.resolve()?, material.resolve()?).into()
+        }
+        ObjectDesc::BezierPatch { points, color, material } => {
+            Primitive::Other(Arc::new(BezierPatch::new((*points).map(vec3d), color.resolve()?, material.resolve()?)))
+        }
+        ObjectDesc::ConvexPolyhedron { planes, color, material } => {
+            let planes = planes.into_iter().map(|plane| Plane::new(vec3d(plane.point), vec3d(plane.normal))).collect();
+            ConvexPolyhedron::new(planes, color.resolve()?, material.resolve()?).into()
+        }
+        ObjectDesc::Curve { points, radius, tip_radius, color, material } => {
+            Primitive::Other(Arc::new(Curve::new(points.map(vec3d), radius, tip_radius.unwrap_or(radius), color.resolve()?, material.resolve()?)))
+        }
+        ObjectDesc::Text { text, font, origin, size, depth, angle, color, material } => {
+            options.allow_file(&font)?;
+            let (depth, color, material) = (depth.unwrap_or(size * 0.2), color.resolve()?, material.resolve()?);
+            let mesh = text::load_font(&font)
+                .and_then(|data| text::text_mesh(&data, &text, size, depth, color, material))
+                .map_err(|e| SceneFileError::Font(font, e))?
+                .with_bvh(build);
+            let mesh = if options.background_bvh { mesh.in_background() } else { mesh };
+            Primitive::Other(Arc::new(mesh.transform(&Mat3::rotation_y(angle), &vec3d(origin))))
+        }
+        ObjectDesc::Mesh { file, origin, angle, triangles, color, material } => {
+            options.allow_file(&file)?;
+            let (color, material) = (color.resolve()?, material.resolve()?);
+            let mesh = if file.to_lowercase().ends_with(".obj") {
+                model(file, triangles, color, material)?
+            } else {
+                let (vertices, faces) = ply::load_mesh(&file).map_err(|e| SceneFileError::Mesh(file, e))?;
+                let (vertices, faces) = match triangles {
+                    Some(target) => simplify(vertices, faces, target),
+                    None => (vertices, faces)
+                };
+                Mesh::new(vertices, faces, color, material)
+            }.with_bvh(build);
+            let mesh = if options.background_bvh { mesh.in_background() } else { mesh };
+            Primitive::Other(Arc::new(mesh.transform(&Mat3::rotation_y(angle), &vec3d(origin))))
+        }
+        ObjectDesc::PointCloud { points, file, radius, color, material } => {
+            let mut splats = points.into_iter().map(|splat| -> Result<Splat, SceneFileError> {
+                Ok(Splat::new(vec3d(splat.pos), splat.normal.map(vec3d), splat.color.map(ColorDesc::resolve).transpose()?))
+            }).collect::<Result<Vec<_>, _>>()?;
+            if let Some(file) = file {
+                options.allow_file(&file)?;
+                splats.extend(ply::load(&file).map_err(|e| SceneFileError::PointCloud(file, e))?);
+            }
+            let cloud = PointCloud::new(splats, radius, color.resolve()?, material.resolve()?).with_bvh(build);
+            Primitive::Other(Arc::new(if options.background_bvh { cloud.in_background() } else { cloud }))
+        }
+        ObjectDesc::Textured { image, repeat, mapping, object: inner } => {
+            options.allow_file(&image)?;
+            let texture = Texture::load(&image).map_err(|e| SceneFileError::Texture(image, e))?;
+            let mapping = match mapping {
+                MappingDesc::Uv => Mapping::Uv,
+                MappingDesc::Triplanar { sharpness } => match sharpness.unwrap_or(4.0) {
+                    s if s.is_finite() && s > 0.0 => Mapping::Triplanar { sharpness: s },
+                    s => return Err(SceneFileError::InvalidSharpness(s))
+                }
+            };
+            let inner: Arc<dyn Object> = Arc::new(object(*inner, build, options)?);
+            Primitive::Other(Arc::new(Textured::new(inner, Arc::new(texture), repeat.unwrap_or(1.0)).with_mapping(mapping)))
+        }
+        ObjectDesc::Flagged { camera, reflections, shadows, shadow_catcher, object: inner } => {
+            let inner: Arc<dyn Object> = Arc::new(object(*inner, build, options)?);
+            Primitive::Other(Arc::new(Flagged::new(inner, RenderFlags { camera, reflections, shadows, shadow_catcher })))
+        }
+        ObjectDesc::Lod { levels } => {
+            if levels.is_empty() {
+                return Err(SceneFileError::NoLevels);
+            }
+            let levels = levels.into_iter()
+                .map(|LevelDesc { distance, object: inner }| Ok((distance, Arc::new(object(inner, build, options)?) as Arc<dyn Object>)))
+                .collect::<Result<_, SceneFileError>>()?;
+            Primitive::Other(Arc::new(Lod::new(levels)))
+        }
+        ObjectDesc::Custom { name, args, color, material } => {
+            options.registry.build(name, args, color.resolve()?, material.resolve()?)?
+        }
+    })
+}
+
+// An OBJ model as a mesh, its faces looking like their materials (or color and material when they have none) unless
+// it's simplified
+fn model(file: String, triangles: Option<usize>, color: usize, material: Material) -> Result<Mesh, SceneFileError> {
+    let model = obj::load(&file).map_err(|e| SceneFileError::Model(file, e))?;
+    if let Some(target) = triangles {
+        let (vertices, faces) = simplify(model.vertices, model.faces, target);
+        return Ok(Mesh::new(vertices, faces, color, material));
+    }
+
+    let mut surfaces = model.materials.iter().map(|mtl| {
+        let texture = match &mtl.texture {
+            Some(path) => Some(Arc::new(Texture::load(path).map_err(|e| SceneFileError::Texture(path.display().to_string(), e))?)),
+            None => None
+        };
+        Ok(Surface { color: mtl.color(), material: mtl.material(), texture })
+    }).collect::<Result<Vec<_>, SceneFileError>>()?;
+    surfaces.push(Surface { color, material: material.clone(), texture: None });
+
+    let fallback = surfaces.len() - 1;
+    let face_surfaces = model.face_materials.iter().map(|i| i.unwrap_or(fallback)).collect();
+    Ok(Mesh::new(model.vertices, model.faces, color, material).with_uvs(model.uvs).with_surfaces(surfaces, face_surfaces))
+}
+
+fn vec3d((x, y, z): Point) -> Vec3d {
+    Vec3d::new(x, y, z)
+}
+
+impl ColorDesc {
+    fn resolve(self) -> Result<usize, SceneFileError> {
+        match self {
+            ColorDesc::Name(name) => Color::from_name(&name).map(|c| c as usize).ok_or(SceneFileError::UnknownColor(name)),
+            ColorDesc::Hex(hex) => Ok(hex as usize & 0xFFFFFF)
+        }
+    }
+}
+
+impl From<FalloffDesc> for Falloff {
+    fn from(desc: FalloffDesc) -> Self {
+        match desc {
+            FalloffDesc::None => Falloff::None,
+            FalloffDesc::Linear(range) => Falloff::Linear { range },
+            FalloffDesc::InverseSquare => Falloff::InverseSquare,
+            FalloffDesc::Lookup(table) => Falloff::Lookup(table)
+        }
+    }
+}
+
+impl MaterialDesc {
+    fn resolve(self) -> Result<Material, SceneFileError> {
+        match self {
+            MaterialDesc::Matte => Ok(Material::default()),
+            MaterialDesc::Shiny { spclr_exp, refl_rat } => Ok(Material::Shiny { spclr_exp, refl_rat, double_sided: true }),
+            MaterialDesc::Emissive { intensity } => Ok(Material::Emissive { intensity, double_sided: true }),
+            MaterialDesc::Preset(name) => presets::from_name(&name).ok_or(SceneFileError::UnknownMaterial(name)),
+            MaterialDesc::SingleSided(material) => Ok(material.resolve()?.single_sided())
+        }
+    }
+}
+
+impl From<BvhDesc> for BvhBuild {
+    fn from(build: BvhDesc) -> Self {
+        match build {
+            BvhDesc::Median => BvhBuild::Median,
+            BvhDesc::Sah => BvhBuild::Sah
+        }
+    }
+}
+
+/*
+
+Registry
+
+Objects a program adds to the ones scene files can describe, each built by a function registered under a name. A scene
+file's Custom(name: ..., args: ...) looks the name up and deserializes args (unit when left out) into whatever type the
+function takes, so objects are described with the same RON as the built-in ones, e.g.
+
+    #[derive(Deserialize)]
+    struct TorusArgs { center: (f64, f64, f64), radii: (f64, f64) }
+
+    let registry = Registry::new().register("torus", |args: TorusArgs, color, material| {
+        Ok(Torus::new(args.center, args.radii, color, material))
+    });
+    let scene = scene_file::load_with(path, &LoadOptions { registry, ..Default::default() })?;
+
+The function is given the object's color and material as resolved from the file, and returns why it couldn't build the
+object otherwise
+
+*/
+
+type Build = dyn Fn(ron::Value, usize, Material) -> Result<Arc<dyn Object>, String> + Send + Sync;
+
+#[derive(Clone, Default)]
+pub struct Registry {
+    builds: HashMap<String, Arc<Build>>
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Build the objects named name with build, replacing anything registered under the name before
+    pub fn register<A, O>(mut self, name: &str, build: impl Fn(A, usize, Material) -> Result<O, String> + Send + Sync + 'static) -> Self
+    where
+        A: DeserializeOwned,
+        O: Object + 'static
+    {
+        let build = move |args: ron::Value, color, material| -> Result<Arc<dyn Object>, String> {
+            let args = args.into_rust::<A>().map_err(|e| format!("invalid args: {}", e))?;
+            Ok(Arc::new(build(args, color, material)?))
+        };
+        self.builds.insert(name.to_string(), Arc::new(build));
+        self
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.builds.keys().map(String::as_str)
+    }
+
+    fn build(&self, name: String, args: ron::Value, color: usize, material: Material) -> Result<Primitive, SceneFileError> {
+        let Some(build) = self.builds.get(&name) else {
+            return Err(SceneFileError::UnknownObject(name));
+        };
+        match build(args, color, material) {
+            Ok(obj) => Ok(Primitive::Other(obj)),
+            Err(e) => Err(SceneFileError::Custom(name, e))
+        }
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+/*
+
+Scene File Error
+
+*/
+
+#[derive(Debug)]
+pub enum SceneFileError {
+    Io(io::Error),
+    Parse(String),
+    UnknownColor(String),
+    UnknownMaterial(String),
+    PointCloud(String, PlyError),  // The point cloud file and what's wrong with it
+    Mesh(String, PlyError),        // The mesh file and what's wrong with it
+    Model(String, ObjError),       // The OBJ file and what's wrong with it
+    Font(String, TextError),       // The font file and what's wrong with it
+    Texture(String, TextureError), // The image file and what's wrong with it
+    InvalidRayBias(Float),
+    InvalidSharpness(Float),
+    Scene(SceneError),      // Why the scene described can't be built, e.g. a light that can't be used
+    NoLevels,               // A level of detail object without any levels
+    FileNotAllowed(String), // A file the scene reads when loaded without files
+    UnknownObject(String),  // A custom object's name that nothing was registered under
+    Custom(String, String)  // The custom object's name and why it couldn't be built
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneFileError::Io(e) => write!(f, "couldn't read scene file: {}", e),
+            SceneFileError::Parse(e) => write!(f, "invalid scene file: {}", e),
+            SceneFileError::UnknownColor(name) => write!(f, "unknown color \"{}\"", name),
+            SceneFileError::UnknownMaterial(name) => write!(f, "unknown material preset \"{}\", expected one of {}", name, presets::NAMES.join(", ")),
+            SceneFileError::PointCloud(file, e) => write!(f, "couldn't load point cloud \"{}\": {}", file, e),
+            SceneFileError::Mesh(file, e) => write!(f, "couldn't load mesh \"{}\": {}", file, e),
+            SceneFileError::Model(file, e) => write!(f, "couldn't load model \"{}\": {}", file, e),
+            SceneFileError::Font(file, e) => write!(f, "couldn't load font \"{}\": {}", file, e),
+            SceneFileError::Texture(file, e) => write!(f, "couldn't load texture \"{}\": {}", file, e),
+            SceneFileError::InvalidRayBias(bias) => write!(f, "ray bias must be non-negative, got {}", bias),
+            SceneFileError::InvalidSharpness(sharpness) => write!(f, "triplanar sharpness must be positive, got {}", sharpness),
+            SceneFileError::Scene(e) => write!(f, "{}", e),
+            SceneFileError::NoLevels => write!(f, "level of detail object has no levels"),
+            SceneFileError::FileNotAllowed(file) => write!(f, "scene reads \"{}\", but can't read files here", file),
+            SceneFileError::UnknownObject(name) => write!(f, "no custom object is registered as \"{}\"", name),
+            SceneFileError::Custom(name, e) => write!(f, "couldn't build custom object \"{}\": {}", name, e)
+        }
+    }
+}
+
+impl Error for SceneFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneFileError::Io(e) => Some(e),
+            SceneFileError::PointCloud(_, e) => Some(e),
+            SceneFileError::Mesh(_, e) => Some(e),
+            SceneFileError::Model(_, e) => Some(e),
+            SceneFileError::Font(_, e) => Some(e),
+            SceneFileError::Texture(_, e) => Some(e),
+            SceneFileError::Scene(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for SceneFileError {
+    fn from(e: io::Error) -> Self {
+        SceneFileError::Io(e)
+    }
+}
+
+impl From<SceneError> for SceneFileError {
+    fn from(e: SceneError) -> Self {
+        SceneFileError::Scene(e)
+    }
+}
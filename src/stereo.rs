@@ -0,0 +1,94 @@
+use std::{fmt, str::FromStr};
+
+use crate::{color::Color, linalg::Float};
+
+/*
+
+Stereo
+
+Renders the scene once for each eye, from cameras set apart sideways by the interocular distance, so it can be viewed
+in 3D. The eyes look in parallel, so distant objects sit at the screen and nearer ones float in front of it
+
+An anaglyph merges the two views into one image for red/cyan glasses: the red channel comes from the left eye's view,
+and green and blue from the right eye's
+
+Side by side puts the left eye's view in the left half of the image and the right eye's in the right half, for VR
+viewers and viewing with eyes apart (parallel viewing). Cross-eyed swaps the halves, for viewing with eyes crossed
+
+*/
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Stereo {
+    #[default]
+    Mono,
+    Anaglyph,
+    SideBySide,
+    CrossEyed
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Eye {
+    Left,
+    Right
+}
+
+impl Eye {
+    // Sideways offset of the eye from the camera, given the distance between the eyes
+    pub fn offset(&self, interocular: Float) -> Float {
+        match self {
+            Eye::Left => -interocular / 2.0,
+            Eye::Right => interocular / 2.0
+        }
+    }
+}
+
+impl Stereo {
+    // Number of views placed next to each other across the canvas
+    pub fn views(&self) -> usize {
+        match self {
+            Stereo::Mono | Stereo::Anaglyph => 1,
+            Stereo::SideBySide | Stereo::CrossEyed => 2
+        }
+    }
+
+    // The eye a canvas column is seen from, given the eye the whole frame is traced from (for anaglyphs)
+    pub fn eye_at(&self, col: usize, canvas_width: usize, frame_eye: Option<Eye>) -> Option<Eye> {
+        let left_half = col < canvas_width / 2;
+        match self {
+            Stereo::Mono => None,
+            Stereo::Anaglyph => frame_eye,
+            Stereo::SideBySide => Some(if left_half { Eye::Left } else { Eye::Right }),
+            Stereo::CrossEyed => Some(if left_half { Eye::Right } else { Eye::Left })
+        }
+    }
+}
+
+// Combine the colors each eye sees at a canvas unit into a red/cyan anaglyph
+pub fn anaglyph(left: usize, right: usize) -> usize {
+    Color::from_rgb(Color::r(left) as Float, Color::g(right) as Float, Color::b(right) as Float)
+}
+
+impl fmt::Display for Stereo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stereo::Mono => write!(f, "mono"),
+            Stereo::Anaglyph => write!(f, "anaglyph"),
+            Stereo::SideBySide => write!(f, "side-by-side"),
+            Stereo::CrossEyed => write!(f, "cross-eyed")
+        }
+    }
+}
+
+impl FromStr for Stereo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mono" => Ok(Stereo::Mono),
+            "anaglyph" => Ok(Stereo::Anaglyph),
+            "side-by-side" => Ok(Stereo::SideBySide),
+            "cross-eyed" => Ok(Stereo::CrossEyed),
+            _ => Err(format!("unknown stereo mode \"{}\", expected mono, anaglyph, side-by-side or cross-eyed", s))
+        }
+    }
+}
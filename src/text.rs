@@ -0,0 +1,379 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use ttf_parser::{Face, FaceParsingError, GlyphId, OutlineBuilder};
+
+use crate::{linalg::{Float, Vec3d}, object::{Material, Mesh}};
+
+/*
+
+3D Text
+
+Solid letters for labels and titles, built from the outlines of a TrueType or OpenType font. Each letter's outline is
+cut into triangles for its front and back, and joined by walls around its edges
+
+The text faces +z, towards a camera looking down -z. It starts at the origin and runs along +x, with the baseline of its
+first line on the x axis, later lines below it, and its depth reaching back towards -z
+
+*/
+
+const CURVE_STEPS: usize = 8; // Straight segments each curve of an outline is split into
+
+type Point = (Float, Float);
+
+// Read a font file
+pub fn load_font(path: impl AsRef<Path>) -> Result<Vec<u8>, TextError> {
+    Ok(fs::read(path)?)
+}
+
+// Build solid text from the contents of a font file. Size is the height of the font's em square, roughly the distance
+// from the bottom of a "g" to the top of an "h"
+pub fn text_mesh(font: &[u8], text: &str, size: Float, depth: Float, color: usize, material: Material) -> Result<Mesh, TextError> {
+    let face = Face::parse(font, 0)?;
+    let scale = size / face.units_per_em() as Float;
+    let line_height = (face.ascender() - face.descender() + face.line_gap()) as Float * scale;
+
+    let mut vertices = Vec::new();
+    let mut faces = Vec::new();
+    let mut pen = (0.0, 0.0);
+
+    for c in text.chars() {
+        if c == '\n' {
+            pen = (0.0, pen.1 - line_height);
+            continue;
+        }
+
+        // Characters missing from the font are drawn as its placeholder glyph
+        let glyph = face.glyph_index(c).unwrap_or(GlyphId(0));
+        let mut outline = Outline { contours: Vec::new(), current: Vec::new(), scale, offset: pen };
+        face.outline_glyph(glyph, &mut outline);
+        extrude(&outline.contours, depth, &mut vertices, &mut faces);
+
+        pen.0 += face.glyph_hor_advance(glyph).unwrap_or(0) as Float * scale;
+    }
+
+    Ok(Mesh::new(vertices, faces, color, material))
+}
+
+// Add the front, back and walls of a glyph given its contours
+fn extrude(contours: &[Vec<Point>], depth: Float, vertices: &mut Vec<Vec3d>, faces: &mut Vec<[usize; 3]>) {
+    let points: Vec<Point> = contours.concat();
+    let front = vertices.len();
+    let back = front + points.len();
+    vertices.extend(points.iter().map(|&(x, y)| Vec3d::new(x, y, 0.0)));
+    vertices.extend(points.iter().map(|&(x, y)| Vec3d::new(x, y, -depth)));
+
+    for [a, b, c] in triangulate(contours) {
+        faces.push([front + a, front + b, front + c]);
+        faces.push([back + a, back + c, back + b]);
+    }
+
+    let mut start = 0;
+    for contour in contours {
+        for i in 0..contour.len() {
+            let (a, b) = (start + i, start + (i + 1) % contour.len());
+            faces.push([front + a, front + b, back + b]);
+            faces.push([front + a, back + b, back + a]);
+        }
+        start += contour.len();
+    }
+}
+
+/*
+
+Triangulation
+
+Outlines are made of closed contours, some of them the outsides of shapes and some holes cut into them (like the middle
+of an "o"). Each hole is joined to the outside around it by a cut, making a single polygon that doubles back along the
+cut, and the polygon is split into triangles by repeatedly cutting off a corner with nothing else inside it (ear
+clipping). Points are referred to by their index in all the contours laid end to end
+
+*/
+
+fn triangulate(contours: &[Vec<Point>]) -> Vec<[usize; 3]> {
+    let mut starts = Vec::with_capacity(contours.len());
+    let mut points = Vec::new();
+    for contour in contours {
+        starts.push(points.len());
+        points.extend_from_slice(contour);
+    }
+    let indices = |c: usize| (starts[c]..starts[c] + contours[c].len()).collect::<Vec<usize>>();
+
+    // A contour inside an odd number of others is a hole
+    let depths: Vec<usize> = (0..contours.len()).map(|c| {
+        (0..contours.len()).filter(|&other| other != c && inside(&contours[other], contours[c][0])).count()
+    }).collect();
+
+    let mut triangles = Vec::new();
+    for outer in (0..contours.len()).filter(|&c| depths[c].is_multiple_of(2)) {
+        let mut polygon = indices(outer);
+        if area(&points, &polygon) < 0.0 {
+            polygon.reverse();
+        }
+
+        let mut holes: Vec<Vec<usize>> = (0..contours.len())
+            .filter(|&h| depths[h] == depths[outer] + 1 && inside(&contours[outer], contours[h][0]))
+            .map(|h| {
+                let mut hole = indices(h);
+                if area(&points, &hole) > 0.0 {
+                    hole.reverse();
+                }
+                hole
+            })
+            .collect();
+
+        // Holes furthest right are joined first, so later cuts don't have to cross earlier ones
+        holes.sort_by(|a, b| rightmost(&points, b).1.total_cmp(&rightmost(&points, a).1));
+        for h in 0..holes.len() {
+            polygon = bridge(&points, polygon, &holes[h], &holes[h + 1..]);
+        }
+
+        clip_ears(&points, polygon, &mut triangles);
+    }
+
+    triangles
+}
+
+// Join a hole into the polygon around it with a cut from the hole's rightmost point to a point of the polygon it can
+// see without crossing any edge
+fn bridge(points: &[Point], polygon: Vec<usize>, hole: &[usize], other_holes: &[Vec<usize>]) -> Vec<usize> {
+    let (m, _) = rightmost(points, hole);
+    let from = points[hole[m]];
+
+    let edges = |ring: &[usize]| (0..ring.len()).map(move |i| (ring[i], ring[(i + 1) % ring.len()])).collect::<Vec<_>>();
+    let mut blockers = edges(&polygon);
+    blockers.extend(edges(hole));
+    for other in other_holes {
+        blockers.extend(edges(other));
+    }
+
+    let dist = |i: usize| {
+        let (x, y) = points[polygon[i]];
+        (x - from.0).powi(2) + (y - from.1).powi(2)
+    };
+    let mut candidates: Vec<usize> = (0..polygon.len()).collect();
+    candidates.sort_by(|&a, &b| dist(a).total_cmp(&dist(b)));
+
+    let visible = candidates.iter().copied().find(|&i| {
+        let to = points[polygon[i]];
+        blockers.iter().all(|&(a, b)| !crosses(from, to, points[a], points[b]))
+    });
+    let Some(v) = visible.or(candidates.first().copied()) else {
+        return polygon;
+    };
+
+    // Out along the cut, around the hole, and back
+    let mut joined = polygon[..=v].to_vec();
+    joined.extend(hole[m..].iter().chain(&hole[..m]));
+    joined.push(hole[m]);
+    joined.extend(&polygon[v..]);
+    joined
+}
+
+// Cut a counterclockwise polygon into triangles
+fn clip_ears(points: &[Point], mut polygon: Vec<usize>, triangles: &mut Vec<[usize; 3]>) {
+    while polygon.len() > 3 {
+        let n = polygon.len();
+        let corner = |i: usize| (polygon[(i + n - 1) % n], polygon[i], polygon[(i + 1) % n]);
+
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = corner(i);
+            let (pa, pb, pc) = (points[a], points[b], points[c]);
+            cross(pa, pb, pc) > 0.0 && polygon.iter().all(|&p| {
+                let pp = points[p];
+                pp == pa || pp == pb || pp == pc || !in_triangle(pp, pa, pb, pc)
+            })
+        });
+
+        // Rounding can leave no clean ear in badly shaped outlines, so cut off the flattest corner rather than stopping
+        let ear = ear.unwrap_or_else(|| {
+            (0..n).min_by(|&i, &j| {
+                let flatness = |k: usize| {
+                    let (a, b, c) = corner(k);
+                    cross(points[a], points[b], points[c]).abs()
+                };
+                flatness(i).total_cmp(&flatness(j))
+            }).unwrap_or(0)
+        });
+
+        let (a, b, c) = corner(ear);
+        if cross(points[a], points[b], points[c]).abs() > 0.0 {
+            triangles.push([a, b, c]);
+        }
+        polygon.remove(ear);
+    }
+
+    if let [a, b, c] = polygon[..] {
+        if cross(points[a], points[b], points[c]).abs() > 0.0 {
+            triangles.push([a, b, c]);
+        }
+    }
+}
+
+// Twice the signed area of a polygon, positive when it runs counterclockwise
+fn area(points: &[Point], polygon: &[usize]) -> Float {
+    (0..polygon.len()).map(|i| {
+        let (x0, y0) = points[polygon[i]];
+        let (x1, y1) = points[polygon[(i + 1) % polygon.len()]];
+        x0 * y1 - x1 * y0
+    }).sum()
+}
+
+// Position in the ring and x of its rightmost point
+fn rightmost(points: &[Point], ring: &[usize]) -> (usize, Float) {
+    ring.iter().enumerate()
+        .map(|(i, &p)| (i, points[p].0))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((0, Float::NEG_INFINITY))
+}
+
+// Positive when a, b, c turn counterclockwise
+fn cross(a: Point, b: Point, c: Point) -> Float {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+fn in_triangle(p: Point, a: Point, b: Point, c: Point) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+}
+
+// Whether segments pq and ab cross at a point other than one they share
+fn crosses(p: Point, q: Point, a: Point, b: Point) -> bool {
+    if p == a || p == b || q == a || q == b {
+        return false;
+    }
+    let (d1, d2) = (cross(p, q, a), cross(p, q, b));
+    let (d3, d4) = (cross(a, b, p), cross(a, b, q));
+    (d1 > 0.0) != (d2 > 0.0) && (d3 > 0.0) != (d4 > 0.0)
+}
+
+// Whether a point is inside a closed contour, counting the edges crossed going right from it
+fn inside(contour: &[Point], (x, y): Point) -> bool {
+    let mut inside = false;
+    for i in 0..contour.len() {
+        let (x0, y0) = contour[i];
+        let (x1, y1) = contour[(i + 1) % contour.len()];
+        if (y0 > y) != (y1 > y) && x < x0 + (y - y0) / (y1 - y0) * (x1 - x0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/*
+
+Outline
+
+Collects a glyph's contours as it's read from the font, with curves split into straight segments
+
+*/
+
+struct Outline {
+    contours: Vec<Vec<Point>>,
+    current: Vec<Point>,
+    scale: Float,
+    offset: Point // Where the glyph's origin sits in the text
+}
+
+impl Outline {
+    fn point(&self, x: f32, y: f32) -> Point {
+        (self.offset.0 + x as Float * self.scale, self.offset.1 + y as Float * self.scale)
+    }
+
+    fn last(&self) -> Point {
+        self.current.last().copied().unwrap_or(self.offset)
+    }
+
+    fn push(&mut self, p: Point) {
+        if self.current.last() != Some(&p) {
+            self.current.push(p);
+        }
+    }
+
+    fn finish(&mut self) {
+        let mut contour = std::mem::take(&mut self.current);
+        if contour.len() > 1 && contour.first() == contour.last() {
+            contour.pop();
+        }
+        if contour.len() >= 3 {
+            self.contours.push(contour);
+        }
+    }
+}
+
+impl OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.finish();
+        let p = self.point(x, y);
+        self.push(p);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.point(x, y);
+        self.push(p);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (p0, p1, p2) = (self.last(), self.point(x1, y1), self.point(x, y));
+        for i in 1..=CURVE_STEPS {
+            let t = i as Float / CURVE_STEPS as Float;
+            let s = 1.0 - t;
+            let blend = |a: Float, b: Float, c: Float| s * s * a + 2.0 * s * t * b + t * t * c;
+            self.push((blend(p0.0, p1.0, p2.0), blend(p0.1, p1.1, p2.1)));
+        }
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (p0, p1, p2, p3) = (self.last(), self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        for i in 1..=CURVE_STEPS {
+            let t = i as Float / CURVE_STEPS as Float;
+            let s = 1.0 - t;
+            let blend = |a: Float, b: Float, c: Float, d: Float| s * s * s * a + 3.0 * s * s * t * b + 3.0 * s * t * t * c + t * t * t * d;
+            self.push((blend(p0.0, p1.0, p2.0, p3.0), blend(p0.1, p1.1, p2.1, p3.1)));
+        }
+    }
+
+    fn close(&mut self) {
+        self.finish();
+    }
+}
+
+/*
+
+Text Error
+
+*/
+
+#[derive(Debug)]
+pub enum TextError {
+    Io(io::Error),
+    Font(FaceParsingError)
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextError::Io(e) => write!(f, "couldn't read font: {}", e),
+            TextError::Font(e) => write!(f, "invalid font: {}", e)
+        }
+    }
+}
+
+impl Error for TextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TextError::Io(e) => Some(e),
+            TextError::Font(e) => Some(e)
+        }
+    }
+}
+
+impl From<io::Error> for TextError {
+    fn from(e: io::Error) -> Self {
+        TextError::Io(e)
+    }
+}
+
+impl From<FaceParsingError> for TextError {
+    fn from(e: FaceParsingError) -> Self {
+        TextError::Font(e)
+    }
+}
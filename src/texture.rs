@@ -0,0 +1,98 @@
+use std::{error::Error, fmt, fs::File, io::{self, BufReader}, path::Path};
+
+use crate::{image::Image, linalg::Float};
+
+/*
+
+Texture
+
+An image wrapped over a surface by the surface's uv coordinates. (0, 0) is the image's bottom left corner and (1, 1)
+its top right, and coordinates outside that wrap around, so the image tiles. Texels are looked up without filtering,
+which anti-aliasing smooths over. Transparency is kept for images painted over others, e.g. decals
+
+*/
+
+pub struct Texture {
+    image: Image,
+    alpha: Option<Vec<u8>> // How opaque each texel is, from 0 to 255, for images with transparency
+}
+
+impl Texture {
+    pub fn new(image: Image) -> Self {
+        Self { image, alpha: None }
+    }
+
+    // Read the PNG stored at path
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TextureError> {
+        let (image, alpha) = Image::read_png_with_alpha(BufReader::new(File::open(path)?))?;
+        if image.width == 0 || image.height == 0 {
+            return Err(TextureError::Empty);
+        }
+        Ok(Self { image, alpha })
+    }
+
+    // Color of the texel at (u, v)
+    pub fn sample(&self, uv: (Float, Float)) -> usize {
+        self.image.pixels[self.texel(uv)] as usize
+    }
+
+    // Color of the texel at (u, v) and how opaque it is, from 0 to 1
+    pub fn sample_with_alpha(&self, uv: (Float, Float)) -> (usize, Float) {
+        let i = self.texel(uv);
+        let alpha = self.alpha.as_ref().map_or(1.0, |alpha| alpha[i] as Float / 255.0);
+        (self.image.pixels[i] as usize, alpha)
+    }
+
+    // Index of the texel at (u, v)
+    fn texel(&self, (u, v): (Float, Float)) -> usize {
+        let (width, height) = (self.image.width, self.image.height);
+        let x = ((u.rem_euclid(1.0) * width as Float) as usize).min(width - 1);
+        let y = (((1.0 - v.rem_euclid(1.0)) * height as Float) as usize).min(height - 1);
+        y * width + x
+    }
+}
+
+/*
+
+Texture Error
+
+*/
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io(io::Error),
+    Decode(png::DecodingError),
+    Empty
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::Io(e) => write!(f, "couldn't read texture: {}", e),
+            TextureError::Decode(e) => write!(f, "invalid PNG: {}", e),
+            TextureError::Empty => write!(f, "texture has no pixels")
+        }
+    }
+}
+
+impl Error for TextureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TextureError::Io(e) => Some(e),
+            TextureError::Decode(e) => Some(e),
+            TextureError::Empty => None
+        }
+    }
+}
+
+impl From<io::Error> for TextureError {
+    fn from(e: io::Error) -> Self {
+        TextureError::Io(e)
+    }
+}
+
+impl From<png::DecodingError> for TextureError {
+    fn from(e: png::DecodingError) -> Self {
+        TextureError::Decode(e)
+    }
+}
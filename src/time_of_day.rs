@@ -0,0 +1,91 @@
+use crate::{color::Color, linalg::{consts::PI, Float, Vec3d}};
+
+/*
+
+Time of Day
+
+Moves the sun across the sky as time passes: a directional light follows an arc that rises in the east (+x), peaks
+in front of the camera's starting view (-z) at noon and sets in the west, brightest at noon and dark at night. Its
+light passes through more air the lower the sun is, which scatters away blue light most, so it turns from white at
+noon to orange as the sun sets. Scenes with an atmosphere have their sky's sun moved along with it, so the sky reddens
+towards sunset too
+
+*/
+
+const NOON_ELEVATION: Float = 60.0; // Degrees the sun is above the horizon at noon
+const EXTINCTION: [Float; 3] = [0.05, 0.12, 0.27]; // Share of red, green and blue light air straight up scatters away
+const MIN_SUN_HEIGHT: Float = 0.05; // Lowest the sun is taken to be, so the air it shines through stays finite at the horizon
+
+pub struct TimeOfDay {
+    light: usize,         // Index of the directional light that's moved
+    hour: Float,          // From 0 to 24, 12 is noon
+    day_length: Float,    // Seconds a whole day takes to pass
+    noon_intensity: Float // Intensity of the light at noon
+}
+
+impl TimeOfDay {
+    pub fn new(light: usize, hour: Float, day_length: Float, noon_intensity: Float) -> Self {
+        Self {
+            light,
+            hour: hour.rem_euclid(24.0),
+            day_length,
+            noon_intensity
+        }
+    }
+
+    pub fn light(&self) -> usize {
+        self.light
+    }
+
+    pub fn hour(&self) -> Float {
+        self.hour
+    }
+
+    // Let some seconds of real time pass
+    pub fn advance(&mut self, secs: Float) {
+        self.hour = (self.hour + secs / self.day_length * 24.0).rem_euclid(24.0);
+    }
+
+    // Unit direction towards the sun. Below the horizon at night
+    pub fn sun_dir(&self) -> Vec3d {
+        let angle = (self.hour - 6.0) / 12.0 * PI; // 0 at sunrise, PI at sunset
+        let elevation = NOON_ELEVATION.to_radians();
+        Vec3d::new(angle.cos(), angle.sin() * elevation.sin(), -angle.sin() * elevation.cos())
+    }
+
+    // Intensity of the light, falling off as the sun gets lower and off at night
+    pub fn intensity(&self) -> Float {
+        let angle = (self.hour - 6.0) / 12.0 * PI;
+        self.noon_intensity * angle.sin().max(0.0)
+    }
+
+    // Color of the light, white at noon and reddening as the sun gets lower and its light crosses more air. Only its
+    // hue changes, intensity is what dims it
+    pub fn color(&self) -> usize {
+        let air = |height: Float| 1.0 / height.max(MIN_SUN_HEIGHT);
+        let extra_air = air(self.sun_dir().y()) - air(NOON_ELEVATION.to_radians().sin());
+        let transmitted = EXTINCTION.map(|k| (-k * extra_air.max(0.0)).exp());
+        Color::from_linear(transmitted.map(|c| c / transmitted[0]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn light_is_white_at_noon_and_warms_towards_sunset() {
+        let color_at = |hour| TimeOfDay::new(0, hour, 60.0, 1.0).color();
+        assert_eq!(color_at(12.0), Color::White as usize);
+
+        // Blue fades faster than green, and both faster than red, the lower the sun
+        let mut last = color_at(12.0);
+        for hour in [14.0, 16.0, 17.0, 17.8] {
+            let color = color_at(hour);
+            assert_eq!(Color::r(color), 255);
+            assert!(Color::g(color) < Color::g(last) && Color::b(color) < Color::b(last), "{:06X} at {} isn't warmer than {:06X}", color, hour, last);
+            assert!(Color::b(color) < Color::g(color));
+            last = color;
+        }
+    }
+}
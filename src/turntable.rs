@@ -0,0 +1,39 @@
+use crate::linalg::{Float, Mat3, Vec3d};
+
+/*
+
+Turntable
+
+Moves the camera round a point in front of it, level with it, as if the scene were spinning on a turntable: the camera
+steps round the circle by the same angle every frame, turning to keep the point straight ahead. Stepping by frames
+rather than time makes every run trace the same views, so renders can be compared and timed against each other
+
+*/
+
+pub struct Turntable {
+    target: Vec3d, // The point orbited, at the camera's height
+    radius: Float, // Distance from the camera to the target
+    speed: Float   // Degrees the camera moves round the target every frame, anticlockwise seen from above when positive
+}
+
+impl Turntable {
+    // Orbit the point radius ahead of a camera at origin, facing y_rot degrees round from -z
+    pub fn new(origin: &Vec3d, y_rot: Float, radius: Float, speed: Float) -> Self {
+        Self {
+            target: origin + &(&forward(y_rot) * radius),
+            radius,
+            speed
+        }
+    }
+
+    // Where the camera moves to from facing y_rot, and the way it faces there
+    pub fn advance(&self, y_rot: Float) -> (Vec3d, Float) {
+        let y_rot = y_rot + self.speed;
+        (&self.target - &(&forward(y_rot) * self.radius), y_rot)
+    }
+}
+
+// Level unit direction a camera faces y_rot degrees round from -z
+fn forward(y_rot: Float) -> Vec3d {
+    &Mat3::rotation_y(y_rot) * &Vec3d::new(0.0, 0.0, -1.0)
+}
@@ -0,0 +1,108 @@
+use std::{fmt, str::FromStr, sync::OnceLock};
+
+use crate::{color::{srgb_to_linear, Color}, linalg::Float};
+
+/*
+
+Upscale
+
+How a canvas with fewer units than the screen has pixels is stretched over it. Nearest repeats each unit over the
+pixels it covers, keeping the blocky look of a low resolution render; bilinear blends the four units around each pixel,
+which is smoother but softer. Blending is done on the encoded values, as graphics hardware stretches textures
+
+A canvas with more units than the screen has pixels (supersampling) is always shrunk by averaging every unit a pixel
+covers, in linear light, as anti-aliasing samples are
+
+*/
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Upscale {
+    #[default]
+    Nearest,
+    Bilinear
+}
+
+impl Upscale {
+    // Stretch (or shrink) packed 0RGB src_width x src_height units over dst_width x dst_height pixels
+    pub fn resample(&self, src: &[u32], src_width: usize, src_height: usize, dst: &mut [u32], dst_width: usize, dst_height: usize) {
+        if src_width > dst_width || src_height > dst_height {
+            shrink(src, src_width, src_height, dst, dst_width, dst_height);
+            return;
+        }
+
+        for row in 0..dst_height {
+            for col in 0..dst_width {
+                dst[row * dst_width + col] = match self {
+                    Upscale::Nearest => src[row * src_height / dst_height * src_width + col * src_width / dst_width],
+                    Upscale::Bilinear => {
+                        // The point under the pixel's center, from the centers of the units
+                        let x = ((col as Float + 0.5) * src_width as Float / dst_width as Float - 0.5).max(0.0);
+                        let y = ((row as Float + 0.5) * src_height as Float / dst_height as Float - 0.5).max(0.0);
+                        let (x0, y0) = (x as usize, y as usize);
+                        let (x1, y1) = ((x0 + 1).min(src_width - 1), (y0 + 1).min(src_height - 1));
+                        let (fx, fy) = (x - x0 as Float, y - y0 as Float);
+                        let [a, b, c, d] = [src[y0 * src_width + x0], src[y0 * src_width + x1], src[y1 * src_width + x0], src[y1 * src_width + x1]];
+                        let channel = |shift: u32| {
+                            let value = |unit: u32| ((unit >> shift) & 0xFF) as Float;
+                            let top = value(a) * (1.0 - fx) + value(b) * fx;
+                            let bottom = value(c) * (1.0 - fx) + value(d) * fx;
+                            ((top * (1.0 - fy) + bottom * fy).round() as u32) << shift
+                        };
+                        channel(16) | channel(8) | channel(0)
+                    }
+                };
+            }
+        }
+    }
+}
+
+// Average the units each pixel covers (at least one along each side), in linear light
+fn shrink(src: &[u32], src_width: usize, src_height: usize, dst: &mut [u32], dst_width: usize, dst_height: usize) {
+    let decode = decoding_table();
+    let span = |i: usize, src_len: usize, dst_len: usize| {
+        let start = i * src_len / dst_len;
+        start..((i + 1) * src_len / dst_len).max(start + 1)
+    };
+
+    for row in 0..dst_height {
+        let rows = span(row, src_height, dst_height);
+        for col in 0..dst_width {
+            let cols = span(col, src_width, dst_width);
+            let mut total = [0.0; 3];
+            for unit in rows.clone().flat_map(|r| &src[r * src_width + cols.start..r * src_width + cols.end]) {
+                for (channel, shift) in total.iter_mut().zip([16, 8, 0]) {
+                    *channel += decode[((unit >> shift) & 0xFF) as usize];
+                }
+            }
+            let count = (rows.len() * cols.len()) as Float;
+            dst[row * dst_width + col] = Color::from_linear(total.map(|channel| channel / count)) as u32;
+        }
+    }
+}
+
+// Linear light of every encoded channel value, worked out once
+fn decoding_table() -> &'static [Float; 256] {
+    static TABLE: OnceLock<[Float; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(|value| srgb_to_linear(value as Float / 255.0)))
+}
+
+impl fmt::Display for Upscale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Upscale::Nearest => write!(f, "nearest"),
+            Upscale::Bilinear => write!(f, "bilinear")
+        }
+    }
+}
+
+impl FromStr for Upscale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(Upscale::Nearest),
+            "bilinear" => Ok(Upscale::Bilinear),
+            _ => Err(format!("unknown upscale \"{}\", expected nearest or bilinear", s))
+        }
+    }
+}
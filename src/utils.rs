@@ -1,5 +1,158 @@
+use rand_pcg::Pcg32;
+
+use crate::linalg::Float;
+
+// std's Instant panics in the browser, web-time provides the same API backed by the performance timer
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+pub use web_time::Instant;
+
 // A range from min to max (inclusive)
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Range <T> {
     pub min: T,
     pub max: T
-}
\ No newline at end of file
+}
+
+impl<T: PartialOrd + Copy> Range<T> {
+    pub fn new(min: T, max: T) -> Self {
+        Self { min, max }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min > self.max
+    }
+
+    // Whether x lies within the range, bounds included
+    pub fn contains(&self, x: T) -> bool {
+        self.min <= x && x <= self.max
+    }
+
+    // Whether x lies strictly within the range, bounds excluded
+    pub fn surrounds(&self, x: T) -> bool {
+        self.min < x && x < self.max
+    }
+
+    pub fn clamp(&self, x: T) -> T {
+        if x < self.min {
+            self.min
+        } else if x > self.max {
+            self.max
+        } else {
+            x
+        }
+    }
+
+    // The overlap of two ranges. Empty if they don't overlap
+    pub fn intersect(&self, other: &Self) -> Self {
+        Self {
+            min: if self.min > other.min { self.min } else { other.min },
+            max: if self.max < other.max { self.max } else { other.max }
+        }
+    }
+
+    // The smallest range containing both ranges
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: if self.min < other.min { self.min } else { other.min },
+            max: if self.max > other.max { self.max } else { other.max }
+        }
+    }
+}
+
+impl Range<Float> {
+    pub const EMPTY: Self = Self { min: Float::INFINITY, max: Float::NEG_INFINITY };
+    pub const UNIVERSE: Self = Self { min: Float::NEG_INFINITY, max: Float::INFINITY };
+
+    pub fn size(&self) -> Float {
+        self.max - self.min
+    }
+
+    // Pad the range by delta on both sides
+    pub fn expand(&self, delta: Float) -> Self {
+        Self {
+            min: self.min - delta,
+            max: self.max + delta
+        }
+    }
+}
+
+// Create the random number generator used to sample a single pixel
+// The generator is seeded from the render seed, the frame number and the pixel index, so a render can be reproduced exactly
+// regardless of how the canvas is split between threads
+pub fn pixel_rng(seed: u64, frame: u64, pixel: usize) -> Pcg32 {
+    Pcg32::new(frame_seed(seed, frame), pixel as u64)
+}
+
+// The part of every pixel's generator seed shared by the whole frame, so it only needs finding once per frame
+pub fn frame_seed(seed: u64, frame: u64) -> u64 {
+    splitmix64(seed ^ splitmix64(frame))
+}
+
+// Scramble the bits of a 64 bit value (SplitMix64 finalizer), so nearby seeds produce unrelated sequences
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_ranges_intersect_to_their_overlap() {
+        assert_eq!(Range::new(0.0, 2.0).intersect(&Range::new(1.0, 3.0)), Range::new(1.0, 2.0));
+        assert_eq!(Range::new(0.0, 3.0).intersect(&Range::new(1.0, 2.0)), Range::new(1.0, 2.0));
+        // Touching ranges share a single point
+        assert_eq!(Range::new(0.0, 1.0).intersect(&Range::new(1.0, 2.0)), Range::new(1.0, 1.0));
+    }
+
+    #[test]
+    fn disjoint_ranges_intersect_to_empty() {
+        let (a, b) = (Range::new(0.0, 1.0), Range::new(2.0, 3.0));
+        assert!(a.intersect(&b).is_empty());
+        assert!(b.intersect(&a).is_empty());
+        assert!(Range::EMPTY.intersect(&a).is_empty());
+    }
+
+    #[test]
+    fn union_covers_both_ranges() {
+        let (a, b) = (Range::new(0.0, 1.0), Range::new(2.0, 3.0));
+        assert_eq!(a.union(&b), Range::new(0.0, 3.0));
+        assert_eq!(b.union(&a), Range::new(0.0, 3.0));
+        assert_eq!(Range::new(0.0, 3.0).union(&Range::new(1.0, 2.0)), Range::new(0.0, 3.0));
+    }
+
+    #[test]
+    fn empty_and_universe_are_identities() {
+        let x = Range::new(-1.5, 4.0);
+        assert_eq!(Range::EMPTY.union(&x), x);
+        assert_eq!(x.union(&Range::EMPTY), x);
+        assert_eq!(Range::UNIVERSE.intersect(&x), x);
+        assert_eq!(Range::UNIVERSE.union(&x), Range::UNIVERSE);
+
+        assert!(Range::EMPTY.is_empty());
+        assert!(!Range::EMPTY.contains(0.0));
+        assert!(!Range::UNIVERSE.is_empty());
+        assert!(Range::UNIVERSE.contains(Float::MAX) && Range::UNIVERSE.contains(Float::INFINITY));
+    }
+
+    #[test]
+    fn expand_pads_both_sides() {
+        assert_eq!(Range::new(0.0, 1.0).expand(0.5), Range::new(-0.5, 1.5));
+        assert_eq!(Range::new(0.0, 1.0).expand(0.5).size(), 2.0);
+        assert!(Range::EMPTY.expand(1.0).is_empty());
+        assert_eq!(Range::UNIVERSE.expand(1.0), Range::UNIVERSE);
+    }
+
+    #[test]
+    fn contains_includes_bounds_and_surrounds_excludes_them() {
+        let x = Range::new(0.0, 1.0);
+        assert!(x.contains(0.0) && x.contains(1.0));
+        assert!(!x.surrounds(0.0) && !x.surrounds(1.0) && x.surrounds(0.5));
+        assert_eq!((x.clamp(-1.0), x.clamp(0.5), x.clamp(2.0)), (0.0, 0.5, 1.0));
+    }
+}
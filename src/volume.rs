@@ -0,0 +1,76 @@
+use crate::linalg::{Float, Ray, Vec3d};
+use crate::object::sphere_roots;
+use crate::utils::Range;
+
+/*
+
+Volume
+
+A region filled with a constant density medium, e.g. smoke, mist or murky water. Light travelling through it is absorbed
+and scattered away (the sum is called extinction), and light from the scene's sources is scattered towards the viewer.
+Coefficients are per unit of distance, so larger values make a thicker medium
+
+*/
+
+pub enum VolumeShape {
+    Sphere { center: Vec3d, radius: Float },
+    Box { min: Vec3d, max: Vec3d } // Axis aligned, between two opposite corners
+}
+
+pub struct Volume {
+    shape: VolumeShape,
+    color: usize,    // Color of the light scattered towards the viewer
+    absorption: Float, // Fraction of light absorbed per unit distance
+    scattering: Float  // Fraction of light scattered per unit distance
+}
+
+impl Volume {
+    pub fn new(shape: VolumeShape, color: usize, absorption: Float, scattering: Float) -> Self {
+        Self {
+            shape,
+            color,
+            absorption,
+            scattering
+        }
+    }
+
+    pub fn color(&self) -> usize {
+        self.color
+    }
+
+    pub fn scattering(&self) -> Float {
+        self.scattering
+    }
+
+    pub fn extinction(&self) -> Float {
+        self.absorption + self.scattering
+    }
+
+    // The part of the ray (as a range of t) that is inside the volume and within t_range
+    pub fn interval(&self, ray: &Ray, t_range: &Range<Float>) -> Option<Range<Float>> {
+        let inside = match &self.shape {
+            VolumeShape::Sphere { center, radius } => {
+                let (near, far) = sphere_roots(ray, center, *radius)?;
+                Range::new(near, far)
+            }
+            VolumeShape::Box { min, max } => {
+                // Intersect the slabs between each pair of opposite faces
+                let o = ray.origin();
+                let d = ray.dir();
+                let slabs = [(o.x(), d.x(), min.x(), max.x()), (o.y(), d.y(), min.y(), max.y()), (o.z(), d.z(), min.z(), max.z())];
+
+                slabs.iter().fold(Range::<Float>::UNIVERSE, |inside, &(o, d, lo, hi)| {
+                    let (t0, t1) = ((lo - o) / d, (hi - o) / d);
+                    inside.intersect(&Range::new(t0.min(t1), t0.max(t1)))
+                })
+            }
+        };
+
+        let inside = inside.intersect(t_range);
+        if inside.is_empty() || inside.size().is_nan() {
+            None
+        } else {
+            Some(inside)
+        }
+    }
+}
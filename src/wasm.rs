@@ -0,0 +1,120 @@
+use std::{cell::RefCell, collections::HashSet, rc::Rc, sync::Arc};
+
+use wasm_bindgen::{prelude::wasm_bindgen, Clamped, JsValue};
+use web_sys::{CanvasRenderingContext2d, ImageData};
+
+use crate::{
+    color::Color,
+    display::{Display, Key},
+    error::{RendererError, SceneError},
+    linalg::Vec3d,
+    Renderer, Scene
+};
+
+/*
+
+WebAssembly Adapter
+
+Exposes the renderer to JavaScript. Every call to render() traces one frame and draws it onto a 2D canvas context.
+Keyboard input is forwarded from the page with key_down/key_up using KeyboardEvent.code values, e.g. "KeyW"
+
+*/
+
+#[wasm_bindgen]
+pub struct WasmRenderer {
+    renderer: Renderer,
+    context: Rc<RefCell<Option<CanvasRenderingContext2d>>>,
+    keys: Rc<RefCell<HashSet<Key>>>
+}
+
+#[wasm_bindgen]
+impl WasmRenderer {
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: usize, height: usize, samples: usize) -> Result<WasmRenderer, JsValue> {
+        let context = Rc::new(RefCell::new(None));
+        let keys = Rc::new(RefCell::new(HashSet::new()));
+
+        let display = CanvasDisplay { context: Rc::clone(&context), keys: Rc::clone(&keys) };
+
+        let scene = demo_scene().map_err(to_js)?;
+        let renderer = Renderer::builder()
+            .threads(1)
+            .screen_width(width)
+            .screen_height(height)
+            .samples(samples)
+            .display(Box::new(display))
+            .build(Arc::new(scene))
+            .map_err(to_js)?;
+
+        Ok(Self { renderer, context, keys })
+    }
+
+    // Trace a frame and draw it onto the given context
+    pub fn render(&mut self, context: CanvasRenderingContext2d) -> Result<(), JsValue> {
+        *self.context.borrow_mut() = Some(context);
+        self.renderer.step().map_err(to_js)?;
+        Ok(())
+    }
+
+    pub fn key_down(&mut self, code: &str) {
+        if let Some(key) = Key::from_dom_code(code) {
+            self.keys.borrow_mut().insert(key);
+        }
+    }
+
+    pub fn key_up(&mut self, code: &str) {
+        if let Some(key) = Key::from_dom_code(code) {
+            self.keys.borrow_mut().remove(&key);
+        }
+    }
+}
+
+// Presents frames to the canvas context most recently passed to render()
+struct CanvasDisplay {
+    context: Rc<RefCell<Option<CanvasRenderingContext2d>>>,
+    keys: Rc<RefCell<HashSet<Key>>>
+}
+
+impl Display for CanvasDisplay {
+    fn is_open(&self) -> bool {
+        true
+    }
+
+    fn present(&mut self, buffer: &[u32], width: usize, height: usize) -> Result<(), RendererError> {
+        let context = self.context.borrow();
+        let Some(context) = context.as_ref() else {
+            return Ok(());
+        };
+
+        // Canvas image data is RGBA bytes
+        let mut rgba = Vec::with_capacity(buffer.len() * 4);
+        for p in buffer {
+            rgba.extend_from_slice(&[(p >> 16) as u8, (p >> 8) as u8, *p as u8, 0xFF]);
+        }
+
+        let image = ImageData::new_with_u8_clamped_array_and_sh(Clamped(&rgba), width as u32, height as u32)
+            .map_err(|e| RendererError::Present(format!("{:?}", e).into()))?;
+        context.put_image_data(&image, 0.0, 0.0).map_err(|e| RendererError::Present(format!("{:?}", e).into()))
+    }
+
+    fn keys_down(&self) -> Vec<Key> {
+        self.keys.borrow().iter().copied().collect()
+    }
+}
+
+fn to_js(e: impl std::fmt::Display) -> JsValue {
+    JsValue::from_str(&e.to_string())
+}
+
+// A small scene for browser demos
+fn demo_scene() -> Result<Scene, SceneError> {
+    Scene::builder()
+        .camera(Vec3d::new(0.0, 2.0, -1.0))
+        .ambient(0.1)
+        .point_light(0.9, Vec3d::new(-3.0, 4.0, -6.0))
+        .prism(Vec3d::new(-40.0, 0.0, -40.0), 80.0, -5.0, 50.0).color(Color::Gray).shiny(500.0, 0.3)
+        .sphere(Vec3d::new(3.0, 2.0, -8.0), 2.0).color(Color::Red).shiny(500.0, 0.1)
+        .sphere(Vec3d::new(-0.3, 0.7, -6.0), 0.7).color(Color::SlateGray).shiny(500.0, 0.1)
+        .sphere(Vec3d::new(-2.5, 1.0, -5.0), 1.0).color(Color::Blue).shiny(500.0, 0.1)
+        .build()
+}
@@ -0,0 +1,337 @@
+#![cfg(not(feature = "f32"))]
+
+use std::{env, fs::{self, File}, io::BufWriter, path::PathBuf, sync::Arc};
+
+use raytracer::{bloom::Bloom, compare::Comparison, config::RendererBuilder, filter::Filter, integrator::Integrator, pipeline::Pipeline, post::{ChromaticAberration, FilmGrain, Sharpen, Vignette}, sample_pattern::SamplePattern, scene_file, stereo::Stereo, upscale::Upscale, Renderer};
+
+/*
+
+Golden Image Tests
+
+Renders small reference scenes headlessly and compares them against the images checked into tests/golden. Channels may
+differ by a small tolerance so harmless floating point differences don't fail the tests.
+After an intentional change to the output, regenerate the images with BLESS=1 cargo test --test golden
+The images are traced in double precision, so the tests are left out when building with the f32 feature
+
+*/
+
+const WIDTH: usize = 160;
+const TOLERANCE: u8 = 2;
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+// Render a single deterministic frame of a scene from the scenes directory, as RGB bytes
+fn render(scene: &str, configure: impl FnOnce(RendererBuilder) -> RendererBuilder) -> (Vec<u8>, usize, usize) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("scenes").join(format!("{}.ron", scene));
+    let image = configure(Renderer::builder())
+        .screen_width(WIDTH)
+        .seed(1)
+        .render_to_image(Arc::new(scene_file::load(path).unwrap()))
+        .unwrap();
+    (image.to_rgb(), image.width, image.height)
+}
+
+fn read_png(path: &PathBuf) -> (Vec<u8>, usize, usize) {
+    let decoder = png::Decoder::new(File::open(path).unwrap_or_else(|e| panic!("missing golden image {}: {}", path.display(), e)));
+    let mut reader = decoder.read_info().unwrap();
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgb, "golden images are 8-bit RGB");
+    data.truncate(info.buffer_size());
+    (data, info.width as usize, info.height as usize)
+}
+
+fn write_png(path: &PathBuf, rgb: &[u8], width: usize, height: usize) {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path).unwrap()), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header().unwrap().write_image_data(rgb).unwrap();
+}
+
+fn check(name: &str, scene: &str, samples: usize) {
+    check_with(name, scene, |builder| builder.samples(samples));
+}
+
+// Like check, with renderer settings beyond the sample count
+fn check_with(name: &str, scene: &str, configure: impl FnOnce(RendererBuilder) -> RendererBuilder) {
+    let (actual, width, height) = render(scene, configure);
+    let golden = golden_dir().join(format!("{}.png", name));
+
+    if env::var_os("BLESS").is_some() {
+        write_png(&golden, &actual, width, height);
+        return;
+    }
+
+    let (expected, expected_width, expected_height) = read_png(&golden);
+    assert_eq!((width, height), (expected_width, expected_height), "{}: image size changed", name);
+
+    let mismatched = actual.chunks_exact(3).zip(expected.chunks_exact(3))
+        .filter(|(a, e)| a.iter().zip(e.iter()).any(|(a, e)| a.abs_diff(*e) > TOLERANCE))
+        .count();
+
+    if mismatched > 0 {
+        // Keep the render around so it can be compared against the golden image
+        let failures = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        fs::create_dir_all(&failures).unwrap();
+        let actual_path = failures.join(format!("{}.png", name));
+        write_png(&actual_path, &actual, width, height);
+
+        panic!("{}: {} of {} pixels differ from the golden image (render saved to {})", name, mismatched, width * height, actual_path.display());
+    }
+}
+
+// Diffuse and specular shading, point light shadows
+#[test]
+fn spheres() {
+    check("spheres", "spheres", 1);
+}
+
+// Images wrapped over a sphere, a box and a quad of triangles by their uv coordinates
+#[test]
+fn textured() {
+    check("textured", "textured", 1);
+}
+
+// Images projected along each axis onto a hill and spheres, blended by their normals
+#[test]
+fn triplanar() {
+    check("triplanar", "triplanar", 1);
+}
+
+// Images projected onto a wall, the floor, a sphere and across the corner of a box
+#[test]
+fn decals() {
+    check("decals", "decals", 1);
+}
+
+// Objects hidden from the camera, reflections or shadows, over a floor that only catches shadows
+#[test]
+fn staging() {
+    check("staging", "staging", 1);
+}
+
+// Quads seen from behind, double-sided and culled, and a closed single-sided box
+#[test]
+fn sided() {
+    check("sided", "sided", 1);
+}
+
+// An object with three levels of detail, placed at distances that show each of them
+#[test]
+fn lod() {
+    check("lod", "lod", 1);
+}
+
+// A PLY mesh as read and simplified to fewer triangles by collapsing edges
+#[test]
+fn simplified() {
+    check("simplified", "simplified", 1);
+}
+
+// OBJ models with the materials of their MTL file: a texture, a shiny material, and faces without a material
+#[test]
+fn model() {
+    check("model", "model", 1);
+}
+
+// Highly reflective floor and spheres
+#[test]
+fn mirror() {
+    check("mirror", "mirror", 1);
+}
+
+// Each material preset, reflected in a mirror behind them
+#[test]
+fn materials() {
+    check("materials", "materials", 1);
+}
+
+// Highlights and shadows stretched along a tube light
+#[test]
+fn tube() {
+    check("tube", "tube", 1);
+}
+
+// Point lights dimming with distance by no falloff, linear, inverse-square and a lookup table
+#[test]
+fn falloff() {
+    check("falloff", "falloff", 1);
+}
+
+// Matte surfaces and a directional light
+#[test]
+fn studio() {
+    check("studio", "studio", 1);
+}
+
+// Jittered anti-aliasing samples, which must be reproducible for a fixed seed
+#[test]
+fn spheres_antialiased() {
+    check("spheres_antialiased", "spheres", 4);
+}
+
+// Anti-aliasing samples spread wider and weighted by a gaussian
+#[test]
+fn spheres_gaussian() {
+    check_with("spheres_gaussian", "spheres", |builder| builder.samples(4).filter(Filter::Gaussian));
+}
+
+// Samples at fixed rotated grid offsets instead of random jitter
+#[test]
+fn spheres_rotated_grid() {
+    check_with("spheres_rotated_grid", "spheres", |builder| builder.samples(4).sample_pattern(SamplePattern::RotatedGrid));
+}
+
+// Shading a tile's samples grouped by material instead of one at a time leaves the image as it was
+#[test]
+fn spheres_sorted() {
+    check_with("spheres_antialiased", "spheres", |builder| builder.samples(4).pipeline(Pipeline::Sorted));
+}
+
+// Edges smoothed after tracing a single sample per canvas unit
+#[test]
+fn spheres_fxaa() {
+    check_with("spheres_fxaa", "spheres", |builder| builder.fxaa(true));
+}
+
+// Edges smoothed, then sharpened back where they're soft
+#[test]
+fn textured_sharpened() {
+    check_with("textured_sharpened", "textured", |builder| builder.fxaa(true).cas(0.8));
+}
+
+// Four canvas units traced for every pixel, averaged down to the image's size
+#[test]
+fn spheres_supersampled() {
+    check_with("spheres_supersampled", "spheres", |builder| builder.render_scale(2.0));
+}
+
+// A canvas of fewer units than pixels, not evenly dividing the image, blended over it
+#[test]
+fn spheres_bilinear() {
+    check_with("spheres_bilinear", "spheres", |builder| builder.render_scale(0.3).upscale(Upscale::Bilinear));
+}
+
+// Traced progressively, the first frame is an eighth of the resolution, stretched over the screen
+#[test]
+fn spheres_progressive() {
+    check_with("spheres_progressive", "spheres", |builder| builder.samples(4).progressive(true));
+}
+
+// Views from each eye merged into a red/cyan anaglyph
+#[test]
+fn spheres_anaglyph() {
+    check_with("spheres_anaglyph", "spheres", |builder| builder.stereo(Stereo::Anaglyph));
+}
+
+// Each eye's view in its own half of the image
+#[test]
+fn spheres_side_by_side() {
+    check_with("spheres_side_by_side", "spheres", |builder| builder.aspect_ratio(32.0 / 9.0).stereo(Stereo::SideBySide));
+}
+
+// Curved Bezier patches, including one with a side collapsed to a point
+#[test]
+fn bezier() {
+    check("bezier", "bezier", 1);
+}
+
+// A room, a frustum and a hexagonal prism, each bounded by planes
+#[test]
+fn room() {
+    check("room", "room", 1);
+}
+
+// Point clouds drawn as disks and spheres, colored per point
+#[test]
+fn point_cloud() {
+    check("point_cloud", "point_cloud", 1);
+}
+
+// Grass blades narrowing to their tips and thin sagging wires
+#[test]
+fn curves() {
+    check("curves", "curves", 1);
+}
+
+// Letters extruded from a font's outlines, holes and all
+#[test]
+fn text() {
+    check("text", "title", 1);
+}
+
+// Exponential distance fog over a large scene
+#[test]
+fn fog() {
+    check("fog", "random_spheres", 1);
+}
+
+// Scattering and absorbing volumes, including their shadows
+#[test]
+fn volumes() {
+    check("volumes", "smoke", 1);
+}
+
+// Beams of light through a slatted wall in lit fog
+#[test]
+fn light_shafts() {
+    check("light_shafts", "light_shafts", 1);
+}
+
+// Sky and distant haze from an atmosphere lit by a low sun
+#[test]
+fn atmosphere() {
+    check("atmosphere", "outdoor", 1);
+}
+
+// Path traced bounces between matte and glossy surfaces, sampled from their BRDFs
+#[test]
+fn path_traced() {
+    check_with("path_traced", "studio", |builder| builder.samples(8).integrator(Integrator::PathTraced));
+}
+
+// Small glowing lamps, sampled directly at every bounce
+#[test]
+fn lamps() {
+    check_with("lamps", "lamps", |builder| builder.samples(8).integrator(Integrator::PathTraced));
+}
+
+// A room lit only through its window, which a portal marks for the path tracer to send rays through
+#[test]
+fn interior() {
+    check_with("interior", "interior", |builder| builder.samples(8).integrator(Integrator::PathTraced));
+}
+
+// White surfaces darkened by nearby geometry
+#[test]
+fn ambient_occlusion() {
+    check_with("ambient_occlusion", "studio", |builder| builder.integrator(Integrator::AmbientOcclusion));
+}
+
+// Whitted left of the divider, path traced right of it
+#[test]
+fn compared() {
+    check_with("compared", "studio", |builder| {
+        builder.compare(Comparison { integrator: Some(Integrator::PathTraced), num_samples: Some(8), ..Default::default() })
+    });
+}
+
+// Light from the lamps blurred over their surroundings
+#[test]
+fn bloom() {
+    check_with("bloom", "lamps", |builder| builder.samples(8).integrator(Integrator::PathTraced).bloom(Bloom::default()));
+}
+
+// Every built in post effect, one after another
+#[test]
+fn post_effects() {
+    check_with("post_effects", "spheres", |builder| {
+        builder
+            .post_effect(Sharpen { amount: 0.5 })
+            .post_effect(ChromaticAberration { amount: 0.02 })
+            .post_effect(Vignette { strength: 0.6 })
+            .post_effect(FilmGrain { strength: 0.03 })
+    });
+}
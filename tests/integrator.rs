@@ -0,0 +1,49 @@
+use std::{fs, path::PathBuf, sync::Arc};
+
+use raytracer::{display::HeadlessDisplay, integrator::Integrator, linalg::Float, scene_file, Renderer};
+
+/*
+
+Integrator Estimates
+
+Ways of sampling light that only make the path tracer less noisy mustn't change what it converges to. Renders a scene
+with and without the help and checks the average light over the frame is the same, within the noise left
+
+*/
+
+const WIDTH: usize = 48;
+const SAMPLES: usize = 64;
+const TOLERANCE: Float = 0.03; // Largest difference in average light allowed, as a fraction of it
+
+// Average linear light over a frame of a scene, path traced from its source
+fn mean_light(src: &str) -> [Float; 3] {
+    let mut renderer = Renderer::builder()
+        .screen_width(WIDTH)
+        .samples(SAMPLES)
+        .integrator(Integrator::PathTraced)
+        .seed(1)
+        .display(Box::new(HeadlessDisplay::new().with_frame_limit(1)))
+        .build(Arc::new(scene_file::parse(src).unwrap()))
+        .unwrap();
+    renderer.run().unwrap();
+
+    let (units, _, _) = renderer.accumulation_buffer();
+    let total = units.iter().fold([0.0; 3], |total, unit| [0, 1, 2].map(|c| total[c] + unit[c]));
+    total.map(|channel| channel / units.len() as Float)
+}
+
+fn assert_close(name: &str, with: [Float; 3], without: [Float; 3]) {
+    for c in 0..3 {
+        let difference = (with[c] - without[c]).abs() / without[c];
+        assert!(difference < TOLERANCE, "{}: channel {} averages {} with and {} without ({:.1}% apart)", name, c, with[c], without[c], difference * 100.0);
+    }
+}
+
+// A window marked as a portal lights the room as brightly as paths finding their own way out of it
+#[test]
+fn portals_keep_brightness() {
+    let src = fs::read_to_string(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("scenes").join("interior.ron")).unwrap();
+    let (before, _) = src.split_once("    portals: [").unwrap();
+    let without = format!("{})\n", before);
+    assert_close("interior", mean_light(&src), mean_light(&without));
+}
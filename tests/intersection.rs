@@ -0,0 +1,240 @@
+#![cfg(not(feature = "f32"))]
+
+use std::sync::Arc;
+
+use proptest::prelude::*;
+use raytracer::{linalg::{Float, Ray, Vec3d, RAY_EPSILON}, object::{Flagged, Material, Mesh, Object, RayKind, RectangularPrism, RenderFlags, Sphere, Surface, Triangle}, utils::Range, Scene};
+
+/*
+
+Intersection Properties
+
+Fires random rays at random spheres, triangles and boxes and checks what every hit must satisfy: t is within the range
+searched, the point is on the surface, and the normal is unit length, points out of the object and agrees with
+front_face. Rays are also started inside the solids, and aimed at points known to be on the surface so misses are
+caught too. Meshes whose hierarchy is still being built must hit what they will once it's done. Failures are shrunk by proptest to the simplest ray and shape that still fail
+Scenes must see through the back faces of single-sided materials, face by face within a mesh, and only hit objects
+with the kinds of ray their render flags let see them
+The tolerances are for double precision, so the tests are left out when building with the f32 feature
+
+*/
+
+const TOLERANCE: Float = 1e-6;
+
+fn coord(max: Float) -> impl Strategy<Value = Float> {
+    -max..max
+}
+
+fn point(max: Float) -> impl Strategy<Value = Vec3d> {
+    (coord(max), coord(max), coord(max)).prop_map(|(x, y, z)| Vec3d::new(x, y, z))
+}
+
+// A direction that isn't too short to normalize. Directions are left unnormalized, since rays needn't be unit length
+fn direction() -> impl Strategy<Value = Vec3d> {
+    point(1.0).prop_filter("direction too short", |dir| dir.magnitude() > 1e-3)
+}
+
+// The range searched along a ray, sometimes starting past the origin or ending short
+fn t_range() -> impl Strategy<Value = Range<Float>> {
+    prop_oneof![
+        Just(Range::new(RAY_EPSILON, Float::INFINITY)),
+        (0.0..10.0 as Float, 0.0..50.0 as Float).prop_map(|(min, len)| Range::new(min, min + len))
+    ]
+}
+
+// A point weighted between the corners of a triangle, for weights that needn't sum to 1
+fn blend(ps: &[Vec3d; 3], (u, v): (Float, Float)) -> Vec3d {
+    let (u, v) = if u + v > 1.0 { (1.0 - u, 1.0 - v) } else { (u, v) };
+    &(&ps[0] + &(&(&ps[1] - &ps[0]) * u)) + &(&(&ps[2] - &ps[0]) * v)
+}
+
+fn sphere(center: &Vec3d, radius: Float) -> Sphere {
+    Sphere::new(center.clone(), radius, 0, Material::default())
+}
+
+// Sizes of a box along each axis, some negative so the box reaches back from its origin
+fn box_size() -> impl Strategy<Value = Vec3d> {
+    let extent = || (0.1..5.0 as Float, any::<bool>()).prop_map(|(size, flip)| if flip { -size } else { size });
+    (extent(), extent(), extent()).prop_map(|(x, y, z)| Vec3d::new(x, y, z))
+}
+
+// A box and its lowest and highest corners
+fn prism(origin: &Vec3d, size: &Vec3d) -> (RectangularPrism, Vec3d, Vec3d) {
+    let far = origin + size;
+    let min = Vec3d::new(origin.x().min(far.x()), origin.y().min(far.y()), origin.z().min(far.z()));
+    let max = Vec3d::new(origin.x().max(far.x()), origin.y().max(far.y()), origin.z().max(far.z()));
+    (RectangularPrism::new(origin.clone(), size.x(), size.y(), size.z(), 0, Material::default()), min, max)
+}
+
+// Invariants held by any hit, given how far out from its surface a point may be, and a point the normal must point away
+// from (the inside of a solid) if there is one
+fn check_hit(object: &dyn Object, ray: &Ray, t_range: &Range<Float>, off_surface: impl Fn(&Vec3d) -> Float, inside: Option<&Vec3d>) -> Result<(), TestCaseError> {
+    let Some(hit) = object.get_closest_intersection(ray, t_range) else {
+        return Ok(());
+    };
+    let scale = 1.0 + hit.point.magnitude() + ray.origin().magnitude();
+
+    prop_assert!(t_range.contains(hit.t), "t {} outside {}..{}", hit.t, t_range.min, t_range.max);
+    prop_assert!((&hit.point - &ray.at(hit.t)).magnitude() <= TOLERANCE * scale, "point isn't along the ray at t");
+    prop_assert!(off_surface(&hit.point).abs() <= TOLERANCE * scale, "point {:?} is {} off the surface", hit.point, off_surface(&hit.point));
+    prop_assert!((hit.normal.magnitude() - 1.0).abs() <= TOLERANCE, "normal has length {}", hit.normal.magnitude());
+    prop_assert_eq!(hit.front_face, &hit.normal * ray.dir() < 0.0, "front_face disagrees with the normal");
+    if let Some(inside) = inside {
+        prop_assert!(&hit.normal * &(&hit.point - inside) > 0.0, "normal {:?} at {:?} points inwards", hit.normal, hit.point);
+    }
+    Ok(())
+}
+
+// How far a point is outside (positive) or inside (negative) an axis aligned box
+fn box_distance(min: &Vec3d, max: &Vec3d, p: &Vec3d) -> Float {
+    let axis = |p: Float, min: Float, max: Float| (min - p).max(p - max);
+    axis(p.x(), min.x(), max.x()).max(axis(p.y(), min.y(), max.y())).max(axis(p.z(), min.z(), max.z()))
+}
+
+proptest! {
+    #[test]
+    fn sphere_hits_are_on_the_surface(center in point(10.0), radius in 0.1..5.0 as Float, origin in point(20.0), dir in direction(), t_range in t_range()) {
+        let object = sphere(&center, radius);
+        check_hit(&object, &Ray::new(origin, dir), &t_range, |p| (p - &center).magnitude() - radius, Some(&center))?;
+    }
+
+    #[test]
+    fn sphere_is_hit_when_aimed_at(center in point(10.0), radius in 0.1..5.0 as Float, origin in point(20.0), target in direction()) {
+        prop_assume!((&origin - &center).magnitude() > radius * (1.0 + TOLERANCE));
+        let target = &center + &(&target.normalize() * (radius * 0.99));
+        let ray = Ray::new(origin.clone(), &target - &origin);
+        prop_assert!(sphere(&center, radius).get_closest_intersection(&ray, &Range::new(RAY_EPSILON, Float::INFINITY)).is_some());
+    }
+
+    #[test]
+    fn rays_inside_spheres_hit_the_far_side(center in point(10.0), radius in 0.1..5.0 as Float, offset in point(1.0), dir in direction()) {
+        let origin = &center + &(&offset * (radius * 0.57)); // Within the sphere, as offset is shorter than sqrt 3
+        let object = sphere(&center, radius);
+        let ray = Ray::new(origin, dir);
+        let t_range = Range::new(RAY_EPSILON, Float::INFINITY);
+
+        let hit = object.get_closest_intersection(&ray, &t_range);
+        prop_assert!(hit.as_ref().is_some_and(|hit| !hit.front_face), "ray from inside didn't leave through the back face");
+        check_hit(&object, &ray, &t_range, |p| (p - &center).magnitude() - radius, Some(&center))?;
+    }
+
+    #[test]
+    fn rays_leaving_sphere_surfaces_cross_to_the_far_side(center in point(1000.0), radius in 0.1..5.0 as Float, outward in direction(), dir in direction()) {
+        // As a ray refracted into the sphere, or a camera on its surface, would be. The root at the origin has to be left
+        // out even far from the scene's origin, where rounding errors in it grow
+        let outward = outward.normalize();
+        prop_assume!(&outward * &dir.normalize() < -1e-2);
+        let origin = &center + &(&outward * radius);
+
+        let ray = Ray::new(origin, dir.clone());
+        let far = -2.0 * radius * (&outward * &dir) / (&dir * &dir);
+        let object = sphere(&center, radius);
+        let hit = object.get_closest_intersection(&ray, &Range::new(RAY_EPSILON, Float::INFINITY));
+        prop_assert!(hit.as_ref().is_some_and(|hit| (hit.t - far).abs() <= 1e-3 * far), "expected the far side at t {}, hit {:?}", far, hit.map(|hit| hit.t));
+    }
+
+    #[test]
+    fn triangle_hits_are_on_the_triangle(ps in [point(10.0), point(10.0), point(10.0)], origin in point(20.0), dir in direction(), t_range in t_range()) {
+        let normal = (&ps[1] - &ps[0]).cross(&(&ps[2] - &ps[0]));
+        prop_assume!(normal.magnitude() > 1e-2);
+        let normal = normal.normalize();
+
+        let object = Triangle::new(ps.clone(), 0, Material::default());
+        check_hit(&object, &Ray::new(origin, dir), &t_range, |p| &normal * &(p - &ps[0]), None)?;
+    }
+
+    #[test]
+    fn triangle_is_hit_when_aimed_at(ps in [point(10.0), point(10.0), point(10.0)], weights in (0.01..0.98 as Float, 0.01..0.98 as Float), origin in point(20.0)) {
+        let normal = (&ps[1] - &ps[0]).cross(&(&ps[2] - &ps[0]));
+        prop_assume!(normal.magnitude() > 1e-2);
+        let target = blend(&ps, weights);
+        // Rays nearly along the triangle's plane may graze past it
+        prop_assume!((&normal.normalize() * &(&target - &origin).normalize()).abs() > 1e-2);
+
+        let object = Triangle::new(ps, 0, Material::default());
+        let hit = object.get_closest_intersection(&Ray::new(origin.clone(), &target - &origin), &Range::new(RAY_EPSILON, Float::INFINITY));
+        prop_assert!(hit.is_some_and(|hit| (hit.t - 1.0).abs() <= TOLERANCE), "aimed at the triangle but missed it");
+    }
+
+    #[test]
+    fn box_hits_are_on_the_surface(origin in point(10.0), size in box_size(), ray_origin in point(20.0), dir in direction(), t_range in t_range()) {
+        let (object, min, max) = prism(&origin, &size);
+        let center = &origin + &(&size * 0.5);
+        check_hit(&object, &Ray::new(ray_origin, dir), &t_range, |p| box_distance(&min, &max, p), Some(&center))?;
+    }
+
+    #[test]
+    fn box_is_hit_from_outside(origin in point(10.0), size in box_size(), at in point(0.45), ray_origin in point(20.0)) {
+        let (object, min, max) = prism(&origin, &size);
+        let center = &origin + &(&size * 0.5);
+        let target = &center + &Vec3d::new(size.x() * at.x(), size.y() * at.y(), size.z() * at.z());
+        prop_assume!(box_distance(&min, &max, &ray_origin) > 1e-3);
+
+        let ray = Ray::new(ray_origin.clone(), &target - &ray_origin);
+        let t_range = Range::new(RAY_EPSILON, Float::INFINITY);
+
+        let hit = object.get_closest_intersection(&ray, &t_range);
+        prop_assert!(hit.as_ref().is_some_and(|hit| hit.front_face), "ray from outside didn't enter through a front face");
+        check_hit(&object, &ray, &t_range, |p| box_distance(&min, &max, p), Some(&center))?;
+    }
+
+    #[test]
+    fn rays_inside_boxes_hit_the_far_side(origin in point(10.0), size in box_size(), at in point(0.45), dir in direction()) {
+        let (object, min, max) = prism(&origin, &size);
+        let center = &origin + &(&size * 0.5);
+        let ray_origin = &center + &Vec3d::new(size.x() * at.x(), size.y() * at.y(), size.z() * at.z());
+
+        let ray = Ray::new(ray_origin, dir);
+        let t_range = Range::new(RAY_EPSILON, Float::INFINITY);
+
+        let hit = object.get_closest_intersection(&ray, &t_range);
+        prop_assert!(hit.as_ref().is_some_and(|hit| !hit.front_face), "ray from inside didn't leave through the back face");
+        check_hit(&object, &ray, &t_range, |p| box_distance(&min, &max, p), Some(&center))?;
+    }
+
+    #[test]
+    fn meshes_hit_the_same_while_their_hierarchy_is_built(vertices in prop::collection::vec(point(10.0), 3..30), origin in point(20.0), dir in direction(), t_range in t_range()) {
+        let faces: Vec<[usize; 3]> = (0..vertices.len() - 2).map(|i| [i, i + 1, i + 2]).collect();
+        let built = Mesh::new(vertices.clone(), faces.clone(), 0, Material::default());
+        let building = Mesh::new(vertices, faces, 0, Material::default()).in_background();
+
+        let ray = Ray::new(origin, dir);
+        let (expected, actual) = (built.get_closest_intersection(&ray, &t_range), building.get_closest_intersection(&ray, &t_range));
+        prop_assert_eq!(expected.map(|hit| hit.t), actual.map(|hit| hit.t));
+    }
+}
+
+#[test]
+fn meshes_mix_single_and_double_sided_faces() {
+    // Two squares side by side facing +z, the left double-sided and the right single-sided
+    let vertices = [(-2.0, 0.0), (0.0, 0.0), (0.0, 1.0), (-2.0, 1.0), (1.0, 0.0), (3.0, 0.0), (3.0, 1.0), (1.0, 1.0)]
+        .map(|(x, y)| Vec3d::new(x, y, -5.0))
+        .to_vec();
+    let faces = vec![[0, 1, 2], [0, 2, 3], [4, 5, 6], [4, 6, 7]];
+    let surfaces = vec![
+        Surface { color: 0, material: Material::default(), texture: None },
+        Surface { color: 0, material: Material::default().single_sided(), texture: None }
+    ];
+    let mesh = Mesh::new(vertices, faces, 0, Material::default()).with_surfaces(surfaces, vec![0, 0, 1, 1]);
+    let scene = Scene::new(Vec3d::new(0.0, 0.0, 0.0), 0, vec![], [Box::new(mesh) as Box<dyn Object>]).unwrap();
+
+    let t_range = Range::new(RAY_EPSILON, Float::INFINITY);
+    let from = |x: Float, z: Float| Ray::new(Vec3d::new(x, 0.5, z), Vec3d::new(0.0, 0.0, if z < -5.0 { 1.0 } else { -1.0 }));
+    for x in [-1.0, 2.0] {
+        assert!(scene.cast_ray(&from(x, 0.0), &t_range, RayKind::Camera).is_some_and(|hit| hit.front_face), "front of the square at x = {} wasn't hit", x);
+    }
+    assert!(scene.cast_ray(&from(-1.0, -10.0), &t_range, RayKind::Camera).is_some_and(|hit| !hit.front_face), "back of the double-sided square wasn't hit");
+    assert!(scene.cast_ray(&from(2.0, -10.0), &t_range, RayKind::Camera).is_none(), "back of the single-sided square was hit");
+}
+
+#[test]
+fn rays_only_hit_objects_their_kind_sees() {
+    let hidden = Flagged::new(Arc::new(sphere(&Vec3d::new(0.0, 0.0, -5.0), 1.0)), RenderFlags { camera: false, ..Default::default() });
+    let scene = Scene::new(Vec3d::new(0.0, 0.0, 0.0), 0, vec![], [Box::new(hidden) as Box<dyn Object>]).unwrap();
+
+    let (ray, t_range) = (Ray::new(Vec3d::new(0.0, 0.0, 0.0), Vec3d::new(0.0, 0.0, -1.0)), Range::new(RAY_EPSILON, Float::INFINITY));
+    assert!(scene.cast_ray(&ray, &t_range, RayKind::Camera).is_none());
+    for kind in [RayKind::Reflection, RayKind::Shadow] {
+        assert!(scene.cast_ray(&ray, &t_range, kind).is_some_and(|hit| (hit.t - 4.0).abs() < TOLERANCE), "{:?} ray missed the sphere", kind);
+    }
+}